@@ -0,0 +1,175 @@
+use std::path::{Path, PathBuf};
+use proc_macro2::{Span, TokenStream};
+use quick_xml::events::Event;
+use quote::{format_ident, quote};
+use syn::LitStr;
+
+struct Entry {
+    name: String,
+    version: Option<String>,
+    variant: Option<String>,
+    file: PathBuf,
+}
+
+pub fn expand(pattern: LitStr) -> syn::Result<TokenStream> {
+    let error = |message: String| syn::Error::new(pattern.span(), message);
+    let root = std::env::var("CARGO_MANIFEST_DIR").map_err(|e| error(e.to_string()))?;
+    let full_pattern = Path::new(&root).join(pattern.value());
+    let mut files = glob::glob(&full_pattern.to_string_lossy())
+        .map_err(|e| error(e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error(e.to_string()))?
+        .into_iter()
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    files.sort();
+    if files.is_empty() {
+        return Err(error(format!("no prompt files match {:?}", pattern.value())))
+    }
+    let mut entries = Vec::<Entry>::default();
+    for file in files.iter() {
+        let source = std::fs::read_to_string(file).map_err(|e| error(format!("{}: {e}", file.display())))?;
+        entries.extend(scan_prompts(&source, file).map_err(|e| error(format!("{}: {e}", file.display())))?);
+    }
+    let mut constants = Vec::default();
+    let mut idents = Vec::<String>::default();
+    for entry in entries.iter() {
+        // Prompts sharing a name are told apart by whichever of the variant
+        // and version differ, e.g. `WELCOME_B` or `TRIAGE_V3`.
+        let group = entries.iter().filter(|x| x.name == entry.name).collect::<Vec<_>>();
+        let mut ident = constant_name(&entry.name);
+        if let Some(variant) = entry.variant.as_ref().filter(|_| group.iter().any(|x| x.variant != entry.variant)) {
+            ident = format!("{ident}_{}", suffix(variant));
+        }
+        if let Some(version) = entry.version.as_ref().filter(|_| group.iter().any(|x| x.version != entry.version)) {
+            ident = format!("{ident}_V{}", suffix(version.trim().trim_start_matches('v')));
+        }
+        if idents.contains(&ident) {
+            return Err(error(format!("prompt {:?} is defined more than once", entry.name)))
+        }
+        let name = &entry.name;
+        let version = match &entry.version {
+            Some(version) => quote!(::core::option::Option::Some(#version)),
+            None => quote!(::core::option::Option::None),
+        };
+        let variant = match &entry.variant {
+            Some(variant) => quote!(::core::option::Option::Some(#variant)),
+            None => quote!(::core::option::Option::None),
+        };
+        // The full path is only needed to include the file; the binary keeps
+        // the one relative to the manifest.
+        let path = entry.file.to_string_lossy().to_string();
+        let file = entry.file.strip_prefix(&root).unwrap_or(&entry.file).to_string_lossy().to_string();
+        let doc = format!("The `{name}` prompt from `{file}`.");
+        let constant = format_ident!("{}", ident, span = Span::call_site());
+        constants.push(quote! {
+            #[doc = #doc]
+            pub const #constant: ::chatgpt_subsystems::embed::EmbeddedPrompt = ::chatgpt_subsystems::embed::EmbeddedPrompt {
+                name: #name,
+                version: #version,
+                variant: #variant,
+                file: #file,
+                source: ::core::include_str!(#path),
+            };
+        });
+        idents.push(ident);
+    }
+    let all = idents.iter().map(|x| format_ident!("{}", x));
+    Ok(quote! {
+        #(#constants)*
+        /// Every embedded prompt.
+        pub const ALL: &[::chatgpt_subsystems::embed::EmbeddedPrompt] = &[#(#all),*];
+    })
+}
+
+/// Checks that the file is well-formed and returns every `<prompt>` in it.
+fn scan_prompts(source: &str, file: &Path) -> Result<Vec<Entry>, String> {
+    let source = mask_stray_brackets(source);
+    let mut reader = quick_xml::Reader::from_str(&source);
+    reader.check_end_names(true);
+    let mut prompts = Vec::default();
+    let mut depth = 0usize;
+    loop {
+        let event = reader.read_event().map_err(|e| {
+            format!("malformed XML at byte {}: {e}", reader.buffer_position())
+        })?;
+        match event {
+            Event::Start(ref tag) | Event::Empty(ref tag) if tag.name().as_ref() == b"prompt" => {
+                let mut name = None;
+                let mut version = None;
+                let mut variant = None;
+                for attribute in tag.attributes() {
+                    let attribute = attribute.map_err(|e| e.to_string())?;
+                    let value = attribute.unescape_value().map_err(|e| e.to_string())?.replace(STRAY, "<");
+                    match attribute.key.as_ref() {
+                        b"name" => name = Some(value),
+                        b"version" => version = Some(value),
+                        b"variant" => variant = Some(value),
+                        _ => {}
+                    }
+                }
+                let name = name.ok_or_else(|| String::from("<prompt> is missing the 'name' attribute"))?;
+                // These are matched against the parsed prompt, which substitutes
+                // environment variables the build cannot know.
+                let keys = [("name", Some(&name)), ("version", version.as_ref()), ("variant", variant.as_ref())];
+                let templated = keys
+                    .into_iter()
+                    .find_map(|(key, value)| value.filter(|x| x.contains("${")).map(|x| (key, x)));
+                if let Some((key, value)) = templated {
+                    return Err(format!("<prompt {key}={value:?}> cannot use environment variables when embedded"))
+                }
+                prompts.push(Entry { name, version, variant, file: file.to_path_buf() });
+                if matches!(event, Event::Start(_)) {
+                    depth += 1;
+                }
+            }
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth = depth.saturating_sub(1),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return Err(String::from("unclosed element"))
+    }
+    Ok(prompts)
+}
+
+const STRAY: char = '\u{1}';
+
+/// Reads a `<` that cannot start a tag, as in `a < b`, as text, the way
+/// the runtime parser does.
+fn mask_stray_brackets(source: &str) -> String {
+    if source.contains(STRAY) {
+        return source.to_string()
+    }
+    let bytes = source.as_bytes();
+    let is_markup = |next: Option<&u8>| match next {
+        Some(x) => x.is_ascii_alphabetic() || !x.is_ascii() || b"/!?_:".contains(x),
+        None => false,
+    };
+    let masked = (0..bytes.len())
+        .map(|i| match bytes[i] == b'<' && !is_markup(bytes.get(i + 1)) {
+            true => STRAY as u8,
+            false => bytes[i],
+        })
+        .collect::<Vec<_>>();
+    String::from_utf8(masked).unwrap_or_else(|_| source.to_string())
+}
+
+/// `1.2-beta` becomes `1_2_BETA`.
+fn suffix(value: &str) -> String {
+    value.replace(|c: char| !c.is_ascii_alphanumeric(), "_").to_ascii_uppercase()
+}
+
+/// `question-1` becomes `QUESTION_1`.
+fn constant_name(name: &str) -> String {
+    let mut output = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect::<String>();
+    if output.starts_with(|c: char| c.is_ascii_digit()) || output.is_empty() {
+        output.insert(0, '_');
+    }
+    output
+}
@@ -0,0 +1,123 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{FnArg, ItemFn, Pat, ReturnType, Type};
+
+pub fn expand(function: ItemFn) -> syn::Result<TokenStream> {
+    let signature = &function.sig;
+    let ident = &signature.ident;
+    let tool_ident = format_ident!("{}_tool", ident);
+    let visibility = &function.vis;
+    let name = ident.to_string();
+    let (description, argument_docs) = parse_docs(&function.attrs);
+    let mut fields = Vec::default();
+    let mut values = Vec::default();
+    for input in signature.inputs.iter() {
+        let FnArg::Typed(input) = input else {
+            return Err(syn::Error::new_spanned(input, "#[ai_function] does not support methods"))
+        };
+        let Pat::Ident(pattern) = input.pat.as_ref() else {
+            return Err(syn::Error::new_spanned(&input.pat, "expected a plain argument name"))
+        };
+        let argument = &pattern.ident;
+        let argument_name = argument.to_string();
+        // Borrowed arguments are deserialized into an owned value and lent out.
+        let (field_type, value) = match input.ty.as_ref() {
+            Type::Reference(reference) => {
+                let owned = match reference.elem.as_ref() {
+                    Type::Path(path) if path.path.is_ident("str") => quote!(::std::string::String),
+                    Type::Slice(slice) => {
+                        let elem = &slice.elem;
+                        quote!(::std::vec::Vec<#elem>)
+                    }
+                    elem => quote!(#elem),
+                };
+                (owned, quote!(&arguments.#argument))
+            }
+            other => (quote!(#other), quote!(arguments.#argument)),
+        };
+        let doc = argument_docs
+            .iter()
+            .find(|(name, _)| *name == argument_name)
+            .map(|(_, doc)| quote!(#[schemars(description = #doc)]));
+        fields.push(quote!(#doc #argument: #field_type));
+        values.push(value);
+    }
+    let call = match signature.asyncness {
+        Some(_) => quote!(#ident(#(#values),*).await),
+        None => quote!(#ident(#(#values),*)),
+    };
+    let returns_result = match &signature.output {
+        ReturnType::Type(_, ty) => match ty.as_ref() {
+            Type::Path(path) => path.path.segments.last().is_some_and(|x| x.ident == "Result"),
+            _ => false,
+        },
+        ReturnType::Default => false,
+    };
+    let output = match returns_result {
+        true => quote! {
+            match output {
+                ::core::result::Result::Ok(value) => ::chatgpt_subsystems::tools::to_output(value),
+                ::core::result::Result::Err(error) => ::core::result::Result::Err(error.to_string().into()),
+            }
+        },
+        false => quote!(::chatgpt_subsystems::tools::to_output(output)),
+    };
+    let description = match description {
+        Some(description) => quote!(::core::option::Option::Some(#description)),
+        None => quote!(::core::option::Option::None),
+    };
+    Ok(quote! {
+        #function
+
+        #[doc = concat!("The [`", #name, "`] function as a tool the model can call.")]
+        #visibility fn #tool_ident() -> ::chatgpt_subsystems::tools::FunctionTool {
+            #[derive(
+                ::chatgpt_subsystems::__private::serde::Deserialize,
+                ::chatgpt_subsystems::__private::schemars::JsonSchema,
+            )]
+            #[serde(crate = "::chatgpt_subsystems::__private::serde")]
+            #[schemars(crate = "::chatgpt_subsystems::__private::schemars")]
+            struct Arguments {
+                #(#fields),*
+            }
+            ::chatgpt_subsystems::tools::FunctionTool::from_fn(#name, #description, |arguments: Arguments| async move {
+                let output = #call;
+                #output
+            })
+        }
+    })
+}
+
+/// Splits the doc comment into the function description and the entries of
+/// its `# Arguments` section, written as ``* `name` - description``.
+fn parse_docs(attrs: &[syn::Attribute]) -> (Option<String>, Vec<(String, String)>) {
+    let lines = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(meta) => match &meta.value {
+                syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(text), .. }) => Some(text.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| line.trim().to_string())
+        .collect::<Vec<_>>();
+    let split = lines
+        .iter()
+        .position(|line| line.trim_start_matches('#').trim() == "Arguments" && line.starts_with('#'))
+        .unwrap_or(lines.len());
+    let description = lines[..split].join("\n").trim().to_string();
+    let arguments = lines[split..]
+        .iter()
+        .filter_map(|line| {
+            let line = line.strip_prefix(['*', '-'])?.trim();
+            let line = line.strip_prefix('`')?;
+            let (name, rest) = line.split_once('`')?;
+            let rest = rest.trim().trim_start_matches(['-', ':']).trim();
+            Some((name.to_string(), rest.to_string()))
+        })
+        .collect();
+    let description = Some(description).filter(|x| !x.is_empty());
+    (description, arguments)
+}
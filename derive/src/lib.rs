@@ -0,0 +1,127 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+mod embed;
+mod function;
+
+/// Implements `chatgpt_subsystems::params::PromptParams`, exposing each named
+/// field as a template variable.
+///
+/// - `#[prompt(name = "...")]` on the struct ties it to a prompt.
+/// - `#[prompt(rename = "...")]` on a field changes its variable name.
+/// - `#[prompt(skip)]` on a field leaves it out.
+#[proc_macro_derive(PromptParams, attributes(prompt))]
+pub fn derive_prompt_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(output) => output.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// Embeds every prompt file matching a glob (relative to the crate root)
+/// and defines a `const` for each prompt, plus `ALL`. The files are checked
+/// at compile time, so a malformed or missing file is a build error rather
+/// than a runtime one.
+///
+/// Editing an embedded file triggers a rebuild; adding a new file that
+/// matches the glob does not.
+#[proc_macro]
+pub fn embed_prompts(input: TokenStream) -> TokenStream {
+    let pattern = parse_macro_input!(input as LitStr);
+    match embed::expand(pattern) {
+        Ok(output) => output.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// Turns a function into a tool: the function is kept as is, and a
+/// `<name>_tool()` constructor is added that returns a
+/// `chatgpt_subsystems::tools::FunctionTool` with a JSON schema built from
+/// the argument types and the doc comment.
+///
+/// ```ignore
+/// /// Looks up the current weather.
+/// ///
+/// /// # Arguments
+/// ///
+/// /// * `city` - The city name, e.g. "Paris".
+/// #[ai_function]
+/// fn get_weather(city: &str, celsius: Option<bool>) -> Result<Weather, Error> { ... }
+///
+/// let tool = get_weather_tool();
+/// ```
+#[proc_macro_attribute]
+pub fn ai_function(_attr: TokenStream, input: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(input as syn::ItemFn);
+    match function::expand(function) {
+        Ok(output) => output.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let mut prompt_name = None;
+    for attr in input.attrs.iter().filter(|x| x.path().is_ident("prompt")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                prompt_name = Some(meta.value()?.parse::<LitStr>()?.value());
+                return Ok(())
+            }
+            Err(meta.error("expected `name = \"...\"`"))
+        })?;
+    }
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(ident, "PromptParams can only be derived for structs"))
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(ident, "PromptParams requires named fields"))
+    };
+    let mut names = Vec::default();
+    let mut members = Vec::default();
+    for field in fields.named.iter() {
+        let member = field.ident.clone().unwrap();
+        let mut name = member.to_string();
+        let mut skip = false;
+        for attr in field.attrs.iter().filter(|x| x.path().is_ident("prompt")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                    return Ok(())
+                }
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                    return Ok(())
+                }
+                Err(meta.error("expected `rename = \"...\"` or `skip`"))
+            })?;
+        }
+        if !skip {
+            names.push(name);
+            members.push(member);
+        }
+    }
+    let prompt_name = match prompt_name {
+        Some(name) => quote!(::core::option::Option::Some(#name)),
+        None => quote!(::core::option::Option::None),
+    };
+    Ok(quote! {
+        impl #impl_generics ::chatgpt_subsystems::params::PromptParams for #ident #type_generics #where_clause {
+            const PROMPT: ::core::option::Option<&'static str> = #prompt_name;
+            const VARIABLES: &'static [&'static str] = &[#(#names),*];
+            fn to_globals(&self) -> ::core::result::Result<
+                ::chatgpt_subsystems::params::Globals,
+                ::chatgpt_subsystems::client::Error,
+            > {
+                let mut globals = ::chatgpt_subsystems::params::Globals::default();
+                #(
+                    ::chatgpt_subsystems::params::insert(&mut globals, #names, &self.#members)?;
+                )*
+                ::core::result::Result::Ok(globals)
+            }
+        }
+    })
+}
@@ -0,0 +1,44 @@
+// Compares the default transport, which builds a client and opens a
+// connection per request, with `ReqwestTransport::high_throughput`, which
+// shares one client and keeps its connection to the local mock server
+// alive between requests. Against a real host the shared pool also skips
+// the TLS handshake, so the gap is wider.
+//
+// ```sh
+// cargo run --release --example throughput --features testing
+// ```
+use std::time::Instant;
+use chatgpt_subsystems::client::{ChatCompletionsBody, ChatCompletionsRequestBuilder, Error, Message, Role};
+use chatgpt_subsystems::testing::{fixtures, MockServer};
+use chatgpt_subsystems::transport::ReqwestTransport;
+
+const REQUESTS: usize = 200;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Error> {
+    let body = ChatCompletionsBody::new("gpt-4o-mini", [Message::new(Role::User, "Hello")]);
+    for (name, transport) in [
+        ("per-request client", ReqwestTransport::default()),
+        ("high throughput", ReqwestTransport::high_throughput()?),
+    ] {
+        let server = MockServer::start().await?;
+        for _ in 0..REQUESTS {
+            server.push(fixtures::text_stream("Hi"));
+        }
+        let prepared = ChatCompletionsRequestBuilder::default()
+            .with_api_endpoint(server.endpoint())
+            .with_transport(transport)
+            .prepare()
+            .unwrap();
+        let started = Instant::now();
+        for _ in 0..REQUESTS {
+            prepared.execute(&body).await?;
+        }
+        let elapsed = started.elapsed();
+        println!(
+            "{name:>18}: {REQUESTS} requests in {elapsed:.2?} ({:.0} requests/s)",
+            REQUESTS as f64 / elapsed.as_secs_f64(),
+        );
+    }
+    Ok(())
+}
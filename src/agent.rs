@@ -0,0 +1,174 @@
+use crate::client::{ChatCompletionsRequestBuilder, CompletionChunk, Error, Message};
+use crate::extract::MissingBody;
+use crate::lint::estimate_tokens;
+use crate::tools::{ToolCall, ToolRegistry};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// AGENT LOOP
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Debug, Clone)]
+pub struct AgentLimits {
+    /// Requests to make before giving up on a final answer.
+    pub max_iterations: usize,
+    /// Total tokens to spend across all requests. Uses the provider's usage
+    /// figures when it reports them and an estimate of the replies otherwise.
+    pub max_tokens: Option<usize>,
+    /// How many tool calls from one reply may run at once.
+    pub tool_concurrency: usize,
+}
+
+impl Default for AgentLimits {
+    fn default() -> Self {
+        AgentLimits { max_iterations: 10, max_tokens: None, tool_concurrency: 4 }
+    }
+}
+
+impl AgentLimits {
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+    pub fn with_tool_concurrency(mut self, tool_concurrency: usize) -> Self {
+        self.tool_concurrency = tool_concurrency;
+        self
+    }
+}
+
+/// Progress reported while the agent runs. Reply text is streamed to the
+/// session's observers as usual.
+///
+/// For each tool call the model makes, `ToolCallStarted` and
+/// `ToolCallArguments` arrive while the reply is still streaming, then
+/// `ToolExecuting` and `ToolResult` once the reply is complete. `index` is
+/// the call's position in the reply.
+#[derive(Debug, Clone)]
+pub enum AgentEvent {
+    TurnStarted { iteration: usize },
+    /// The model began a call; its arguments follow.
+    ToolCallStarted { index: usize, id: String, name: String },
+    /// The next fragment of a call's JSON arguments.
+    ToolCallArguments { index: usize, delta: String },
+    AssistantMessage(Message),
+    ToolExecuting(ToolCall),
+    ToolResult { call: ToolCall, output: Result<serde_json::Value, String> },
+    Finished(StopReason),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The model replied without calling any tools.
+    Answer,
+    MaxIterations,
+    MaxTokens,
+}
+
+#[derive(Debug, Clone)]
+pub struct AgentOutcome {
+    /// The whole conversation, including tool calls and results.
+    pub messages: Vec<Message>,
+    /// The final reply, if the model gave one.
+    pub answer: Option<String>,
+    pub iterations: usize,
+    pub tokens: usize,
+    pub stop_reason: StopReason,
+}
+
+/// Runs the conversation in `session`, executing the tools the model calls
+/// and sending their results back, until the model answers without calling
+/// any tools or a limit is reached.
+///
+/// Tool failures are reported to the model as `{"error": "..."}` rather
+/// than ending the run.
+pub async fn run_agent(
+    session: ChatCompletionsRequestBuilder,
+    registry: &ToolRegistry,
+    limits: AgentLimits,
+) -> Result<AgentOutcome, Error> {
+    run_agent_with_events(session, registry, limits, |_| {}).await
+}
+
+pub async fn run_agent_with_events(
+    session: ChatCompletionsRequestBuilder,
+    registry: &ToolRegistry,
+    limits: AgentLimits,
+    mut on_event: impl FnMut(&AgentEvent),
+) -> Result<AgentOutcome, Error> {
+    let mut body = session.body.clone().ok_or(Box::new(MissingBody))?;
+    if !registry.is_empty() {
+        body.tools = Some(registry.definitions());
+    }
+    // The response is read as a server-sent event stream.
+    body.stream = Some(true);
+    let mut tokens = 0;
+    let mut stop_reason = StopReason::MaxIterations;
+    let mut answer = None;
+    let mut iterations = 0;
+    while iterations < limits.max_iterations {
+        iterations += 1;
+        on_event(&AgentEvent::TurnStarted { iteration: iterations });
+        #[cfg(feature = "tracing")]
+        tracing::debug!(iteration = iterations, "agent turn");
+        let request = session.clone()
+            .with_body(body.clone())
+            .build()
+            .ok_or(Box::new(MissingBody))?;
+        let response = request.execute_with(|chunk| tool_call_events(chunk, &mut on_event)).await?;
+        let message = response.message(0);
+        tokens += match response.usage() {
+            Some(usage) => usage.total_tokens,
+            None => {
+                let arguments = message.tool_calls.iter().map(|x| x.function.arguments.as_str());
+                std::iter::once(message.content.as_str()).chain(arguments).map(estimate_tokens).sum()
+            }
+        };
+        body.messages.push(message.clone());
+        on_event(&AgentEvent::AssistantMessage(message.clone()));
+        if message.tool_calls.is_empty() {
+            answer = Some(message.content);
+            stop_reason = StopReason::Answer;
+            break
+        }
+        for call in message.tool_calls.iter() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(tool = %call.function.name, id = %call.id, "executing tool call");
+            on_event(&AgentEvent::ToolExecuting(call.clone()));
+        }
+        let outputs = registry.dispatch_all(&message.tool_calls, limits.tool_concurrency).await;
+        // Results go back in call order, each tagged with its call's id.
+        for (call, output) in message.tool_calls.into_iter().zip(outputs) {
+            let output = output.map_err(|e| e.to_string());
+            body.messages.push(Message::tool_result(&call.id, &output));
+            on_event(&AgentEvent::ToolResult { call, output });
+        }
+        if limits.max_tokens.is_some_and(|max| tokens >= max) {
+            stop_reason = StopReason::MaxTokens;
+            break
+        }
+    }
+    on_event(&AgentEvent::Finished(stop_reason));
+    Ok(AgentOutcome { messages: body.messages, answer, iterations, tokens, stop_reason })
+}
+
+/// Reports the tool-call fragments of the first choice. A fragment with an
+/// id starts a new call.
+fn tool_call_events(chunk: &CompletionChunk, on_event: &mut impl FnMut(&AgentEvent)) {
+    let deltas = chunk.choices
+        .iter()
+        .filter(|choice| choice.index == 0)
+        .filter_map(|choice| choice.delta.tool_calls.as_ref())
+        .flatten();
+    for delta in deltas {
+        let function = delta.function.as_ref();
+        if let Some(id) = delta.id.clone() {
+            let name = function.and_then(|x| x.name.clone()).unwrap_or_default();
+            on_event(&AgentEvent::ToolCallStarted { index: delta.index, id, name });
+        }
+        if let Some(arguments) = function.and_then(|x| x.arguments.clone()).filter(|x| !x.is_empty()) {
+            on_event(&AgentEvent::ToolCallArguments { index: delta.index, delta: arguments });
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use std::path::PathBuf;
+
+use crate::client::{ApiEndpoint, Error};
+use crate::extract::{strip_code_fence, MissingBody};
+use crate::validate::Diagnostic;
+use crate::xml;
+use crate::xml_dsl::{Prompt, PromptCollection};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// ASSERTIONS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// An expectation on a prompt's reply, declared with `<assert>`:
+///
+/// ```xml
+/// <assert contains="refund"/>
+/// <assert not-contains="As an AI"/>
+/// <assert matches="^(yes|no)$"/>
+/// <assert equals="4"/>
+/// <assert json-path="$.status" equals="ok"/>
+/// <assert json-path="$.items[0].id"/>
+/// ```
+///
+/// Run with [`PromptCollection::run_tests`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Assertion {
+    Contains(String),
+    NotContains(String),
+    /// A regular expression searched for in the trimmed reply.
+    Matches(String),
+    /// The trimmed reply must be exactly this.
+    Equals(String),
+    /// The reply is parsed as JSON, ignoring a surrounding Markdown code
+    /// block, and the path must exist; with `equals`, its value must also
+    /// match. Paths are written `$.a.b`, `$.items[0]` or `$['a key']`.
+    JsonPath { path: String, equals: Option<String> },
+}
+
+impl Assertion {
+    /// Returns why the reply fails the assertion.
+    pub fn check(&self, reply: &str) -> Result<(), String> {
+        match self {
+            Assertion::Contains(text) => {
+                if reply.contains(text.as_str()) {
+                    return Ok(())
+                }
+                Err(format!("the reply does not contain {text:?}"))
+            }
+            Assertion::NotContains(text) => {
+                if !reply.contains(text.as_str()) {
+                    return Ok(())
+                }
+                Err(format!("the reply contains {text:?}"))
+            }
+            Assertion::Matches(pattern) => {
+                let regex = regex::Regex::new(pattern).map_err(|e| format!("invalid pattern /{pattern}/ ({e})"))?;
+                if regex.is_match(reply.trim()) {
+                    return Ok(())
+                }
+                Err(format!("the reply does not match /{pattern}/"))
+            }
+            Assertion::Equals(expected) => {
+                if reply.trim() == expected {
+                    return Ok(())
+                }
+                Err(format!("expected {expected:?}, got {:?}", reply.trim()))
+            }
+            Assertion::JsonPath { path, equals } => {
+                let value = serde_json::from_str::<serde_json::Value>(strip_code_fence(reply))
+                    .map_err(|e| format!("the reply is not valid JSON ({e})"))?;
+                let segments = parse_json_path(path).ok_or_else(|| format!("invalid JSON path {path:?}"))?;
+                let found = resolve(&value, &segments).ok_or_else(|| format!("{path} is missing from the reply"))?;
+                let Some(expected) = equals else {
+                    return Ok(())
+                };
+                let actual = match found {
+                    serde_json::Value::String(x) => x.clone(),
+                    other => other.to_string(),
+                };
+                if &actual == expected {
+                    return Ok(())
+                }
+                Err(format!("expected {path} to equal {expected:?}, got {actual:?}"))
+            }
+        }
+    }
+    /// Reads an `<assert>` element's attributes.
+    pub(crate) fn from_element(element: &xml::Element) -> Result<Self, Diagnostic> {
+        let equals = element.attr("equals").map(str::to_string);
+        let assertion = if let Some(path) = element.attr("json-path") {
+            if parse_json_path(path).is_none() {
+                return Err(Diagnostic::invalid_value("json-path", path).at(element.position))
+            }
+            Assertion::JsonPath { path: path.to_string(), equals }
+        } else if let Some(text) = element.attr("contains") {
+            Assertion::Contains(text.to_string())
+        } else if let Some(text) = element.attr("not-contains") {
+            Assertion::NotContains(text.to_string())
+        } else if let Some(pattern) = element.attr("matches") {
+            if regex::Regex::new(pattern).is_err() {
+                return Err(Diagnostic::invalid_value("matches", pattern).at(element.position))
+            }
+            Assertion::Matches(pattern.to_string())
+        } else if let Some(expected) = equals {
+            Assertion::Equals(expected)
+        } else {
+            return Err(Diagnostic::missing_attribute("assert", "contains").at(element.position))
+        };
+        Ok(assertion)
+    }
+    /// The `<assert>` attributes that declare this assertion.
+    pub(crate) fn attributes(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Assertion::Contains(x) => vec![("contains", x.clone())],
+            Assertion::NotContains(x) => vec![("not-contains", x.clone())],
+            Assertion::Matches(x) => vec![("matches", x.clone())],
+            Assertion::Equals(x) => vec![("equals", x.clone())],
+            Assertion::JsonPath { path, equals } => {
+                let mut attributes = vec![("json-path", path.clone())];
+                attributes.extend(equals.as_ref().map(|x| ("equals", x.clone())));
+                attributes
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Assertion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let attributes = self.attributes()
+            .into_iter()
+            .map(|(key, value)| format!("{key}={value:?}"))
+            .collect::<Vec<_>>();
+        write!(f, "{}", attributes.join(" "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Supports the dot and bracket forms only; no wildcards or filters.
+fn parse_json_path(path: &str) -> Option<Vec<PathSegment>> {
+    let mut rest = path.trim().strip_prefix('$')?;
+    let mut segments = Vec::default();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            let key = &after[..end];
+            if key.is_empty() {
+                return None
+            }
+            segments.push(PathSegment::Key(key.to_string()));
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']')?;
+            let inner = after[..end].trim();
+            let quoted = inner
+                .strip_prefix('\'')
+                .and_then(|x| x.strip_suffix('\''))
+                .or_else(|| inner.strip_prefix('"').and_then(|x| x.strip_suffix('"')));
+            let segment = match quoted {
+                Some(key) => PathSegment::Key(key.to_string()),
+                None => PathSegment::Index(inner.parse().ok()?),
+            };
+            segments.push(segment);
+            rest = &after[end + 1..];
+        } else {
+            return None
+        }
+    }
+    Some(segments)
+}
+
+fn resolve<'a>(value: &'a serde_json::Value, segments: &[PathSegment]) -> Option<&'a serde_json::Value> {
+    segments.iter().try_fold(value, |value, segment| match segment {
+        PathSegment::Key(key) => value.get(key),
+        PathSegment::Index(index) => value.get(index),
+    })
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// TEST RUNNER
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+impl PromptCollection {
+    /// Sends every prompt that has `<assert>` elements to `api_endpoint` and
+    /// checks the reply, for prompt regression suites:
+    ///
+    /// ```rust,ignore
+    /// let collection = PromptCollection::open_dir("prompts")?;
+    /// let report = collection.run_tests(&ApiEndpoint::from_env()?).await;
+    /// println!("{report}");
+    /// assert!(report.passed());
+    /// ```
+    ///
+    /// Prompts are rendered without globals, so any variables they read
+    /// need a `<params>` default. A prompt that cannot be rendered or sent
+    /// fails rather than ending the run.
+    pub async fn run_tests(&self, api_endpoint: &ApiEndpoint) -> TestReport {
+        let mut results = Vec::default();
+        for prompt in self.prompts().iter().filter(|x| !x.assertions.is_empty()) {
+            let result = match run_prompt(prompt, api_endpoint).await {
+                Ok(reply) => {
+                    let outcomes = prompt.assertions
+                        .iter()
+                        .map(|assertion| AssertionOutcome {
+                            assertion: assertion.clone(),
+                            failure: assertion.check(&reply).err(),
+                        })
+                        .collect();
+                    TestResult::new(prompt, Some(reply), outcomes, None)
+                }
+                Err(error) => TestResult::new(prompt, None, Vec::default(), Some(error.to_string())),
+            };
+            results.push(result);
+        }
+        TestReport { results }
+    }
+}
+
+async fn run_prompt(prompt: &Prompt, api_endpoint: &ApiEndpoint) -> Result<String, Error> {
+    let request = prompt
+        .render_request_builder(&liquid::Object::new())?
+        .with_api_endpoint(api_endpoint.clone())
+        .build()
+        .ok_or(Box::new(MissingBody))?;
+    let response = request.execute().await?;
+    Ok(response.content(0))
+}
+
+#[derive(Debug, Clone)]
+pub struct AssertionOutcome {
+    pub assertion: Assertion,
+    /// Why the assertion failed, if it did.
+    pub failure: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    pub prompt: Option<String>,
+    pub source_file: Option<PathBuf>,
+    pub reply: Option<String>,
+    pub outcomes: Vec<AssertionOutcome>,
+    /// Set when the prompt could not be rendered or sent.
+    pub error: Option<String>,
+}
+
+impl TestResult {
+    fn new(prompt: &Prompt, reply: Option<String>, outcomes: Vec<AssertionOutcome>, error: Option<String>) -> Self {
+        TestResult { prompt: prompt.name.clone(), source_file: prompt.source_file.clone(), reply, outcomes, error }
+    }
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.outcomes.iter().all(|x| x.failure.is_none())
+    }
+}
+
+/// The result of [`PromptCollection::run_tests`], one entry per prompt.
+#[derive(Debug, Clone, Default)]
+pub struct TestReport {
+    pub results: Vec<TestResult>,
+}
+
+impl TestReport {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(TestResult::passed)
+    }
+    pub fn failures(&self) -> impl Iterator<Item = &TestResult> {
+        self.results.iter().filter(|x| !x.passed())
+    }
+}
+
+impl std::fmt::Display for TestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for result in self.results.iter() {
+            let mut name = result.prompt.clone().unwrap_or_else(|| String::from("<unnamed>"));
+            if let Some(file) = result.source_file.as_ref() {
+                name = format!("{}: {name}", file.display());
+            }
+            match result.passed() {
+                true => writeln!(f, "ok: {name}")?,
+                false => writeln!(f, "FAILED: {name}")?,
+            }
+            for line in result.error.iter().flat_map(|x| x.lines()) {
+                writeln!(f, "    {line}")?;
+            }
+            for outcome in result.outcomes.iter() {
+                if let Some(failure) = outcome.failure.as_ref() {
+                    writeln!(f, "    <assert {}/>: {failure}", outcome.assertion)?;
+                }
+            }
+        }
+        let failed = self.failures().count();
+        write!(f, "{} passed, {failed} failed", self.results.len() - failed)
+    }
+}
@@ -0,0 +1,143 @@
+use std::{cell::RefCell, io::Write, path::Path, rc::Rc};
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+
+use crate::client::{ChatCompletionsRequest, ChatCompletionsResponse, Error, Usage};
+use crate::metrics::RequestMetrics;
+
+/// Output longer than this is truncated, unless changed with
+/// [`AuditLog::with_max_output_chars`].
+pub const DEFAULT_MAX_OUTPUT_CHARS: usize = 2_000;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// RECORDS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// One completed or failed request.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditRecord {
+    /// When the record was written, as RFC 3339 in UTC.
+    pub timestamp: String,
+    /// SHA-256 of the request body as sent, in hex; identical requests
+    /// share a hash.
+    pub request_hash: String,
+    /// The prompt the request was rendered from, if any.
+    pub prompt: Option<String>,
+    pub model: String,
+    pub host: Option<String>,
+    pub latency_ms: u64,
+    pub time_to_first_token_ms: Option<u64>,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub usage: Option<Usage>,
+    /// The first choice's content, truncated.
+    pub output: String,
+    pub output_truncated: bool,
+}
+
+/// Where records go. Implementations should only ever append.
+pub trait AuditSink {
+    fn write(&self, record: &AuditRecord) -> Result<(), Error>;
+}
+
+/// Appends each record to a file as a line of JSON.
+pub struct JsonlFile {
+    file: RefCell<std::fs::File>,
+}
+
+impl JsonlFile {
+    /// Opens `path` for appending, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(JsonlFile { file: RefCell::new(file) })
+    }
+}
+
+impl AuditSink for JsonlFile {
+    fn write(&self, record: &AuditRecord) -> Result<(), Error> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = self.file.borrow_mut();
+        file.write_all(line.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// AUDIT LOG
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+pub type Redactor = Rc<dyn Fn(&mut AuditRecord)>;
+
+/// Writes an [`AuditRecord`] for every request made by a client it is
+/// attached to with
+/// [`crate::client::ChatCompletionsRequestBuilder::with_audit_log`].
+///
+/// Redactors run in order on each record before it is written. A record
+/// that cannot be written fails the request, so no output goes unaudited.
+#[derive(Clone)]
+pub struct AuditLog {
+    sink: Rc<dyn AuditSink>,
+    redactors: Vec<Redactor>,
+    max_output_chars: usize,
+}
+
+impl AuditLog {
+    pub fn new(sink: impl AuditSink + 'static) -> Self {
+        AuditLog { sink: Rc::new(sink), redactors: Vec::default(), max_output_chars: DEFAULT_MAX_OUTPUT_CHARS }
+    }
+    /// Appends to a JSONL file.
+    pub fn jsonl(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self::new(JsonlFile::open(path)?))
+    }
+    pub fn with_redactor(mut self, redactor: impl Fn(&mut AuditRecord) + 'static) -> Self {
+        self.redactors.push(Rc::new(redactor));
+        self
+    }
+    pub fn with_max_output_chars(mut self, max_output_chars: usize) -> Self {
+        self.max_output_chars = max_output_chars;
+        self
+    }
+    pub(crate) fn record(
+        &self,
+        request: &ChatCompletionsRequest,
+        body: &[u8],
+        metrics: &RequestMetrics,
+        result: &Result<ChatCompletionsResponse, Error>,
+    ) -> Result<(), Error> {
+        let content = result.as_ref().map(|x| x.content(0)).unwrap_or_default();
+        let (output, output_truncated) = match content.char_indices().nth(self.max_output_chars) {
+            Some((offset, _)) => (content[..offset].to_string(), true),
+            None => (content, false),
+        };
+        let mut record = AuditRecord {
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            request_hash: format!("{:x}", sha2::Sha256::digest(body)),
+            prompt: request.prompt_name.clone(),
+            model: metrics.request.model.clone(),
+            host: metrics.request.host.clone(),
+            latency_ms: metrics.latency.as_millis() as u64,
+            time_to_first_token_ms: metrics.time_to_first_token.map(|x| x.as_millis() as u64),
+            status: metrics.status,
+            error: metrics.error.clone(),
+            usage: metrics.usage,
+            output,
+            output_truncated,
+        };
+        for redactor in self.redactors.iter() {
+            redactor(&mut record);
+        }
+        self.sink.write(&record)
+    }
+}
+
+impl std::fmt::Debug for AuditLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuditLog")
+            .field("redactors", &self.redactors.len())
+            .field("max_output_chars", &self.max_output_chars)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,70 @@
+use futures::future::LocalBoxFuture;
+
+use crate::client::Error;
+use crate::secret::Secret;
+use crate::transport::HttpRequest;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// AUTH SCHEMES
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Produces the headers that authenticate a request, in place of the
+/// default `Authorization: Bearer` header; set it with
+/// [`crate::client::ApiEndpoint::with_auth`].
+///
+/// It is called right before every send, retries included, with the
+/// request as it will go out, so it can refresh a token or sign the body.
+/// Headers it returns replace any of the same name.
+///
+/// Plain closures work for schemes that need no awaiting:
+///
+/// ```rust,ignore
+/// let endpoint = ApiEndpoint::new(gateway_url, "").with_auth(move |request: &HttpRequest| {
+///     let signature = hmac_sha256(&secret, &request.body);
+///     Ok(vec![(String::from("X-Signature"), hex::encode(signature))])
+/// });
+/// ```
+pub trait AuthScheme {
+    fn headers<'a>(&'a self, request: &'a HttpRequest) -> LocalBoxFuture<'a, Result<Vec<(String, String)>, Error>>;
+}
+
+impl<F> AuthScheme for F
+where
+    F: Fn(&HttpRequest) -> Result<Vec<(String, String)>, Error>,
+{
+    fn headers<'a>(&'a self, request: &'a HttpRequest) -> LocalBoxFuture<'a, Result<Vec<(String, String)>, Error>> {
+        Box::pin(futures::future::ready(self(request)))
+    }
+}
+
+/// Sends the key in a header of its own, such as Azure OpenAI's `api-key`.
+#[derive(Debug, Clone)]
+pub struct HeaderAuth {
+    pub name: String,
+    /// Redacted when printed; see [`crate::secret::Secret`].
+    pub value: Secret<String>,
+}
+
+impl HeaderAuth {
+    pub fn new(name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        HeaderAuth { name: name.as_ref().to_string(), value: Secret::from(value.as_ref()) }
+    }
+}
+
+impl AuthScheme for HeaderAuth {
+    fn headers<'a>(&'a self, _: &'a HttpRequest) -> LocalBoxFuture<'a, Result<Vec<(String, String)>, Error>> {
+        Box::pin(futures::future::ready(Ok(vec![(self.name.clone(), self.value.expose().clone())])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_auth_hides_the_key() {
+        let auth = HeaderAuth::new("api-key", "sk-very-secret");
+        let printed = format!("{auth:?}");
+        assert!(printed.contains("api-key"), "{printed}");
+        assert!(!printed.contains("sk-very-secret"), "{printed}");
+    }
+}
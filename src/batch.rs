@@ -0,0 +1,468 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::client::{ApiError, ChatCompletionsBody, ChatCompletionsRequestBuilder, Error, FinishReason, Message, Usage};
+use crate::xml_dsl::Prompt;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// WORK ITEMS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Debug, Clone)]
+pub struct WorkItem {
+    /// Identifies the item in results, events and checkpoints; should be
+    /// unique within a batch.
+    pub id: String,
+    pub prompt: Prompt,
+    pub variables: liquid::Object,
+}
+
+impl WorkItem {
+    pub fn new(id: impl AsRef<str>, prompt: Prompt, variables: liquid::Object) -> Self {
+        WorkItem { id: id.as_ref().to_string(), prompt, variables }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ItemResult {
+    pub id: String,
+    /// The reply, if the item succeeded.
+    pub message: Option<Message>,
+    pub finish_reason: Option<FinishReason>,
+    pub usage: Option<Usage>,
+    pub error: Option<String>,
+    /// Requests sent for the item, including retries.
+    pub attempts: usize,
+    /// Read from the checkpoint rather than sent this run.
+    #[serde(skip)]
+    pub resumed: bool,
+}
+
+impl ItemResult {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+    pub fn content(&self) -> Option<&str> {
+        self.message.as_ref().map(|x| x.content.as_str())
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// EVENTS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Debug, Clone)]
+pub enum BatchEvent<'a> {
+    /// A retryable error; the item is sent again after `delay`.
+    Retrying { id: &'a str, attempt: usize, delay: Duration, error: String },
+    Completed(&'a ItemResult),
+    Failed(&'a ItemResult),
+}
+
+/// Running totals, passed along with every [`BatchEvent`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BatchProgress {
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+    /// Items skipped because the checkpoint already had them.
+    pub resumed: usize,
+}
+
+impl BatchProgress {
+    /// Items not yet finished.
+    pub fn remaining(&self) -> usize {
+        self.total - self.completed - self.failed - self.resumed
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// RUNNER
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+type Setup = Rc<dyn Fn(ChatCompletionsRequestBuilder) -> ChatCompletionsRequestBuilder>;
+
+/// Renders and sends many prompts, a bounded number at a time.
+///
+/// Each finished item is appended to the checkpoint file, if one is set, as
+/// a line of JSON. A later run with the same checkpoint reuses the
+/// successful results found there instead of sending those items again,
+/// so an interrupted batch can pick up where it stopped; failed items are
+/// retried.
+///
+/// ```rust,ignore
+/// let runner = BatchRunner::new()
+///     .with_setup(move |x| x.with_api_key(&api_key))
+///     .with_concurrency(8)
+///     .with_requests_per_minute(500)
+///     .with_checkpoint("summaries.checkpoint.jsonl");
+/// let results = runner.run_with_events(items, |event, progress| {
+///     eprintln!("{event:?} ({} left)", progress.remaining());
+/// }).await?;
+/// ```
+#[derive(Clone)]
+pub struct BatchRunner {
+    setup: Option<Setup>,
+    concurrency: usize,
+    requests_per_minute: Option<u32>,
+    max_retries: usize,
+    backoff: Duration,
+    checkpoint: Option<PathBuf>,
+}
+
+impl Default for BatchRunner {
+    fn default() -> Self {
+        BatchRunner {
+            setup: None,
+            concurrency: 4,
+            requests_per_minute: None,
+            max_retries: 2,
+            backoff: Duration::from_secs(1),
+            checkpoint: None,
+        }
+    }
+}
+
+impl BatchRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Applied to each item's request before it is sent, e.g. to set the
+    /// API key, transport or observers.
+    pub fn with_setup(
+        mut self,
+        setup: impl Fn(ChatCompletionsRequestBuilder) -> ChatCompletionsRequestBuilder + 'static,
+    ) -> Self {
+        self.setup = Some(Rc::new(setup));
+        self
+    }
+    /// How many items may be in flight at once. Defaults to 4.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    /// Spaces requests, retries included, evenly to stay under this rate.
+    pub fn with_requests_per_minute(mut self, requests_per_minute: u32) -> Self {
+        self.requests_per_minute = Some(requests_per_minute.max(1));
+        self
+    }
+    /// How many times to resend an item after a retryable error. Defaults
+    /// to 2.
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+    /// The wait before the first retry; it doubles with each one, up to a
+    /// minute. A rate limited response's `retry-after` takes precedence.
+    /// Defaults to one second.
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+    pub fn with_checkpoint(mut self, path: impl AsRef<Path>) -> Self {
+        self.checkpoint = Some(path.as_ref().to_path_buf());
+        self
+    }
+    /// Results come back in the order of `items`.
+    pub async fn run(&self, items: impl IntoIterator<Item = WorkItem>) -> Result<Vec<ItemResult>, Error> {
+        self.run_with_events(items, |_, _| {}).await
+    }
+    pub async fn run_with_events(
+        &self,
+        items: impl IntoIterator<Item = WorkItem>,
+        mut on_event: impl FnMut(&BatchEvent, &BatchProgress),
+    ) -> Result<Vec<ItemResult>, Error> {
+        let items = items.into_iter().collect::<Vec<_>>();
+        let mut finished = match self.checkpoint.as_ref() {
+            Some(path) => read_checkpoint(path)?,
+            None => HashMap::default(),
+        };
+        let mut checkpoint = match self.checkpoint.as_ref() {
+            Some(path) => Some(std::fs::OpenOptions::new().create(true).append(true).open(path)?),
+            None => None,
+        };
+        let progress = RefCell::new(BatchProgress { total: items.len(), ..Default::default() });
+        let on_event = RefCell::new(&mut on_event);
+        let next_slot = RefCell::new(Instant::now());
+        let mut results = Vec::with_capacity(items.len());
+        let mut pending = Vec::default();
+        for (index, item) in items.iter().enumerate() {
+            match finished.remove(&item.id) {
+                Some(mut result) => {
+                    result.resumed = true;
+                    progress.borrow_mut().resumed += 1;
+                    results.push(Some(result));
+                }
+                None => {
+                    results.push(None);
+                    pending.push((index, item));
+                }
+            }
+        }
+        let emit = |event: BatchEvent| {
+            let progress = *progress.borrow();
+            (on_event.borrow_mut())(&event, &progress);
+        };
+        let (next_slot, emit) = (&next_slot, &emit);
+        let mut stream = futures::stream::iter(pending)
+            .map(|(index, item)| async move { (index, self.run_item(item, next_slot, emit).await) })
+            .buffer_unordered(self.concurrency);
+        while let Some((index, result)) = stream.next().await {
+            if let Some(file) = checkpoint.as_mut() {
+                writeln!(file, "{}", serde_json::to_string(&result)?)?;
+                file.flush()?;
+            }
+            match result.is_ok() {
+                true => {
+                    progress.borrow_mut().completed += 1;
+                    emit(BatchEvent::Completed(&result));
+                }
+                false => {
+                    progress.borrow_mut().failed += 1;
+                    emit(BatchEvent::Failed(&result));
+                }
+            }
+            results[index] = Some(result);
+        }
+        Ok(results.into_iter().flatten().collect())
+    }
+    async fn run_item(
+        &self,
+        item: &WorkItem,
+        next_slot: &RefCell<Instant>,
+        emit: &impl Fn(BatchEvent),
+    ) -> ItemResult {
+        let mut result = ItemResult {
+            id: item.id.clone(),
+            message: None,
+            finish_reason: None,
+            usage: None,
+            error: None,
+            attempts: 0,
+            resumed: false,
+        };
+        let request = match self.request(item) {
+            Ok(request) => request,
+            Err(error) => {
+                result.error = Some(error.to_string());
+                return result
+            }
+        };
+        loop {
+            self.wait_for_slot(next_slot).await;
+            result.attempts += 1;
+            let request = request.clone().build().ok_or(MissingRequest);
+            let response = match request {
+                Ok(request) => request.execute().await,
+                Err(error) => Err(Box::new(error) as Error),
+            };
+            match response {
+                Ok(response) => {
+                    result.message = Some(response.message(0));
+                    result.finish_reason = response.finish_reason(0);
+                    result.usage = response.usage();
+                    return result
+                }
+                Err(error) if result.attempts <= self.max_retries && is_retryable(error.as_ref()) => {
+                    let delay = retry_delay(self.backoff, result.attempts, error.as_ref());
+                    let error = error.to_string();
+                    emit(BatchEvent::Retrying { id: &item.id, attempt: result.attempts, delay, error });
+                    tokio::time::sleep(delay).await;
+                }
+                Err(error) => {
+                    result.error = Some(error.to_string());
+                    return result
+                }
+            }
+        }
+    }
+    fn request(&self, item: &WorkItem) -> Result<ChatCompletionsRequestBuilder, Error> {
+        let builder = item.prompt.render_request_builder(&item.variables)?;
+        Ok(match self.setup.as_ref() {
+            Some(setup) => setup(builder),
+            None => builder,
+        })
+    }
+    /// Claims the next free start time under the rate limit and sleeps
+    /// until it.
+    async fn wait_for_slot(&self, next_slot: &RefCell<Instant>) {
+        let Some(requests_per_minute) = self.requests_per_minute else {
+            return
+        };
+        let interval = Duration::from_secs(60) / requests_per_minute;
+        let start = {
+            let mut next_slot = next_slot.borrow_mut();
+            let start = (*next_slot).max(Instant::now());
+            *next_slot = start + interval;
+            start
+        };
+        tokio::time::sleep_until(start.into()).await;
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// BATCH API FILES
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// One line of an input file for OpenAI's Batch API (`/v1/batches`), which
+/// runs requests asynchronously within a day at a discount.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchApiRequest {
+    /// Matches the request to its line in the output file.
+    pub custom_id: String,
+    pub method: String,
+    pub url: String,
+    pub body: ChatCompletionsBody,
+}
+
+impl BatchApiRequest {
+    /// A chat completions request. The batch API does not stream, so the
+    /// stream settings are cleared.
+    pub fn new(custom_id: impl AsRef<str>, mut body: ChatCompletionsBody) -> Self {
+        body.stream = None;
+        body.stream_options = None;
+        BatchApiRequest {
+            custom_id: custom_id.as_ref().to_string(),
+            method: String::from("POST"),
+            url: String::from("/v1/chat/completions"),
+            body,
+        }
+    }
+}
+
+/// Renders `prompt` once per variable set and writes the requests as Batch
+/// API JSONL, using `<prompt name>-<index>` as each line's `custom_id`.
+/// Returns the number of lines written.
+pub fn write_batch_api_requests(
+    writer: impl Write,
+    prompt: &Prompt,
+    variables: impl IntoIterator<Item = liquid::Object>,
+) -> Result<usize, Error> {
+    let name = prompt.name.clone().unwrap_or_else(|| String::from("request"));
+    let items = variables
+        .into_iter()
+        .enumerate()
+        .map(|(index, variables)| WorkItem::new(format!("{name}-{index}"), prompt.clone(), variables));
+    write_batch_api_items(writer, items)
+}
+
+/// Like [`write_batch_api_requests`], using each item's id as its
+/// `custom_id`. Ids must be unique.
+pub fn write_batch_api_items(
+    mut writer: impl Write,
+    items: impl IntoIterator<Item = WorkItem>,
+) -> Result<usize, Error> {
+    let mut seen = std::collections::HashSet::<String>::default();
+    for item in items {
+        if !seen.insert(item.id.clone()) {
+            return Err(Box::new(DuplicateCustomId(item.id)))
+        }
+        let body = item.prompt.render_body(&item.variables)?;
+        let request = BatchApiRequest::new(&item.id, body);
+        writeln!(writer, "{}", serde_json::to_string(&request)?)?;
+    }
+    writer.flush()?;
+    Ok(seen.len())
+}
+
+/// API errors say whether they are worth retrying; a failure to reach the
+/// server at all is.
+pub fn is_retryable(error: &(dyn std::error::Error + 'static)) -> bool {
+    match error.downcast_ref::<ApiError>() {
+        Some(error) => error.is_retryable(),
+        None => error.downcast_ref::<reqwest::Error>().is_some(),
+    }
+}
+
+/// The longest the doubling backoff grows to, unless the first wait is
+/// already longer.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The wait before resending an item: the server's `retry-after` if it sent
+/// one, else `backoff` doubled for each earlier attempt.
+fn retry_delay(backoff: Duration, attempts: usize, error: &(dyn std::error::Error + 'static)) -> Duration {
+    if let Some(delay) = error.downcast_ref::<ApiError>().and_then(ApiError::retry_after) {
+        return delay
+    }
+    let factor = u32::try_from(attempts.saturating_sub(1))
+        .ok()
+        .and_then(|x| 2u32.checked_pow(x))
+        .unwrap_or(u32::MAX);
+    backoff.saturating_mul(factor).min(MAX_BACKOFF.max(backoff))
+}
+
+/// The successful results in a checkpoint file, by id. Unreadable lines,
+/// such as one cut short by a crash, are ignored.
+fn read_checkpoint(path: &Path) -> Result<HashMap<String, ItemResult>, Error> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::default()),
+        Err(error) => return Err(Box::new(error)),
+    };
+    let mut results = HashMap::default();
+    for line in std::io::BufReader::new(file).lines() {
+        let Ok(result) = serde_json::from_str::<ItemResult>(&line?) else {
+            continue
+        };
+        if result.is_ok() {
+            results.insert(result.id.clone(), result);
+        }
+    }
+    Ok(results)
+}
+
+#[derive(Debug, Clone)]
+pub struct MissingRequest;
+impl std::fmt::Display for MissingRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot build a request for the item; it needs an endpoint and a body.")
+    }
+}
+impl std::error::Error for MissingRequest {}
+
+#[derive(Debug, Clone)]
+pub struct DuplicateCustomId(pub String);
+impl std::fmt::Display for DuplicateCustomId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot write batch requests: the custom_id {:?} is used more than once.", self.0)
+    }
+}
+impl std::error::Error for DuplicateCustomId {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::RateLimitMetadata;
+
+    fn rate_limited(retry_after: usize, retry_after_ms: usize) -> Error {
+        Box::new(ApiError::RateLimitError(Some(RateLimitMetadata {
+            retry_after,
+            retry_after_ms,
+            ratelimit_limit_requests: 500,
+            ratelimit_limit_tokens: 30_000,
+            ratelimit_remaining_requests: 0,
+            ratelimit_remaining_tokens: 0,
+            ratelimit_reset_requests: String::from("2s"),
+            ratelimit_reset_tokens: String::from("2s"),
+        })))
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let error: Error = Box::new(ApiError::InternalServerError);
+        let delays = [1, 2, 3, 7, 33, usize::MAX]
+            .map(|attempts| retry_delay(Duration::from_secs(1), attempts, error.as_ref()));
+        let expected = [1, 2, 4, 60, 60, 60].map(Duration::from_secs);
+        assert_eq!(delays, expected);
+        assert_eq!(retry_delay(Duration::from_secs(90), 4, error.as_ref()), Duration::from_secs(90));
+    }
+
+    #[test]
+    fn backoff_honours_retry_after() {
+        let backoff = Duration::from_secs(1);
+        assert_eq!(retry_delay(backoff, 1, rate_limited(7, 0).as_ref()), Duration::from_secs(7));
+        assert_eq!(retry_delay(backoff, 1, rate_limited(7, 6_500).as_ref()), Duration::from_millis(6_500));
+        let error: Error = Box::new(ApiError::RateLimitError(None));
+        assert_eq!(retry_delay(backoff, 2, error.as_ref()), Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,139 @@
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use futures::StreamExt;
+use serde_json::json;
+
+use chatgpt_subsystems::batch::is_retryable;
+use chatgpt_subsystems::client::{ChatCompletionsResponse, Error, Usage};
+use chatgpt_subsystems::pricing;
+use chatgpt_subsystems::xml_dsl::{Prompt, PromptCollection, PromptNotFound};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// The XML prompt file.
+    file: PathBuf,
+    /// The name of the prompt to run.
+    prompt: String,
+    /// JSONL with one object of template variables per line.
+    #[arg(long)]
+    input: PathBuf,
+    /// Where to write one result per input line, in input order.
+    #[arg(long)]
+    output: PathBuf,
+    #[arg(long, default_value_t = 4)]
+    concurrency: usize,
+    /// How many times to retry a row after a retryable error.
+    #[arg(long, default_value_t = 2)]
+    retries: usize,
+    #[command(flatten)]
+    connection: crate::Connection,
+}
+
+pub async fn run(mut args: Args) -> Result<(), Error> {
+    args.connection = args.connection.resolve()?;
+    let collection = PromptCollection::open(&args.file)?;
+    let prompt = collection
+        .get(&args.prompt)
+        .ok_or_else(|| Box::new(PromptNotFound(args.prompt.clone())))?;
+    let prompt = args.connection.with_default_model(prompt);
+    let lines = std::io::BufReader::new(std::fs::File::open(&args.input)?)
+        .lines()
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut output = std::io::BufWriter::new(std::fs::File::create(&args.output)?);
+    let rows = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| run_row(&args, &prompt, index + 1, line));
+    let mut results = futures::stream::iter(rows).buffered(args.concurrency.max(1));
+    let (mut total, mut failed) = (0, 0);
+    let mut usage = Usage::default();
+    let mut cost = 0.0;
+    while let Some(result) = results.next().await {
+        total += 1;
+        if let Some(row_usage) = result.usage {
+            usage.prompt_tokens += row_usage.prompt_tokens;
+            usage.completion_tokens += row_usage.completion_tokens;
+            usage.total_tokens += row_usage.total_tokens;
+            cost += pricing::cost(&result.model, &row_usage).unwrap_or_default();
+        }
+        if result.record["error"].is_string() {
+            failed += 1;
+        }
+        writeln!(output, "{}", result.record)?;
+        output.flush()?;
+    }
+    eprintln!(
+        "{total} rows, {failed} failed; {} prompt + {} completion tokens, ${cost:.6}",
+        usage.prompt_tokens,
+        usage.completion_tokens,
+    );
+    Ok(())
+}
+
+struct RowResult {
+    record: serde_json::Value,
+    model: String,
+    usage: Option<Usage>,
+}
+
+async fn run_row(args: &Args, prompt: &Prompt, line: usize, text: &str) -> RowResult {
+    let vars = serde_json::from_str::<serde_json::Value>(text);
+    let mut attempts = 0;
+    let result = match vars.as_ref() {
+        Ok(vars) => loop {
+            attempts += 1;
+            let result = send(args, prompt, vars).await;
+            match result.as_ref() {
+                Err(error) if attempts <= args.retries && is_retryable(error.as_ref()) => {
+                    tokio::time::sleep(Duration::from_secs(1 << (attempts - 1))).await;
+                }
+                _ => break result,
+            }
+        },
+        Err(error) => Err(format!("Invalid JSON on line {line}: {error}.").into()),
+    };
+    let vars = vars.unwrap_or_default();
+    match result {
+        Ok(response) => {
+            let model = response.output.first().map(|x| x.model.clone()).unwrap_or_default();
+            let usage = response.usage();
+            let record = json!({
+                "line": line,
+                "vars": vars,
+                "output": response.content(0),
+                "finish_reason": response.finish_reason(0),
+                "usage": usage,
+                "attempts": attempts,
+                "error": null,
+            });
+            RowResult { record, model, usage }
+        }
+        Err(error) => {
+            let record = json!({
+                "line": line,
+                "vars": vars,
+                "output": null,
+                "usage": null,
+                "attempts": attempts,
+                "error": error.to_string(),
+            });
+            RowResult { record, model: String::default(), usage: None }
+        }
+    }
+}
+
+async fn send(args: &Args, prompt: &Prompt, vars: &serde_json::Value) -> Result<ChatCompletionsResponse, Error> {
+    let globals = match liquid::model::to_value(vars)? {
+        liquid::model::Value::Object(globals) => globals,
+        _ => return Err("Each input line must be a JSON object.".into()),
+    };
+    let mut builder = prompt.render_request_builder(&globals)?;
+    builder.body = builder.body.map(|x| x.with_stream_usage());
+    let request = args.connection
+        .apply(builder)
+        .build()
+        .ok_or("Cannot build a request from the prompt.")?;
+    request.execute().await
+}
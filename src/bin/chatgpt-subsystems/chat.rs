@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+use colored::Colorize;
+use tokio::io::AsyncBufReadExt;
+
+use chatgpt_subsystems::client::{ChatCompletionsBody, ChatCompletionsRequestBuilder, Error, Message, Role};
+
+const HELP: &str = "\
+/reset          start over, keeping the system prompt
+/save <path>    write the conversation to a JSON file
+/model [name]   show or switch the model
+/exit           quit (or press Ctrl-D)";
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Defaults to the profile's model, then gpt-4o-mini.
+    #[arg(long)]
+    model: Option<String>,
+    /// Sent as the first message of every conversation.
+    #[arg(long)]
+    system: Option<String>,
+    #[command(flatten)]
+    connection: crate::Connection,
+}
+
+pub async fn run(mut args: Args) -> Result<(), Error> {
+    args.connection = args.connection.resolve()?;
+    let mut model = args.model
+        .clone()
+        .or_else(|| args.connection.model.clone())
+        .unwrap_or_else(|| String::from("gpt-4o-mini"));
+    let initial = args.system
+        .iter()
+        .map(|x| Message::new(Role::System, x))
+        .collect::<Vec<_>>();
+    let mut messages = initial.clone();
+    let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    eprintln!("{}", format!("Chatting with {model}; /help for commands.").dimmed());
+    loop {
+        eprint!("{} ", ">".bold());
+        let Some(line) = lines.next_line().await? else {
+            break
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue
+        }
+        if let Some(command) = line.strip_prefix('/') {
+            let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+            let argument = argument.trim();
+            match name {
+                "exit" | "quit" => break,
+                "help" => eprintln!("{HELP}"),
+                "reset" => {
+                    messages = initial.clone();
+                    eprintln!("{}", "Conversation cleared.".dimmed());
+                }
+                "save" if !argument.is_empty() => match save(&messages, argument) {
+                    Ok(()) => eprintln!("{}", format!("Saved to {argument}.").dimmed()),
+                    Err(error) => eprintln!("error: {error}"),
+                },
+                "model" if !argument.is_empty() => {
+                    model = argument.to_string();
+                    eprintln!("{}", format!("Switched to {model}.").dimmed());
+                }
+                "model" => eprintln!("{model}"),
+                _ => eprintln!("{HELP}"),
+            }
+            continue
+        }
+        messages.push(Message::new(Role::User, line));
+        let body = ChatCompletionsBody::new(&model, messages.clone()).with_stream_usage();
+        let request = args.connection
+            .apply(ChatCompletionsRequestBuilder::default().with_body(body))
+            .with_logger_closure(crate::print_delta)
+            .build()
+            .ok_or("Cannot build the request.")?;
+        match request.execute().await {
+            Ok(response) => {
+                println!();
+                messages.push(response.message(0));
+                if let Some(usage) = response.usage() {
+                    crate::print_usage(&model, &usage);
+                }
+            }
+            Err(error) => {
+                // Drop the turn so it can be retried.
+                messages.pop();
+                eprintln!("error: {error}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn save(messages: &[Message], path: impl Into<PathBuf>) -> Result<(), Error> {
+    std::fs::write(path.into(), serde_json::to_string_pretty(messages)?)?;
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use clap::{Parser, Subcommand};
+use colored::Colorize;
+
+use chatgpt_subsystems::client::{ApiEndpoint, ChatCompletionsRequestBuilder, Error, MissingEnvVar, Usage};
+use chatgpt_subsystems::config::Config;
+use chatgpt_subsystems::pricing;
+use chatgpt_subsystems::xml_dsl::Prompt;
+
+mod batch;
+mod chat;
+mod report;
+mod run;
+mod validate;
+
+/// Runs prompts defined in XML prompt files.
+#[derive(Parser)]
+#[command(name = "chatgpt-subsystems", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a prompt and stream the reply to stdout.
+    Run(run::Args),
+    /// Chat interactively, streaming each reply.
+    Chat(chat::Args),
+    /// Run a prompt once per line of a JSONL file of variables.
+    Batch(batch::Args),
+    /// Check prompt files, printing diagnostics and failing on errors.
+    Validate(validate::Args),
+    /// Summarize tokens and cost from audit logs.
+    Report(report::Args),
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> std::process::ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Run(args) => run::run(args).await,
+        Command::Chat(args) => chat::run(args).await,
+        Command::Batch(args) => batch::run(args).await,
+        Command::Validate(args) => validate::run(args).await,
+        Command::Report(args) => report::run(args).await,
+    };
+    match result {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// SHARED
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(clap::Args)]
+pub struct Connection {
+    /// Defaults to the profile's key, then `OPENAI_API_KEY`.
+    #[arg(long)]
+    api_key: Option<String>,
+    /// For OpenAI compatible APIs; overrides any endpoint a prompt sets.
+    /// Defaults to the profile's, then `OPENAI_BASE_URL`.
+    #[arg(long)]
+    base_url: Option<String>,
+    /// A profile from the config file; defaults to the file's `default`.
+    #[arg(long, env = "CHATGPT_SUBSYSTEMS_PROFILE")]
+    profile: Option<String>,
+    #[arg(skip)]
+    organization: Option<String>,
+    #[arg(skip)]
+    explicit_nulls: bool,
+    /// The profile's model, for prompts that do not specify one.
+    #[arg(skip)]
+    model: Option<String>,
+}
+
+impl Connection {
+    /// Fills in what the flags leave unset from the profile if one is
+    /// selected, otherwise from the `OPENAI_*` environment variables.
+    pub fn resolve(mut self) -> Result<Self, Error> {
+        let config = Config::load()?;
+        let profile = match self.profile.as_ref() {
+            Some(name) => Some(config.profile(name)?),
+            None => config.default_profile()?,
+        };
+        let env = |name: &str| std::env::var(name).ok().filter(|x| !x.trim().is_empty());
+        match profile {
+            Some(profile) => {
+                self.base_url = self.base_url.or_else(|| profile.base_url.clone());
+                self.organization = profile.organization.clone();
+                self.explicit_nulls = profile.explicit_nulls;
+                self.model = profile.model.clone();
+                if self.api_key.is_none() {
+                    self.api_key = Some(profile.api_key()?);
+                }
+            }
+            None => {
+                self.base_url = self.base_url.or_else(|| env("OPENAI_BASE_URL"));
+                self.organization = env("OPENAI_ORG_ID");
+                if self.api_key.is_none() {
+                    self.api_key = Some(env("OPENAI_API_KEY").ok_or_else(|| MissingEnvVar(String::from("OPENAI_API_KEY")))?);
+                }
+            }
+        }
+        Ok(self)
+    }
+    /// Points the builder at the base URL if one was given, then sets the
+    /// key, falling back to OpenAI if no endpoint is set.
+    pub fn apply(&self, mut builder: ChatCompletionsRequestBuilder) -> ChatCompletionsRequestBuilder {
+        if let Some(base_url) = self.base_url.as_ref() {
+            builder = builder.with_api_endpoint(ApiEndpoint::from_base_url(base_url, ""));
+        }
+        let mut builder = builder.with_api_key(self.api_key.as_deref().unwrap_or_default());
+        if let Some(api_endpoint) = builder.api_endpoint.as_mut() {
+            api_endpoint.organization = self.organization.clone();
+            api_endpoint.explicit_nulls = self.explicit_nulls;
+        }
+        builder
+    }
+    /// Gives the prompt the profile's model if it does not specify one.
+    pub fn with_default_model(&self, prompt: Prompt) -> Prompt {
+        match self.model.as_ref() {
+            Some(model) if prompt.effective_configuration().model.is_none() => prompt.override_model(model),
+            _ => prompt,
+        }
+    }
+}
+
+/// Parses `key=value` for `--var`.
+fn parse_var(text: &str) -> Result<(String, String), String> {
+    let (key, value) = text
+        .split_once('=')
+        .ok_or_else(|| format!("expected key=value, got {text:?}"))?;
+    Ok((key.trim().to_string(), value.to_string()))
+}
+
+fn globals(vars: &[(String, String)]) -> liquid::Object {
+    let mut globals = liquid::Object::new();
+    for (key, value) in vars {
+        globals.insert(key.clone().into(), liquid::model::Value::scalar(value.clone()));
+    }
+    globals
+}
+
+/// Writes token counts and cost to stderr.
+fn print_usage(model: &str, usage: &Usage) {
+    let cost = match pricing::cost(model, usage) {
+        Some(cost) => format!("${cost:.6}"),
+        None => String::from("unknown cost"),
+    };
+    let summary = format!(
+        "{model}: {} prompt ({} cached) + {} completion = {} tokens, {cost}",
+        usage.prompt_tokens,
+        usage.cached_tokens(),
+        usage.completion_tokens,
+        usage.total_tokens,
+    );
+    eprintln!("{}", summary.dimmed());
+}
+
+/// Writes a delta to stdout as soon as it arrives.
+fn print_delta(delta: &str) {
+    use std::io::Write;
+    let mut stdout = std::io::stdout().lock();
+    let _ = stdout.write_all(delta.as_bytes());
+    let _ = stdout.flush();
+}
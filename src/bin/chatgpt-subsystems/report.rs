@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::path::PathBuf;
+use chrono::{DateTime, NaiveDate, Utc};
+
+use chatgpt_subsystems::audit::AuditRecord;
+use chatgpt_subsystems::client::Error;
+use chatgpt_subsystems::pricing;
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum GroupBy {
+    Prompt,
+    Model,
+    PromptModel,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Audit logs in JSONL, as written by `AuditLog::jsonl`.
+    #[arg(required = true)]
+    logs: Vec<PathBuf>,
+    /// Only records at or after this time, as RFC 3339 or YYYY-MM-DD (UTC).
+    #[arg(long, value_parser = parse_time)]
+    since: Option<DateTime<Utc>>,
+    /// Only records before this time, as RFC 3339 or YYYY-MM-DD (UTC).
+    #[arg(long, value_parser = parse_time)]
+    until: Option<DateTime<Utc>>,
+    #[arg(long, value_enum, default_value_t = GroupBy::PromptModel)]
+    group_by: GroupBy,
+}
+
+#[derive(Default)]
+struct Row {
+    requests: usize,
+    errors: usize,
+    prompt_tokens: usize,
+    cached_tokens: usize,
+    completion_tokens: usize,
+    cost: f64,
+    /// Requests whose model has no known price.
+    unpriced: usize,
+}
+
+pub async fn run(args: Args) -> Result<(), Error> {
+    let mut rows = BTreeMap::<String, Row>::default();
+    let mut total = Row::default();
+    for path in args.logs.iter() {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        for (index, line) in file.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue
+            }
+            let record = serde_json::from_str::<AuditRecord>(&line)
+                .map_err(|error| format!("Cannot read {}:{}: {error}.", path.display(), index + 1))?;
+            let timestamp = DateTime::parse_from_rfc3339(&record.timestamp)?.with_timezone(&Utc);
+            if args.since.is_some_and(|x| timestamp < x) || args.until.is_some_and(|x| timestamp >= x) {
+                continue
+            }
+            let prompt = record.prompt.as_deref().unwrap_or("<none>");
+            let key = match args.group_by {
+                GroupBy::Prompt => prompt.to_string(),
+                GroupBy::Model => record.model.clone(),
+                GroupBy::PromptModel => format!("{prompt} / {}", record.model),
+            };
+            add(rows.entry(key).or_default(), &record);
+            add(&mut total, &record);
+        }
+    }
+    let mut table = vec![[
+        String::from("group"),
+        String::from("requests"),
+        String::from("errors"),
+        String::from("prompt"),
+        String::from("cached"),
+        String::from("completion"),
+        String::from("cost (USD)"),
+    ]];
+    table.extend(rows.iter().map(|(key, row)| cells(key, row)));
+    table.push(cells("total", &total));
+    let widths = (0..7)
+        .map(|column| table.iter().map(|x| x[column].chars().count()).max().unwrap_or_default())
+        .collect::<Vec<_>>();
+    for (index, cells) in table.iter().enumerate() {
+        if index == table.len() - 1 {
+            println!("{}", "-".repeat(widths.iter().sum::<usize>() + 2 * 6));
+        }
+        let line = cells
+            .iter()
+            .zip(widths.iter())
+            .enumerate()
+            .map(|(column, (cell, width))| match column {
+                0 => format!("{cell:<width$}"),
+                _ => format!("{cell:>width$}"),
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+    if total.unpriced > 0 {
+        eprintln!("{} request(s) used models with no known price and are not costed.", total.unpriced);
+    }
+    Ok(())
+}
+
+fn add(row: &mut Row, record: &AuditRecord) {
+    row.requests += 1;
+    if record.error.is_some() {
+        row.errors += 1;
+    }
+    let Some(usage) = record.usage.as_ref() else {
+        return
+    };
+    row.prompt_tokens += usage.prompt_tokens;
+    row.cached_tokens += usage.cached_tokens();
+    row.completion_tokens += usage.completion_tokens;
+    match pricing::cost(&record.model, usage) {
+        Some(cost) => row.cost += cost,
+        None => row.unpriced += 1,
+    }
+}
+
+fn cells(key: &str, row: &Row) -> [String; 7] {
+    [
+        key.to_string(),
+        row.requests.to_string(),
+        row.errors.to_string(),
+        row.prompt_tokens.to_string(),
+        row.cached_tokens.to_string(),
+        row.completion_tokens.to_string(),
+        format!("{:.4}", row.cost),
+    ]
+}
+
+fn parse_time(text: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Utc))
+    }
+    let date = NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map_err(|_| format!("expected RFC 3339 or YYYY-MM-DD, got {text:?}"))?;
+    Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+}
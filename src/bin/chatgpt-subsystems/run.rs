@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use chatgpt_subsystems::client::Error;
+use chatgpt_subsystems::xml_dsl::{PromptCollection, PromptNotFound};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// The XML prompt file.
+    file: PathBuf,
+    /// The name of the prompt to run.
+    prompt: String,
+    /// A template variable, as key=value; repeatable.
+    #[arg(long = "var", value_parser = crate::parse_var)]
+    vars: Vec<(String, String)>,
+    /// Print the request, with the API key redacted, instead of sending it.
+    #[arg(long)]
+    dry_run: bool,
+    #[command(flatten)]
+    connection: crate::Connection,
+}
+
+pub async fn run(args: Args) -> Result<(), Error> {
+    let connection = args.connection.resolve()?;
+    let collection = PromptCollection::open(&args.file)?;
+    let prompt = collection
+        .get(&args.prompt)
+        .ok_or_else(|| Box::new(PromptNotFound(args.prompt.clone())))?;
+    let prompt = connection.with_default_model(prompt);
+    let mut builder = prompt.render_request_builder(&crate::globals(&args.vars))?;
+    builder.body = builder.body.map(|x| x.with_stream_usage());
+    let request = connection
+        .apply(builder)
+        .with_logger_closure(crate::print_delta)
+        .build()
+        .ok_or("Cannot build a request from the prompt.")?;
+    if args.dry_run {
+        println!("{}", request.dry_run()?);
+        return Ok(())
+    }
+    let response = request.execute().await?;
+    println!();
+    if let Some(usage) = response.usage() {
+        let model = response.output.first().map(|x| x.model.as_str()).unwrap_or(&request.body.model);
+        crate::print_usage(model, &usage);
+    }
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use std::path::{Path, PathBuf};
+
+use chatgpt_subsystems::client::Error;
+use chatgpt_subsystems::schema::validate_against_schema;
+use chatgpt_subsystems::validate::{DiagnosticKind, Diagnostics};
+use chatgpt_subsystems::xml_dsl::{escape_glob, is_prompt_file, PromptCollection};
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Prompt files, or directories to search recursively.
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+    /// Also run the style and cost lints.
+    #[arg(long)]
+    lint: bool,
+    /// Fail on warnings as well as errors.
+    #[arg(long)]
+    deny_warnings: bool,
+}
+
+pub async fn run(args: Args) -> Result<(), Error> {
+    let mut files = Vec::default();
+    for path in args.paths.iter() {
+        files.extend(prompt_files(path)?);
+    }
+    let mut diagnostics = Vec::default();
+    let mut prompts = Vec::default();
+    for file in files.iter() {
+        // YAML and JSON files have no schema to check beyond what loading does.
+        if file.extension().is_some_and(|x| x == "yaml" || x == "yml" || x == "json") {
+            prompts.extend(PromptCollection::open_any(file)?.prompts().to_vec());
+            continue
+        }
+        let source = std::fs::read_to_string(file)?;
+        let schema = validate_against_schema(&source).0;
+        let malformed = schema.iter().any(|x| x.kind == DiagnosticKind::MalformedXml);
+        diagnostics.extend(schema.into_iter().map(|mut x| {
+            x.source_file = Some(file.clone());
+            x
+        }));
+        if !malformed {
+            prompts.extend(PromptCollection::parse(source)?.with_source_file(file).prompts().to_vec());
+        }
+    }
+    let collection = PromptCollection::from_prompts(prompts);
+    diagnostics.extend(collection.validate().0);
+    if args.lint {
+        diagnostics.extend(collection.lint().0);
+    }
+    // The schema check and the parser both report unknown attributes.
+    let mut seen = Vec::default();
+    diagnostics.retain(|x| {
+        let key = (x.source_file.clone(), x.position, x.kind, x.prompt.clone());
+        let duplicate = seen.contains(&key);
+        seen.push(key);
+        !duplicate
+    });
+    let diagnostics = Diagnostics(diagnostics);
+    println!("{diagnostics}");
+    let failed = !diagnostics.is_ok() || (args.deny_warnings && diagnostics.warnings().next().is_some());
+    match failed {
+        true => Err("Validation failed.".into()),
+        false => Ok(()),
+    }
+}
+
+fn prompt_files(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()])
+    }
+    let pattern = escape_glob(path).join("**").join("*");
+    let mut files = glob::glob(&pattern.to_string_lossy())?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter(|x| x.is_file())
+        .filter(|x| is_prompt_file(x))
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
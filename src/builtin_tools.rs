@@ -0,0 +1,390 @@
+use std::path::{Path, PathBuf};
+use futures::future::LocalBoxFuture;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::client::Error;
+use crate::tools::{Tool, ToolDefinition, ToolRegistry};
+
+/// Longest body or file returned to the model, in characters.
+pub const MAX_OUTPUT_CHARS: usize = 20_000;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// REGISTRATION
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+impl ToolRegistry {
+    /// Adds [`HttpGet`], limited to the given hosts and their subdomains.
+    pub fn with_http_get<S: AsRef<str>>(self, allowed_hosts: impl IntoIterator<Item = S>) -> Self {
+        self.with_tool(HttpGet::new(allowed_hosts))
+    }
+    /// Adds [`Calculator`].
+    pub fn with_calculator(self) -> Self {
+        self.with_tool(Calculator)
+    }
+    /// Adds [`CurrentTime`].
+    pub fn with_current_time(self) -> Self {
+        self.with_tool(CurrentTime)
+    }
+    /// Adds [`ReadFile`], limited to files under `root`.
+    pub fn with_read_file(self, root: impl AsRef<Path>) -> Self {
+        self.with_tool(ReadFile::new(root))
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// HTTP GET
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// `http_get`: fetches a URL and returns the status and body as text.
+#[derive(Debug, Clone)]
+pub struct HttpGet {
+    allowed_hosts: Vec<String>,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct HttpGetArguments {
+    url: String,
+}
+
+impl HttpGet {
+    pub fn new<S: AsRef<str>>(allowed_hosts: impl IntoIterator<Item = S>) -> Self {
+        let allowed_hosts = allowed_hosts
+            .into_iter()
+            .map(|x| x.as_ref().to_lowercase())
+            .collect();
+        HttpGet { allowed_hosts, client: reqwest::Client::new() }
+    }
+    fn is_allowed(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        self.allowed_hosts.iter().any(|allowed| {
+            host == *allowed || host.ends_with(&format!(".{allowed}"))
+        })
+    }
+}
+
+impl Tool for HttpGet {
+    fn definition(&self) -> ToolDefinition {
+        let parameters = json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string", "description": "An absolute http or https URL." }
+            },
+            "required": ["url"],
+            "additionalProperties": false,
+        });
+        let description = format!(
+            "Fetches a web page and returns its text. Only these hosts are allowed: {}.",
+            self.allowed_hosts.join(", "),
+        );
+        ToolDefinition::function("http_get", parameters).with_description(description)
+    }
+    fn call(&self, arguments: &str) -> LocalBoxFuture<'static, Result<serde_json::Value, Error>> {
+        let this = self.clone();
+        let arguments = serde_json::from_str::<HttpGetArguments>(arguments);
+        Box::pin(async move {
+            let url = reqwest::Url::parse(&arguments?.url)?;
+            let host = url.host_str().unwrap_or_default();
+            if !matches!(url.scheme(), "http" | "https") || !this.is_allowed(host) {
+                return Err(Box::new(HostNotAllowed(url.to_string())) as Error)
+            }
+            let response = this.client.get(url).send().await?;
+            let status = response.status().as_u16();
+            let body = response.text().await?;
+            Ok(json!({ "status": status, "body": truncate(body) }))
+        })
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// CALCULATOR
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// `calculator`: evaluates an arithmetic expression.
+///
+/// Supports `+ - * / % ^`, parentheses, `pi`, `e`, and the functions `sqrt`,
+/// `abs`, `ln`, `log10`, `exp`, `sin`, `cos`, `tan`, `floor`, `ceil` and
+/// `round`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Calculator;
+
+#[derive(Deserialize)]
+struct CalculatorArguments {
+    expression: String,
+}
+
+impl Tool for Calculator {
+    fn definition(&self) -> ToolDefinition {
+        let parameters = json!({
+            "type": "object",
+            "properties": {
+                "expression": { "type": "string", "description": "For example `2 * (3 + 4) ^ 2`." }
+            },
+            "required": ["expression"],
+            "additionalProperties": false,
+        });
+        ToolDefinition::function("calculator", parameters)
+            .with_description("Evaluates an arithmetic expression exactly, instead of estimating it.")
+    }
+    fn call(&self, arguments: &str) -> LocalBoxFuture<'static, Result<serde_json::Value, Error>> {
+        let result = serde_json::from_str::<CalculatorArguments>(arguments)
+            .map_err(|e| Box::new(e) as Error)
+            .and_then(|arguments| Ok(json!(evaluate(&arguments.expression)?)));
+        Box::pin(futures::future::ready(result))
+    }
+}
+
+/// Evaluates an expression as described on [`Calculator`].
+pub fn evaluate(expression: &str) -> Result<f64, InvalidExpression> {
+    let mut parser = Parser { source: expression, chars: expression.char_indices().peekable() };
+    let value = parser.expression()?;
+    parser.skip_whitespace();
+    match parser.chars.peek() {
+        Some(&(offset, c)) => Err(invalid(offset, format!("unexpected '{c}'"))),
+        None if value.is_finite() => Ok(value),
+        None => Err(invalid(expression.len(), String::from("result is not a finite number"))),
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    // expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<f64, InvalidExpression> {
+        let mut value = self.term()?;
+        while let Some(op) = self.eat_any(&['+', '-']) {
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+    // term := unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Result<f64, InvalidExpression> {
+        let mut value = self.unary()?;
+        while let Some(op) = self.eat_any(&['*', '/', '%']) {
+            let rhs = self.unary()?;
+            value = match op {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Ok(value)
+    }
+    // unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<f64, InvalidExpression> {
+        match self.eat_any(&['-', '+']) {
+            Some('-') => Ok(-self.unary()?),
+            Some(_) => self.unary(),
+            None => self.power(),
+        }
+    }
+    // power := atom ('^' unary)?, right associative
+    fn power(&mut self) -> Result<f64, InvalidExpression> {
+        let base = self.atom()?;
+        match self.eat_any(&['^']) {
+            Some(_) => Ok(base.powf(self.unary()?)),
+            None => Ok(base),
+        }
+    }
+    // atom := number | '(' expression ')' | name | name '(' expression ')'
+    fn atom(&mut self) -> Result<f64, InvalidExpression> {
+        self.skip_whitespace();
+        let Some(&(start, c)) = self.chars.peek() else {
+            return Err(invalid(self.source.len(), String::from("unexpected end of expression")))
+        };
+        if c == '(' {
+            self.chars.next();
+            let value = self.expression()?;
+            return match self.eat_any(&[')']) {
+                Some(_) => Ok(value),
+                None => Err(invalid(self.offset(), String::from("expected ')'"))),
+            }
+        }
+        if c.is_ascii_digit() || c == '.' {
+            let end = self.take_while(|c| c.is_ascii_digit() || c == '.');
+            return self.source[start..end]
+                .parse::<f64>()
+                .map_err(|_| invalid(start, format!("invalid number {:?}", &self.source[start..end])))
+        }
+        if c.is_ascii_alphabetic() {
+            let end = self.take_while(|c| c.is_ascii_alphanumeric());
+            let name = &self.source[start..end];
+            match name {
+                "pi" => return Ok(std::f64::consts::PI),
+                "e" => return Ok(std::f64::consts::E),
+                _ => {}
+            }
+            let function: fn(f64) -> f64 = match name {
+                "sqrt" => f64::sqrt,
+                "abs" => f64::abs,
+                "ln" => f64::ln,
+                "log10" => f64::log10,
+                "exp" => f64::exp,
+                "sin" => f64::sin,
+                "cos" => f64::cos,
+                "tan" => f64::tan,
+                "floor" => f64::floor,
+                "ceil" => f64::ceil,
+                "round" => f64::round,
+                _ => return Err(invalid(start, format!("unknown name {name:?}"))),
+            };
+            if self.eat_any(&['(']).is_none() {
+                return Err(invalid(self.offset(), format!("expected '(' after {name:?}")))
+            }
+            let argument = self.expression()?;
+            if self.eat_any(&[')']).is_none() {
+                return Err(invalid(self.offset(), String::from("expected ')'")))
+            }
+            return Ok(function(argument))
+        }
+        Err(invalid(start, format!("unexpected '{c}'")))
+    }
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+    fn eat_any(&mut self, options: &[char]) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.next_if(|(_, c)| options.contains(c)).map(|(_, c)| c)
+    }
+    /// Consumes matching characters, returning the offset after them.
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> usize {
+        while self.chars.next_if(|&(_, c)| f(c)).is_some() {}
+        self.offset()
+    }
+    fn offset(&mut self) -> usize {
+        self.chars.peek().map(|(offset, _)| *offset).unwrap_or(self.source.len())
+    }
+}
+
+fn invalid(offset: usize, message: String) -> InvalidExpression {
+    InvalidExpression { offset, message }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// CURRENT TIME
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// `current_time`: the current date and time, in UTC and local time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CurrentTime;
+
+impl Tool for CurrentTime {
+    fn definition(&self) -> ToolDefinition {
+        let parameters = json!({
+            "type": "object",
+            "properties": {},
+            "additionalProperties": false,
+        });
+        ToolDefinition::function("current_time", parameters)
+            .with_description("Returns the current date and time.")
+    }
+    fn call(&self, _: &str) -> LocalBoxFuture<'static, Result<serde_json::Value, Error>> {
+        let now = chrono::Utc::now();
+        let local = now.with_timezone(&chrono::Local);
+        let output = json!({
+            "utc": now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            "local": local.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            "weekday": local.format("%A").to_string(),
+            "unix": now.timestamp(),
+        });
+        Box::pin(futures::future::ready(Ok(output)))
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// READ FILE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// `read_file`: reads a text file under a sandbox directory.
+///
+/// Paths are resolved relative to the root and rejected if they lead
+/// outside it, including through `..` or symlinks.
+#[derive(Debug, Clone)]
+pub struct ReadFile {
+    root: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct ReadFileArguments {
+    path: String,
+}
+
+impl ReadFile {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        ReadFile { root: root.as_ref().to_path_buf() }
+    }
+    fn resolve(&self, path: &str) -> Result<PathBuf, Error> {
+        let root = self.root.canonicalize()?;
+        let path = root.join(path.trim_start_matches('/')).canonicalize()?;
+        if !path.starts_with(&root) {
+            return Err(Box::new(OutsideSandbox(path)))
+        }
+        Ok(path)
+    }
+}
+
+impl Tool for ReadFile {
+    fn definition(&self) -> ToolDefinition {
+        let parameters = json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "A path relative to the sandbox directory." }
+            },
+            "required": ["path"],
+            "additionalProperties": false,
+        });
+        ToolDefinition::function("read_file", parameters)
+            .with_description("Reads a text file from the sandbox directory.")
+    }
+    fn call(&self, arguments: &str) -> LocalBoxFuture<'static, Result<serde_json::Value, Error>> {
+        let path = serde_json::from_str::<ReadFileArguments>(arguments)
+            .map_err(|e| Box::new(e) as Error)
+            .and_then(|arguments| self.resolve(&arguments.path));
+        Box::pin(async move {
+            let contents = tokio::fs::read_to_string(path?).await?;
+            Ok(json!(truncate(contents)))
+        })
+    }
+}
+
+fn truncate(mut text: String) -> String {
+    if let Some((offset, _)) = text.char_indices().nth(MAX_OUTPUT_CHARS) {
+        text.truncate(offset);
+        text.push_str("\n[truncated]");
+    }
+    text
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// ERRORS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Debug, Clone)]
+pub struct HostNotAllowed(pub String);
+impl std::fmt::Display for HostNotAllowed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot fetch {}: the host is not on the allowlist.", self.0)
+    }
+}
+impl std::error::Error for HostNotAllowed {}
+
+#[derive(Debug, Clone)]
+pub struct OutsideSandbox(pub PathBuf);
+impl std::fmt::Display for OutsideSandbox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot read {}: the path is outside the sandbox directory.", self.0.display())
+    }
+}
+impl std::error::Error for OutsideSandbox {}
+
+#[derive(Debug, Clone)]
+pub struct InvalidExpression {
+    /// Byte offset into the expression.
+    pub offset: usize,
+    pub message: String,
+}
+impl std::fmt::Display for InvalidExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid expression at offset {}: {}.", self.offset, self.message)
+    }
+}
+impl std::error::Error for InvalidExpression {}
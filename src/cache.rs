@@ -0,0 +1,220 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use futures::future::LocalBoxFuture;
+use sha2::Digest;
+
+use crate::client::{ChatCompletionsBody, Error};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// KEYS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// SHA-256, in hex, of the body with the `stream` flag and unset fields
+/// removed and object keys sorted, so requests that differ only in those
+/// share a key.
+pub fn cache_key(body: &ChatCompletionsBody) -> Result<String, Error> {
+    let mut value = serde_json::to_value(body)?;
+    if let Some(object) = value.as_object_mut() {
+        object.remove("stream");
+    }
+    let canonical = canonicalize(value).to_string();
+    Ok(format!("{:x}", sha2::Sha256::digest(canonical.as_bytes())))
+}
+
+fn canonicalize(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries = map
+                .into_iter()
+                .filter(|(_, x)| !x.is_null())
+                .map(|(key, x)| (key, canonicalize(x)))
+                .collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            serde_json::Value::Object(entries.into_iter().collect())
+        }
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.into_iter().map(canonicalize).collect())
+        }
+        value => value,
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// CACHE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Stores responses by [`cache_key`]. Attach any number with
+/// [`crate::client::ChatCompletionsRequestBuilder::with_cache`].
+///
+/// Values are opaque to the cache: a response's chunks serialized as JSON.
+/// Implement this to keep them somewhere else, such as Redis or S3; the
+/// client treats errors from a cache as misses and never fails a request
+/// because of one.
+pub trait Cache {
+    fn get<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Result<Option<String>, Error>>;
+    fn put<'a>(&'a self, key: &'a str, value: &'a str) -> LocalBoxFuture<'a, Result<(), Error>>;
+}
+
+impl<T: Cache + ?Sized> Cache for Rc<T> {
+    fn get<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Result<Option<String>, Error>> {
+        (**self).get(key)
+    }
+    fn put<'a>(&'a self, key: &'a str, value: &'a str) -> LocalBoxFuture<'a, Result<(), Error>> {
+        (**self).put(key, value)
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// MEMORY CACHE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Keeps responses for the life of the process. Cloning shares the
+/// entries, so one cache can serve many requests.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryCache {
+    entries: Rc<RefCell<HashMap<String, String>>>,
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+    pub fn clear(&self) {
+        self.entries.borrow_mut().clear();
+    }
+}
+
+impl Cache for MemoryCache {
+    fn get<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Result<Option<String>, Error>> {
+        let value = self.entries.borrow().get(key).cloned();
+        Box::pin(futures::future::ready(Ok(value)))
+    }
+    fn put<'a>(&'a self, key: &'a str, value: &'a str) -> LocalBoxFuture<'a, Result<(), Error>> {
+        self.entries.borrow_mut().insert(key.to_string(), value.to_string());
+        Box::pin(futures::future::ready(Ok(())))
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// DISK CACHE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Keeps responses as JSON files named by [`cache_key`] in a directory, so
+/// they survive restarts.
+///
+/// Entries older than the TTL are treated as missing and removed when
+/// read. When a write takes the directory over the size limit, the oldest
+/// entries are removed until it fits.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+    ttl: Option<Duration>,
+    max_bytes: Option<u64>,
+}
+
+impl DiskCache {
+    /// Creates `dir` if needed. Entries never expire and the size is
+    /// unbounded until set.
+    pub fn open(dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        Ok(DiskCache { dir: dir.as_ref().to_path_buf(), ttl: None, max_bytes: None })
+    }
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    /// Removes every entry past the TTL.
+    pub fn remove_expired(&self) -> std::io::Result<()> {
+        for (path, metadata) in self.entries()? {
+            if self.is_expired(&metadata) {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+    pub fn clear(&self) -> std::io::Result<()> {
+        for (path, _) in self.entries()? {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+    /// The total size of the entries.
+    pub fn size_bytes(&self) -> std::io::Result<u64> {
+        Ok(self.entries()?.iter().map(|(_, x)| x.len()).sum())
+    }
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+    fn is_expired(&self, metadata: &std::fs::Metadata) -> bool {
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|x| SystemTime::now().duration_since(x).ok());
+        matches!((self.ttl, age), (Some(ttl), Some(age)) if age > ttl)
+    }
+    fn entries(&self) -> std::io::Result<Vec<(PathBuf, std::fs::Metadata)>> {
+        let mut entries = Vec::default();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().is_some_and(|x| x == "json") {
+                entries.push((path, entry.metadata()?));
+            }
+        }
+        Ok(entries)
+    }
+    fn evict(&self, max_bytes: u64) -> std::io::Result<()> {
+        let mut entries = self.entries()?;
+        let mut total = entries.iter().map(|(_, x)| x.len()).sum::<u64>();
+        entries.sort_by_key(|(_, x)| x.modified().ok());
+        for (path, metadata) in entries {
+            if total <= max_bytes {
+                break
+            }
+            std::fs::remove_file(path)?;
+            total -= metadata.len();
+        }
+        Ok(())
+    }
+}
+
+impl Cache for DiskCache {
+    fn get<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Result<Option<String>, Error>> {
+        Box::pin(async move {
+            let path = self.path(key);
+            let metadata = match std::fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                Err(error) => return Err(Box::new(error) as Error),
+            };
+            if self.is_expired(&metadata) {
+                std::fs::remove_file(&path)?;
+                return Ok(None)
+            }
+            Ok(Some(std::fs::read_to_string(&path)?))
+        })
+    }
+    fn put<'a>(&'a self, key: &'a str, value: &'a str) -> LocalBoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let path = self.path(key);
+            // Write then rename, so readers never see a partial entry.
+            let temporary = path.with_extension(format!("tmp-{}", std::process::id()));
+            std::fs::write(&temporary, value)?;
+            std::fs::rename(&temporary, &path)?;
+            if let Some(max_bytes) = self.max_bytes {
+                self.evict(max_bytes)?;
+            }
+            Ok(())
+        })
+    }
+}
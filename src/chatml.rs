@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+use crate::client::{Message, Role};
+
+pub const IM_START: &str = "<|im_start|>";
+pub const IM_END: &str = "<|im_end|>";
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// CHATML
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Writes a conversation as ChatML, the raw prompt format of many local
+/// models, so it can be replayed against llama.cpp-style tooling:
+///
+/// ```rust,ignore
+/// let messages = store.load("support-42")?.unwrap_or_default();
+/// let prompt = ChatMl::new().with_generation_prompt(true).render(&messages);
+/// ```
+///
+/// Names go on the role line, as in `<|im_start|>user name=alice`. Tool
+/// calls are written after the content in Hermes style, one JSON object
+/// per `<tool_call>` tag. Images have no text form and are left out.
+#[derive(Debug, Clone, Default)]
+pub struct ChatMl {
+    generation_prompt: bool,
+    system_prompt: Option<String>,
+}
+
+impl ChatMl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Ends with an open assistant turn for the model to complete.
+    pub fn with_generation_prompt(mut self, generation_prompt: bool) -> Self {
+        self.generation_prompt = generation_prompt;
+        self
+    }
+    /// Added first when the conversation has no system message, as some
+    /// templates require one.
+    pub fn with_system_prompt(mut self, system_prompt: impl AsRef<str>) -> Self {
+        self.system_prompt = Some(system_prompt.as_ref().to_string());
+        self
+    }
+    pub fn render(&self, messages: &[Message]) -> String {
+        let mut output = String::default();
+        let has_system = messages.iter().any(|x| matches!(x.role, Role::System | Role::Developer));
+        if let Some(system_prompt) = self.system_prompt.as_ref().filter(|_| !has_system) {
+            write_turn(&mut output, "system", None, system_prompt);
+        }
+        for message in messages {
+            let mut content = message.content.clone();
+            for call in message.tool_calls.iter() {
+                let call = serde_json::json!({
+                    "name": call.function.name,
+                    "arguments": serde_json::from_str::<serde_json::Value>(&call.function.arguments)
+                        .unwrap_or_else(|_| serde_json::Value::String(call.function.arguments.clone())),
+                });
+                if !content.is_empty() {
+                    content.push('\n');
+                }
+                content.push_str(&format!("<tool_call>\n{call}\n</tool_call>"));
+            }
+            write_turn(&mut output, message.role.as_str(), message.name.as_deref(), &content);
+        }
+        if self.generation_prompt {
+            output.push_str(IM_START);
+            output.push_str("assistant\n");
+        }
+        output
+    }
+}
+
+fn write_turn(output: &mut String, role: &str, name: Option<&str>, content: &str) {
+    output.push_str(IM_START);
+    output.push_str(role);
+    if let Some(name) = name {
+        output.push_str(&format!(" name={name}"));
+    }
+    output.push('\n');
+    output.push_str(content);
+    output.push_str(IM_END);
+    output.push('\n');
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// MESSAGES ARRAY
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// A message in the plain `{"role": ..., "content": ...}` form that local
+/// inference servers accept, with content always a string.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlainMessage {
+    pub role: String,
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<crate::tools::ToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl From<&Message> for PlainMessage {
+    fn from(message: &Message) -> Self {
+        PlainMessage {
+            role: message.role.as_str().to_string(),
+            content: message.content.clone(),
+            name: message.name.clone(),
+            tool_calls: message.tool_calls.clone(),
+            tool_call_id: message.tool_call_id.clone(),
+        }
+    }
+}
+
+/// The conversation as a JSON messages array. Unlike serializing the
+/// messages directly, images and cache breakpoints are dropped so content
+/// is never a list of parts, which servers such as llama.cpp's reject for
+/// text-only models.
+pub fn to_messages_json(messages: &[Message]) -> serde_json::Value {
+    let messages = messages.iter().map(PlainMessage::from).collect::<Vec<_>>();
+    serde_json::to_value(messages).unwrap_or_default()
+}
@@ -0,0 +1,69 @@
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+
+use crate::client::{ChatCompletionsRequestBuilder, Error, Message, Role};
+use crate::extract::{MissingBody, DEFAULT_RETRIES};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// CLASSIFICATION
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+impl ChatCompletionsRequestBuilder {
+    /// Asks the model to label `text` with one of `E`'s variants, as written
+    /// by its `Display` impl, e.g. with strum's derives:
+    ///
+    /// ```ignore
+    /// #[derive(EnumIter, EnumString, Display)]
+    /// #[strum(serialize_all = "snake_case")]
+    /// enum Sentiment { Positive, Negative, Neutral }
+    ///
+    /// let sentiment: Sentiment = builder.classify("I love it!").await?;
+    /// ```
+    ///
+    /// Replies are matched case-insensitively, ignoring surrounding quotes
+    /// and punctuation; anything else is sent back for another try.
+    pub async fn classify<E>(&self, text: impl AsRef<str>) -> Result<E, Error>
+    where
+        E: IntoEnumIterator + FromStr + std::fmt::Display,
+    {
+        self.classify_with_instructions::<E>("", text).await
+    }
+    /// Like [`ChatCompletionsRequestBuilder::classify`], with extra guidance
+    /// on what the labels mean.
+    pub async fn classify_with_instructions<E>(
+        &self,
+        instructions: impl AsRef<str>,
+        text: impl AsRef<str>,
+    ) -> Result<E, Error>
+    where
+        E: IntoEnumIterator + FromStr + std::fmt::Display,
+    {
+        let labels = E::iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        let mut body = self.body.clone().ok_or(Box::new(MissingBody))?;
+        if body.temperature.is_none() {
+            body.temperature = Some(0.0);
+        }
+        let mut prompt = format!(
+            "Classify the text into exactly one of these labels: {}. \
+             Reply with the label only.",
+            labels.join(", "),
+        );
+        let instructions = instructions.as_ref().trim();
+        if !instructions.is_empty() {
+            prompt = format!("{instructions}\n\n{prompt}");
+        }
+        body.messages.push(Message::new(Role::System, prompt));
+        body.messages.push(Message::new(Role::User, text));
+        let parse = |content: &str| {
+            let answer = content.trim().trim_matches(|c: char| !c.is_alphanumeric());
+            labels
+                .iter()
+                .find(|label| label.eq_ignore_ascii_case(answer))
+                .and_then(|label| E::from_str(label).ok())
+                .ok_or_else(|| format!("{answer:?} is not one of the labels"))
+        };
+        let correction = |error: &str| {
+            format!("{error}. Reply with exactly one of: {}.", labels.join(", "))
+        };
+        self.run_with_corrections(body, DEFAULT_RETRIES, parse, correction).await
+    }
+}
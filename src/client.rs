@@ -87,17 +87,32 @@ pub struct ConfigurationBuilder {
     pub seed: Option<isize>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum ResponseType {
     Text,
     JsonObject,
+    JsonSchema,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub struct ResponseFormat {
-    r#type: ResponseType
+    r#type: ResponseType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    json_schema: Option<JsonSchemaFormat>,
+}
+
+/// Structured outputs: the model's reply must match `schema`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JsonSchemaFormat {
+    /// Letters, digits, `_` and `-` only.
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub schema: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
 }
 
 impl ConfigurationBuilder {
@@ -137,6 +152,12 @@ impl ConfigurationBuilder {
         self.logprobs = Some(logprobs);
         self
     }
+    /// Also sets `logprobs`, which the API requires alongside it.
+    pub fn with_top_logprobs(mut self, top_logprobs: usize) -> Self {
+        self.logprobs = Some(true);
+        self.top_logprobs = Some(top_logprobs);
+        self
+    }
     pub fn with_response_format(mut self, response_format: ResponseFormat) -> Self {
         self.response_format = Some(response_format);
         self
@@ -145,19 +166,43 @@ impl ConfigurationBuilder {
         self.stop = Some(stop);
         self
     }
+    pub fn with_seed(mut self, seed: isize) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+    /// Layers `other` on top of `self`; fields set in `other` win.
+    pub fn merge(self, other: ConfigurationBuilder) -> Self {
+        ConfigurationBuilder {
+            model: other.model.or(self.model),
+            stream: other.stream.or(self.stream),
+            temperature: other.temperature.or(self.temperature),
+            n: other.n.or(self.n),
+            max_tokens: other.max_tokens.or(self.max_tokens),
+            top_p: other.top_p.or(self.top_p),
+            frequency_penalty: other.frequency_penalty.or(self.frequency_penalty),
+            presence_penalty: other.presence_penalty.or(self.presence_penalty),
+            logprobs: other.logprobs.or(self.logprobs),
+            top_logprobs: other.top_logprobs.or(self.top_logprobs),
+            response_format: other.response_format.or(self.response_format),
+            stop: other.stop.or(self.stop),
+            seed: other.seed.or(self.seed),
+        }
+    }
     pub fn build(self, messages: impl IntoIterator<Item=Message>) -> Option<ChatCompletionsBody> {
         let model = self.model.as_ref()?;
         let mut chat_request = ChatCompletionsBody::new(model, messages);
-        chat_request.stream = self.stream.clone();
-        chat_request.temperature = self.temperature.clone();
-        chat_request.n = self.n.clone();
-        chat_request.max_tokens = self.max_tokens.clone();
-        chat_request.top_p = self.top_p.clone();
-        chat_request.frequency_penalty = self.frequency_penalty.clone();
-        chat_request.presence_penalty = self.presence_penalty.clone();
-        chat_request.logprobs = self.logprobs.clone();
+        chat_request.stream = self.stream;
+        chat_request.temperature = self.temperature;
+        chat_request.n = self.n;
+        chat_request.max_tokens = self.max_tokens;
+        chat_request.top_p = self.top_p;
+        chat_request.frequency_penalty = self.frequency_penalty;
+        chat_request.presence_penalty = self.presence_penalty;
+        chat_request.logprobs = self.logprobs;
+        chat_request.top_logprobs = self.top_logprobs;
         chat_request.response_format = self.response_format.clone();
         chat_request.stop = self.stop.clone();
+        chat_request.seed = self.seed;
         Some(chat_request)
     }
 }
@@ -167,12 +212,190 @@ impl ConfigurationBuilder {
 // TODO
 //―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(into = "MessageWire", from = "MessageWire")]
 pub struct Message {
     pub role: Role,
     pub content: String,
+    /// Tells apart participants sharing a role, e.g. several users in one
+    /// conversation. Letters, digits, `_` and `-` only.
+    pub name: Option<String>,
+    /// When non-empty, the message is sent as a list of content parts: the
+    /// text followed by each image.
+    pub images: Vec<ImageUrl>,
+    /// Calls requested by an assistant message.
+    pub tool_calls: Vec<crate::tools::ToolCall>,
+    /// For `tool` messages, the call this is the result of.
+    pub tool_call_id: Option<String>,
+    /// Marks the end of a cacheable prefix, for providers that take
+    /// explicit cache breakpoints. When set, the message is sent as a list
+    /// of content parts.
+    pub cache_control: Option<CacheControl>,
+}
+
+/// A prompt-caching breakpoint, sent as `{"type": "ephemeral"}`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CacheControl {
+    Ephemeral,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImageUrl {
+    /// Either a web URL or a base64 `data:` URL.
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<ImageDetail>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageDetail {
+    Auto,
+    Low,
+    High,
+}
+
+/// The wire format of [`Message`], where `content` is either a string or a
+/// list of typed parts.
+#[derive(Serialize, Deserialize)]
+struct MessageWire {
+    role: Role,
+    /// Null for assistant messages that only call tools.
+    content: Option<MessageContent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<crate::tools::ToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentPart {
+    Text {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    ImageUrl { image_url: ImageUrl },
+}
+
+impl Message {
+    pub fn new(role: Role, content: impl AsRef<str>) -> Self {
+        let content = content.as_ref().to_string();
+        Message {
+            role,
+            content,
+            name: None,
+            images: Vec::default(),
+            tool_calls: Vec::default(),
+            tool_call_id: None,
+            cache_control: None,
+        }
+    }
+    /// A user message from one of several participants.
+    pub fn named_user(name: impl AsRef<str>, content: impl AsRef<str>) -> Self {
+        Message::new(Role::User, content).with_name(name)
+    }
+    pub fn named_assistant(name: impl AsRef<str>, content: impl AsRef<str>) -> Self {
+        Message::new(Role::Assistant, content).with_name(name)
+    }
+    /// The result of a tool call, sent back to the model.
+    pub fn tool(tool_call_id: impl AsRef<str>, content: impl AsRef<str>) -> Self {
+        let mut message = Message::new(Role::Tool, content);
+        message.tool_call_id = Some(tool_call_id.as_ref().to_string());
+        message
+    }
+    /// Like [`Message::tool`], encoding the output the way models expect:
+    /// strings as is, other values as JSON, and errors as
+    /// `{"error": "..."}`.
+    pub fn tool_result<E: std::fmt::Display>(
+        tool_call_id: impl AsRef<str>,
+        output: &Result<serde_json::Value, E>,
+    ) -> Self {
+        let content = match output {
+            Ok(serde_json::Value::String(text)) => text.clone(),
+            Ok(value) => value.to_string(),
+            Err(error) => serde_json::json!({ "error": error.to_string() }).to_string(),
+        };
+        Message::tool(tool_call_id, content)
+    }
+    /// An assistant message that calls tools, for replaying a conversation.
+    pub fn with_tool_calls(mut self, tool_calls: Vec<crate::tools::ToolCall>) -> Self {
+        self.tool_calls = tool_calls;
+        self
+    }
+    pub fn with_name(mut self, name: impl AsRef<str>) -> Self {
+        self.name = Some(name.as_ref().to_string());
+        self
+    }
+    pub fn with_cache_control(mut self, cache_control: CacheControl) -> Self {
+        self.cache_control = Some(cache_control);
+        self
+    }
+    pub fn with_image(mut self, url: impl AsRef<str>, detail: Option<ImageDetail>) -> Self {
+        let url = url.as_ref().to_string();
+        self.images.push(ImageUrl { url, detail });
+        self
+    }
+}
+
+impl From<Message> for MessageWire {
+    fn from(message: Message) -> Self {
+        let content = if message.images.is_empty() && message.cache_control.is_none() {
+            let only_calls = message.content.is_empty() && !message.tool_calls.is_empty();
+            Some(MessageContent::Text(message.content)).filter(|_| !only_calls)
+        } else {
+            let text = ContentPart::Text { text: message.content, cache_control: message.cache_control };
+            let images = message.images
+                .into_iter()
+                .map(|image_url| ContentPart::ImageUrl { image_url });
+            Some(MessageContent::Parts(std::iter::once(text).chain(images).collect()))
+        };
+        MessageWire {
+            role: message.role,
+            content,
+            name: message.name,
+            tool_calls: message.tool_calls,
+            tool_call_id: message.tool_call_id,
+        }
+    }
+}
+
+impl From<MessageWire> for Message {
+    fn from(wire: MessageWire) -> Self {
+        let mut message = Message::new(wire.role, "");
+        match wire.content {
+            Some(MessageContent::Text(content)) => message.content = content,
+            Some(MessageContent::Parts(parts)) => {
+                for part in parts {
+                    match part {
+                        ContentPart::Text { text, cache_control } => {
+                            message.content.push_str(&text);
+                            message.cache_control = message.cache_control.or(cache_control);
+                        }
+                        ContentPart::ImageUrl { image_url } => message.images.push(image_url),
+                    }
+                }
+            }
+            None => {}
+        }
+        message.name = wire.name;
+        message.tool_calls = wire.tool_calls;
+        message.tool_call_id = wire.tool_call_id;
+        message
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum Role {
     #[serde(rename = "system")]
     System,
@@ -180,25 +403,267 @@ pub enum Role {
     User,
     #[serde(rename = "assistant")]
     Assistant,
+    #[serde(rename = "tool")]
+    Tool,
+    /// Instructions for reasoning models, which take the place of system
+    /// messages.
+    #[serde(rename = "developer")]
+    Developer,
+    /// Function results, for providers that predate tool calls.
+    #[serde(rename = "function")]
+    Function,
 }
 
 impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::User => "user",
+            Self::Assistant => "assistant",
+            Self::Tool => "tool",
+            Self::Developer => "developer",
+            Self::Function => "function",
+        }
+    }
+    /// System and developer messages, which carry instructions rather than
+    /// conversation.
+    pub fn is_instruction(&self) -> bool {
+        matches!(self, Self::System | Self::Developer)
+    }
+    #[deprecated(note = "use `str::parse` or `Role::try_from`")]
     pub fn from(string: &str) -> Option<Self> {
+        string.parse().ok()
+    }
+}
+
+/// Case-insensitive.
+impl FromStr for Role {
+    type Err = UnknownVariant;
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
         match string.to_lowercase().as_str() {
-            "system" => Some(Self::System),
-            "assistant" => Some(Self::Assistant),
-            "user" => Some(Self::User),
-            _ => None
+            "system" => Ok(Self::System),
+            "assistant" => Ok(Self::Assistant),
+            "user" => Ok(Self::User),
+            "tool" => Ok(Self::Tool),
+            "developer" => Ok(Self::Developer),
+            "function" => Ok(Self::Function),
+            _ => Err(UnknownVariant::new("role", string)),
         }
     }
 }
 
+impl TryFrom<&str> for Role {
+    type Error = UnknownVariant;
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        string.parse()
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ResponseType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::JsonObject => "json_object",
+            Self::JsonSchema => "json_schema",
+        }
+    }
+}
+
+/// Case-insensitive; accepts `-` for `_`, as in the XML DSL.
+impl FromStr for ResponseType {
+    type Err = UnknownVariant;
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string.to_lowercase().replace('-', "_").as_str() {
+            "text" => Ok(Self::Text),
+            "json_object" => Ok(Self::JsonObject),
+            "json_schema" => Ok(Self::JsonSchema),
+            _ => Err(UnknownVariant::new("response type", string)),
+        }
+    }
+}
+
+impl TryFrom<&str> for ResponseType {
+    type Error = UnknownVariant;
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        string.parse()
+    }
+}
+
+impl std::fmt::Display for ResponseType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Why a choice stopped. Reasons without a variant, such as other
+/// providers' own, are kept as `Other`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FinishReason {
+    /// The reply ended or hit a stop sequence.
+    Stop,
+    /// Cut off by `max_tokens` or the context window.
+    Length,
+    ToolCalls,
+    ContentFilter,
+    /// The legacy form of `ToolCalls`.
+    FunctionCall,
+    Other(String),
+}
+
+impl FinishReason {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Stop => "stop",
+            Self::Length => "length",
+            Self::ToolCalls => "tool_calls",
+            Self::ContentFilter => "content_filter",
+            Self::FunctionCall => "function_call",
+            Self::Other(reason) => reason,
+        }
+    }
+}
+
+impl FromStr for FinishReason {
+    type Err = std::convert::Infallible;
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        Ok(FinishReason::from(string))
+    }
+}
+
+impl From<&str> for FinishReason {
+    fn from(string: &str) -> Self {
+        match string {
+            "stop" => Self::Stop,
+            "length" => Self::Length,
+            "tool_calls" => Self::ToolCalls,
+            "content_filter" => Self::ContentFilter,
+            "function_call" => Self::FunctionCall,
+            reason => Self::Other(reason.to_string()),
+        }
+    }
+}
+
+impl std::fmt::Display for FinishReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for FinishReason {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for FinishReason {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(FinishReason::from(String::deserialize(deserializer)?.as_str()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnknownVariant {
+    pub kind: &'static str,
+    pub value: String,
+}
+impl UnknownVariant {
+    fn new(kind: &'static str, value: &str) -> Self {
+        UnknownVariant { kind, value: value.to_string() }
+    }
+}
+impl std::fmt::Display for UnknownVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot parse {:?} as a {}.", self.value, self.kind)
+    }
+}
+impl std::error::Error for UnknownVariant {}
+
 impl ResponseFormat {
     pub fn json_object() -> Self {
-        Self { r#type: ResponseType::JsonObject }
+        Self { r#type: ResponseType::JsonObject, json_schema: None }
     }
     pub fn text() -> Self {
-        Self { r#type: ResponseType::Text }
+        Self { r#type: ResponseType::Text, json_schema: None }
+    }
+    pub fn json_schema(json_schema: JsonSchemaFormat) -> Self {
+        Self { r#type: ResponseType::JsonSchema, json_schema: Some(json_schema) }
+    }
+    /// A strict structured-output format generated from `T`, so the schema
+    /// sent to the model always matches the type the reply is parsed into.
+    #[cfg(feature = "schemars")]
+    pub fn json_schema_for<T: schemars::JsonSchema>() -> Self {
+        let schema = strict_schema_for::<T>();
+        let description = schema
+            .get("description")
+            .and_then(|x| x.as_str())
+            .map(str::to_string);
+        let name = T::schema_name()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        Self::json_schema(JsonSchemaFormat { name, description, schema, strict: Some(true) })
+    }
+    pub fn response_type(&self) -> &ResponseType {
+        &self.r#type
+    }
+    pub fn schema(&self) -> Option<&JsonSchemaFormat> {
+        self.json_schema.as_ref()
+    }
+}
+
+/// The schema of `T`, restricted as described in [`make_strict`].
+#[cfg(feature = "schemars")]
+pub(crate) fn strict_schema_for<T: schemars::JsonSchema>() -> serde_json::Value {
+    let generator = schemars::gen::SchemaSettings::draft2019_09().into_generator();
+    let schema = generator.into_root_schema_for::<T>();
+    let mut schema = serde_json::to_value(schema).unwrap_or_default();
+    make_strict(&mut schema);
+    schema
+}
+
+/// Rewrites a generated schema into the subset OpenAI accepts in strict
+/// mode: every object closed with all of its properties required (optional
+/// fields stay nullable), `anyOf` in place of `oneOf`, and no validation
+/// keywords it does not support.
+#[cfg(feature = "schemars")]
+fn make_strict(schema: &mut serde_json::Value) {
+    const UNSUPPORTED: &[&str] = &[
+        "$schema", "title", "format", "default", "minimum", "maximum", "exclusiveMinimum",
+        "exclusiveMaximum", "multipleOf", "minLength", "maxLength", "pattern", "minItems",
+        "maxItems", "uniqueItems", "minProperties", "maxProperties",
+    ];
+    match schema {
+        serde_json::Value::Object(object) => {
+            for key in UNSUPPORTED {
+                object.remove(*key);
+            }
+            if let Some(variants) = object.remove("oneOf") {
+                object.insert(String::from("anyOf"), variants);
+            }
+            if let Some(properties) = object.get("properties").and_then(|x| x.as_object()) {
+                let required = properties.keys().cloned().map(serde_json::Value::String).collect();
+                object.insert(String::from("required"), serde_json::Value::Array(required));
+                object.insert(String::from("additionalProperties"), serde_json::Value::Bool(false));
+            }
+            for (key, value) in object.iter_mut() {
+                // Property names are not schemas, so leave them alone.
+                if key == "properties" || key == "$defs" || key == "definitions" {
+                    if let Some(children) = value.as_object_mut() {
+                        children.values_mut().for_each(make_strict);
+                    }
+                } else {
+                    make_strict(value);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(make_strict),
+        _ => {}
     }
 }
 
@@ -207,6 +672,8 @@ impl ResponseFormat {
 // TODO
 //―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
 pub type Error = Box<dyn std::error::Error>;
+pub type Logger = Rc<RefCell<dyn FnMut(&str)>>;
+pub type Observers = Vec<Rc<dyn crate::observer::Observer>>;
 
 #[derive(Debug, Clone)]
 pub enum ApiError {
@@ -214,7 +681,7 @@ pub enum ApiError {
     APIConnectionError,
     /// # TODO
     APITimeoutError,
-    /// # TODO
+    /// # 5xx - Internal Server Error
     InternalServerError,
     /// # 401 - Invalid Authentication
     AuthenticationError,
@@ -227,7 +694,9 @@ pub enum ApiError {
     /// # 403 - Permission Denied Error
     PermissionDeniedError,
     /// # 429 - Rate limit reached for requests
-    RateLimitError,
+    ///
+    /// With the rate limit headers, if the response had them all.
+    RateLimitError(Option<RateLimitMetadata>),
     /// # 422 - Unprocessable Entity Error
     UnprocessableEntityError,
 }
@@ -249,6 +718,26 @@ pub struct RateLimitMetadata {
 pub struct MissingHeader(String);
 
 impl ApiError {
+    /// Whether the same request may succeed if sent again later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ApiError::APIConnectionError
+                | ApiError::APITimeoutError
+                | ApiError::InternalServerError
+                | ApiError::RateLimitError(_)
+        )
+    }
+    /// How long the server asked to wait before retrying, if it said.
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        let ApiError::RateLimitError(Some(metadata)) = self else {
+            return None
+        };
+        match metadata.retry_after_ms {
+            0 => Some(std::time::Duration::from_secs(metadata.retry_after as u64)),
+            ms => Some(std::time::Duration::from_millis(ms as u64)),
+        }
+    }
     pub(crate) fn from_code(status: impl Into<u16>) -> Option<Self> {
         match status.into() {
             400 => Some(ApiError::BadRequestError),
@@ -257,65 +746,58 @@ impl ApiError {
             404 => Some(ApiError::NotFoundError),
             409 => Some(ApiError::ConflictError),
             422 => Some(ApiError::UnprocessableEntityError),
-            429 => Some(ApiError::RateLimitError),
+            429 => Some(ApiError::RateLimitError(None)),
+            500..=599 => Some(ApiError::InternalServerError),
             _ => None,
         }
     }
 }
 
 impl RateLimitMetadata {
-    fn from_response(response: &reqwest::Response) -> Result<Self, Box<dyn std::error::Error>> {
-        let retry_after = response
-            .headers()
+    fn from_headers(headers: &reqwest::header::HeaderMap) -> Result<Self, Box<dyn std::error::Error>> {
+        let retry_after = headers
             .get("retry-after")
             .ok_or(MissingHeader(String::from("retry-after")))
             .map_err(Box::new)?
             .to_str()?
             .to_string();
-        let retry_after_ms = response
-            .headers()
+        let retry_after_ms = headers
             .get("retry-after-ms")
             .ok_or(MissingHeader(String::from("retry-after-ms")))
             .map_err(Box::new)?
             .to_str()?
             .to_string();
-        let ratelimit_limit_requests = response
-            .headers()
+        let ratelimit_limit_requests = headers
             .get("x-ratelimit-limit-requests")
             .ok_or(MissingHeader(String::from("x-ratelimit-limit-requests")))
             .map_err(Box::new)?
             .to_str()?
             .to_string();
-        let ratelimit_limit_tokens = response
-            .headers()
+        let ratelimit_limit_tokens = headers
             .get("x-ratelimit-limit-tokens")
             .ok_or(MissingHeader(String::from("x-ratelimit-limit-tokens")))
             .map_err(Box::new)?
             .to_str()?
             .to_string();
-        let ratelimit_remaining_requests = response
-            .headers()
+        let ratelimit_remaining_requests = headers
             .get("x-ratelimit-remaining-requests")
             .ok_or(MissingHeader(String::from("x-ratelimit-remaining-requests")))
             .map_err(Box::new)?
             .to_str()?
             .to_string();
-        let ratelimit_remaining_tokens = response
-            .headers()
+        let ratelimit_remaining_tokens = headers
             .get("x-ratelimit-remaining-tokens")
             .ok_or(MissingHeader(String::from("x-ratelimit-remaining-tokens")))
             .map_err(Box::new)?
             .to_str()?
             .to_string();
-        let ratelimit_reset_requests = response
-            .headers()
+        let ratelimit_reset_requests = headers
             .get("x-ratelimit-reset-requests")
             .ok_or(MissingHeader(String::from("x-ratelimit-reset-requests")))
             .map_err(Box::new)?
             .to_str()?
             .to_string();
-        let ratelimit_reset_tokens = response
-            .headers()
+        let ratelimit_reset_tokens = headers
             .get("x-ratelimit-reset-tokens")
             .ok_or(MissingHeader(String::from("x-ratelimit-reset-tokens")))
             .map_err(Box::new)?
@@ -350,7 +832,7 @@ impl std::fmt::Display for ApiError {
             ApiError::ConflictError => "conflict error",
             ApiError::NotFoundError => "not found error",
             ApiError::PermissionDeniedError => "permission denied error",
-            ApiError::RateLimitError => "rate limit error",
+            ApiError::RateLimitError(_) => "rate limit error",
             ApiError::UnprocessableEntityError => "unprocessable entity error",
         };
         write!(f, "{label}")
@@ -371,34 +853,41 @@ pub struct ChatCompletionsBody {
     /// If set, partial message deltas will be sent, like in ChatGPT.
     /// Tokens will be sent as data-only server-sent events as they become
     /// available, with the stream terminated by a data: [DONE] message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
     /// What sampling temperature to use, between 0 and 2.
     ///
     /// Higher values like 0.8 will make the output more random,
     /// while lower values like 0.2 will make it more focused and deterministic.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
     /// How many chat completion choices to generate for each input message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub n: Option<usize>,
     /// The maximum number of tokens allowed for the generated answer.
     ///
     /// By default, the number of tokens the model can
     /// return will be (4096 - prompt tokens).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<usize>,
     /// An alternative to sampling with temperature, called nucleus sampling, where
     /// the model considers the results of the tokens with `topP` probability mass.
     ///
     /// So `0.1` means only the tokens comprising the top 10% probability mass are
     /// considered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
     /// Number between `-2.0` and `2.0.`
     ///
     /// Positive values penalize new tokens based on their existing frequency in the text
     /// so far, decreasing the model's likelihood to repeat the same line verbatim.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub frequency_penalty: Option<f32>,
     /// Number between -2.0 and 2.0.
     ///
     /// Positive values penalize new tokens based on whether they appear in the text so far,
     /// increasing the model's likelihood to talk about new topics.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub presence_penalty: Option<f32>,
     /// Whether to return log probabilities of the output tokens or not.
     /// 
@@ -406,11 +895,13 @@ pub struct ChatCompletionsBody {
     /// in the `content` of `message`.
     /// 
     /// This option is currently **not available** on the `gpt-4-vision-preview` model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub logprobs: Option<bool>,
     /// An integer between 0 and 5 specifying the number of most likely tokens to
     /// return at each token position, each with an associated log probability.
     /// 
     /// `logprobs` must be set to true if this parameter is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub top_logprobs: Option<usize>,
     /// An object specifying the format that the model must output.
     /// 
@@ -425,10 +916,12 @@ pub struct ChatCompletionsBody {
     /// Also note that the message content may be partially cut off if `finish_reason="length"`,
     /// which indicates the generation exceeded max_tokens or the conversation exceeded the max
     /// context length.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub response_format: Option<ResponseFormat>,
     /// Up to 4 sequences where the API will stop generating further tokens.
     ///
     /// The returned text will not contain the stop sequence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub stop: Option<Vec<String>>,
     /// This feature is in Beta.
     /// 
@@ -438,10 +931,69 @@ pub struct ChatCompletionsBody {
     /// 
     /// Determinism is not guaranteed, and you should refer to the system_fingerprint
     /// response parameter to monitor changes in the backend.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub seed: Option<isize>,
+    /// Functions the model may call; see [`crate::tools::ToolRegistry`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<crate::tools::ToolDefinition>>,
+    /// Only valid with `stream`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+}
+
+/// The parameters sent as `null` when unset, with
+/// [`ApiEndpoint::explicit_nulls`].
+const NULLABLE_PARAMETERS: &[&str] = &[
+    "stream",
+    "temperature",
+    "n",
+    "max_tokens",
+    "top_p",
+    "frequency_penalty",
+    "presence_penalty",
+    "logprobs",
+    "top_logprobs",
+    "response_format",
+    "stop",
+    "seed",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct StreamOptions {
+    /// Adds a final chunk carrying the token usage for the whole request.
+    pub include_usage: bool,
 }
 
 impl ChatCompletionsBody {
+    /// A copy for `model`, with `max_tokens` lowered to what it can
+    /// generate and, for reasoning models, the parameters they reject
+    /// removed. `None` if the messages alone would overflow its context
+    /// window. Models missing from the registry get the body unchanged.
+    pub fn adapted_to(&self, model: impl AsRef<str>) -> Option<Self> {
+        let model = model.as_ref();
+        let mut body = self.clone().with_model(model);
+        if let Some(limit) = crate::lint::max_output_tokens(model) {
+            body.max_tokens = body.max_tokens.map(|x| x.min(limit));
+        }
+        if crate::lint::is_reasoning_model(model) {
+            body.temperature = None;
+            body.top_p = None;
+            body.frequency_penalty = None;
+            body.presence_penalty = None;
+            body.logprobs = None;
+            body.top_logprobs = None;
+        }
+        if let Some(window) = crate::lint::context_window(model) {
+            let prompt_tokens = body.messages
+                .iter()
+                .map(|x| crate::lint::estimate_tokens(&x.content))
+                .sum::<usize>();
+            if prompt_tokens + body.max_tokens.unwrap_or_default() > window {
+                return None
+            }
+        }
+        Some(body)
+    }
     pub fn new(model: impl AsRef<str>, messages: impl IntoIterator<Item=Message>) -> Self {
         let model = model.as_ref().to_string();
         let messages = messages.into_iter().collect::<Vec<_>>();
@@ -460,6 +1012,8 @@ impl ChatCompletionsBody {
             response_format: None,
             stop: None,
             seed: None,
+            tools: None,
+            stream_options: None,
         }
     }
     pub fn with_model(mut self, model: impl AsRef<str>) -> Self {
@@ -470,6 +1024,12 @@ impl ChatCompletionsBody {
         self.stream = Some(stream);
         self
     }
+    /// Streams and asks for a final usage chunk.
+    pub fn with_stream_usage(mut self) -> Self {
+        self.stream = Some(true);
+        self.stream_options = Some(StreamOptions { include_usage: true });
+        self
+    }
     pub fn with_temperature(mut self, temperature: f32) -> Self {
         self.temperature = Some(temperature);
         self
@@ -498,6 +1058,12 @@ impl ChatCompletionsBody {
         self.logprobs = Some(logprobs);
         self
     }
+    /// Also sets `logprobs`, which the API requires alongside it.
+    pub fn with_top_logprobs(mut self, top_logprobs: usize) -> Self {
+        self.logprobs = Some(true);
+        self.top_logprobs = Some(top_logprobs);
+        self
+    }
     pub fn with_response_format(mut self, response_format: ResponseFormat) -> Self {
         self.response_format = Some(response_format);
         self
@@ -506,38 +1072,228 @@ impl ChatCompletionsBody {
         self.stop = Some(stop);
         self
     }
+    pub fn with_seed(mut self, seed: isize) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+    pub fn with_tools(mut self, tools: Vec<crate::tools::ToolDefinition>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+    /// Moves system and developer messages ahead of the rest, keeping the
+    /// relative order of each. Prompt caches match on the longest identical
+    /// prefix, so instructions that rarely change should come before the
+    /// conversation.
+    pub fn order_for_caching(&mut self) {
+        self.messages.sort_by_key(|x| !x.role.is_instruction());
+    }
+    /// Puts a [`CacheControl::Ephemeral`] breakpoint on the last of the
+    /// leading system (or developer) messages, for providers that need
+    /// explicit breakpoints. Does nothing if the first message is neither.
+    pub fn with_cache_breakpoint(mut self) -> Self {
+        let prefix = self.messages.iter().take_while(|x| x.role.is_instruction()).count();
+        if let Some(message) = prefix.checked_sub(1).and_then(|x| self.messages.get_mut(x)) {
+            message.cache_control = Some(CacheControl::Ephemeral);
+        }
+        self
+    }
 }
 
 //―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
 // TODO
 //―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct ApiEndpoint {
-    pub api_key: String,
+    /// Redacted when printed; see [`crate::secret::Secret`].
+    pub api_key: crate::secret::Secret<String>,
     pub api_url: String,
+    /// Sent as the `OpenAI-Organization` header, for keys that belong to
+    /// several organizations.
+    pub organization: Option<String>,
+    /// Sends unset body parameters as explicit `null`s, as earlier releases
+    /// did, for backends that expect every parameter to be present.
+    pub explicit_nulls: bool,
+    /// Replaces the `Authorization: Bearer` header; see
+    /// [`crate::auth::AuthScheme`].
+    pub auth: Option<Rc<dyn crate::auth::AuthScheme>>,
+}
+
+impl std::fmt::Debug for ApiEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiEndpoint")
+            .field("api_key", &self.api_key)
+            .field("api_url", &self.api_url)
+            .field("organization", &self.organization)
+            .field("explicit_nulls", &self.explicit_nulls)
+            .field("auth", &self.auth.is_some())
+            .finish()
+    }
 }
 
 impl ApiEndpoint {
+    /// The body as sent here; see [`ApiEndpoint::explicit_nulls`].
+    pub(crate) fn serialized_body(&self, body: &ChatCompletionsBody) -> serde_json::Result<Vec<u8>> {
+        if !self.explicit_nulls {
+            return serde_json::to_vec(body)
+        }
+        let mut body = serde_json::to_value(body)?;
+        if let Some(object) = body.as_object_mut() {
+            for key in NULLABLE_PARAMETERS {
+                object.entry(*key).or_insert(serde_json::Value::Null);
+            }
+        }
+        serde_json::to_vec(&body)
+    }
+    pub fn new(api_url: impl AsRef<str>, api_key: impl AsRef<str>) -> Self {
+        let api_url = api_url.as_ref().to_string();
+        let api_key = crate::secret::Secret::from(api_key.as_ref());
+        ApiEndpoint { api_key, api_url, organization: None, explicit_nulls: false, auth: None }
+    }
+    /// Reads `OPENAI_API_KEY`, which must be set, the optional
+    /// `OPENAI_BASE_URL` (see [`ApiEndpoint::from_base_url`]), falling back
+    /// to OpenAI, and the optional `OPENAI_ORG_ID`.
+    pub fn from_env() -> Result<Self, MissingEnvVar> {
+        let var = |name: &str| std::env::var(name).ok().filter(|x| !x.trim().is_empty());
+        let api_key = var("OPENAI_API_KEY").ok_or_else(|| MissingEnvVar(String::from("OPENAI_API_KEY")))?;
+        let endpoint = match var("OPENAI_BASE_URL") {
+            Some(base_url) => Self::from_base_url(base_url, api_key),
+            None => Self::open_ai_chat_completions(api_key),
+        };
+        Ok(match var("OPENAI_ORG_ID") {
+            Some(organization) => endpoint.with_organization(organization),
+            None => endpoint,
+        })
+    }
+    /// Loads the named profile from the config file; see
+    /// [`crate::config::Config`].
+    #[cfg(feature = "config")]
+    pub fn from_profile(name: impl AsRef<str>) -> Result<Self, Error> {
+        let config = crate::config::Config::load()?;
+        Ok(config.profile(name)?.endpoint()?)
+    }
+    /// Resolves a provider name such as `openai` or `octoai`.
+    pub fn for_provider(provider: impl AsRef<str>, api_key: impl AsRef<str>) -> Option<Self> {
+        match provider.as_ref().to_lowercase().replace(['-', '_'], "").as_str() {
+            "openai" => Some(Self::open_ai_chat_completions(api_key)),
+            "octoai" => Some(Self::octo_ai_chat_completions(api_key)),
+            _ => None,
+        }
+    }
+    /// For OpenAI compatible APIs; `/chat/completions` is appended unless the
+    /// URL already ends with it.
+    pub fn from_base_url(base_url: impl AsRef<str>, api_key: impl AsRef<str>) -> Self {
+        let base_url = base_url.as_ref().trim_end_matches('/');
+        if base_url.ends_with("/chat/completions") {
+            return Self::new(base_url, api_key)
+        }
+        Self::new(format!("{base_url}/chat/completions"), api_key)
+    }
+    pub fn with_api_key(mut self, api_key: impl AsRef<str>) -> Self {
+        self.api_key = crate::secret::Secret::from(api_key.as_ref());
+        self
+    }
+    pub fn with_organization(mut self, organization: impl AsRef<str>) -> Self {
+        self.organization = Some(organization.as_ref().to_string());
+        self
+    }
+    pub fn with_explicit_nulls(mut self, explicit_nulls: bool) -> Self {
+        self.explicit_nulls = explicit_nulls;
+        self
+    }
+    pub fn with_auth(mut self, auth: impl crate::auth::AuthScheme + 'static) -> Self {
+        self.auth = Some(Rc::new(auth));
+        self
+    }
+    /// Every header but those from [`ApiEndpoint::auth`].
+    pub(crate) fn headers(&self) -> Vec<(String, String)> {
+        let mut headers = vec![(String::from("Content-Type"), String::from("application/json"))];
+        if self.auth.is_none() {
+            headers.insert(0, (String::from("Authorization"), format!("Bearer {}", self.api_key.expose())));
+        }
+        if let Some(organization) = self.organization.as_ref() {
+            headers.push((String::from("OpenAI-Organization"), organization.clone()));
+        }
+        headers
+    }
+    /// Adds the headers from [`ApiEndpoint::auth`], replacing any of the
+    /// same name.
+    pub(crate) async fn authenticate(&self, request: &mut crate::transport::HttpRequest) -> Result<(), Error> {
+        let Some(auth) = self.auth.as_ref() else {
+            return Ok(())
+        };
+        for (name, value) in auth.headers(request).await? {
+            request.headers.retain(|(key, _)| !key.eq_ignore_ascii_case(&name));
+            request.headers.push((name, value));
+        }
+        Ok(())
+    }
+    /// The URL of another endpoint of the same API, e.g. `moderations`
+    /// for an OpenAI compatible chat completions URL.
+    pub fn sibling_url(&self, path: impl AsRef<str>) -> Option<String> {
+        let base = self.api_url.trim_end_matches('/').strip_suffix("/chat/completions")?;
+        Some(format!("{base}/{}", path.as_ref().trim_start_matches('/')))
+    }
+    /// The host part of the URL, if it parses.
+    pub fn host(&self) -> Option<String> {
+        let url = reqwest::Url::parse(&self.api_url).ok()?;
+        url.host_str().map(str::to_string)
+    }
     pub fn open_ai_chat_completions(api_key: impl AsRef<str>) -> Self {
-        let api_key = api_key.as_ref().to_string();
+        let api_key = crate::secret::Secret::from(api_key.as_ref());
         let api_url = "https://api.openai.com/v1/chat/completions".to_string();
-        ApiEndpoint { api_key, api_url }
+        ApiEndpoint { api_key, api_url, organization: None, explicit_nulls: false, auth: None }
     }
     pub fn octo_ai_chat_completions(api_key: impl AsRef<str>) -> Self {
-        let api_key = api_key.as_ref().to_string();
+        let api_key = crate::secret::Secret::from(api_key.as_ref());
         let api_url = "https://text.octoai.run/v1/chat/completions".to_string();
-        ApiEndpoint { api_key, api_url }
+        ApiEndpoint { api_key, api_url, organization: None, explicit_nulls: false, auth: None }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MissingEnvVar(pub String);
+impl std::fmt::Display for MissingEnvVar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot configure the API endpoint: {} is not set.", self.0)
     }
 }
+impl std::error::Error for MissingEnvVar {}
 
 //―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
 // TODO
 //―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Clone)]
 pub struct ChatCompletionsRequest {
     pub api_endpoint: ApiEndpoint,
     pub body: ChatCompletionsBody,
     pub timeout: Option<std::time::Duration>,
-    pub logger: Option<Rc<RefCell<dyn FnMut(&str) -> ()>>>,
+    pub observers: Observers,
+    pub metrics: Option<Rc<dyn crate::metrics::MetricsSink>>,
+    pub request_log: Option<crate::request_log::RequestLog>,
+    /// The prompt the body was rendered from, for audit records.
+    pub prompt_name: Option<String>,
+    /// Defaults to [`crate::transport::ReqwestTransport`].
+    pub transport: Option<Rc<dyn crate::transport::Transport>>,
+    #[cfg(feature = "audit")]
+    pub audit_log: Option<crate::audit::AuditLog>,
+    /// Consulted in order.
+    #[cfg(feature = "cache")]
+    pub caches: Vec<Rc<dyn crate::cache::Cache>>,
+    /// Skips [`ChatCompletionsBody::validate`] before sending.
+    pub skip_parameter_checks: bool,
+    pub aggregation: Aggregation,
+    /// Screens messages before sending.
+    pub moderation: Option<crate::moderation::ModerationFilter>,
+    /// Masks personal data before sending and restores it in responses.
+    pub pii_scrubber: Option<crate::pii::PiiScrubber>,
+    /// Checks generated content before it is returned.
+    pub output_check: Option<crate::moderation::OutputCheck>,
+    /// Re-requests replies cut off by `max_tokens`.
+    pub continuation: Option<Continuation>,
+    /// Tried in order when the model is missing, rate limited or down.
+    pub fallbacks: Vec<Fallback>,
+    #[cfg(feature = "compression")]
+    pub compression: crate::transport::Compression,
 }
 
 #[derive(Clone, Default)]
@@ -545,7 +1301,35 @@ pub struct ChatCompletionsRequestBuilder {
     pub api_endpoint: Option<ApiEndpoint>,
     pub body: Option<ChatCompletionsBody>,
     pub timeout: Option<std::time::Duration>,
-    pub logger: Option<Rc<RefCell<dyn FnMut(&str) -> ()>>>,
+    pub observers: Observers,
+    pub metrics: Option<Rc<dyn crate::metrics::MetricsSink>>,
+    pub request_log: Option<crate::request_log::RequestLog>,
+    /// The prompt the body was rendered from, for audit records.
+    pub prompt_name: Option<String>,
+    /// Defaults to [`crate::transport::ReqwestTransport`].
+    pub transport: Option<Rc<dyn crate::transport::Transport>>,
+    #[cfg(feature = "audit")]
+    pub audit_log: Option<crate::audit::AuditLog>,
+    /// Consulted in order.
+    #[cfg(feature = "cache")]
+    pub caches: Vec<Rc<dyn crate::cache::Cache>>,
+    /// Checked by [`ChatCompletionsRequestBuilder::execute_validated`].
+    pub validators: Vec<crate::validators::Validator>,
+    /// Skips [`ChatCompletionsBody::validate`] before sending.
+    pub skip_parameter_checks: bool,
+    pub aggregation: Aggregation,
+    /// Screens messages before sending.
+    pub moderation: Option<crate::moderation::ModerationFilter>,
+    /// Masks personal data before sending and restores it in responses.
+    pub pii_scrubber: Option<crate::pii::PiiScrubber>,
+    /// Checks generated content before it is returned.
+    pub output_check: Option<crate::moderation::OutputCheck>,
+    /// Re-requests replies cut off by `max_tokens`.
+    pub continuation: Option<Continuation>,
+    /// Tried in order when the model is missing, rate limited or down.
+    pub fallbacks: Vec<Fallback>,
+    #[cfg(feature = "compression")]
+    pub compression: crate::transport::Compression,
 }
 
 impl ChatCompletionsRequestBuilder {
@@ -557,25 +1341,196 @@ impl ChatCompletionsRequestBuilder {
         self.body = Some(body);
         self
     }
+    /// Sets the key on the current endpoint, defaulting to OpenAI if no
+    /// endpoint was set.
+    pub fn with_api_key(mut self, api_key: impl AsRef<str>) -> Self {
+        let api_endpoint = match self.api_endpoint.take() {
+            Some(api_endpoint) => api_endpoint.with_api_key(api_key),
+            None => ApiEndpoint::open_ai_chat_completions(api_key),
+        };
+        self.api_endpoint = Some(api_endpoint);
+        self
+    }
     pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
         self.timeout = Some(timeout);
         self
     }
-    pub fn with_logger(mut self, logger: Rc<RefCell<dyn FnMut(&str) -> ()>>) -> Self {
-        self.logger = Some(logger);
+    /// Adds an observer; every observer added receives every callback.
+    pub fn with_observer(mut self, observer: impl crate::observer::Observer + 'static) -> Self {
+        self.observers.push(Rc::new(observer));
+        self
+    }
+    /// Adds an observer that passes each delta to `logger`.
+    pub fn with_logger(self, logger: Logger) -> Self {
+        self.with_logger_closure(move |delta| {
+            let mut logger = logger.borrow_mut();
+            logger(delta)
+        })
+    }
+    /// Adds an observer that passes each delta to `logger`.
+    pub fn with_logger_closure(self, logger: impl FnMut(&str) + 'static) -> Self {
+        self.with_observer(crate::observer::DeltaFn::new(logger))
+    }
+    /// Measures every request made with this builder.
+    pub fn with_metrics(mut self, metrics: impl crate::metrics::MetricsSink + 'static) -> Self {
+        self.metrics = Some(Rc::new(metrics));
+        self
+    }
+    /// Logs every request body and response chunk, with the API key
+    /// redacted.
+    pub fn with_request_log(mut self, request_log: crate::request_log::RequestLog) -> Self {
+        self.request_log = Some(request_log);
+        self
+    }
+    pub fn with_transport(mut self, transport: impl crate::transport::Transport + 'static) -> Self {
+        self.transport = Some(Rc::new(transport));
+        self
+    }
+    pub fn with_prompt_name(mut self, prompt_name: impl AsRef<str>) -> Self {
+        self.prompt_name = Some(prompt_name.as_ref().to_string());
         self
     }
-    pub fn with_logger_closure(mut self, logger: impl FnMut(&str) -> () + 'static) -> Self {
-        let logger = Rc::new(RefCell::new(logger));
-        self.logger = Some(logger);
+    /// Sends parameters the API would reject, e.g. for providers with
+    /// wider ranges.
+    pub fn without_parameter_checks(mut self) -> Self {
+        self.skip_parameter_checks = true;
+        self
+    }
+    /// Screens messages before each request is sent or answered from a
+    /// cache; see [`crate::moderation::ModerationFilter`].
+    pub fn with_moderation(mut self, moderation: crate::moderation::ModerationFilter) -> Self {
+        self.moderation = Some(moderation);
+        self
+    }
+    /// Masks personal data in every request and restores it in every
+    /// response; see [`crate::pii::PiiScrubber`].
+    pub fn with_pii_scrubber(mut self, pii_scrubber: crate::pii::PiiScrubber) -> Self {
+        self.pii_scrubber = Some(pii_scrubber);
+        self
+    }
+    /// Checks every response before it is returned; see
+    /// [`crate::moderation::OutputCheck`].
+    pub fn with_output_check(mut self, output_check: crate::moderation::OutputCheck) -> Self {
+        self.output_check = Some(output_check);
+        self
+    }
+    /// Adds a model to try if the ones before it fail; see [`Fallback`].
+    pub fn with_fallback(mut self, fallback: Fallback) -> Self {
+        self.fallbacks.push(fallback);
+        self
+    }
+    /// Adds fallback models on the same endpoint.
+    pub fn with_fallback_models(mut self, models: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.fallbacks.extend(models.into_iter().map(Fallback::new));
+        self
+    }
+    /// Asks for the rest of replies cut off by `max_tokens`; see
+    /// [`Continuation`].
+    pub fn with_continuation(mut self, continuation: Continuation) -> Self {
+        self.continuation = Some(continuation);
+        self
+    }
+    /// Bounds the memory a response takes; see [`Aggregation`].
+    pub fn with_aggregation(mut self, aggregation: Aggregation) -> Self {
+        self.aggregation = aggregation;
+        self
+    }
+    /// Writes an audit record for every request made with this builder.
+    #[cfg(feature = "audit")]
+    pub fn with_audit_log(mut self, audit_log: crate::audit::AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+    /// Gzips large request bodies or turns off compressed responses; see
+    /// [`crate::transport::Compression`].
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, compression: crate::transport::Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+    /// Answers repeated requests from `cache` instead of sending them.
+    /// Caches are consulted in the order added, and a hit is copied into
+    /// the ones before it; e.g. a [`crate::cache::MemoryCache`] in front of
+    /// a [`crate::cache::DiskCache`].
+    #[cfg(feature = "cache")]
+    pub fn with_cache(mut self, cache: impl crate::cache::Cache + 'static) -> Self {
+        self.caches.push(Rc::new(cache));
         self
     }
     pub fn build(self) -> Option<ChatCompletionsRequest> {
-        let api_endpoint = self.api_endpoint.clone()?;
-        let body = self.body.clone()?;
-        let timeout = self.timeout.clone();
-        let logger = self.logger.clone();
-        Some(ChatCompletionsRequest { api_endpoint, body, timeout, logger })
+        Some(ChatCompletionsRequest {
+            api_endpoint: self.api_endpoint?,
+            body: self.body?,
+            timeout: self.timeout,
+            observers: self.observers,
+            metrics: self.metrics,
+            request_log: self.request_log,
+            prompt_name: self.prompt_name,
+            transport: self.transport,
+            #[cfg(feature = "audit")]
+            audit_log: self.audit_log,
+            #[cfg(feature = "cache")]
+            caches: self.caches,
+            skip_parameter_checks: self.skip_parameter_checks,
+            aggregation: self.aggregation,
+            moderation: self.moderation,
+            pii_scrubber: self.pii_scrubber,
+            output_check: self.output_check,
+            continuation: self.continuation,
+            fallbacks: self.fallbacks,
+            #[cfg(feature = "compression")]
+            compression: self.compression,
+        })
+    }
+    /// Like [`ChatCompletionsRequestBuilder::build`] but without a body,
+    /// for sending many bodies with the same settings. Any body set is
+    /// ignored.
+    pub fn prepare(self) -> Option<PreparedRequest> {
+        let placeholder = ChatCompletionsBody::new("", []);
+        let request = self.with_body(placeholder).build()?;
+        Some(PreparedRequest { request })
+    }
+}
+
+/// An endpoint and its settings, built once and reused. Each call borrows
+/// its body and serializes it directly, so nothing is cloned per request:
+///
+/// ```rust,ignore
+/// let prepared = ChatCompletionsRequestBuilder::default()
+///     .with_api_endpoint(ApiEndpoint::from_env()?)
+///     .with_metrics(metrics)
+///     .prepare()
+///     .unwrap();
+/// for body in bodies.iter() {
+///     let response = prepared.execute(body).await?;
+/// }
+/// ```
+pub struct PreparedRequest {
+    /// Its body is a placeholder and never sent.
+    request: ChatCompletionsRequest,
+}
+
+impl PreparedRequest {
+    pub fn api_endpoint(&self) -> &ApiEndpoint {
+        &self.request.api_endpoint
+    }
+    pub async fn execute(&self, body: &ChatCompletionsBody) -> Result<ChatCompletionsResponse, Error> {
+        self.request.execute_body(body).await
+    }
+    pub async fn execute_with(
+        &self,
+        body: &ChatCompletionsBody,
+        on_chunk: impl FnMut(&CompletionChunk),
+    ) -> Result<ChatCompletionsResponse, Error> {
+        self.request.execute_body_with(body, on_chunk).await
+    }
+    /// See [`ChatCompletionsRequest::dry_run`].
+    pub fn dry_run(&self, body: &ChatCompletionsBody) -> Result<DryRun, Error> {
+        self.request.dry_run_body(body)
+    }
+    /// See [`ChatCompletionsRequest::to_curl`].
+    pub fn to_curl(&self, body: &ChatCompletionsBody) -> Result<String, Error> {
+        Ok(curl_command(&self.request.dry_run_body(body)?, None, self.request.timeout))
     }
 }
 
@@ -590,6 +1545,122 @@ pub struct CompletionChunk {
     pub model: String,
     pub system_fingerprint: Option<String>,
     pub object: String,
+    /// Only on the final chunk, and only if the provider reports it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+impl CompletionChunk {
+    /// Appends a later chunk of the same stream, as though the two had
+    /// arrived as one.
+    pub fn merge(&mut self, chunk: CompletionChunk) {
+        fn append(target: &mut Option<String>, value: Option<String>) {
+            match (target.as_mut(), value) {
+                (Some(target), Some(value)) => target.push_str(&value),
+                (None, value) => *target = value,
+                (Some(_), None) => {}
+            }
+        }
+        for choice in chunk.choices {
+            let Some(existing) = self.choices.iter_mut().find(|x| x.index == choice.index) else {
+                self.choices.push(choice);
+                continue
+            };
+            append(&mut existing.delta.content, choice.delta.content);
+            for call in choice.delta.tool_calls.into_iter().flatten() {
+                let calls = existing.delta.tool_calls.get_or_insert_with(Vec::default);
+                let Some(existing) = calls.iter_mut().find(|x| x.index == call.index) else {
+                    calls.push(call);
+                    continue
+                };
+                append(&mut existing.id, call.id);
+                match (existing.function.as_mut(), call.function) {
+                    (Some(function), Some(call)) => {
+                        append(&mut function.name, call.name);
+                        append(&mut function.arguments, call.arguments);
+                    }
+                    (None, function) => existing.function = function,
+                    (Some(_), None) => {}
+                }
+            }
+            if choice.finish_reason.is_some() {
+                existing.finish_reason = choice.finish_reason;
+            }
+            if let Some(tokens) = choice.logprobs.and_then(|x| x.content) {
+                let logprobs = existing.logprobs.get_or_insert_with(ChoiceLogprobs::default);
+                logprobs.content.get_or_insert_with(Vec::default).extend(tokens);
+            }
+        }
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage;
+        }
+        if self.system_fingerprint.is_none() {
+            self.system_fingerprint = chunk.system_fingerprint;
+        }
+    }
+    /// Bytes of content and tool-call arguments.
+    fn output_len(&self) -> usize {
+        self.choices
+            .iter()
+            .map(|choice| {
+                let content = choice.delta.content.as_ref().map(String::len).unwrap_or_default();
+                let arguments = choice.delta.tool_calls
+                    .iter()
+                    .flatten()
+                    .filter_map(|x| x.function.as_ref()?.arguments.as_ref())
+                    .map(String::len)
+                    .sum::<usize>();
+                content + arguments
+            })
+            .sum()
+    }
+    /// The content of every choice's delta, joined; borrowed unless
+    /// several choices carry content.
+    pub fn delta(&self) -> std::borrow::Cow<'_, str> {
+        let mut contents = self.choices.iter().filter_map(|x| x.delta.content.as_deref());
+        match (contents.next(), contents.next()) {
+            (None, _) => std::borrow::Cow::Borrowed(""),
+            (Some(first), None) => std::borrow::Cow::Borrowed(first),
+            (Some(first), Some(second)) => {
+                std::borrow::Cow::Owned([first, second].into_iter().chain(contents).collect())
+            }
+        }
+    }
+    /// The non-empty content of each choice's delta, with its index.
+    pub fn choice_deltas(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.choices
+            .iter()
+            .filter_map(|x| Some((x.index, x.delta.content.as_deref().filter(|x| !x.is_empty())?)))
+    }
+    /// Whether the chunk carries any content or tool-call fragments.
+    pub fn has_output(&self) -> bool {
+        self.choices.iter().any(|choice| {
+            choice.delta.content.as_ref().is_some_and(|x| !x.is_empty()) || choice.delta.tool_calls.is_some()
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct Usage {
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    pub total_tokens: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+}
+
+impl Usage {
+    /// Prompt tokens served from the provider's prompt cache; these are
+    /// included in `prompt_tokens` and usually billed at a discount.
+    pub fn cached_tokens(&self) -> usize {
+        self.prompt_tokens_details.map(|x| x.cached_tokens).unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct PromptTokensDetails {
+    #[serde(default)]
+    pub cached_tokens: usize,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -597,11 +1668,266 @@ pub struct ChatResponseChoice {
     pub index: usize,
     pub delta: ChatResponseDelta,
     pub finish_reason: Option<String>,
+    /// Only when the request sets `logprobs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<ChoiceLogprobs>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ChoiceLogprobs {
+    #[serde(default)]
+    pub content: Option<Vec<TokenLogprob>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenLogprob {
+    pub token: String,
+    /// The natural log of the token's probability.
+    pub logprob: f64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ChatResponseDelta {
     pub content: Option<String>,
+    /// Tool calls arrive in fragments keyed by `index`; the first fragment
+    /// of each carries its id and name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCallDelta>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ToolCallDelta {
+    pub index: usize,
+    pub id: Option<String>,
+    pub function: Option<FunctionCallDelta>,
+}
+
+impl ToolCallDelta {
+    /// Appends the fragment to the call it belongs to.
+    fn apply(&self, calls: &mut Vec<crate::tools::ToolCall>) {
+        while calls.len() <= self.index {
+            calls.push(crate::tools::ToolCall {
+                id: String::default(),
+                r#type: String::from("function"),
+                function: crate::tools::FunctionCall { name: String::default(), arguments: String::default() },
+            });
+        }
+        let call = &mut calls[self.index];
+        if let Some(id) = self.id.as_ref() {
+            call.id.push_str(id);
+        }
+        if let Some(function) = self.function.as_ref() {
+            call.function.name.push_str(function.name.as_deref().unwrap_or_default());
+            call.function.arguments.push_str(function.arguments.as_deref().unwrap_or_default());
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FunctionCallDelta {
+    pub name: Option<String>,
+    pub arguments: Option<String>,
+}
+
+/// How a streamed response is held in memory. By default every chunk is
+/// kept, as received, in [`ChatCompletionsResponse::output`].
+///
+/// ```rust,ignore
+/// let builder = builder.with_aggregation(Aggregation::folded().with_max_output_bytes(4 << 20));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Aggregation {
+    /// Merges the chunks into one as they arrive, so memory grows with
+    /// the output rather than the number of chunks. `on_chunk` and
+    /// observers still see every chunk.
+    pub fold: bool,
+    /// Fails the request with [`OutputTooLarge`] once the content and
+    /// tool-call arguments pass this many bytes.
+    pub max_output_bytes: Option<usize>,
+}
+
+impl Aggregation {
+    pub fn folded() -> Self {
+        Aggregation { fold: true, max_output_bytes: None }
+    }
+    pub fn with_max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = Some(max_output_bytes);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OutputTooLarge {
+    pub limit: usize,
+}
+impl std::fmt::Display for OutputTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot read the response: its output passed the {} byte limit.", self.limit)
+    }
+}
+impl std::error::Error for OutputTooLarge {}
+
+/// A model to try when the request's own fails with a 404, a 429 or a 5xx,
+/// optionally on another endpoint:
+///
+/// ```rust,ignore
+/// let builder = builder
+///     .with_fallback(Fallback::new(Model::Gpt4oMini))
+///     .with_fallback(Fallback::new("meta-llama-3-70b-instruct").with_api_endpoint(octo_ai));
+/// ```
+///
+/// Fallbacks are tried in order with the body adapted to each model; see
+/// [`ChatCompletionsBody::adapted_to`]. A model whose context window is too
+/// small for the messages is skipped. The model that answered is the one
+/// named in the response's chunks.
+///
+/// Chunks are passed to `on_chunk` and observers as they arrive, so those
+/// of an attempt that fails partway, such as the first segments of a
+/// [`Continuation`], are not taken back; the fallback's chunks follow them.
+#[derive(Debug, Clone)]
+pub struct Fallback {
+    pub model: String,
+    /// Defaults to the request's endpoint.
+    pub api_endpoint: Option<ApiEndpoint>,
+}
+
+impl Fallback {
+    pub fn new(model: impl AsRef<str>) -> Self {
+        Fallback { model: model.as_ref().to_string(), api_endpoint: None }
+    }
+    pub fn with_api_endpoint(mut self, api_endpoint: ApiEndpoint) -> Self {
+        self.api_endpoint = Some(api_endpoint);
+        self
+    }
+}
+
+/// Whether a failed request should move on to the next fallback.
+fn falls_back(error: &Error) -> bool {
+    matches!(
+        error.downcast_ref::<ApiError>(),
+        Some(ApiError::NotFoundError | ApiError::RateLimitError(_) | ApiError::InternalServerError),
+    )
+}
+
+/// Continues replies cut off by `max_tokens`: the partial reply is sent
+/// back as the assistant's turn with a request to go on, and the segments
+/// are stitched into one response, up to `max_segments` requests in all.
+///
+/// ```rust,ignore
+/// let builder = builder.with_continuation(Continuation::new().with_max_segments(4));
+/// ```
+///
+/// The stitched response has the last segment's finish reason and the
+/// summed usage. `on_chunk` sees every segment's chunks in order, so the
+/// text streams on uninterrupted; observers see each segment as a request
+/// of its own. Only the first choice is continued, and never while it is
+/// calling tools.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Continuation {
+    pub max_segments: usize,
+    /// Sent as a user message after each partial reply.
+    pub instruction: String,
+}
+
+impl Default for Continuation {
+    fn default() -> Self {
+        Continuation {
+            max_segments: 3,
+            instruction: String::from(Continuation::DEFAULT_INSTRUCTION),
+        }
+    }
+}
+
+impl Continuation {
+    pub const DEFAULT_INSTRUCTION: &'static str =
+        "Continue exactly where you left off, without repeating anything or adding commentary.";
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_max_segments(mut self, max_segments: usize) -> Self {
+        self.max_segments = max_segments.max(1);
+        self
+    }
+    pub fn with_instruction(mut self, instruction: impl AsRef<str>) -> Self {
+        self.instruction = instruction.as_ref().to_string();
+        self
+    }
+}
+
+/// A request as it would be sent; see [`ChatCompletionsRequest::dry_run`].
+/// Displays as the raw HTTP request.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DryRun {
+    pub method: String,
+    pub url: String,
+    /// The API key is always redacted.
+    pub headers: Vec<(String, String)>,
+    /// The serialized body, byte for byte.
+    pub body: String,
+}
+
+impl DryRun {
+    pub fn json(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::from_str(&self.body)
+    }
+    pub fn header(&self, name: impl AsRef<str>) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name.as_ref()))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl std::fmt::Display for DryRun {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} {}", self.method, self.url)?;
+        for (key, value) in self.headers.iter() {
+            writeln!(f, "{key}: {value}")?;
+        }
+        writeln!(f)?;
+        write!(f, "{}", self.body)
+    }
+}
+
+/// Stands in for the API key in [`ChatCompletionsRequest::to_curl`]; set
+/// the variable before running the command.
+pub const CURL_KEY_PLACEHOLDER: &str = "$OPENAI_API_KEY";
+
+impl DryRun {
+    /// A `curl` command sending this request, with the API key read from
+    /// [`CURL_KEY_PLACEHOLDER`].
+    pub fn to_curl(&self) -> String {
+        curl_command(self, None, None)
+    }
+}
+
+fn curl_command(dry_run: &DryRun, api_key: Option<&str>, timeout: Option<std::time::Duration>) -> String {
+    let mut lines = vec![format!("curl -X {} {}", dry_run.method, shell_quote(&dry_run.url))];
+    for (key, value) in dry_run.headers.iter() {
+        match (key.eq_ignore_ascii_case("Authorization"), api_key) {
+            (true, Some(api_key)) => lines.push(format!("-H {}", shell_quote(&format!("{key}: Bearer {api_key}")))),
+            // Double quotes, so the shell expands the placeholder.
+            (true, None) => lines.push(format!("-H \"{key}: Bearer {CURL_KEY_PLACEHOLDER}\"")),
+            (false, _) => lines.push(format!("-H {}", shell_quote(&format!("{key}: {value}")))),
+        }
+    }
+    let streaming = dry_run.json()
+        .ok()
+        .and_then(|x| x.get("stream").and_then(serde_json::Value::as_bool))
+        .unwrap_or(false);
+    if streaming {
+        lines.push(String::from("--no-buffer"));
+    }
+    if let Some(timeout) = timeout {
+        lines.push(format!("--max-time {}", timeout.as_secs_f64()));
+    }
+    lines.push(format!("--data-raw {}", shell_quote(&dry_run.body)));
+    lines.join(" \\\n  ")
+}
+
+/// Single quotes `text` for a POSIX shell.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
 }
 
 //―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
@@ -614,55 +1940,431 @@ pub struct ChatCompletionsStream {
 
 impl ChatCompletionsRequest {
     pub async fn execute(&self) -> Result<ChatCompletionsResponse, Error> {
-        let url = self.api_endpoint.api_url.as_str();
-        let api_key = self.api_endpoint.api_key.as_str();
-        let client = {
-            if let Some(timeout) = self.timeout.as_ref() {
-                reqwest::ClientBuilder::new()
-                    .timeout(timeout.clone())
-                    .build()
-                    .unwrap()
-            } else {
-                reqwest::ClientBuilder::new().build().unwrap()
+        self.execute_with(|_| {}).await
+    }
+    /// What [`ChatCompletionsRequest::execute`] would send, with the API key
+    /// redacted, without sending anything. Headers from an
+    /// [`crate::auth::AuthScheme`] are left out, since producing them may
+    /// refresh a token.
+    pub fn dry_run(&self) -> Result<DryRun, Error> {
+        self.dry_run_body(&self.body)
+    }
+    /// A copy-pasteable `curl` command sending what
+    /// [`ChatCompletionsRequest::execute`] would, for debugging against the
+    /// raw API. The key is left as [`CURL_KEY_PLACEHOLDER`]; the caveats of
+    /// [`ChatCompletionsRequest::dry_run`] apply, and the body is never
+    /// compressed.
+    pub fn to_curl(&self) -> Result<String, Error> {
+        Ok(curl_command(&self.dry_run()?, None, self.timeout))
+    }
+    /// Like [`ChatCompletionsRequest::to_curl`], but with the API key in
+    /// the command. Mind where it is pasted.
+    pub fn to_curl_with_key(&self) -> Result<String, Error> {
+        let api_key = self.api_endpoint.api_key.expose();
+        Ok(curl_command(&self.dry_run()?, Some(api_key), self.timeout))
+    }
+    fn dry_run_body(&self, body: &ChatCompletionsBody) -> Result<DryRun, Error> {
+        let api_key = self.api_endpoint.api_key.expose().as_str();
+        let body = String::from_utf8(self.serialized_body(body)?)?;
+        Ok(DryRun {
+            method: String::from("POST"),
+            url: crate::request_log::redact(&self.api_endpoint.api_url, api_key),
+            headers: crate::request_log::redact_headers(&self.api_endpoint.headers(), api_key),
+            body: crate::request_log::redact(&body, api_key),
+        })
+    }
+    /// The body as sent; see [`ApiEndpoint::explicit_nulls`].
+    pub(crate) fn serialized_body(&self, body: &ChatCompletionsBody) -> serde_json::Result<Vec<u8>> {
+        self.api_endpoint.serialized_body(body)
+    }
+    /// Like [`ChatCompletionsRequest::execute`], calling `on_chunk` with
+    /// each chunk as it arrives.
+    pub async fn execute_with(
+        &self,
+        on_chunk: impl FnMut(&CompletionChunk),
+    ) -> Result<ChatCompletionsResponse, Error> {
+        self.execute_body_with(&self.body, on_chunk).await
+    }
+    /// Sends `body` in place of this request's own, with the same endpoint
+    /// and settings. The body is borrowed, so one request can send many
+    /// bodies without cloning them; see also [`PreparedRequest`].
+    pub async fn execute_body(&self, body: &ChatCompletionsBody) -> Result<ChatCompletionsResponse, Error> {
+        self.execute_body_with(body, |_| {}).await
+    }
+    /// Like [`ChatCompletionsRequest::execute_body`], calling `on_chunk`
+    /// with each chunk as it arrives.
+    ///
+    /// With the `tracing` feature each request runs in a `chat_completions` span
+    /// that records the model, endpoint host, request id and token usage.
+    /// With the `opentelemetry` feature it also reports a GenAI client span
+    /// through the global tracer provider.
+    pub async fn execute_body_with(
+        &self,
+        body: &ChatCompletionsBody,
+        mut on_chunk: impl FnMut(&CompletionChunk),
+    ) -> Result<ChatCompletionsResponse, Error> {
+        let mut result = self.execute_continued(&self.api_endpoint, body, &mut on_chunk).await;
+        for fallback in self.fallbacks.iter() {
+            match result.as_ref() {
+                Err(error) if falls_back(error) => {}
+                _ => break,
+            }
+            let Some(body) = body.adapted_to(&fallback.model) else {
+                continue
+            };
+            #[cfg(feature = "tracing")]
+            if let Err(error) = result.as_ref() {
+                tracing::warn!(error = %error, model = %fallback.model, "falling back");
+            }
+            let endpoint = fallback.api_endpoint.as_ref().unwrap_or(&self.api_endpoint);
+            result = self.execute_continued(endpoint, &body, &mut on_chunk).await;
+        }
+        result
+    }
+    /// Sends the body to `endpoint`, the request's own or a fallback's,
+    /// continuing the reply if it was cut off and the request allows it.
+    async fn execute_continued(
+        &self,
+        endpoint: &ApiEndpoint,
+        body: &ChatCompletionsBody,
+        mut on_chunk: impl FnMut(&CompletionChunk),
+    ) -> Result<ChatCompletionsResponse, Error> {
+        let Some(continuation) = self.continuation.as_ref() else {
+            return self.execute_segment(endpoint, body, on_chunk).await
+        };
+        let mut body = std::borrow::Cow::Borrowed(body);
+        let mut stitched = None;
+        for segment in 1..=continuation.max_segments {
+            let response = self.execute_segment(endpoint, &body, &mut on_chunk).await?;
+            let cut_off = response.finish_reason(0) == Some(FinishReason::Length) && response.tool_calls(0).is_empty();
+            let partial = response.content(0);
+            stitched = Some(match stitched {
+                Some(previous) => stitch(previous, response),
+                None => response,
+            });
+            if !cut_off || segment == continuation.max_segments {
+                break
+            }
+            #[cfg(feature = "tracing")]
+            tracing::debug!(segment, "reply cut off by max_tokens; continuing");
+            let body = body.to_mut();
+            body.messages.push(Message::new(Role::Assistant, partial));
+            body.messages.push(Message::new(Role::User, &continuation.instruction));
+        }
+        Ok(stitched.unwrap_or_default())
+    }
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        name = "chat_completions",
+        skip_all,
+        fields(
+            model = %body.model,
+            host = endpoint.host().unwrap_or_default(),
+            request_id = tracing::field::Empty,
+            status = tracing::field::Empty,
+            chunks = tracing::field::Empty,
+            prompt_tokens = tracing::field::Empty,
+            completion_tokens = tracing::field::Empty,
+            total_tokens = tracing::field::Empty,
+            cached_tokens = tracing::field::Empty,
+        ),
+    ))]
+    async fn execute_segment(
+        &self,
+        endpoint: &ApiEndpoint,
+        body: &ChatCompletionsBody,
+        mut on_chunk: impl FnMut(&CompletionChunk),
+    ) -> Result<ChatCompletionsResponse, Error> {
+        if !self.skip_parameter_checks {
+            let mut diagnostics = body.validate();
+            if !diagnostics.is_ok() {
+                diagnostics.0.iter_mut().for_each(|x| x.prompt = self.prompt_name.clone());
+                return Err(Box::new(diagnostics))
+            }
+        }
+        let scrubbed;
+        let body = match self.pii_scrubber.as_ref() {
+            Some(scrubber) => {
+                scrubbed = scrubber.scrub_body(body);
+                &scrubbed
+            }
+            None => body,
+        };
+        // Screened before the cache lookup, so a cached reply is never
+        // returned for a request the filter would block.
+        if let Some(moderation) = self.moderation.as_ref() {
+            if let Err(error) = moderation.screen(body, &self.observers).await {
+                for observer in self.observers.iter() {
+                    observer.on_error(error.as_ref());
+                }
+                return Err(error)
+            }
+        }
+        #[cfg(feature = "cache")]
+        let cache_key = match self.caches.is_empty() {
+            true => None,
+            false => Some(crate::cache::cache_key(body)?),
+        };
+        #[cfg(feature = "cache")]
+        if let Some(output) = self.cached(cache_key.as_deref()).await {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(chunks = output.len(), "cache hit");
+            let response = self.replay(output, &mut on_chunk);
+            let result = self.postprocess(response).await;
+            for observer in self.observers.iter() {
+                match result.as_ref() {
+                    Ok(response) => observer.on_done(response),
+                    Err(error) => observer.on_error(error.as_ref()),
+                }
+            }
+            return result
+        }
+        #[cfg(feature = "opentelemetry")]
+        let mut span = crate::otel::start_span(endpoint, body);
+        #[cfg(feature = "audit")]
+        let measure = self.metrics.is_some() || self.audit_log.is_some();
+        #[cfg(not(feature = "audit"))]
+        let measure = self.metrics.is_some();
+        let result = match measure {
+            true => self.send_measured(endpoint, body, &mut on_chunk).await,
+            false => self.send(endpoint, body, &mut on_chunk, &mut None).await,
+        };
+        #[cfg(feature = "opentelemetry")]
+        crate::otel::end_span(&mut span, &result);
+        #[cfg(feature = "cache")]
+        if let (Some(key), Ok(response)) = (cache_key, result.as_ref()) {
+            self.store_cached(&key, response).await;
+        }
+        let result = match result {
+            Ok(response) => self.postprocess(response).await,
+            Err(error) => Err(error),
+        };
+        for observer in self.observers.iter() {
+            match result.as_ref() {
+                Ok(response) => observer.on_done(response),
+                Err(error) => observer.on_error(error.as_ref()),
+            }
+        }
+        result
+    }
+    /// Runs the output check, then restores scrubbed personal data, so the
+    /// check never sees it.
+    async fn postprocess(&self, mut response: ChatCompletionsResponse) -> Result<ChatCompletionsResponse, Error> {
+        if let Some(output_check) = self.output_check.as_ref() {
+            response = output_check.apply(response).await?;
+        }
+        if let Some(scrubber) = self.pii_scrubber.as_ref() {
+            response = scrubber.restore_response(response);
+        }
+        Ok(response)
+    }
+    /// Like [`ChatCompletionsRequest::send`], timing the request for the
+    /// metrics sink and audit log.
+    async fn send_measured(
+        &self,
+        endpoint: &ApiEndpoint,
+        body: &ChatCompletionsBody,
+        on_chunk: &mut dyn FnMut(&CompletionChunk),
+    ) -> Result<ChatCompletionsResponse, Error> {
+        let sink = self.metrics.as_deref();
+        let request = crate::metrics::RequestInfo::for_body(endpoint, body);
+        if let Some(sink) = sink {
+            sink.on_request(&request);
+        }
+        let started = std::time::Instant::now();
+        let mut time_to_first_token = None;
+        let mut status = None;
+        let mut on_chunk = |chunk: &CompletionChunk| {
+            if time_to_first_token.is_none() && chunk.has_output() {
+                let latency = started.elapsed();
+                time_to_first_token = Some(latency);
+                if let Some(sink) = sink {
+                    sink.on_first_token(&request, latency);
+                }
             }
+            on_chunk(chunk)
+        };
+        let result = self.send(endpoint, body, &mut on_chunk, &mut status).await;
+        let metrics = crate::metrics::RequestMetrics {
+            request,
+            status,
+            error: result.as_ref().err().map(|e| e.to_string()),
+            latency: started.elapsed(),
+            time_to_first_token,
+            usage: result.as_ref().ok().and_then(|x| x.usage()),
         };
-        let response = client
-            .post(url)
-            .header("Authorization", format!("Bearer {}", api_key))
-            .json(&self.body)
-            .send()
-            .await?;
-        if let Some(error) = ApiError::from_code(response.status().as_u16()) {
+        if let Some(sink) = sink {
+            sink.on_complete(&metrics);
+        }
+        #[cfg(feature = "audit")]
+        if let Some(audit_log) = self.audit_log.as_ref() {
+            audit_log.record(self, &endpoint.serialized_body(body)?, &metrics, &result)?;
+        }
+        result
+    }
+    /// Sends the request and reads the event stream, setting `status` once
+    /// the response arrives.
+    async fn send(
+        &self,
+        endpoint: &ApiEndpoint,
+        body: &ChatCompletionsBody,
+        on_chunk: &mut dyn FnMut(&CompletionChunk),
+        status: &mut Option<u16>,
+    ) -> Result<ChatCompletionsResponse, Error> {
+        let url = endpoint.api_url.as_str();
+        let api_key = endpoint.api_key.expose().as_str();
+        let headers = endpoint.headers();
+        if let Some(request_log) = self.request_log.as_ref() {
+            request_log.log_request(url, api_key, &headers, body);
+        }
+        let mut request = crate::transport::HttpRequest {
+            url: url.to_string(),
+            headers,
+            body: endpoint.serialized_body(body)?,
+            timeout: self.timeout,
+            prompt: self.prompt_name.clone(),
+        };
+        #[cfg(feature = "compression")]
+        self.compression.apply(&mut request)?;
+        endpoint.authenticate(&mut request).await?;
+        let mut progress = crate::observer::ProgressTracker::new(body.max_tokens);
+        let response = match self.transport.as_ref() {
+            Some(transport) => transport.send(request).await?,
+            None => crate::transport::Transport::send(&crate::transport::ReqwestTransport::default(), request).await?,
+        };
+        *status = Some(response.status);
+        if let Some(request_log) = self.request_log.as_ref() {
+            request_log.log_response(response.status);
+        }
+        #[cfg(feature = "tracing")]
+        {
+            let span = tracing::Span::current();
+            span.record("status", response.status);
+            if let Some(request_id) = response.headers.get("x-request-id").and_then(|x| x.to_str().ok()) {
+                span.record("request_id", request_id);
+            }
+        }
+        if let Some(mut error) = ApiError::from_code(response.status) {
+            if let ApiError::RateLimitError(metadata) = &mut error {
+                *metadata = RateLimitMetadata::from_headers(&response.headers).ok();
+            }
+            #[cfg(feature = "tracing")]
+            tracing::warn!(error = %error, "request failed");
             return Err(Box::new(error))
         }
-        let rate_limit_metadata = RateLimitMetadata::from_response(&response).ok();
-        let response = response.bytes_stream();
-        tokio::pin!(response);
+        let rate_limit_metadata = RateLimitMetadata::from_headers(&response.headers).ok();
+        let mut response = response.body;
         let mut results: Vec<CompletionChunk> = Vec::default();
-        while let Some(item) = response.next().await {
-            let chunk = item?;
-            let text = String::from_utf8(chunk.to_vec())?;
-            for line in text.lines() {
-                if line.starts_with("data: ") {
-                    let json_part = &line["data: ".len()..];
-                    if let Ok(response) = serde_json::from_str::<CompletionChunk>(json_part) {
-                        results.push(response.clone());
-                        let msg = response.choices
-                            .iter()
-                            .filter_map(|x| x.delta.content.clone())
-                            .collect::<String>();
-                        if let Some(logger) = self.logger.as_ref() {
-                            let mut logger = logger.borrow_mut();
-                            logger(&msg);
-                        }
+        let mut chunks = 0;
+        let output_bytes = std::cell::Cell::new(0);
+        let mut on_data = |json_part: &[u8]| {
+            let Ok(response) = serde_json::from_slice::<CompletionChunk>(json_part) else {
+                return
+            };
+            if let Some(request_log) = self.request_log.as_ref() {
+                request_log.log_chunk(api_key, &response);
+            }
+            on_chunk(&response);
+            if !self.observers.is_empty() {
+                let msg = response.delta();
+                let progress = progress.update(&response);
+                for observer in self.observers.iter() {
+                    observer.on_chunk(&response);
+                    if !msg.is_empty() {
+                        observer.on_delta(&msg);
+                    }
+                    for (index, delta) in response.choice_deltas() {
+                        observer.on_choice_delta(index, delta);
+                    }
+                    if let Some(progress) = progress.as_ref() {
+                        observer.on_progress(progress);
                     }
                 }
             }
+            chunks += 1;
+            output_bytes.set(output_bytes.get() + response.output_len());
+            match results.last_mut() {
+                Some(folded) if self.aggregation.fold => folded.merge(response),
+                _ => results.push(response),
+            }
+        };
+        let mut decoder = crate::sse::SseDecoder::new();
+        while let Some(item) = response.next().await {
+            decoder.feed(&item?, &mut on_data);
+            if let Some(limit) = self.aggregation.max_output_bytes.filter(|x| output_bytes.get() > *x) {
+                return Err(Box::new(OutputTooLarge { limit }))
+            }
+        }
+        decoder.finish(&mut on_data);
+        if let Some(limit) = self.aggregation.max_output_bytes.filter(|x| output_bytes.get() > *x) {
+            return Err(Box::new(OutputTooLarge { limit }))
         }
         let output = results;
-        Ok(ChatCompletionsResponse { rate_limit_metadata, output })
+        let response = ChatCompletionsResponse { rate_limit_metadata, output, safety: Vec::default() };
+        #[cfg(feature = "tracing")]
+        {
+            let span = tracing::Span::current();
+            span.record("chunks", chunks);
+            if let Some(usage) = response.usage() {
+                span.record("prompt_tokens", usage.prompt_tokens);
+                span.record("completion_tokens", usage.completion_tokens);
+                span.record("total_tokens", usage.total_tokens);
+                span.record("cached_tokens", usage.cached_tokens());
+            }
+            tracing::debug!(finish_reason = response.finish_reason(0).as_ref().map(FinishReason::as_str), "response complete");
+        }
+        Ok(response)
     }
-    pub fn execute_blocking<L: FnMut(&str) -> ()>(&self) -> Result<ChatCompletionsResponse, Error> {
+    #[cfg(feature = "cache")]
+    async fn cached(&self, key: Option<&str>) -> Option<Vec<CompletionChunk>> {
+        let key = key?;
+        for (index, cache) in self.caches.iter().enumerate() {
+            let Ok(Some(value)) = cache.get(key).await else {
+                continue
+            };
+            let Ok(output) = serde_json::from_str(&value) else {
+                continue
+            };
+            for cache in self.caches[..index].iter() {
+                let _ = cache.put(key, &value).await;
+            }
+            return Some(output)
+        }
+        None
+    }
+    /// Failing to store a response does not fail the request.
+    #[cfg(feature = "cache")]
+    async fn store_cached(&self, key: &str, response: &ChatCompletionsResponse) {
+        let Ok(value) = serde_json::to_string(&response.output) else {
+            return
+        };
+        for cache in self.caches.iter() {
+            let _result = cache.put(key, &value).await;
+            #[cfg(feature = "tracing")]
+            if let Err(error) = _result {
+                tracing::warn!(error = %error, "cannot write to cache");
+            }
+        }
+    }
+    /// Passes cached chunks to the callback and observers as though they
+    /// had just streamed in.
+    #[cfg(feature = "cache")]
+    fn replay(&self, output: Vec<CompletionChunk>, on_chunk: &mut dyn FnMut(&CompletionChunk)) -> ChatCompletionsResponse {
+        for chunk in output.iter() {
+            on_chunk(chunk);
+            let msg = chunk.delta();
+            for observer in self.observers.iter() {
+                observer.on_chunk(chunk);
+                if !msg.is_empty() {
+                    observer.on_delta(&msg);
+                }
+                for (index, delta) in chunk.choice_deltas() {
+                    observer.on_choice_delta(index, delta);
+                }
+            }
+        }
+        ChatCompletionsResponse { rate_limit_metadata: None, output, safety: Vec::default() }
+    }
+    pub fn execute_blocking<L: FnMut(&str)>(&self) -> Result<ChatCompletionsResponse, Error> {
         RUNTIME.with(|rt| {
             rt.borrow().block_on(async {
                 self.execute().await
@@ -671,16 +2373,67 @@ impl ChatCompletionsRequest {
     }
 }
 
+/// Appends a continuation to the response it continues. Only the last
+/// segment's finish reasons are kept, and usage is summed onto its final
+/// chunk.
+fn stitch(mut previous: ChatCompletionsResponse, next: ChatCompletionsResponse) -> ChatCompletionsResponse {
+    let usage = match (previous.usage(), next.usage()) {
+        (Some(a), Some(b)) => Some(Usage {
+            prompt_tokens: a.prompt_tokens + b.prompt_tokens,
+            completion_tokens: a.completion_tokens + b.completion_tokens,
+            total_tokens: a.total_tokens + b.total_tokens,
+            prompt_tokens_details: None,
+        }),
+        (a, b) => a.or(b),
+    };
+    for chunk in previous.output.iter_mut() {
+        chunk.usage = None;
+        chunk.choices.iter_mut().for_each(|x| x.finish_reason = None);
+    }
+    previous.output.extend(next.output);
+    for chunk in previous.output.iter_mut().filter(|x| x.usage.is_some()) {
+        chunk.usage = None;
+    }
+    if let Some(last) = previous.output.last_mut() {
+        last.usage = usage;
+    }
+    previous.rate_limit_metadata = next.rate_limit_metadata.or(previous.rate_limit_metadata);
+    previous.safety.extend(next.safety);
+    previous
+}
+
 //―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
 // TODO
 //―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ChatCompletionsResponse {
     pub rate_limit_metadata: Option<RateLimitMetadata>,
     pub output: Vec<CompletionChunk>,
+    /// One verdict per choice when the request has an
+    /// [`crate::moderation::OutputCheck`].
+    pub safety: Vec<crate::moderation::SafetyVerdict>,
 }
 
 impl ChatCompletionsResponse {
+    /// The chunks merged into one; see [`CompletionChunk::merge`].
+    pub fn folded(self) -> Self {
+        let mut output = self.output.into_iter();
+        let Some(mut folded) = output.next() else {
+            return ChatCompletionsResponse { output: Vec::default(), ..self }
+        };
+        output.for_each(|x| folded.merge(x));
+        ChatCompletionsResponse { output: vec![folded], ..self }
+    }
+    /// The indices of the choices present, in order.
+    pub fn choice_indices(&self) -> Vec<usize> {
+        let mut indices = self.output
+            .iter()
+            .flat_map(|chunk| chunk.choices.iter().map(|x| x.index))
+            .collect::<Vec<_>>();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
     pub fn content(&self, index: usize) -> String {
         self.output
             .iter()
@@ -698,4 +2451,146 @@ impl ChatCompletionsResponse {
             .collect::<Vec<_>>()
             .join("")
     }
+    /// The tool calls of choice `index`, reassembled from their fragments.
+    pub fn tool_calls(&self, index: usize) -> Vec<crate::tools::ToolCall> {
+        let mut calls = Vec::<crate::tools::ToolCall>::default();
+        let deltas = self.output
+            .iter()
+            .flat_map(|chunk| chunk.choices.iter())
+            .filter(|choice| choice.index == index)
+            .filter_map(|choice| choice.delta.tool_calls.as_ref())
+            .flatten();
+        for delta in deltas {
+            delta.apply(&mut calls);
+        }
+        calls
+    }
+    /// Choice `index` as an assistant message, ready to append to the
+    /// conversation.
+    pub fn message(&self, index: usize) -> Message {
+        let mut message = Message::new(Role::Assistant, self.content(index));
+        message.tool_calls = self.tool_calls(index);
+        message
+    }
+    pub fn finish_reason(&self, index: usize) -> Option<FinishReason> {
+        self.output
+            .iter()
+            .flat_map(|chunk| chunk.choices.iter())
+            .filter(|choice| choice.index == index)
+            .find_map(|choice| choice.finish_reason.as_deref())
+            .map(FinishReason::from)
+    }
+    pub fn usage(&self) -> Option<Usage> {
+        self.output.iter().find_map(|chunk| chunk.usage)
+    }
+    /// The log probability of each token of choice `index`, if the request
+    /// asked for them.
+    pub fn logprobs(&self, index: usize) -> Vec<TokenLogprob> {
+        self.output
+            .iter()
+            .flat_map(|chunk| chunk.choices.iter())
+            .filter(|choice| choice.index == index)
+            .filter_map(|choice| choice.logprobs.as_ref()?.content.as_ref())
+            .flatten()
+            .cloned()
+            .collect()
+    }
+    /// Every choice, accumulated from the chunks in order.
+    pub fn choices(&self) -> Vec<AccumulatedChoice> {
+        let mut accumulator = ChoiceAccumulator::new();
+        self.output.iter().for_each(|x| accumulator.push(x));
+        accumulator.into_choices()
+    }
+}
+
+/// Builds up each choice of a streamed response as its chunks arrive, for
+/// requests with `n` above 1, where chunks of different choices interleave.
+/// Fragments are kept in the order they arrived for their choice.
+///
+/// ```rust,ignore
+/// let mut accumulator = ChoiceAccumulator::new();
+/// let response = prepared.execute_with(&body, |chunk| {
+///     accumulator.push(chunk);
+///     for choice in accumulator.choices() {
+///         render(choice.index, &choice.content);
+///     }
+/// }).await?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ChoiceAccumulator {
+    choices: Vec<AccumulatedChoice>,
+}
+
+impl ChoiceAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds each choice in `chunk` to the choice of the same index.
+    pub fn push(&mut self, chunk: &CompletionChunk) {
+        for choice in chunk.choices.iter() {
+            let position = match self.choices.binary_search_by_key(&choice.index, |x| x.index) {
+                Ok(position) => position,
+                Err(position) => {
+                    self.choices.insert(position, AccumulatedChoice { index: choice.index, ..Default::default() });
+                    position
+                }
+            };
+            self.choices[position].push(choice);
+        }
+    }
+    /// The choices seen so far, by index.
+    pub fn choices(&self) -> &[AccumulatedChoice] {
+        &self.choices
+    }
+    pub fn choice(&self, index: usize) -> Option<&AccumulatedChoice> {
+        self.choices.iter().find(|x| x.index == index)
+    }
+    /// Whether every choice seen so far has a finish reason.
+    pub fn is_finished(&self) -> bool {
+        !self.choices.is_empty() && self.choices.iter().all(|x| x.finish_reason.is_some())
+    }
+    pub fn into_choices(self) -> Vec<AccumulatedChoice> {
+        self.choices
+    }
+}
+
+/// One choice of a streamed response, as accumulated so far.
+#[derive(Debug, Clone, Default)]
+pub struct AccumulatedChoice {
+    pub index: usize,
+    pub content: String,
+    pub tool_calls: Vec<crate::tools::ToolCall>,
+    pub finish_reason: Option<FinishReason>,
+    /// Where each content fragment ends in `content`.
+    boundaries: Vec<usize>,
+}
+
+impl AccumulatedChoice {
+    /// The content fragments in the order they arrived.
+    pub fn fragments(&self) -> impl Iterator<Item = &str> {
+        let starts = std::iter::once(0).chain(self.boundaries.iter().copied());
+        starts.zip(self.boundaries.iter().copied()).map(|(start, end)| &self.content[start..end])
+    }
+    /// The most recent content fragment.
+    pub fn last_fragment(&self) -> Option<&str> {
+        self.fragments().last()
+    }
+    /// The choice as an assistant message.
+    pub fn message(&self) -> Message {
+        let mut message = Message::new(Role::Assistant, self.content.clone());
+        message.tool_calls = self.tool_calls.clone();
+        message
+    }
+    fn push(&mut self, choice: &ChatResponseChoice) {
+        if let Some(content) = choice.delta.content.as_deref().filter(|x| !x.is_empty()) {
+            self.content.push_str(content);
+            self.boundaries.push(self.content.len());
+        }
+        for delta in choice.delta.tool_calls.iter().flatten() {
+            delta.apply(&mut self.tool_calls);
+        }
+        if let Some(reason) = choice.finish_reason.as_deref() {
+            self.finish_reason = Some(FinishReason::from(reason));
+        }
+    }
 }
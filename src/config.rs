@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::client::{ApiEndpoint, Error, MissingEnvVar};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// CONFIG FILE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Named endpoint profiles, shared by the library and the CLI's
+/// `--profile`:
+///
+/// ```toml
+/// default = "work"
+///
+/// [profiles.work]
+/// base_url = "https://llm.example.com/v1"
+/// api_key_env = "WORK_OPENAI_KEY"
+/// model = "gpt-4o"
+/// organization = "org-123"
+///
+/// [profiles.local]
+/// base_url = "http://localhost:11434/v1"
+/// ```
+///
+/// Keys are never stored in the file, only the names of the environment
+/// variables holding them.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The profile used when none is named.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Config {
+    /// `$CHATGPT_SUBSYSTEMS_CONFIG` if set, otherwise
+    /// `chatgpt-subsystems/config.toml` under `$XDG_CONFIG_HOME` or
+    /// `~/.config`.
+    pub fn path() -> Option<PathBuf> {
+        let var = |name: &str| std::env::var_os(name).filter(|x| !x.is_empty()).map(PathBuf::from);
+        if let Some(path) = var("CHATGPT_SUBSYSTEMS_CONFIG") {
+            return Some(path)
+        }
+        let config_dir = var("XDG_CONFIG_HOME")
+            .or_else(|| var("HOME").map(|x| x.join(".config")))
+            .or_else(|| var("USERPROFILE").map(|x| x.join(".config")))?;
+        Some(config_dir.join("chatgpt-subsystems").join("config.toml"))
+    }
+    /// Reads the file at [`Config::path`]; a missing file is an empty config.
+    pub fn load() -> Result<Self, Error> {
+        match Self::path() {
+            Some(path) if path.exists() => Self::open(path),
+            _ => Ok(Self::default()),
+        }
+    }
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+        toml::from_str(&source).map_err(|error| {
+            format!("Cannot parse config file {}: {}.", path.display(), error.message()).into()
+        })
+    }
+    pub fn profile(&self, name: impl AsRef<str>) -> Result<&Profile, ProfileNotFound> {
+        let name = name.as_ref();
+        self.profiles.get(name).ok_or_else(|| ProfileNotFound(name.to_string()))
+    }
+    /// The profile named by `default`, if any.
+    pub fn default_profile(&self) -> Result<Option<&Profile>, ProfileNotFound> {
+        self.default.as_ref().map(|name| self.profile(name)).transpose()
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// PROFILES
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// For OpenAI compatible APIs; see [`ApiEndpoint::from_base_url`].
+    /// Defaults to OpenAI.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// The environment variable holding the key. Defaults to
+    /// `OPENAI_API_KEY`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
+    /// Used for prompts that do not specify a model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+    /// See [`ApiEndpoint::explicit_nulls`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub explicit_nulls: bool,
+}
+
+impl Profile {
+    pub fn api_key_env(&self) -> &str {
+        self.api_key_env.as_deref().unwrap_or("OPENAI_API_KEY")
+    }
+    /// Reads the key from [`Profile::api_key_env`].
+    pub fn api_key(&self) -> Result<String, MissingEnvVar> {
+        let name = self.api_key_env();
+        std::env::var(name)
+            .ok()
+            .filter(|x| !x.trim().is_empty())
+            .ok_or_else(|| MissingEnvVar(name.to_string()))
+    }
+    pub fn endpoint(&self) -> Result<ApiEndpoint, MissingEnvVar> {
+        let api_key = self.api_key()?;
+        let endpoint = match self.base_url.as_ref() {
+            Some(base_url) => ApiEndpoint::from_base_url(base_url, api_key),
+            None => ApiEndpoint::open_ai_chat_completions(api_key),
+        };
+        let endpoint = endpoint.with_explicit_nulls(self.explicit_nulls);
+        Ok(match self.organization.as_ref() {
+            Some(organization) => endpoint.with_organization(organization),
+            None => endpoint,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProfileNotFound(pub String);
+impl std::fmt::Display for ProfileNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot find profile {:?} in the config file.", self.0)
+    }
+}
+impl std::error::Error for ProfileNotFound {}
@@ -0,0 +1,183 @@
+use std::rc::Rc;
+use regex::Regex;
+
+use crate::client::{ChatCompletionsRequestBuilder, Error, Usage};
+use crate::extract::MissingBody;
+
+/// The temperature samples are drawn at when the body sets none; at the
+/// API's default of 1 answers vary more than self-consistency needs.
+pub const DEFAULT_TEMPERATURE: f32 = 0.7;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// ANSWERS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Lowercases, collapses whitespace and drops surrounding punctuation, so
+/// `"Paris."` and `" paris"` count as the same answer. Returns `None` for
+/// empty text.
+pub fn normalize_answer(text: &str) -> Option<String> {
+    let text = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    let text = text.trim_matches(|x: char| x.is_ascii_punctuation() && !matches!(x, '-' | '$' | '%'));
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// SELF-CONSISTENCY
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+pub type Extractor = Rc<dyn Fn(&str) -> Option<String>>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Sampling {
+    /// One request with `n` set to the number of samples.
+    #[default]
+    Choices,
+    /// One request per sample, for providers that ignore `n`.
+    Repeated,
+}
+
+/// Asks the same question several times and keeps the answer given most
+/// often, which is more accurate than a single reply on reasoning tasks.
+///
+/// Each sample's answer is pulled out by the extractor, by default the
+/// whole reply passed through [`normalize_answer`]; samples without one
+/// do not vote. Ties go to the answer seen first.
+///
+/// ```rust,ignore
+/// let result = SelfConsistency::new(5)
+///     .with_answer_pattern(r"(?i)answer:\s*(.+)")?
+///     .run(&builder)
+///     .await?;
+/// println!("{} ({} of {} votes)", result.answer, result.votes[0].1, result.voters());
+/// ```
+#[derive(Clone)]
+pub struct SelfConsistency {
+    samples: usize,
+    sampling: Sampling,
+    extractor: Extractor,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsistencyResult {
+    /// The winning answer, normalized.
+    pub answer: String,
+    /// The first full reply that gave the winning answer.
+    pub reply: String,
+    /// Every answer with its vote count, most votes first.
+    pub votes: Vec<(String, usize)>,
+    /// Every reply, in order.
+    pub samples: Vec<String>,
+    /// Summed over every request that reported it.
+    pub usage: Usage,
+}
+
+impl ConsistencyResult {
+    /// The samples that had an answer.
+    pub fn voters(&self) -> usize {
+        self.votes.iter().map(|(_, x)| x).sum()
+    }
+    /// The share of voters that gave the winning answer, from 0 to 1.
+    pub fn agreement(&self) -> f64 {
+        match (self.votes.first(), self.voters()) {
+            (Some((_, votes)), voters) if voters > 0 => *votes as f64 / voters as f64,
+            _ => 0.0,
+        }
+    }
+}
+
+impl SelfConsistency {
+    pub fn new(samples: usize) -> Self {
+        SelfConsistency {
+            samples: samples.max(1),
+            sampling: Sampling::Choices,
+            extractor: Rc::new(normalize_answer),
+        }
+    }
+    pub fn with_sampling(mut self, sampling: Sampling) -> Self {
+        self.sampling = sampling;
+        self
+    }
+    /// Pulls the answer out of a reply; return `None` when it has none.
+    /// The result is compared as is, so normalize it as needed.
+    pub fn with_extractor(mut self, extractor: impl Fn(&str) -> Option<String> + 'static) -> Self {
+        self.extractor = Rc::new(extractor);
+        self
+    }
+    /// Takes the answer from the first capture group of the last match, or
+    /// the whole match if the pattern has no groups, normalized.
+    pub fn with_answer_pattern(self, pattern: impl AsRef<str>) -> Result<Self, Error> {
+        let regex = Regex::new(pattern.as_ref())?;
+        Ok(self.with_extractor(move |reply| {
+            let captures = regex.captures_iter(reply).last()?;
+            let answer = captures.get(1).or_else(|| captures.get(0))?;
+            normalize_answer(answer.as_str())
+        }))
+    }
+    /// Samples the builder's body and tallies the answers.
+    pub async fn run(&self, builder: &ChatCompletionsRequestBuilder) -> Result<ConsistencyResult, Error> {
+        let mut body = builder.body.clone().ok_or(Box::new(MissingBody))?;
+        body.temperature = body.temperature.or(Some(DEFAULT_TEMPERATURE));
+        let mut usage = Usage::default();
+        let mut samples = Vec::default();
+        let requests = match self.sampling {
+            Sampling::Choices => {
+                body.n = Some(self.samples);
+                1
+            }
+            Sampling::Repeated => {
+                body.n = None;
+                self.samples
+            }
+        };
+        for _ in 0..requests {
+            let request = builder.clone()
+                .with_body(body.clone())
+                .build()
+                .ok_or(Box::new(MissingBody))?;
+            let response = request.execute().await?;
+            if let Some(x) = response.usage() {
+                usage.prompt_tokens += x.prompt_tokens;
+                usage.completion_tokens += x.completion_tokens;
+                usage.total_tokens += x.total_tokens;
+            }
+            samples.extend(response.choices().into_iter().map(|x| x.content));
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(samples = samples.len(), "self-consistency samples drawn");
+        self.tally(samples, usage)
+    }
+    fn tally(&self, samples: Vec<String>, usage: Usage) -> Result<ConsistencyResult, Error> {
+        let answers = samples.iter().map(|x| (self.extractor)(x)).collect::<Vec<_>>();
+        let mut votes = Vec::<(String, usize)>::default();
+        for answer in answers.iter().flatten() {
+            match votes.iter_mut().find(|(x, _)| x == answer) {
+                Some((_, count)) => *count += 1,
+                None => votes.push((answer.clone(), 1)),
+            }
+        }
+        // Stable, so ties keep the order answers were first seen in.
+        votes.sort_by(|(_, a), (_, b)| b.cmp(a));
+        let Some((answer, _)) = votes.first().cloned() else {
+            return Err(Box::new(NoAnswer { samples: samples.len() }))
+        };
+        let reply = answers
+            .iter()
+            .position(|x| x.as_ref() == Some(&answer))
+            .map(|x| samples[x].clone())
+            .unwrap_or_default();
+        Ok(ConsistencyResult { answer, reply, votes, samples, usage })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NoAnswer {
+    pub samples: usize,
+}
+impl std::fmt::Display for NoAnswer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot pick an answer: none of the {} samples had one.", self.samples)
+    }
+}
+impl std::error::Error for NoAnswer {}
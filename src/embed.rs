@@ -0,0 +1,60 @@
+use crate::xml_dsl::{parse_version, Prompt, PromptCollection};
+
+#[cfg(feature = "derive")]
+pub use chatgpt_subsystems_derive::embed_prompts;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// EMBEDDED PROMPTS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// A prompt compiled into the binary by `embed_prompts!`:
+///
+/// ```ignore
+/// mod prompts {
+///     chatgpt_subsystems::embed::embed_prompts!("prompts/*.xml");
+/// }
+///
+/// let body = prompts::QUESTION_1.prompt().build_body();
+/// let collection = chatgpt_subsystems::embed::collection(prompts::ALL);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct EmbeddedPrompt {
+    pub name: &'static str,
+    pub version: Option<&'static str>,
+    pub variant: Option<&'static str>,
+    /// The file the prompt was read from at build time, relative to the
+    /// crate root.
+    pub file: &'static str,
+    /// The whole file, which may define other prompts too.
+    pub source: &'static str,
+}
+
+impl EmbeddedPrompt {
+    /// Parses the embedded source. The macro has already checked that the
+    /// file is well-formed and defines this prompt, so this only panics if
+    /// the two parsers disagree.
+    pub fn prompt(&self) -> Prompt {
+        // Compared parsed, as the attribute may be written `3` or `v3.0`.
+        let version = self.version.and_then(parse_version);
+        self.collection()
+            .prompts()
+            .iter()
+            .find(|x| {
+                x.name.as_deref() == Some(self.name)
+                    && x.version == version
+                    && x.variant.as_deref() == self.variant
+            })
+            .cloned()
+            .unwrap_or_else(|| panic!("embedded prompt {:?} failed to parse", self.name))
+    }
+    fn collection(&self) -> PromptCollection {
+        PromptCollection::parse(self.source)
+            .unwrap_or_else(|e| panic!("embedded prompt file {} failed to parse: {e}", self.file))
+            .with_source_file(self.file)
+    }
+}
+
+/// Builds a collection from embedded prompts, e.g. the `ALL` constant that
+/// `embed_prompts!` defines.
+pub fn collection(prompts: &[EmbeddedPrompt]) -> PromptCollection {
+    PromptCollection::from_prompts(prompts.iter().map(EmbeddedPrompt::prompt))
+}
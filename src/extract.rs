@@ -0,0 +1,140 @@
+use serde::de::DeserializeOwned;
+
+use crate::client::{ChatCompletionsBody, ChatCompletionsRequestBuilder, Error, Message, ResponseFormat, Role};
+
+/// Retries after the first attempt, used by
+/// [`ChatCompletionsRequestBuilder::extract`].
+pub const DEFAULT_RETRIES: usize = 2;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// TYPED EXTRACTION
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+impl ChatCompletionsRequestBuilder {
+    /// Sends `prompt` as a system message and `input` as a user message,
+    /// then parses the JSON reply into `T`. When parsing fails the reply and
+    /// the parse error are sent back so the model can correct itself.
+    ///
+    /// The body supplies the model and parameters; any messages it already
+    /// has come first. JSON mode is enabled unless a response format is set.
+    pub async fn extract<T: DeserializeOwned>(
+        &self,
+        prompt: impl AsRef<str>,
+        input: impl AsRef<str>,
+    ) -> Result<T, Error> {
+        self.extract_with_retries(prompt, input, DEFAULT_RETRIES).await
+    }
+    pub async fn extract_with_retries<T: DeserializeOwned>(
+        &self,
+        prompt: impl AsRef<str>,
+        input: impl AsRef<str>,
+        max_retries: usize,
+    ) -> Result<T, Error> {
+        let mut body = self.body.clone().ok_or(Box::new(MissingBody))?;
+        let mut prompt = prompt.as_ref().to_string();
+        if body.response_format.is_none() {
+            body.response_format = Some(ResponseFormat::json_object());
+            // JSON mode is rejected unless the messages mention JSON.
+            if !prompt.to_lowercase().contains("json") {
+                prompt.push_str("\n\nRespond with JSON.");
+            }
+        }
+        body.messages.push(Message::new(Role::System, prompt));
+        body.messages.push(Message::new(Role::User, input));
+        let parse = |content: &str| {
+            serde_json::from_str::<T>(strip_code_fence(content))
+                .map_err(|error| error.to_string())
+        };
+        let correction = |error: &str| format!(
+            "Your reply could not be parsed: {error}. \
+             Reply again with only valid JSON in the requested format."
+        );
+        self.run_with_corrections(body, max_retries, parse, correction).await
+    }
+    /// Like [`ChatCompletionsRequestBuilder::extract`], but constrains the
+    /// reply with a strict JSON schema generated from `T`.
+    #[cfg(feature = "schemars")]
+    pub async fn extract_with_schema<T: DeserializeOwned + schemars::JsonSchema>(
+        &self,
+        prompt: impl AsRef<str>,
+        input: impl AsRef<str>,
+    ) -> Result<T, Error> {
+        let body = self.body.clone().ok_or(Box::new(MissingBody))?;
+        let body = body.with_response_format(ResponseFormat::json_schema_for::<T>());
+        self.clone()
+            .with_body(body)
+            .extract_with_retries(prompt, input, DEFAULT_RETRIES)
+            .await
+    }
+}
+
+impl ChatCompletionsRequestBuilder {
+    /// Executes `body`, passing the reply to `parse`. On failure the reply
+    /// and `correction(error)` are appended as a follow-up turn, up to
+    /// `max_retries` times.
+    pub(crate) async fn run_with_corrections<T>(
+        &self,
+        mut body: ChatCompletionsBody,
+        max_retries: usize,
+        parse: impl Fn(&str) -> Result<T, String>,
+        correction: impl Fn(&str) -> String,
+    ) -> Result<T, Error> {
+        // The response is read as a server-sent event stream.
+        body.stream = Some(true);
+        let mut last_error = String::default();
+        let mut last_content = String::default();
+        for _ in 0..=max_retries {
+            let request = self.clone()
+                .with_body(body.clone())
+                .build()
+                .ok_or(Box::new(MissingBody))?;
+            let content = request.execute().await?.content(0);
+            match parse(&content) {
+                Ok(value) => return Ok(value),
+                Err(error) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::info!(error = %error, "reply rejected");
+                    body.messages.push(Message::new(Role::Assistant, &content));
+                    body.messages.push(Message::new(Role::User, correction(&error)));
+                    last_error = error;
+                    last_content = content;
+                }
+            }
+        }
+        Err(Box::new(ExtractionFailed { attempts: max_retries + 1, error: last_error, content: last_content }))
+    }
+}
+
+/// Models often wrap JSON in a Markdown code block despite being asked not
+/// to.
+pub(crate) fn strip_code_fence(content: &str) -> &str {
+    let content = content.trim();
+    let Some(rest) = content.strip_prefix("```") else {
+        return content
+    };
+    let rest = rest.trim_start_matches(|c: char| c.is_ascii_alphanumeric());
+    rest.strip_suffix("```").unwrap_or(rest).trim()
+}
+
+#[derive(Debug, Clone)]
+pub struct MissingBody;
+impl std::fmt::Display for MissingBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Request has no endpoint or body; set them to choose the model.")
+    }
+}
+impl std::error::Error for MissingBody {}
+
+#[derive(Debug, Clone)]
+pub struct ExtractionFailed {
+    pub attempts: usize,
+    /// The last parse error.
+    pub error: String,
+    /// The last reply.
+    pub content: String,
+}
+impl std::fmt::Display for ExtractionFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not parse the reply after {} attempt(s): {}.", self.attempts, self.error)
+    }
+}
+impl std::error::Error for ExtractionFailed {}
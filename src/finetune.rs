@@ -0,0 +1,245 @@
+use std::io::Write;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use crate::client::{ChatCompletionsBody, ChatCompletionsResponse, Error, Message, Role};
+use crate::lint::estimate_tokens;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// EXAMPLES
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// One line of a chat fine-tuning dataset.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrainingExample {
+    pub messages: Vec<Message>,
+}
+
+impl TrainingExample {
+    pub fn new(messages: impl IntoIterator<Item = Message>) -> Self {
+        TrainingExample { messages: messages.into_iter().collect() }
+    }
+    /// A single exchange: an optional system prompt, the input and the
+    /// reply to learn.
+    pub fn from_pair(system: Option<&str>, input: impl AsRef<str>, output: impl AsRef<str>) -> Self {
+        let system = system.map(|x| Message::new(Role::System, x));
+        let messages = system
+            .into_iter()
+            .chain([Message::new(Role::User, input), Message::new(Role::Assistant, output)]);
+        Self::new(messages)
+    }
+    /// The messages sent followed by the reply received.
+    pub fn from_exchange(body: &ChatCompletionsBody, response: &ChatCompletionsResponse) -> Self {
+        Self::new(body.messages.iter().cloned().chain([response.message(0)]))
+    }
+    /// An estimate in the style of OpenAI's counting guide: the tokens of
+    /// every message's text and tool calls, plus a few per message for the
+    /// chat format. See [`estimate_tokens`].
+    pub fn token_count(&self) -> usize {
+        let tokens = self.messages
+            .iter()
+            .map(|message| {
+                let calls = message.tool_calls
+                    .iter()
+                    .map(|x| estimate_tokens(&x.function.name) + estimate_tokens(&x.function.arguments))
+                    .sum::<usize>();
+                let name = message.name.as_ref().map(|_| 1).unwrap_or_default();
+                3 + estimate_tokens(&message.content) + calls + name
+            })
+            .sum::<usize>();
+        tokens + 3
+    }
+    /// What is wrong with the example's role sequence, if anything:
+    ///
+    /// - system messages may only open the conversation;
+    /// - the first other message must be from the user;
+    /// - assistant messages follow a user or tool message and have content
+    ///   or tool calls;
+    /// - tool messages answer a call made by the assistant message before
+    ///   them;
+    /// - the example ends with an assistant message, which is what is
+    ///   learned.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::default();
+        let mut pending_calls = Vec::<&str>::default();
+        let mut previous = None::<&Role>;
+        let mut started = false;
+        for (index, message) in self.messages.iter().enumerate() {
+            let position = index + 1;
+            match message.role {
+                Role::System | Role::Developer => {
+                    if previous.is_some_and(|x| !matches!(x, Role::System | Role::Developer)) {
+                        problems.push(format!("message {position}: {} messages must come first", message.role.as_str()));
+                    }
+                }
+                Role::User => {
+                    if message.content.trim().is_empty() && message.images.is_empty() {
+                        problems.push(format!("message {position}: user message is empty"));
+                    }
+                }
+                Role::Assistant => {
+                    if !matches!(previous, Some(Role::User | Role::Tool)) {
+                        problems.push(format!("message {position}: assistant message must follow a user or tool message"));
+                    }
+                    if message.content.trim().is_empty() && message.tool_calls.is_empty() {
+                        problems.push(format!("message {position}: assistant message is empty"));
+                    }
+                    pending_calls = message.tool_calls.iter().map(|x| x.id.as_str()).collect();
+                }
+                Role::Tool => {
+                    let id = message.tool_call_id.as_deref().unwrap_or_default();
+                    match pending_calls.iter().position(|x| *x == id) {
+                        Some(x) => {
+                            pending_calls.remove(x);
+                        }
+                        None => problems.push(format!("message {position}: tool result {id:?} answers no pending call")),
+                    }
+                }
+                Role::Function => {
+                    problems.push(format!("message {position}: function messages are not supported; use tool messages"));
+                }
+            }
+            if !started && !matches!(message.role, Role::System | Role::Developer) {
+                started = true;
+                if message.role != Role::User {
+                    problems.push(format!("message {position}: the conversation must start with a user message"));
+                }
+            }
+            previous = Some(&message.role);
+        }
+        match self.messages.last() {
+            None => problems.push(String::from("the example has no messages")),
+            Some(x) if x.role != Role::Assistant => problems.push(String::from("the example must end with an assistant message")),
+            Some(_) => {}
+        }
+        problems
+    }
+    pub fn is_valid(&self) -> bool {
+        self.problems().is_empty()
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<&crate::history::RunRecord> for TrainingExample {
+    /// The body sent in a recorded run followed by its first reply.
+    fn from(run: &crate::history::RunRecord) -> Self {
+        let reply = Message::new(Role::Assistant, &run.content);
+        Self::new(run.body.messages.iter().cloned().chain([reply]))
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// DATASET
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Collects examples and writes them in the chat fine-tuning JSONL format,
+/// one `{"messages": [...]}` object per line:
+///
+/// ```rust,ignore
+/// let dataset = FineTuneDataset::new()
+///     .with_max_tokens(65_536)
+///     .with_examples(store.runs(None)?.iter().map(TrainingExample::from))
+///     .with_example(TrainingExample::from_pair(None, "2 + 2?", "4"));
+/// let report = dataset.check();
+/// dataset.retain_valid().write_jsonl("train.jsonl")?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FineTuneDataset {
+    pub examples: Vec<TrainingExample>,
+    max_tokens: Option<usize>,
+}
+
+/// The checks on one example.
+#[derive(Debug, Clone)]
+pub struct ExampleReport {
+    pub index: usize,
+    pub tokens: usize,
+    pub problems: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DatasetReport {
+    pub examples: Vec<ExampleReport>,
+}
+
+impl DatasetReport {
+    pub fn total_tokens(&self) -> usize {
+        self.examples.iter().map(|x| x.tokens).sum()
+    }
+    pub fn invalid(&self) -> impl Iterator<Item = &ExampleReport> {
+        self.examples.iter().filter(|x| !x.problems.is_empty())
+    }
+    pub fn is_valid(&self) -> bool {
+        self.invalid().next().is_none()
+    }
+}
+
+impl FineTuneDataset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Examples estimated above this many tokens are reported; training
+    /// would truncate them.
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+    pub fn with_example(mut self, example: TrainingExample) -> Self {
+        self.examples.push(example);
+        self
+    }
+    pub fn with_examples(mut self, examples: impl IntoIterator<Item = TrainingExample>) -> Self {
+        self.examples.extend(examples);
+        self
+    }
+    /// Counts the tokens of every example and lists its problems.
+    pub fn check(&self) -> DatasetReport {
+        let examples = self.examples
+            .iter()
+            .enumerate()
+            .map(|(index, example)| {
+                let tokens = example.token_count();
+                let mut problems = example.problems();
+                if let Some(max_tokens) = self.max_tokens.filter(|x| tokens > *x) {
+                    problems.push(format!("about {tokens} tokens, over the limit of {max_tokens}"));
+                }
+                ExampleReport { index, tokens, problems }
+            })
+            .collect();
+        DatasetReport { examples }
+    }
+    /// Drops the examples [`FineTuneDataset::check`] finds problems with.
+    pub fn retain_valid(mut self) -> Self {
+        let report = self.check();
+        let mut reports = report.examples.iter();
+        self.examples.retain(|_| reports.next().is_some_and(|x| x.problems.is_empty()));
+        self
+    }
+    /// Fails with [`InvalidDataset`], writing nothing, if any example has
+    /// problems.
+    pub fn write_jsonl(&self, path: impl AsRef<Path>) -> Result<DatasetReport, Error> {
+        let report = self.check();
+        if !report.is_valid() {
+            return Err(Box::new(InvalidDataset { examples: report.invalid().cloned().collect() }))
+        }
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        for example in self.examples.iter() {
+            writeln!(file, "{}", serde_json::to_string(example)?)?;
+        }
+        file.flush()?;
+        Ok(report)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidDataset {
+    pub examples: Vec<ExampleReport>,
+}
+impl std::fmt::Display for InvalidDataset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let first = self.examples
+            .first()
+            .map(|x| format!("example {}: {}", x.index, x.problems.join("; ")))
+            .unwrap_or_default();
+        write!(f, "Cannot write the dataset: {} example(s) have problems, e.g. {first}.", self.examples.len())
+    }
+}
+impl std::error::Error for InvalidDataset {}
@@ -0,0 +1,398 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use crate::client as api;
+use crate::params::{ParamDeclaration, ParamType};
+use crate::xml_dsl::{parse_image_detail, parse_response_format, parse_version, ForEach, MessageNode, Prompt, PromptCollection, PromptMetadata};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// SCHEMA
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Mirrors the attributes of the `<prompt>` element.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PromptDocument {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub variant: Option<String>,
+    pub weight: Option<f32>,
+    /// A provider name, e.g. `openai` or `octoai`.
+    pub endpoint: Option<String>,
+    pub base_url: Option<String>,
+    pub model: Option<String>,
+    pub stream: Option<bool>,
+    pub temperature: Option<f32>,
+    pub n: Option<usize>,
+    pub max_tokens: Option<usize>,
+    pub top_p: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub presence_penalty: Option<f32>,
+    pub logprobs: Option<bool>,
+    pub top_logprobs: Option<usize>,
+    /// Up to 4 sequences where the API will stop generating.
+    #[serde(default)]
+    pub stop: Vec<String>,
+    pub seed: Option<isize>,
+    /// Either `json-object` or `text`.
+    pub response_format: Option<String>,
+    /// Models tried in order when `model` fails.
+    #[serde(default)]
+    pub fallback: Vec<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub params: Vec<ParamDocument>,
+    #[serde(default)]
+    pub messages: Vec<MessageDocument>,
+}
+
+/// Mirrors `<param>`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ParamDocument {
+    pub name: String,
+    /// One of `string`, `int`, `bool` or `list`; defaults to `string`.
+    #[serde(rename = "type")]
+    pub param_type: Option<String>,
+    pub required: Option<bool>,
+    /// A string, number, boolean or list; converted to the declared type.
+    pub default: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum MessageDocument {
+    Message(MessageEntry),
+    ForEach(ForEachEntry),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MessageEntry {
+    /// Defaults to `user`, as in the XML DSL.
+    pub role: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageDocument>,
+}
+
+/// Mirrors `<image>`: `src` for local files, `url` for web URLs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ImageDocument {
+    pub src: Option<String>,
+    pub url: Option<String>,
+    /// One of `auto`, `low` or `high`.
+    pub detail: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ForEachEntry {
+    #[serde(rename = "for-each")]
+    pub for_each: ForEachDocument,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ForEachDocument {
+    pub var: Option<String>,
+    #[serde(rename = "in")]
+    pub source: String,
+    #[serde(default)]
+    pub messages: Vec<MessageDocument>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidValue(pub String, pub String);
+impl std::fmt::Display for InvalidValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid value {:?} for field '{}'.", self.1, self.0)
+    }
+}
+impl std::error::Error for InvalidValue {}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// LOADERS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+impl PromptCollection {
+    /// Parses a YAML sequence of prompts.
+    ///
+    /// ```yaml
+    /// - name: question-1
+    ///   model: gpt-3.5-turbo-0125
+    ///   max-tokens: 4000
+    ///   messages:
+    ///     - role: system
+    ///       content: |
+    ///         You are a helpful assistant.
+    ///     - content: What is ChatGPT?
+    /// ```
+    pub fn from_yaml(contents: impl AsRef<str>) -> Result<Self, api::Error> {
+        let documents: Vec<PromptDocument> = serde_yaml::from_str(contents.as_ref())?;
+        Self::from_documents(documents)
+    }
+    pub fn open_yaml(file_path: impl AsRef<Path>) -> Result<Self, api::Error> {
+        let file_path = file_path.as_ref();
+        let source = std::fs::read_to_string(file_path)?;
+        let collection = Self::from_yaml(source)?;
+        Ok(collection.with_source_file(file_path))
+    }
+    /// Parses a JSON array of prompt objects, using the same fields as the
+    /// YAML format.
+    pub fn from_json(contents: impl AsRef<str>) -> Result<Self, api::Error> {
+        let documents: Vec<PromptDocument> = serde_json::from_str(contents.as_ref())?;
+        Self::from_documents(documents)
+    }
+    pub fn from_json_value(value: serde_json::Value) -> Result<Self, api::Error> {
+        let documents: Vec<PromptDocument> = serde_json::from_value(value)?;
+        Self::from_documents(documents)
+    }
+    pub fn open_json(file_path: impl AsRef<Path>) -> Result<Self, api::Error> {
+        let file_path = file_path.as_ref();
+        let source = std::fs::read_to_string(file_path)?;
+        let collection = Self::from_json(source)?;
+        Ok(collection.with_source_file(file_path))
+    }
+    pub fn from_documents(documents: impl IntoIterator<Item = PromptDocument>) -> Result<Self, api::Error> {
+        let prompts = documents
+            .into_iter()
+            .map(Prompt::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(PromptCollection::from_prompts(prompts))
+    }
+}
+
+impl TryFrom<PromptDocument> for Prompt {
+    type Error = api::Error;
+    fn try_from(document: PromptDocument) -> Result<Self, Self::Error> {
+        let version = document.version
+            .map(|x| parse_version(&x).ok_or(InvalidValue(String::from("version"), x)))
+            .transpose()?;
+        if let Some(endpoint) = document.endpoint.as_ref() {
+            if api::ApiEndpoint::for_provider(endpoint, "").is_none() {
+                return Err(Box::new(InvalidValue(String::from("endpoint"), endpoint.clone())))
+            }
+        }
+        if let Some(weight) = document.weight.filter(|x| *x < 0.0) {
+            return Err(Box::new(InvalidValue(String::from("weight"), weight.to_string())))
+        }
+        let response_format = document.response_format
+            .map(|x| parse_response_format(&x).ok_or(InvalidValue(String::from("response-format"), x)))
+            .transpose()?;
+        let configuration = api::ConfigurationBuilder {
+            model: document.model,
+            stream: document.stream,
+            temperature: document.temperature,
+            n: document.n,
+            max_tokens: document.max_tokens,
+            top_p: document.top_p,
+            frequency_penalty: document.frequency_penalty,
+            presence_penalty: document.presence_penalty,
+            logprobs: document.logprobs,
+            top_logprobs: document.top_logprobs,
+            response_format,
+            stop: (!document.stop.is_empty()).then_some(document.stop),
+            seed: document.seed,
+        };
+        let metadata = PromptMetadata {
+            description: document.description,
+            tags: document.tags,
+            owner: document.owner,
+            extra: Default::default(),
+        };
+        let params = document.params
+            .into_iter()
+            .map(to_param_declaration)
+            .collect::<Result<Vec<_>, _>>()?;
+        let messages = to_message_nodes(document.messages)?;
+        Ok(Prompt {
+            name: document.name,
+            version,
+            variant: document.variant,
+            weight: document.weight,
+            endpoint: document.endpoint,
+            base_url: document.base_url,
+            fallbacks: document.fallback,
+            source_file: None,
+            configuration,
+            overrides: Default::default(),
+            metadata,
+            params,
+            messages,
+            assertions: Vec::default(),
+            diagnostics: Vec::default(),
+        })
+    }
+}
+
+fn to_param_declaration(document: ParamDocument) -> Result<ParamDeclaration, InvalidValue> {
+    let param_type = document.param_type
+        .map(|x| x.parse::<ParamType>().map_err(|x| InvalidValue(String::from("type"), x)))
+        .transpose()?
+        .unwrap_or_default();
+    let default = document.default.map(|x| match x {
+        serde_json::Value::String(x) => x,
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(|x| x.as_str().map(str::to_string).unwrap_or_else(|| x.to_string()))
+            .collect::<Vec<_>>()
+            .join(", "),
+        other => other.to_string(),
+    });
+    let declaration = ParamDeclaration { name: document.name, param_type, required: document.required, default };
+    if let Some(default) = declaration.default.as_ref().filter(|_| declaration.default_value().is_none()) {
+        return Err(InvalidValue(String::from("default"), default.clone()))
+    }
+    Ok(declaration)
+}
+
+fn to_message_nodes(documents: Vec<MessageDocument>) -> Result<Vec<MessageNode>, api::Error> {
+    let mut nodes = Vec::default();
+    for document in documents {
+        match document {
+            MessageDocument::Message(entry) => {
+                let role = entry.role.unwrap_or_else(|| String::from("user"));
+                let role = role.parse::<api::Role>().map_err(|_| InvalidValue(String::from("role"), role))?;
+                let content = unindent::unindent(entry.content.trim());
+                let mut images = Vec::default();
+                for image in entry.images {
+                    let url = image.src
+                        .or(image.url)
+                        .ok_or(InvalidValue(String::from("images"), String::from("missing src or url")))?;
+                    let detail = image.detail
+                        .map(|x| parse_image_detail(&x).ok_or(InvalidValue(String::from("detail"), x)))
+                        .transpose()?;
+                    images.push(api::ImageUrl { url, detail });
+                }
+                let name = entry.name;
+                nodes.push(MessageNode::Message(api::Message { images, name, ..api::Message::new(role, content) }));
+            }
+            MessageDocument::ForEach(ForEachEntry { for_each }) => {
+                let var = for_each.var.unwrap_or_else(|| String::from("item"));
+                let children = to_message_nodes(for_each.messages)?;
+                nodes.push(MessageNode::ForEach(ForEach { var, source: for_each.source, children }));
+            }
+        }
+    }
+    Ok(nodes)
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// PLAYGROUND
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// A preset exported from the OpenAI Playground: the JSON body shown under
+/// "View code", optionally with a name and description. Fields the prompt
+/// format has no place for, such as `tools`, are ignored.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PlaygroundPreset {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub model: Option<String>,
+    /// Some exports keep the system prompt apart from the messages.
+    #[serde(alias = "instructions")]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub messages: Vec<api::Message>,
+    pub temperature: Option<f32>,
+    #[serde(alias = "max_completion_tokens")]
+    pub max_tokens: Option<usize>,
+    pub top_p: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub presence_penalty: Option<f32>,
+    /// A string or a list of strings.
+    pub stop: Option<serde_json::Value>,
+    pub seed: Option<isize>,
+    pub response_format: Option<api::ResponseFormat>,
+}
+
+impl PlaygroundPreset {
+    pub fn configuration(&self) -> api::ConfigurationBuilder {
+        let stop = match self.stop.as_ref() {
+            Some(serde_json::Value::String(x)) => Some(vec![x.clone()]),
+            Some(serde_json::Value::Array(xs)) => Some(xs.iter().filter_map(|x| x.as_str().map(str::to_string)).collect()),
+            _ => None,
+        };
+        api::ConfigurationBuilder {
+            model: self.model.clone(),
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            top_p: self.top_p,
+            frequency_penalty: self.frequency_penalty,
+            presence_penalty: self.presence_penalty,
+            response_format: self.response_format.clone(),
+            stop,
+            seed: self.seed,
+            ..Default::default()
+        }
+    }
+    /// The system prompt, if kept apart, followed by the messages.
+    pub fn messages(&self) -> Vec<api::Message> {
+        let system = self.system
+            .as_ref()
+            .filter(|x| !x.trim().is_empty())
+            .map(|x| api::Message::new(api::Role::System, x));
+        system.into_iter().chain(self.messages.iter().cloned()).collect()
+    }
+    /// The request the Playground would send, with the messages taken as
+    /// they are rather than rendered as templates. `None` without a model.
+    pub fn to_body(&self) -> Option<api::ChatCompletionsBody> {
+        self.configuration().build(self.messages())
+    }
+}
+
+impl From<PlaygroundPreset> for Prompt {
+    fn from(preset: PlaygroundPreset) -> Self {
+        let configuration = preset.configuration();
+        let messages = preset.messages().into_iter().map(MessageNode::Message).collect();
+        Prompt {
+            name: preset.name,
+            version: None,
+            variant: None,
+            weight: None,
+            endpoint: None,
+            base_url: None,
+            fallbacks: Vec::default(),
+            source_file: None,
+            configuration,
+            overrides: Default::default(),
+            metadata: PromptMetadata { description: preset.description, ..Default::default() },
+            params: Vec::default(),
+            messages,
+            assertions: Vec::default(),
+            diagnostics: Vec::default(),
+        }
+    }
+}
+
+impl Prompt {
+    /// Converts an exported Playground preset, so prompts prototyped there
+    /// can join a collection:
+    ///
+    /// ```rust,ignore
+    /// let prompt = Prompt::open_playground("presets/summarize.json")?;
+    /// std::fs::write("prompts/summarize.xml", prompt.to_xml())?;
+    /// ```
+    ///
+    /// Message text is rendered as a template like any other prompt's, which
+    /// suits the Playground's `{{variable}}` placeholders.
+    pub fn from_playground(contents: impl AsRef<str>) -> Result<Self, api::Error> {
+        let preset: PlaygroundPreset = serde_json::from_str(contents.as_ref())?;
+        Ok(Prompt::from(preset))
+    }
+    /// Like [`Prompt::from_playground`]; a preset without a name is named
+    /// after the file.
+    pub fn open_playground(file_path: impl AsRef<Path>) -> Result<Self, api::Error> {
+        let file_path = file_path.as_ref();
+        let mut prompt = Self::from_playground(std::fs::read_to_string(file_path)?)?;
+        if prompt.name.is_none() {
+            prompt.name = file_path.file_stem().map(|x| x.to_string_lossy().into_owned());
+        }
+        prompt.source_file = Some(file_path.to_path_buf());
+        Ok(prompt)
+    }
+}
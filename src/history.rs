@@ -0,0 +1,288 @@
+use std::path::Path;
+use std::rc::Rc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::client::{ChatCompletionsBody, ChatCompletionsResponse, Error, Message, Usage};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS conversations (
+        id INTEGER PRIMARY KEY,
+        title TEXT,
+        created_at TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS messages (
+        id INTEGER PRIMARY KEY,
+        conversation_id INTEGER NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+        role TEXT NOT NULL,
+        content TEXT NOT NULL,
+        message TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS messages_conversation ON messages(conversation_id, id);
+    CREATE TABLE IF NOT EXISTS runs (
+        id INTEGER PRIMARY KEY,
+        conversation_id INTEGER REFERENCES conversations(id) ON DELETE SET NULL,
+        model TEXT NOT NULL,
+        body TEXT NOT NULL,
+        content TEXT NOT NULL,
+        output TEXT NOT NULL,
+        prompt_tokens INTEGER,
+        completion_tokens INTEGER,
+        total_tokens INTEGER,
+        created_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS runs_conversation ON runs(conversation_id, id);
+    CREATE INDEX IF NOT EXISTS runs_model ON runs(model);
+";
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// RECORDS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConversationSummary {
+    pub id: i64,
+    pub title: Option<String>,
+    /// RFC 3339, in UTC.
+    pub created_at: String,
+    pub message_count: usize,
+}
+
+/// A request sent and the response it got.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RunRecord {
+    pub id: i64,
+    pub conversation_id: Option<i64>,
+    pub model: String,
+    pub body: ChatCompletionsBody,
+    /// The content of the first choice.
+    pub content: String,
+    pub usage: Option<Usage>,
+    /// RFC 3339, in UTC.
+    pub created_at: String,
+}
+
+/// Token totals for one model across every run.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ModelUsage {
+    pub model: String,
+    pub runs: usize,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    pub total_tokens: usize,
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// STORE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Keeps conversations and the requests sent for them in a SQLite
+/// database, so history survives restarts without designing a schema.
+/// The tables are created on open; cloning shares the connection.
+///
+/// ```rust,ignore
+/// let store = SqliteStore::open("history.db")?;
+/// let conversation = store.create_conversation(Some("Support"))?;
+/// store.append_message(conversation, &Message::new(Role::User, "Hello"))?;
+/// let body = ChatCompletionsBody::new("gpt-4o-mini", store.messages(conversation)?);
+/// let response = prepared.execute(&body).await?;
+/// store.record_run(Some(conversation), &body, &response)?;
+/// store.append_message(conversation, &response.message(0))?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    connection: Rc<Connection>,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_connection(Connection::open(path)?)
+    }
+    /// A database that lasts as long as the store.
+    pub fn in_memory() -> Result<Self, Error> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+    /// Uses an open connection, creating the tables if needed.
+    pub fn from_connection(connection: Connection) -> Result<Self, Error> {
+        connection.execute_batch("PRAGMA foreign_keys = ON;")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(SqliteStore { connection: Rc::new(connection) })
+    }
+    /// The underlying connection, for queries the helpers do not cover.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+    pub fn create_conversation(&self, title: Option<&str>) -> Result<i64, Error> {
+        self.connection.execute(
+            "INSERT INTO conversations (title, created_at) VALUES (?1, ?2)",
+            params![title, now()],
+        )?;
+        Ok(self.connection.last_insert_rowid())
+    }
+    pub fn rename_conversation(&self, conversation: i64, title: Option<&str>) -> Result<(), Error> {
+        self.connection.execute("UPDATE conversations SET title = ?1 WHERE id = ?2", params![title, conversation])?;
+        Ok(())
+    }
+    /// Removes the conversation and its messages. Its runs are kept,
+    /// detached from it.
+    pub fn delete_conversation(&self, conversation: i64) -> Result<(), Error> {
+        self.connection.execute("DELETE FROM conversations WHERE id = ?1", params![conversation])?;
+        Ok(())
+    }
+    pub fn conversation(&self, conversation: i64) -> Result<Option<ConversationSummary>, Error> {
+        let summary = self.connection
+            .query_row(
+                &format!("{SUMMARY_QUERY} WHERE c.id = ?1 GROUP BY c.id"),
+                params![conversation],
+                summary_from_row,
+            )
+            .optional()?;
+        Ok(summary)
+    }
+    /// Every conversation, newest first.
+    pub fn conversations(&self) -> Result<Vec<ConversationSummary>, Error> {
+        let mut statement = self.connection.prepare(&format!("{SUMMARY_QUERY} GROUP BY c.id ORDER BY c.id DESC"))?;
+        let summaries = statement.query_map([], summary_from_row)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(summaries)
+    }
+    pub fn append_message(&self, conversation: i64, message: &Message) -> Result<i64, Error> {
+        self.connection.execute(
+            "INSERT INTO messages (conversation_id, role, content, message, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                conversation,
+                message.role.as_str(),
+                message.content,
+                serde_json::to_string(message)?,
+                now(),
+            ],
+        )?;
+        Ok(self.connection.last_insert_rowid())
+    }
+    /// The conversation's messages in the order appended, ready to send.
+    pub fn messages(&self, conversation: i64) -> Result<Vec<Message>, Error> {
+        let mut statement = self.connection.prepare("SELECT message FROM messages WHERE conversation_id = ?1 ORDER BY id")?;
+        let rows = statement.query_map(params![conversation], |row| row.get::<_, String>(0))?;
+        let mut messages = Vec::default();
+        for row in rows {
+            messages.push(serde_json::from_str(&row?)?);
+        }
+        Ok(messages)
+    }
+    /// The conversations with a message containing `text`, newest first.
+    pub fn search(&self, text: &str) -> Result<Vec<ConversationSummary>, Error> {
+        let mut statement = self.connection.prepare(&format!(
+            "{SUMMARY_QUERY} WHERE c.id IN (SELECT conversation_id FROM messages WHERE instr(content, ?1) > 0) \
+             GROUP BY c.id ORDER BY c.id DESC"
+        ))?;
+        let summaries = statement.query_map(params![text], summary_from_row)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(summaries)
+    }
+    /// Stores the body sent, the response's chunks and its usage.
+    pub fn record_run(
+        &self,
+        conversation: Option<i64>,
+        body: &ChatCompletionsBody,
+        response: &ChatCompletionsResponse,
+    ) -> Result<i64, Error> {
+        let usage = response.usage();
+        self.connection.execute(
+            "INSERT INTO runs (conversation_id, model, body, content, output, prompt_tokens, completion_tokens, total_tokens, created_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                conversation,
+                body.model,
+                serde_json::to_string(body)?,
+                response.content(0),
+                serde_json::to_string(&response.output)?,
+                usage.map(|x| x.prompt_tokens as i64),
+                usage.map(|x| x.completion_tokens as i64),
+                usage.map(|x| x.total_tokens as i64),
+                now(),
+            ],
+        )?;
+        Ok(self.connection.last_insert_rowid())
+    }
+    /// Runs newest first, all of them or a conversation's only.
+    pub fn runs(&self, conversation: Option<i64>) -> Result<Vec<RunRecord>, Error> {
+        let mut statement = self.connection.prepare(
+            "SELECT id, conversation_id, model, body, content, prompt_tokens, completion_tokens, total_tokens, created_at \
+             FROM runs WHERE ?1 IS NULL OR conversation_id = ?1 ORDER BY id DESC",
+        )?;
+        let rows = statement.query_map(params![conversation], |row| {
+            let tokens = (
+                row.get::<_, Option<i64>>(5)?,
+                row.get::<_, Option<i64>>(6)?,
+                row.get::<_, Option<i64>>(7)?,
+            );
+            let usage = match tokens {
+                (Some(prompt), Some(completion), Some(total)) => Some(Usage {
+                    prompt_tokens: prompt as usize,
+                    completion_tokens: completion as usize,
+                    total_tokens: total as usize,
+                    prompt_tokens_details: None,
+                }),
+                _ => None,
+            };
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                usage,
+                row.get::<_, String>(8)?,
+            ))
+        })?;
+        let mut runs = Vec::default();
+        for row in rows {
+            let (id, conversation_id, model, body, content, usage, created_at) = row?;
+            let body = serde_json::from_str(&body)?;
+            runs.push(RunRecord { id, conversation_id, model, body, content, usage, created_at });
+        }
+        Ok(runs)
+    }
+    /// The stored response of a run, as it was returned.
+    pub fn response(&self, run: i64) -> Result<Option<ChatCompletionsResponse>, Error> {
+        let output = self.connection
+            .query_row("SELECT output FROM runs WHERE id = ?1", params![run], |row| row.get::<_, String>(0))
+            .optional()?;
+        let Some(output) = output else {
+            return Ok(None)
+        };
+        let output = serde_json::from_str(&output)?;
+        Ok(Some(ChatCompletionsResponse { output, ..ChatCompletionsResponse::default() }))
+    }
+    /// Token totals per model, most used first.
+    pub fn usage_by_model(&self) -> Result<Vec<ModelUsage>, Error> {
+        let mut statement = self.connection.prepare(
+            "SELECT model, COUNT(*), SUM(COALESCE(prompt_tokens, 0)), SUM(COALESCE(completion_tokens, 0)), \
+             SUM(COALESCE(total_tokens, 0)) AS total FROM runs GROUP BY model ORDER BY total DESC, model",
+        )?;
+        let usage = statement
+            .query_map([], |row| Ok(ModelUsage {
+                model: row.get(0)?,
+                runs: row.get::<_, i64>(1)? as usize,
+                prompt_tokens: row.get::<_, i64>(2)? as usize,
+                completion_tokens: row.get::<_, i64>(3)? as usize,
+                total_tokens: row.get::<_, i64>(4)? as usize,
+            }))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(usage)
+    }
+}
+
+const SUMMARY_QUERY: &str = "SELECT c.id, c.title, c.created_at, COUNT(m.id) \
+    FROM conversations c LEFT JOIN messages m ON m.conversation_id = c.id";
+
+fn summary_from_row(row: &rusqlite::Row) -> rusqlite::Result<ConversationSummary> {
+    Ok(ConversationSummary {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        created_at: row.get(2)?,
+        message_count: row.get::<_, i64>(3)? as usize,
+    })
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
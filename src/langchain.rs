@@ -0,0 +1,410 @@
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use serde_json::Value;
+
+use crate::client::{self as api, Message, Role};
+use crate::validate::{Diagnostic, DiagnosticKind};
+use crate::xml_dsl::{MessageNode, Prompt, PromptMetadata};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// TEMPLATE SYNTAX
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// The `template_format` of a LangChain prompt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TemplateFormat {
+    /// Python's `str.format` syntax: `{name}`, with `{{` and `}}` for
+    /// literal braces.
+    #[default]
+    FString,
+    Jinja2,
+    Mustache,
+}
+
+impl FromStr for TemplateFormat {
+    type Err = UnsupportedTemplate;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "f-string" | "fstring" | "f_string" => Ok(TemplateFormat::FString),
+            "jinja2" | "jinja" => Ok(TemplateFormat::Jinja2),
+            "mustache" => Ok(TemplateFormat::Mustache),
+            _ => Err(UnsupportedTemplate(format!("unknown template format {value:?}"))),
+        }
+    }
+}
+
+/// Rewrites a template into the liquid syntax message bodies use.
+///
+/// f-string fields become `{{ name }}`; format specs and conversions
+/// such as `{price:.2f}` have no equivalent and are rejected. Jinja's
+/// common differences from liquid are translated: `elif`, `set`, filter
+/// calls like `join(", ")`, `loop.` and `{# comments #}`. Mustache
+/// variables carry over; sections are rejected.
+pub fn to_liquid(template: &str, format: TemplateFormat) -> Result<String, UnsupportedTemplate> {
+    match format {
+        TemplateFormat::FString => fstring_to_liquid(template),
+        TemplateFormat::Jinja2 => Ok(jinja_to_liquid(template)),
+        TemplateFormat::Mustache => mustache_to_liquid(template),
+    }
+}
+
+/// Text that renders as itself, for messages that are not templates.
+pub fn escape_liquid(text: &str) -> String {
+    match text.contains("{{") || text.contains("{%") {
+        true => format!("{{% raw %}}{text}{{% endraw %}}"),
+        false => text.to_string(),
+    }
+}
+
+fn fstring_to_liquid(template: &str) -> Result<String, UnsupportedTemplate> {
+    let mut output = String::default();
+    let mut literal = String::default();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut field = String::default();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(x) => field.push(x),
+                        None => return Err(UnsupportedTemplate(format!("the field {{{field} is not closed"))),
+                    }
+                }
+                if field.contains([':', '!']) {
+                    return Err(UnsupportedTemplate(format!("format specs such as {{{field}}} are not supported")))
+                }
+                let field = field.trim();
+                if field.is_empty() || field.starts_with(|x: char| x.is_ascii_digit()) {
+                    return Err(UnsupportedTemplate(format!("positional fields such as {{{field}}} are not supported")))
+                }
+                output.push_str(&escape_liquid(&std::mem::take(&mut literal)));
+                output.push_str(&format!("{{{{ {} }}}}", fstring_field(field)));
+            }
+            '}' => return Err(UnsupportedTemplate(String::from("a literal '}' must be written '}}'"))),
+            _ => literal.push(c),
+        }
+    }
+    output.push_str(&escape_liquid(&literal));
+    Ok(output)
+}
+
+/// `user.name` stays as is; `doc[title]` indexes by key in Python's format
+/// syntax and becomes `doc.title`.
+fn fstring_field(field: &str) -> String {
+    let mut output = String::default();
+    let mut rest = field;
+    while let Some(start) = rest.find('[') {
+        output.push_str(&rest[..start]);
+        let end = rest[start..].find(']').map(|x| start + x).unwrap_or(rest.len() - 1);
+        let key = &rest[start + 1..end];
+        match key.chars().all(|x| x.is_ascii_digit()) {
+            true => output.push_str(&format!("[{key}]")),
+            false => output.push_str(&format!(".{key}")),
+        }
+        rest = &rest[(end + 1).min(rest.len())..];
+    }
+    output.push_str(rest);
+    output
+}
+
+fn jinja_to_liquid(template: &str) -> String {
+    // Compiled once; this runs for every message of every converted prompt.
+    static COMMENTS: OnceLock<regex::Regex> = OnceLock::new();
+    static TAGS: OnceLock<regex::Regex> = OnceLock::new();
+    static FILTER_CALL: OnceLock<regex::Regex> = OnceLock::new();
+    static LOOP_VARIABLE: OnceLock<regex::Regex> = OnceLock::new();
+    let comments = COMMENTS.get_or_init(|| regex::Regex::new(r"(?s)\{#.*?#\}").unwrap());
+    let tags = TAGS.get_or_init(|| regex::Regex::new(r"(?s)\{\{.*?\}\}|\{%.*?%\}").unwrap());
+    let filter_call = FILTER_CALL.get_or_init(|| regex::Regex::new(r"\|\s*(\w+)\(([^()]*)\)").unwrap());
+    let loop_variable = LOOP_VARIABLE.get_or_init(|| regex::Regex::new(r"\bloop\.").unwrap());
+    let template = comments.replace_all(template, "");
+    let template = tags.replace_all(&template, |captures: &regex::Captures| {
+        let tag = filter_call.replace_all(&captures[0], |x: &regex::Captures| match x[2].trim() {
+            "" => format!("| {}", &x[1]),
+            arguments => format!("| {}: {arguments}", &x[1]),
+        });
+        loop_variable
+            .replace_all(&tag, "forloop.")
+            .replacen("{% elif ", "{% elsif ", 1)
+            .replacen("{%- elif ", "{%- elsif ", 1)
+            .replacen("{% set ", "{% assign ", 1)
+            .replacen("{%- set ", "{%- assign ", 1)
+    });
+    template.into_owned()
+}
+
+fn mustache_to_liquid(template: &str) -> Result<String, UnsupportedTemplate> {
+    static TAGS: OnceLock<regex::Regex> = OnceLock::new();
+    let tags = TAGS.get_or_init(|| regex::Regex::new(r"\{\{\{?\s*([#^/!&>]?)\s*([^}]*?)\s*\}?\}\}").unwrap());
+    let mut output = String::default();
+    let mut last = 0;
+    for captures in tags.captures_iter(template) {
+        let whole = captures.get(0).unwrap();
+        output.push_str(&escape_liquid(&template[last..whole.start()]));
+        last = whole.end();
+        match &captures[1] {
+            "!" => {}
+            "" | "&" => output.push_str(&format!("{{{{ {} }}}}", &captures[2])),
+            _ => return Err(UnsupportedTemplate(format!("mustache sections such as {} are not supported", whole.as_str()))),
+        }
+    }
+    output.push_str(&escape_liquid(&template[last..]));
+    Ok(output)
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// ROLE MARKERS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// The role for a LangChain role name, e.g. `human` or `ai`.
+pub fn parse_role(name: &str) -> Option<Role> {
+    match name.to_lowercase().as_str() {
+        "system" => Some(Role::System),
+        "human" | "user" => Some(Role::User),
+        "ai" | "assistant" => Some(Role::Assistant),
+        "developer" => Some(Role::Developer),
+        _ => None,
+    }
+}
+
+/// Splits text written as a transcript, with a role marker such as
+/// `System:`, `Human:` or `AI:` opening each turn's first line. Text before
+/// the first marker, or all of it when there are none, is a user turn.
+pub fn split_role_markers(text: &str) -> Vec<(Role, String)> {
+    let mut turns = Vec::<(Role, String)>::default();
+    let mut current = (Role::User, String::default());
+    for line in text.lines() {
+        let marker = line
+            .split_once(':')
+            .and_then(|(name, rest)| Some((parse_role(name.trim_end()).filter(|_| !name.starts_with(' '))?, rest)));
+        match marker {
+            Some((role, rest)) => {
+                let (role_before, content) = std::mem::replace(&mut current, (role, rest.trim_start().to_string()));
+                if !content.trim().is_empty() {
+                    turns.push((role_before, content.trim().to_string()));
+                }
+            }
+            None => {
+                if !current.1.is_empty() {
+                    current.1.push('\n');
+                }
+                current.1.push_str(line);
+            }
+        }
+    }
+    if !current.1.trim().is_empty() {
+        turns.push((current.0, current.1.trim().to_string()));
+    }
+    turns
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// IMPORT
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+impl Prompt {
+    /// Converts a prompt saved by LangChain, in JSON or YAML, for migrating
+    /// existing prompt libraries:
+    ///
+    /// ```rust,ignore
+    /// let prompt = Prompt::open_langchain("prompts/qa.json")?
+    ///     .with_overrides(|x| x.with_model(Model::Gpt4oMini));
+    /// std::fs::write("prompts/qa.xml", prompt.to_xml())?;
+    /// ```
+    ///
+    /// Reads `PromptTemplate` files (`_type: prompt`), serialized
+    /// `ChatPromptTemplate`s and lists of `[role, template]` pairs. A plain
+    /// template is split on role markers; see [`split_role_markers`].
+    /// Templates are rewritten with [`to_liquid`], so their input variables
+    /// become the prompt's variables under the same names. LangChain prompts
+    /// name no model, so one must be set before rendering.
+    ///
+    /// `MessagesPlaceholder`s have no equivalent and are dropped with an
+    /// [`DiagnosticKind::Unsupported`] warning in the prompt's diagnostics.
+    pub fn from_langchain(contents: impl AsRef<str>) -> Result<Self, api::Error> {
+        // YAML is a superset of JSON, so one parser reads both.
+        let value: Value = serde_yaml::from_str(contents.as_ref())?;
+        let mut diagnostics = Vec::default();
+        let messages = prompt_nodes(&value, &mut diagnostics)?;
+        let name = value.get("name").and_then(Value::as_str).map(str::to_string);
+        let description = value.get("description").and_then(Value::as_str).map(str::to_string);
+        Ok(imported_prompt(name, description, messages, diagnostics))
+    }
+    /// Like [`Prompt::from_langchain`]; a prompt without a name is named
+    /// after the file.
+    pub fn open_langchain(file_path: impl AsRef<Path>) -> Result<Self, api::Error> {
+        let file_path = file_path.as_ref();
+        let mut prompt = Self::from_langchain(std::fs::read_to_string(file_path)?)?;
+        if prompt.name.is_none() {
+            prompt.name = file_path.file_stem().map(|x| x.to_string_lossy().into_owned());
+        }
+        prompt.source_file = Some(file_path.to_path_buf());
+        Ok(prompt)
+    }
+    /// A prompt from template text with role markers, e.g. copied out of
+    /// Python source.
+    pub fn from_template_text(text: impl AsRef<str>, format: TemplateFormat) -> Result<Self, api::Error> {
+        let messages = split_role_markers(text.as_ref())
+            .into_iter()
+            .map(|(role, content)| Ok(MessageNode::Message(Message::new(role, to_liquid(&content, format)?))))
+            .collect::<Result<Vec<_>, UnsupportedTemplate>>()?;
+        Ok(imported_prompt(None, None, messages, Vec::default()))
+    }
+}
+
+fn imported_prompt(
+    name: Option<String>,
+    description: Option<String>,
+    messages: Vec<MessageNode>,
+    diagnostics: Vec<Diagnostic>,
+) -> Prompt {
+    Prompt {
+        name,
+        version: None,
+        variant: None,
+        weight: None,
+        endpoint: None,
+        base_url: None,
+        fallbacks: Vec::default(),
+        source_file: None,
+        configuration: Default::default(),
+        overrides: Default::default(),
+        metadata: PromptMetadata { description, ..Default::default() },
+        params: Vec::default(),
+        messages,
+        assertions: Vec::default(),
+        diagnostics,
+    }
+}
+
+fn prompt_nodes(value: &Value, diagnostics: &mut Vec<Diagnostic>) -> Result<Vec<MessageNode>, api::Error> {
+    if let Some(messages) = value.as_array() {
+        let mut nodes = Vec::default();
+        for message in messages {
+            nodes.extend(message_node(message, diagnostics)?);
+        }
+        return Ok(nodes)
+    }
+    if let Some(class) = constructor(value) {
+        let kwargs = value.get("kwargs").unwrap_or(&Value::Null);
+        return match class {
+            "ChatPromptTemplate" => prompt_nodes(kwargs.get("messages").unwrap_or(&Value::Null), diagnostics),
+            "PromptTemplate" => template_nodes(kwargs),
+            _ => Ok(message_node(value, diagnostics)?.into_iter().collect()),
+        }
+    }
+    match value.get("_type").and_then(Value::as_str) {
+        Some("prompt") | None if value.get("template").is_some() => template_nodes(value),
+        None if value.get("messages").is_some() => prompt_nodes(&value["messages"], diagnostics),
+        Some(kind) => Err(Box::new(UnsupportedTemplate(format!("prompts of type {kind:?} are not supported")))),
+        None => Err(Box::new(UnsupportedTemplate(String::from("the document has no template or messages")))),
+    }
+}
+
+/// A `PromptTemplate`, split into messages on role markers.
+fn template_nodes(value: &Value) -> Result<Vec<MessageNode>, api::Error> {
+    let (template, format) = template(value)?;
+    let nodes = split_role_markers(&template)
+        .into_iter()
+        .map(|(role, content)| Ok(MessageNode::Message(Message::new(role, to_liquid(&content, format)?))))
+        .collect::<Result<Vec<_>, UnsupportedTemplate>>()?;
+    Ok(nodes)
+}
+
+fn template(value: &Value) -> Result<(String, TemplateFormat), UnsupportedTemplate> {
+    if value.get("template_path").is_some() {
+        return Err(UnsupportedTemplate(String::from("templates read from template_path are not supported")))
+    }
+    let value = match constructor(value) {
+        Some(_) => value.get("kwargs").unwrap_or(&Value::Null),
+        None => value,
+    };
+    let template = value
+        .get("template")
+        .and_then(Value::as_str)
+        .ok_or_else(|| UnsupportedTemplate(String::from("the prompt has no template")))?;
+    let format = value
+        .get("template_format")
+        .and_then(Value::as_str)
+        .map(TemplateFormat::from_str)
+        .transpose()?
+        .unwrap_or_default();
+    Ok((template.to_string(), format))
+}
+
+/// The class name of a serialized LangChain object, e.g. `PromptTemplate`.
+fn constructor(value: &Value) -> Option<&str> {
+    value.get("lc")?;
+    value.get("id")?.as_array()?.last()?.as_str()
+}
+
+fn message_node(value: &Value, diagnostics: &mut Vec<Diagnostic>) -> Result<Option<MessageNode>, api::Error> {
+    let placeholder = |name: &str, diagnostics: &mut Vec<Diagnostic>| {
+        diagnostics.push(Diagnostic::warning(
+            DiagnosticKind::Unsupported,
+            format!("messages placeholder {name:?} has no equivalent and was dropped"),
+        ));
+        Ok(None)
+    };
+    let message = |role: &str, content: String| -> Result<Option<MessageNode>, api::Error> {
+        let role = parse_role(role).ok_or_else(|| UnsupportedTemplate(format!("the role {role:?} is not supported")))?;
+        Ok(Some(MessageNode::Message(Message::new(role, content))))
+    };
+    // `("human", "{question}")`, as passed to `ChatPromptTemplate.from_messages`.
+    if let Some([role, template]) = value.as_array().map(Vec::as_slice) {
+        let (Some(role), Some(template)) = (role.as_str(), template.as_str()) else {
+            return Err(Box::new(UnsupportedTemplate(format!("the message {value} is malformed"))))
+        };
+        if role == "placeholder" {
+            return placeholder(template.trim_matches(['{', '}']), diagnostics)
+        }
+        return message(role, to_liquid(template, TemplateFormat::FString)?)
+    }
+    if let Some(template) = value.as_str() {
+        return message("human", to_liquid(template, TemplateFormat::FString)?)
+    }
+    let Some(class) = constructor(value) else {
+        let role = value.get("role").and_then(Value::as_str).unwrap_or("human");
+        let (template, format) = match value.get("content") {
+            Some(Value::String(content)) => (content.clone(), TemplateFormat::FString),
+            _ => template(value)?,
+        };
+        return message(role, to_liquid(&template, format)?)
+    };
+    let kwargs = value.get("kwargs").unwrap_or(&Value::Null);
+    let role = match class {
+        "MessagesPlaceholder" => {
+            let name = kwargs.get("variable_name").and_then(Value::as_str).unwrap_or_default();
+            return placeholder(name, diagnostics)
+        }
+        "SystemMessagePromptTemplate" | "SystemMessage" => "system",
+        "HumanMessagePromptTemplate" | "HumanMessage" => "human",
+        "AIMessagePromptTemplate" | "AIMessage" => "ai",
+        "ChatMessagePromptTemplate" | "ChatMessage" => kwargs.get("role").and_then(Value::as_str).unwrap_or_default(),
+        _ => return Err(Box::new(UnsupportedTemplate(format!("{class} objects are not supported")))),
+    };
+    // Messages are fixed text; message templates wrap a `PromptTemplate`.
+    match kwargs.get("content").and_then(Value::as_str) {
+        Some(content) => message(role, escape_liquid(content)),
+        None => {
+            let (template, format) = template(kwargs.get("prompt").unwrap_or(&Value::Null))?;
+            message(role, to_liquid(&template, format)?)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnsupportedTemplate(pub String);
+impl std::fmt::Display for UnsupportedTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot convert the prompt template: {}.", self.0)
+    }
+}
+impl std::error::Error for UnsupportedTemplate {}
@@ -1,2 +1,70 @@
 pub mod client;
+pub mod model;
+pub mod transport;
+pub mod auth;
+pub mod secret;
+pub mod sse;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod extract;
+#[cfg(feature = "strum")]
+pub mod classify;
+pub mod tools;
+#[cfg(feature = "builtin-tools")]
+pub mod builtin_tools;
+pub mod agent;
+pub mod batch;
+pub mod map_reduce;
+pub mod consistency;
+pub mod routing;
+pub mod sweep;
+pub mod pipeline;
+pub mod workflow;
+pub mod validators;
+pub mod moderation;
+pub mod pii;
+pub mod observer;
+pub mod metrics;
+pub mod request_log;
+pub mod pricing;
+#[cfg(feature = "opentelemetry")]
+pub mod otel;
+#[cfg(feature = "audit")]
+pub mod audit;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "testing")]
+pub mod vcr;
+#[cfg(feature = "cache")]
+pub mod cache;
+pub mod store;
+#[cfg(feature = "sqlite")]
+pub mod history;
 pub mod xml_dsl;
+pub mod xml;
+pub mod validate;
+pub mod schema;
+pub mod lint;
+pub mod assertions;
+pub mod formats;
+pub mod langchain;
+pub mod chatml;
+pub mod finetune;
+pub mod params;
+pub mod embed;
+pub mod vector;
+pub mod vector_store;
+pub mod rag;
+pub mod variants;
+#[cfg(feature = "minijinja")]
+pub mod templates;
+#[cfg(feature = "watch")]
+pub mod watch;
+
+/// Re-exports used by the derive macros.
+#[doc(hidden)]
+pub mod __private {
+    pub use serde;
+    #[cfg(feature = "schemars")]
+    pub use schemars;
+}
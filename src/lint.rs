@@ -0,0 +1,234 @@
+use std::collections::BTreeSet;
+
+use crate::client as api;
+use crate::validate::{Diagnostic, DiagnosticKind, Diagnostics};
+use crate::xml_dsl::{template_variables, MessageNode, Prompt, PromptCollection};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// LINTS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Context window sizes, in tokens, by model name prefix. More specific
+/// prefixes come first.
+const CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4-1106", 128_000),
+    ("gpt-4-0125", 128_000),
+    ("gpt-4-vision", 128_000),
+    ("gpt-4-32k", 32_768),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo-instruct", 4_096),
+    ("gpt-3.5-turbo-16k", 16_385),
+    ("gpt-3.5-turbo", 16_385),
+    ("o1-mini", 128_000),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4-mini", 200_000),
+];
+
+pub fn context_window(model: &str) -> Option<usize> {
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map(|(_, size)| *size)
+}
+
+/// The most tokens a model will generate in one reply, by model name
+/// prefix. More specific prefixes come first.
+const MAX_OUTPUT_TOKENS: &[(&str, usize)] = &[
+    ("gpt-4.1", 32_768),
+    ("gpt-4o", 16_384),
+    ("gpt-4-turbo", 4_096),
+    ("gpt-4-1106", 4_096),
+    ("gpt-4-0125", 4_096),
+    ("gpt-4-vision", 4_096),
+    ("gpt-4-32k", 8_192),
+    ("gpt-4", 8_192),
+    ("gpt-3.5-turbo", 4_096),
+    ("o1-mini", 65_536),
+    ("o1", 100_000),
+    ("o3", 100_000),
+    ("o4-mini", 100_000),
+];
+
+pub fn max_output_tokens(model: &str) -> Option<usize> {
+    MAX_OUTPUT_TOKENS
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map(|(_, size)| *size)
+}
+
+/// Whether the model is one of OpenAI's reasoning models, which reject the
+/// sampling parameters and `logprobs`.
+pub fn is_reasoning_model(model: &str) -> bool {
+    ["o1", "o3", "o4"].iter().any(|x| model.starts_with(x))
+}
+
+/// A rough token count, at about four characters per token.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+impl PromptCollection {
+    /// Style and cost checks that go beyond [`PromptCollection::validate`].
+    /// Every lint is a warning, so CI can decide how strict to be.
+    pub fn lint(&self) -> Diagnostics {
+        let diagnostics = self.prompts()
+            .iter()
+            .flat_map(|prompt| prompt.lint().0)
+            .collect();
+        Diagnostics(diagnostics)
+    }
+    /// Like [`PromptCollection::lint`], but also checks variable usage
+    /// against the globals the prompts will be rendered with.
+    pub fn lint_with(&self, globals: &liquid::Object) -> Diagnostics {
+        let diagnostics = self.prompts()
+            .iter()
+            .flat_map(|prompt| prompt.lint_with(globals).0)
+            .collect();
+        Diagnostics(diagnostics)
+    }
+}
+
+impl Prompt {
+    pub fn lint(&self) -> Diagnostics {
+        let mut diagnostics = Vec::default();
+        let configuration = self.effective_configuration();
+        check_token_budget(&self.messages, &configuration, &mut diagnostics);
+        check_system_message(&self.messages, &mut diagnostics);
+        check_sampling(&configuration, &mut diagnostics);
+        check_loop_variables(&self.messages, &mut diagnostics);
+        check_params(self, &mut diagnostics);
+        self.attach(diagnostics)
+    }
+    pub fn lint_with(&self, globals: &liquid::Object) -> Diagnostics {
+        let mut diagnostics = self.lint().0;
+        let used = self.variables();
+        let unused = globals
+            .keys()
+            .filter(|key| !used.contains(key.as_str()))
+            .map(|key| Diagnostic::warning(DiagnosticKind::UnusedVariable, format!("variable {key:?} is never used")));
+        let undeclared = used
+            .iter()
+            .filter(|variable| !globals.contains_key(variable.as_str()))
+            .filter(|variable| !self.params.iter().any(|x| &x.name == *variable && !x.is_required()))
+            .map(|variable| {
+                Diagnostic::warning(DiagnosticKind::UndeclaredVariable, format!("variable {variable:?} is not provided"))
+            });
+        let extra = unused.chain(undeclared).collect();
+        diagnostics.extend(self.attach(extra).0);
+        Diagnostics(diagnostics)
+    }
+    fn attach(&self, diagnostics: Vec<Diagnostic>) -> Diagnostics {
+        let diagnostics = diagnostics
+            .into_iter()
+            .map(|mut x| {
+                x.prompt = self.name.clone();
+                x.source_file = self.source_file.clone();
+                x
+            })
+            .collect();
+        Diagnostics(diagnostics)
+    }
+}
+
+fn check_token_budget(
+    nodes: &[MessageNode],
+    configuration: &api::ConfigurationBuilder,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let Some(model) = configuration.model.as_deref() else { return };
+    let Some(window) = context_window(model) else { return };
+    // Loop bodies are counted once, so this is a lower bound.
+    let prompt_tokens = message_texts(nodes)
+        .iter()
+        .map(|text| estimate_tokens(text) + 4)
+        .sum::<usize>();
+    let completion_tokens = configuration.max_tokens.unwrap_or_default();
+    if prompt_tokens + completion_tokens > window {
+        diagnostics.push(Diagnostic::warning(
+            DiagnosticKind::TokenBudget,
+            format!(
+                "about {prompt_tokens} prompt tokens plus {completion_tokens} max-tokens exceeds \
+                 the {window} token context of {model:?}"
+            ),
+        ));
+    }
+}
+
+fn check_system_message(nodes: &[MessageNode], diagnostics: &mut Vec<Diagnostic>) {
+    let has_system = nodes.iter().any(|node| match node {
+        MessageNode::Message(message) => message.role.is_instruction(),
+        MessageNode::ForEach(_) => false,
+    });
+    if !has_system {
+        diagnostics.push(Diagnostic::warning(DiagnosticKind::MissingSystemMessage, "prompt has no system message"));
+    }
+}
+
+/// OpenAI recommends adjusting `temperature` or `top-p`, not both.
+fn check_sampling(configuration: &api::ConfigurationBuilder, diagnostics: &mut Vec<Diagnostic>) {
+    let (Some(temperature), Some(top_p)) = (configuration.temperature, configuration.top_p) else {
+        return
+    };
+    if temperature != 1.0 && top_p != 1.0 {
+        diagnostics.push(Diagnostic::warning(
+            DiagnosticKind::ConflictingParameters,
+            format!("both 'temperature' ({temperature}) and 'top-p' ({top_p}) are set; adjust only one"),
+        ));
+    }
+}
+
+fn check_loop_variables(nodes: &[MessageNode], diagnostics: &mut Vec<Diagnostic>) {
+    for node in nodes {
+        let MessageNode::ForEach(for_each) = node else { continue };
+        let used = message_texts(&for_each.children)
+            .iter()
+            .flat_map(|text| template_variables(text))
+            .collect::<BTreeSet<_>>();
+        if !used.contains(&for_each.var) {
+            diagnostics.push(Diagnostic::warning(
+                DiagnosticKind::UnusedVariable,
+                format!("<for-each> variable {:?} is never used", for_each.var),
+            ));
+        }
+        check_loop_variables(&for_each.children, diagnostics);
+    }
+}
+
+/// Once a prompt declares `<params>`, every variable it reads should be
+/// declared, and every declaration read.
+fn check_params(prompt: &Prompt, diagnostics: &mut Vec<Diagnostic>) {
+    if prompt.params.is_empty() {
+        return
+    }
+    let used = prompt.variables();
+    for variable in used.iter().filter(|x| !prompt.params.iter().any(|param| &param.name == *x)) {
+        diagnostics.push(Diagnostic::warning(
+            DiagnosticKind::UndeclaredVariable,
+            format!("variable {variable:?} is not declared in <params>"),
+        ));
+    }
+    for param in prompt.params.iter().filter(|x| !used.contains(&x.name)) {
+        diagnostics.push(Diagnostic::warning(
+            DiagnosticKind::UnusedVariable,
+            format!("parameter {:?} is never used", param.name),
+        ));
+    }
+}
+
+/// Message contents and image URLs, including those inside loops.
+fn message_texts(nodes: &[MessageNode]) -> Vec<&str> {
+    let mut output = Vec::default();
+    for node in nodes {
+        match node {
+            MessageNode::Message(message) => {
+                output.push(message.content.as_str());
+                output.extend(message.images.iter().map(|x| x.url.as_str()));
+            }
+            MessageNode::ForEach(for_each) => output.extend(message_texts(&for_each.children)),
+        }
+    }
+    output
+}
@@ -0,0 +1,219 @@
+use crate::batch::{BatchRunner, ItemResult, WorkItem};
+use crate::client::{Error, Usage};
+use crate::lint::estimate_tokens;
+use crate::xml_dsl::Prompt;
+
+/// Characters per token, matching [`estimate_tokens`].
+const CHARS_PER_TOKEN: usize = 4;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// CHUNKING
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Splits `text` into pieces of about `chunk_tokens` tokens, each starting
+/// about `overlap_tokens` before the previous one ended. Pieces break at
+/// whitespace where they can.
+pub fn chunk_text(text: &str, chunk_tokens: usize, overlap_tokens: usize) -> Vec<String> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let size = chunk_tokens.max(1) * CHARS_PER_TOKEN;
+    // Each chunk must move forward by at least half its size.
+    let overlap = (overlap_tokens * CHARS_PER_TOKEN).min(size / 2);
+    let mut chunks = Vec::default();
+    let mut start = 0;
+    while start < chars.len() {
+        let mut end = (start + size).min(chars.len());
+        if end < chars.len() {
+            // Back up to the last whitespace in the second half of the chunk.
+            if let Some(offset) = chars[start + size / 2..end].iter().rposition(|x| x.is_whitespace()) {
+                end = start + size / 2 + offset + 1;
+            }
+        }
+        let chunk = chars[start..end].iter().collect::<String>();
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk.trim().to_string());
+        }
+        if end == chars.len() {
+            break
+        }
+        let mut next = end.saturating_sub(overlap).max(start + 1);
+        // Start the overlap at a word boundary.
+        while overlap > 0 && next < end && !chars[next - 1].is_whitespace() {
+            next += 1;
+        }
+        start = next;
+    }
+    chunks
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// MAP-REDUCE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Summarizes (or otherwise condenses) text too long for one request.
+///
+/// The input is split with [`chunk_text`] and the map prompt runs once per
+/// chunk, with the chunk as `chunk` and its position as `index` and
+/// `count`. The reduce prompt then runs over the map outputs, given as the
+/// array `outputs`. If those outputs together are still longer than a
+/// chunk, they are reduced in groups first, level by level, until they
+/// fit or `max_depth` reduce levels have run; the last level always
+/// reduces everything that is left.
+///
+/// Both prompts also see the globals passed to [`MapReduce::run`].
+/// Requests go through the [`BatchRunner`], which sets concurrency,
+/// retries and the API key.
+///
+/// ```rust,ignore
+/// let summary = MapReduce::new(summarize_chunk, combine_summaries)
+///     .with_runner(BatchRunner::new().with_setup(move |x| x.with_api_key(&api_key)))
+///     .with_chunk_tokens(2000)
+///     .run(&document, &liquid::object!({ "audience": "executives" }))
+///     .await?;
+/// println!("{}", summary.output);
+/// ```
+#[derive(Clone)]
+pub struct MapReduce {
+    map: Prompt,
+    reduce: Prompt,
+    runner: BatchRunner,
+    chunk_tokens: usize,
+    overlap_tokens: usize,
+    max_depth: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct MapReduceOutput {
+    /// The final reduce output.
+    pub output: String,
+    /// What the map prompt produced for each chunk, in order.
+    pub map_outputs: Vec<String>,
+    pub chunks: usize,
+    /// Reduce levels run, counting the final one.
+    pub depth: usize,
+    /// Summed over every request that reported it.
+    pub usage: Usage,
+}
+
+impl MapReduce {
+    /// Chunks default to 3000 tokens overlapping by 200, with at most 3
+    /// reduce levels.
+    pub fn new(map: Prompt, reduce: Prompt) -> Self {
+        MapReduce {
+            map,
+            reduce,
+            runner: BatchRunner::new(),
+            chunk_tokens: 3000,
+            overlap_tokens: 200,
+            max_depth: 3,
+        }
+    }
+    pub fn with_runner(mut self, runner: BatchRunner) -> Self {
+        self.runner = runner;
+        self
+    }
+    pub fn with_chunk_tokens(mut self, chunk_tokens: usize) -> Self {
+        self.chunk_tokens = chunk_tokens.max(1);
+        self
+    }
+    pub fn with_overlap_tokens(mut self, overlap_tokens: usize) -> Self {
+        self.overlap_tokens = overlap_tokens;
+        self
+    }
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth.max(1);
+        self
+    }
+    pub async fn run(&self, input: &str, globals: &liquid::Object) -> Result<MapReduceOutput, Error> {
+        let chunks = chunk_text(input, self.chunk_tokens, self.overlap_tokens);
+        let count = chunks.len();
+        let items = chunks.into_iter().enumerate().map(|(index, chunk)| {
+            let mut variables = globals.clone();
+            variables.insert("chunk".into(), liquid::model::Value::scalar(chunk));
+            variables.insert("index".into(), liquid::model::Value::scalar(index as i64));
+            variables.insert("count".into(), liquid::model::Value::scalar(count as i64));
+            WorkItem::new(format!("map-{index}"), self.map.clone(), variables)
+        });
+        let mut usage = Usage::default();
+        let map_outputs = self.run_items(items, &mut usage).await?;
+        let mut outputs = map_outputs.clone();
+        let mut depth = 0;
+        loop {
+            depth += 1;
+            let groups = match depth < self.max_depth {
+                true => self.group(&outputs),
+                false => vec![outputs.clone()],
+            };
+            // Grouping only helps if it shrinks the list.
+            let groups = match groups.len() < outputs.len() {
+                true => groups,
+                false => vec![outputs.clone()],
+            };
+            let is_final = groups.len() == 1;
+            let items = groups.into_iter().enumerate().map(|(index, group)| {
+                let mut variables = globals.clone();
+                let group = group.into_iter().map(liquid::model::Value::scalar).collect::<Vec<_>>();
+                variables.insert("outputs".into(), liquid::model::Value::Array(group));
+                WorkItem::new(format!("reduce-{depth}-{index}"), self.reduce.clone(), variables)
+            });
+            outputs = self.run_items(items, &mut usage).await?;
+            if is_final {
+                break
+            }
+        }
+        let output = outputs.into_iter().next().unwrap_or_default();
+        Ok(MapReduceOutput { output, map_outputs, chunks: count, depth, usage })
+    }
+    /// Splits outputs into consecutive groups of at most a chunk's worth of
+    /// tokens each; everything in one group if it all fits.
+    fn group(&self, outputs: &[String]) -> Vec<Vec<String>> {
+        let mut groups = Vec::<Vec<String>>::default();
+        let mut tokens = 0;
+        for output in outputs {
+            let size = estimate_tokens(output);
+            match groups.last_mut() {
+                Some(group) if tokens + size <= self.chunk_tokens => {
+                    group.push(output.clone());
+                    tokens += size;
+                }
+                _ => {
+                    groups.push(vec![output.clone()]);
+                    tokens = size;
+                }
+            }
+        }
+        groups
+    }
+    async fn run_items(
+        &self,
+        items: impl Iterator<Item = WorkItem>,
+        usage: &mut Usage,
+    ) -> Result<Vec<String>, Error> {
+        let results = self.runner.run(items).await?;
+        let mut outputs = Vec::with_capacity(results.len());
+        for result in results {
+            if let Some(x) = result.usage {
+                usage.prompt_tokens += x.prompt_tokens;
+                usage.completion_tokens += x.completion_tokens;
+                usage.total_tokens += x.total_tokens;
+            }
+            match result {
+                ItemResult { message: Some(message), error: None, .. } => outputs.push(message.content),
+                ItemResult { id, error, .. } => {
+                    return Err(Box::new(StepFailed { id, error: error.unwrap_or_default() }))
+                }
+            }
+        }
+        Ok(outputs)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StepFailed {
+    /// `map-<chunk>` or `reduce-<level>-<group>`.
+    pub id: String,
+    pub error: String,
+}
+impl std::fmt::Display for StepFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot finish map-reduce: step {} failed: {}.", self.id, self.error.trim_end_matches('.'))
+    }
+}
+impl std::error::Error for StepFailed {}
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use crate::client::{ApiEndpoint, ChatCompletionsBody, ChatCompletionsRequest, Usage};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// METRICS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Receives measurements for every request made by a client that has it
+/// attached with [`crate::client::ChatCompletionsRequestBuilder::with_metrics`].
+///
+/// Implement it to forward to Prometheus, StatsD and the like. Every method
+/// has an empty default, so implement only the ones you need.
+pub trait MetricsSink {
+    /// Called just before the request is sent.
+    fn on_request(&self, request: &RequestInfo) {
+        let _ = request;
+    }
+    /// Called when the first content or tool-call fragment arrives.
+    fn on_first_token(&self, request: &RequestInfo, latency: Duration) {
+        let _ = (request, latency);
+    }
+    /// Called once the response is complete or the request has failed.
+    fn on_complete(&self, metrics: &RequestMetrics) {
+        let _ = metrics;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestInfo {
+    pub model: String,
+    /// The endpoint's host, used to tell providers apart.
+    pub host: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestMetrics {
+    pub request: RequestInfo,
+    /// The HTTP status, if a response was received.
+    pub status: Option<u16>,
+    /// Set when the request failed.
+    pub error: Option<String>,
+    /// From sending the request to the end of the stream.
+    pub latency: Duration,
+    pub time_to_first_token: Option<Duration>,
+    /// Only when the provider reports it.
+    pub usage: Option<Usage>,
+}
+
+impl RequestInfo {
+    pub fn new(request: &ChatCompletionsRequest) -> Self {
+        Self::for_body(&request.api_endpoint, &request.body)
+    }
+    /// For `body` sent to `endpoint`, which may be a fallback's.
+    pub(crate) fn for_body(endpoint: &ApiEndpoint, body: &ChatCompletionsBody) -> Self {
+        RequestInfo {
+            model: body.model.clone(),
+            host: endpoint.host(),
+        }
+    }
+}
+
+impl RequestMetrics {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
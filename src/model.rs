@@ -0,0 +1,143 @@
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::pricing::{self, ModelPrice};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// MODEL NAMES
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+pub const GPT_4O: &str = "gpt-4o";
+pub const GPT_4O_MINI: &str = "gpt-4o-mini";
+pub const GPT_4_1: &str = "gpt-4.1";
+pub const GPT_4_1_MINI: &str = "gpt-4.1-mini";
+pub const GPT_4_1_NANO: &str = "gpt-4.1-nano";
+pub const GPT_4_TURBO: &str = "gpt-4-turbo";
+pub const GPT_4: &str = "gpt-4";
+pub const GPT_3_5_TURBO: &str = "gpt-3.5-turbo";
+pub const O1: &str = "o1";
+pub const O1_MINI: &str = "o1-mini";
+pub const O3: &str = "o3";
+pub const O3_MINI: &str = "o3-mini";
+pub const O4_MINI: &str = "o4-mini";
+
+/// A model name. Anything passed to `with_model` can be one of these, so
+/// typos in common names are caught at compile time:
+///
+/// ```rust,ignore
+/// let body = ChatCompletionsBody::new(Model::Gpt4oMini, messages);
+/// ```
+///
+/// Names without a variant, such as dated snapshots or other providers'
+/// models, parse to `Other`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Model {
+    Gpt4o,
+    Gpt4oMini,
+    Gpt41,
+    Gpt41Mini,
+    Gpt41Nano,
+    Gpt4Turbo,
+    Gpt4,
+    Gpt35Turbo,
+    O1,
+    O1Mini,
+    O3,
+    O3Mini,
+    O4Mini,
+    Other(String),
+}
+
+impl Model {
+    pub const KNOWN: &'static [Model] = &[
+        Model::Gpt4o,
+        Model::Gpt4oMini,
+        Model::Gpt41,
+        Model::Gpt41Mini,
+        Model::Gpt41Nano,
+        Model::Gpt4Turbo,
+        Model::Gpt4,
+        Model::Gpt35Turbo,
+        Model::O1,
+        Model::O1Mini,
+        Model::O3,
+        Model::O3Mini,
+        Model::O4Mini,
+    ];
+    pub fn as_str(&self) -> &str {
+        match self {
+            Model::Gpt4o => GPT_4O,
+            Model::Gpt4oMini => GPT_4O_MINI,
+            Model::Gpt41 => GPT_4_1,
+            Model::Gpt41Mini => GPT_4_1_MINI,
+            Model::Gpt41Nano => GPT_4_1_NANO,
+            Model::Gpt4Turbo => GPT_4_TURBO,
+            Model::Gpt4 => GPT_4,
+            Model::Gpt35Turbo => GPT_3_5_TURBO,
+            Model::O1 => O1,
+            Model::O1Mini => O1_MINI,
+            Model::O3 => O3,
+            Model::O3Mini => O3_MINI,
+            Model::O4Mini => O4_MINI,
+            Model::Other(name) => name,
+        }
+    }
+    /// In tokens, if known; see [`crate::lint::context_window`].
+    pub fn context_window(&self) -> Option<usize> {
+        crate::lint::context_window(self.as_str())
+    }
+    /// In tokens, if known; see [`crate::lint::max_output_tokens`].
+    pub fn max_output_tokens(&self) -> Option<usize> {
+        crate::lint::max_output_tokens(self.as_str())
+    }
+    /// See [`crate::pricing::price`].
+    pub fn price(&self) -> Option<ModelPrice> {
+        pricing::price(self.as_str())
+    }
+}
+
+impl FromStr for Model {
+    type Err = std::convert::Infallible;
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Ok(Model::from(name))
+    }
+}
+
+impl From<&str> for Model {
+    fn from(name: &str) -> Self {
+        Model::KNOWN
+            .iter()
+            .find(|x| x.as_str() == name)
+            .cloned()
+            .unwrap_or_else(|| Model::Other(name.to_string()))
+    }
+}
+
+impl From<String> for Model {
+    fn from(name: String) -> Self {
+        Model::from(name.as_str())
+    }
+}
+
+impl AsRef<str> for Model {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl std::fmt::Display for Model {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Model {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Model {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Model::from(String::deserialize(deserializer)?))
+    }
+}
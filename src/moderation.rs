@@ -0,0 +1,478 @@
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use futures::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::client::{ApiEndpoint, ApiError, ChatCompletionsBody, ChatCompletionsResponse, Error, Observers, Role};
+use crate::transport::{HttpRequest, ReqwestTransport, Transport};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// CLASSIFIERS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Classification {
+    pub flagged: bool,
+    /// The categories the text was flagged for.
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Every category scored, from 0 to 1, if the classifier gives scores.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scores: BTreeMap<String, f64>,
+}
+
+impl Classification {
+    pub fn flagged(categories: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        let categories = categories.into_iter().map(|x| x.as_ref().to_string()).collect();
+        Classification { flagged: true, categories, scores: BTreeMap::default() }
+    }
+}
+
+/// Decides whether text is harmful. [`OpenAiModeration`] uses OpenAI's
+/// moderation endpoint; closures work for local classifiers:
+///
+/// ```rust,ignore
+/// let classifier = |text: &str| Ok(match text.contains("password") {
+///     true => Classification::flagged(["credentials"]),
+///     false => Classification::default(),
+/// });
+/// ```
+pub trait Classifier {
+    fn classify<'a>(&'a self, text: &'a str) -> LocalBoxFuture<'a, Result<Classification, Error>>;
+}
+
+impl<F> Classifier for F
+where
+    F: Fn(&str) -> Result<Classification, Error>,
+{
+    fn classify<'a>(&'a self, text: &'a str) -> LocalBoxFuture<'a, Result<Classification, Error>> {
+        Box::pin(futures::future::ready(self(text)))
+    }
+}
+
+/// Classifies with the `moderations` endpoint of an OpenAI compatible API,
+/// which OpenAI does not bill for.
+///
+/// Without a transport of its own it sends with one `reqwest` client,
+/// made on first use and shared by its clones, so screening every request
+/// reuses the same connections.
+#[derive(Clone)]
+pub struct OpenAiModeration {
+    endpoint: ApiEndpoint,
+    url: Option<String>,
+    model: String,
+    transport: Option<Rc<dyn Transport>>,
+    shared: Rc<OnceCell<ReqwestTransport>>,
+}
+
+impl OpenAiModeration {
+    pub const DEFAULT_MODEL: &'static str = "omni-moderation-latest";
+    /// Uses the endpoint's credentials, and its URL with
+    /// `chat/completions` swapped for `moderations`.
+    pub fn new(endpoint: ApiEndpoint) -> Self {
+        OpenAiModeration {
+            endpoint,
+            url: None,
+            model: String::from(Self::DEFAULT_MODEL),
+            transport: None,
+            shared: Rc::default(),
+        }
+    }
+    pub fn with_url(mut self, url: impl AsRef<str>) -> Self {
+        self.url = Some(url.as_ref().to_string());
+        self
+    }
+    pub fn with_model(mut self, model: impl AsRef<str>) -> Self {
+        self.model = model.as_ref().to_string();
+        self
+    }
+    /// E.g. the transport the screened requests go out on, to share its
+    /// connection pool.
+    pub fn with_transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Rc::new(transport));
+        self
+    }
+    async fn send(&self, text: &str) -> Result<Classification, Error> {
+        let url = self.url
+            .clone()
+            .or_else(|| self.endpoint.sibling_url("moderations"))
+            .ok_or_else(|| NoModerationUrl(self.endpoint.api_url.clone()))?;
+        let body = serde_json::json!({ "model": self.model, "input": text });
+        let mut request = HttpRequest {
+            url,
+            headers: self.endpoint.headers(),
+            body: serde_json::to_vec(&body)?,
+            timeout: None,
+            prompt: None,
+        };
+        self.endpoint.authenticate(&mut request).await?;
+        let response = match self.transport.as_ref() {
+            Some(transport) => transport.send(request).await?,
+            None => self.shared_transport()?.send(request).await?,
+        };
+        if let Some(error) = ApiError::from_code(response.status) {
+            return Err(Box::new(error))
+        }
+        let response = serde_json::from_slice::<ModerationResponse>(&response.bytes().await?)?;
+        let result = response.results.into_iter().next().ok_or(NoModerationResult)?;
+        Ok(Classification {
+            flagged: result.flagged,
+            categories: result.categories.into_iter().filter(|(_, x)| *x).map(|(x, _)| x).collect(),
+            scores: result.category_scores,
+        })
+    }
+    fn shared_transport(&self) -> Result<&ReqwestTransport, Error> {
+        if let Some(transport) = self.shared.get() {
+            return Ok(transport)
+        }
+        let client = reqwest::ClientBuilder::new().build()?;
+        Ok(self.shared.get_or_init(|| ReqwestTransport::with_client(client)))
+    }
+}
+
+impl Classifier for OpenAiModeration {
+    fn classify<'a>(&'a self, text: &'a str) -> LocalBoxFuture<'a, Result<Classification, Error>> {
+        Box::pin(self.send(text))
+    }
+}
+
+#[derive(Deserialize)]
+struct ModerationResponse {
+    results: Vec<ModerationResult>,
+}
+
+#[derive(Deserialize)]
+struct ModerationResult {
+    flagged: bool,
+    #[serde(default)]
+    categories: BTreeMap<String, bool>,
+    #[serde(default)]
+    category_scores: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NoModerationUrl(pub String);
+impl std::fmt::Display for NoModerationUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot find the moderations endpoint for {}; set it with `with_url`.", self.0)
+    }
+}
+impl std::error::Error for NoModerationUrl {}
+
+/// The moderations endpoint answered without a result, so the text was not
+/// classified; treating it as unflagged would let it through unscreened.
+#[derive(Debug, Clone)]
+pub struct NoModerationResult;
+impl std::fmt::Display for NoModerationResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot classify the text: the moderations endpoint returned no results.")
+    }
+}
+impl std::error::Error for NoModerationResult {}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// PRE-SEND FILTER
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ModerationAction {
+    /// Fails the request with [`ContentBlocked`].
+    #[default]
+    Block,
+    /// Sends the request anyway, reporting each flag to the observers'
+    /// [`crate::observer::Observer::on_flagged`].
+    Flag,
+}
+
+/// Screens outgoing messages before a request is sent, so nothing is spent
+/// on content that would be refused. Set it with
+/// [`crate::client::ChatCompletionsRequestBuilder::with_moderation`]:
+///
+/// ```rust,ignore
+/// let filter = ModerationFilter::new(OpenAiModeration::new(endpoint.clone()))
+///     .with_categories(["self-harm", "violence"]);
+/// let builder = builder.with_moderation(filter);
+/// ```
+#[derive(Clone)]
+pub struct ModerationFilter {
+    classifier: Rc<dyn Classifier>,
+    action: ModerationAction,
+    categories: Vec<String>,
+    roles: Vec<Role>,
+}
+
+impl ModerationFilter {
+    /// Blocks user messages flagged for any category.
+    pub fn new(classifier: impl Classifier + 'static) -> Self {
+        ModerationFilter {
+            classifier: Rc::new(classifier),
+            action: ModerationAction::Block,
+            categories: Vec::default(),
+            roles: vec![Role::User],
+        }
+    }
+    pub fn with_action(mut self, action: ModerationAction) -> Self {
+        self.action = action;
+        self
+    }
+    /// Acts only on these categories; others are ignored.
+    pub fn with_categories(mut self, categories: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.categories = categories.into_iter().map(|x| x.as_ref().to_string()).collect();
+        self
+    }
+    /// The roles whose messages are screened. Defaults to user messages.
+    pub fn with_roles(mut self, roles: impl IntoIterator<Item = Role>) -> Self {
+        self.roles = roles.into_iter().collect();
+        self
+    }
+    /// The screened messages that were flagged, classifying them
+    /// concurrently.
+    pub async fn check(&self, body: &ChatCompletionsBody) -> Result<Vec<ContentFlag>, Error> {
+        let screened = body.messages
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| self.roles.contains(&x.role) && !x.content.trim().is_empty())
+            .map(|(index, message)| async move { (index, self.classifier.classify(&message.content).await) });
+        let mut flags = Vec::default();
+        for (message, classification) in futures::future::join_all(screened).await {
+            let classification = classification?;
+            let categories = classification.categories
+                .iter()
+                .filter(|x| self.categories.is_empty() || self.categories.contains(x))
+                .cloned()
+                .collect::<Vec<_>>();
+            let flagged = match self.categories.is_empty() {
+                true => classification.flagged,
+                false => !categories.is_empty(),
+            };
+            if flagged {
+                flags.push(ContentFlag { message, categories, classification });
+            }
+        }
+        Ok(flags)
+    }
+    /// Checks `body`, failing or reporting to `observers` per the action.
+    pub(crate) async fn screen(&self, body: &ChatCompletionsBody, observers: &Observers) -> Result<(), Error> {
+        let flags = self.check(body).await?;
+        if flags.is_empty() {
+            return Ok(())
+        }
+        #[cfg(feature = "tracing")]
+        tracing::warn!(flags = flags.len(), action = ?self.action, "content flagged by moderation");
+        if self.action == ModerationAction::Block {
+            return Err(Box::new(ContentBlocked { flags }))
+        }
+        for flag in flags.iter() {
+            observers.iter().for_each(|x| x.on_flagged(flag));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ContentFlag {
+    /// The index of the flagged message.
+    pub message: usize,
+    /// The flagged categories acted on.
+    pub categories: Vec<String>,
+    pub classification: Classification,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContentBlocked {
+    pub flags: Vec<ContentFlag>,
+}
+impl std::fmt::Display for ContentBlocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let flags = self.flags
+            .iter()
+            .map(|x| match x.categories.is_empty() {
+                true => format!("message {}", x.message),
+                false => format!("message {} ({})", x.message, x.categories.join(", ")),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "Cannot send the request: moderation flagged {flags}.")
+    }
+}
+impl std::error::Error for ContentBlocked {}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// OUTPUT CHECK
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputAction {
+    /// Fails the request with [`OutputRejected`].
+    #[default]
+    Reject,
+    /// Replaces the failing choice's content with a notice.
+    Redact,
+    /// Returns the response as it is, with the verdicts attached.
+    Annotate,
+}
+
+/// What an [`OutputCheck`] found in one choice.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SafetyVerdict {
+    pub choice: usize,
+    pub passed: bool,
+    /// Flagged by the classifiers.
+    pub categories: Vec<String>,
+    /// Reported by the validators.
+    pub violations: Vec<String>,
+    /// Taken when the choice failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<OutputAction>,
+}
+
+/// Checks generated content with classifiers and
+/// [`crate::validators::Validator`]s before the response is returned.
+/// Every choice gets a [`SafetyVerdict`] in
+/// [`crate::client::ChatCompletionsResponse::safety`]; what happens to one
+/// that fails depends on the [`OutputAction`]. Set it with
+/// [`crate::client::ChatCompletionsRequestBuilder::with_output_check`]:
+///
+/// ```rust,ignore
+/// let check = OutputCheck::new()
+///     .with_classifier(OpenAiModeration::new(endpoint.clone()))
+///     .with_validator(Validator::max_length(2000))
+///     .with_action(OutputAction::Redact);
+/// let builder = builder.with_output_check(check);
+/// ```
+///
+/// Streamed chunks reach `on_chunk` and observers before the check runs.
+#[derive(Clone, Default)]
+pub struct OutputCheck {
+    classifiers: Vec<Rc<dyn Classifier>>,
+    validators: Vec<crate::validators::Validator>,
+    action: OutputAction,
+    notice: Option<String>,
+}
+
+impl OutputCheck {
+    pub const DEFAULT_NOTICE: &'static str = "[Content removed by a safety check.]";
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_classifier(mut self, classifier: impl Classifier + 'static) -> Self {
+        self.classifiers.push(Rc::new(classifier));
+        self
+    }
+    pub fn with_validator(mut self, validator: crate::validators::Validator) -> Self {
+        self.validators.push(validator);
+        self
+    }
+    pub fn with_action(mut self, action: OutputAction) -> Self {
+        self.action = action;
+        self
+    }
+    /// The text that replaces redacted content. Defaults to
+    /// [`OutputCheck::DEFAULT_NOTICE`].
+    pub fn with_notice(mut self, notice: impl AsRef<str>) -> Self {
+        self.notice = Some(notice.as_ref().to_string());
+        self
+    }
+    pub async fn check(&self, choice: usize, content: &str) -> Result<SafetyVerdict, Error> {
+        let classifications = self.classifiers.iter().map(|x| x.classify(content));
+        let mut categories = Vec::<String>::default();
+        let mut flagged = false;
+        for classification in futures::future::join_all(classifications).await {
+            let classification = classification?;
+            flagged |= classification.flagged;
+            categories.extend(classification.categories);
+        }
+        categories.sort();
+        categories.dedup();
+        let violations = self.validators
+            .iter()
+            .filter_map(|x| x.check(content).err())
+            .collect::<Vec<_>>();
+        let passed = !flagged && violations.is_empty();
+        let action = (!passed).then_some(self.action);
+        Ok(SafetyVerdict { choice, passed, categories, violations, action })
+    }
+    /// Checks every choice, then rejects, redacts or annotates.
+    pub(crate) async fn apply(&self, response: ChatCompletionsResponse) -> Result<ChatCompletionsResponse, Error> {
+        let mut verdicts = Vec::default();
+        for choice in response.choice_indices() {
+            verdicts.push(self.check(choice, &response.content(choice)).await?);
+        }
+        let failed = verdicts.iter().filter(|x| !x.passed).cloned().collect::<Vec<_>>();
+        #[cfg(feature = "tracing")]
+        if !failed.is_empty() {
+            tracing::warn!(failed = failed.len(), action = ?self.action, "output failed the safety check");
+        }
+        let mut response = match (self.action, failed.is_empty()) {
+            (_, true) | (OutputAction::Annotate, false) => response,
+            (OutputAction::Reject, false) => return Err(Box::new(OutputRejected { verdicts: failed })),
+            (OutputAction::Redact, false) => {
+                let notice = self.notice.as_deref().unwrap_or(Self::DEFAULT_NOTICE);
+                let mut response = response.folded();
+                let choices = response.output.iter_mut().flat_map(|x| x.choices.iter_mut());
+                for choice in choices.filter(|x| failed.iter().any(|y| y.choice == x.index)) {
+                    choice.delta.content = Some(notice.to_string());
+                }
+                response
+            }
+        };
+        response.safety = verdicts;
+        Ok(response)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OutputRejected {
+    pub verdicts: Vec<SafetyVerdict>,
+}
+impl std::fmt::Display for OutputRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reasons = self.verdicts
+            .iter()
+            .flat_map(|x| x.categories.iter().chain(x.violations.iter()))
+            .cloned()
+            .collect::<Vec<_>>();
+        match reasons.is_empty() {
+            true => write!(f, "Cannot return the response: it failed the safety check."),
+            false => write!(f, "Cannot return the response: it failed the safety check ({}).", reasons.join("; ")),
+        }
+    }
+}
+impl std::error::Error for OutputRejected {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{MockResponse, MockTransport};
+
+    fn moderation(body: serde_json::Value) -> (OpenAiModeration, MockTransport) {
+        let transport = MockTransport::new().with_response(MockResponse::new(200, body.to_string()));
+        let endpoint = ApiEndpoint::new("https://api.example.com/v1/chat/completions", "sk-test");
+        (OpenAiModeration::new(endpoint).with_transport(transport.clone()), transport)
+    }
+
+    #[tokio::test]
+    async fn reads_the_first_result() {
+        let (moderation, transport) = moderation(serde_json::json!({
+            "results": [{
+                "flagged": true,
+                "categories": { "violence": true, "harassment": false },
+                "category_scores": { "violence": 0.9, "harassment": 0.1 },
+            }],
+        }));
+        let classification = moderation.classify("text").await.unwrap();
+        assert!(classification.flagged);
+        assert_eq!(classification.categories, ["violence"]);
+        assert_eq!(classification.scores.get("violence"), Some(&0.9));
+        assert_eq!(transport.requests()[0].url, "https://api.example.com/v1/moderations");
+    }
+
+    #[tokio::test]
+    async fn fails_closed_without_results() {
+        let (moderation, _) = moderation(serde_json::json!({ "results": [] }));
+        let error = moderation.classify("text").await.unwrap_err();
+        assert!(error.downcast_ref::<NoModerationResult>().is_some(), "{error}");
+        let filter = ModerationFilter::new(moderation);
+        let body = ChatCompletionsBody::new("gpt-4o-mini", [crate::client::Message::new(Role::User, "text")]);
+        assert!(filter.check(&body).await.is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc, sync::Arc};
+use std::time::{Duration, Instant};
+
+use crate::client::{ChatCompletionsResponse, CompletionChunk};
+use crate::moderation::ContentFlag;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// OBSERVER
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Follows a request as it streams. Attach any number with
+/// [`crate::client::ChatCompletionsRequestBuilder::with_observer`]; each
+/// receives every callback.
+///
+/// Methods take `&self` and have empty defaults, so an observer that is
+/// `Send + Sync` (using atomics or a mutex for its state) can be shared
+/// across clients through an `Arc`.
+pub trait Observer {
+    /// The text content of a chunk, when it has any. With `n` above 1 this
+    /// joins the choices; use [`Observer::on_choice_delta`] to tell them
+    /// apart.
+    fn on_delta(&self, delta: &str) {
+        let _ = delta;
+    }
+    /// The text content of one choice in a chunk, in the order it arrived
+    /// for that choice.
+    fn on_choice_delta(&self, index: usize, delta: &str) {
+        let _ = (index, delta);
+    }
+    fn on_chunk(&self, chunk: &CompletionChunk) {
+        let _ = chunk;
+    }
+    /// Called after each chunk that carries output.
+    fn on_progress(&self, progress: &StreamProgress) {
+        let _ = progress;
+    }
+    fn on_error(&self, error: &dyn std::error::Error) {
+        let _ = error;
+    }
+    /// A message flagged by a [`crate::moderation::ModerationFilter`] set
+    /// to flag rather than block.
+    fn on_flagged(&self, flag: &ContentFlag) {
+        let _ = flag;
+    }
+    fn on_done(&self, response: &ChatCompletionsResponse) {
+        let _ = response;
+    }
+}
+
+impl<T: Observer + ?Sized> Observer for Rc<T> {
+    fn on_delta(&self, delta: &str) {
+        (**self).on_delta(delta)
+    }
+    fn on_choice_delta(&self, index: usize, delta: &str) {
+        (**self).on_choice_delta(index, delta)
+    }
+    fn on_chunk(&self, chunk: &CompletionChunk) {
+        (**self).on_chunk(chunk)
+    }
+    fn on_progress(&self, progress: &StreamProgress) {
+        (**self).on_progress(progress)
+    }
+    fn on_error(&self, error: &dyn std::error::Error) {
+        (**self).on_error(error)
+    }
+    fn on_flagged(&self, flag: &ContentFlag) {
+        (**self).on_flagged(flag)
+    }
+    fn on_done(&self, response: &ChatCompletionsResponse) {
+        (**self).on_done(response)
+    }
+}
+
+impl<T: Observer + ?Sized> Observer for Arc<T> {
+    fn on_delta(&self, delta: &str) {
+        (**self).on_delta(delta)
+    }
+    fn on_choice_delta(&self, index: usize, delta: &str) {
+        (**self).on_choice_delta(index, delta)
+    }
+    fn on_chunk(&self, chunk: &CompletionChunk) {
+        (**self).on_chunk(chunk)
+    }
+    fn on_progress(&self, progress: &StreamProgress) {
+        (**self).on_progress(progress)
+    }
+    fn on_error(&self, error: &dyn std::error::Error) {
+        (**self).on_error(error)
+    }
+    fn on_flagged(&self, flag: &ContentFlag) {
+        (**self).on_flagged(flag)
+    }
+    fn on_done(&self, response: &ChatCompletionsResponse) {
+        (**self).on_done(response)
+    }
+}
+
+/// Fans every callback out to each observer in order.
+impl<T: Observer> Observer for Vec<T> {
+    fn on_delta(&self, delta: &str) {
+        self.iter().for_each(|x| x.on_delta(delta))
+    }
+    fn on_choice_delta(&self, index: usize, delta: &str) {
+        self.iter().for_each(|x| x.on_choice_delta(index, delta))
+    }
+    fn on_chunk(&self, chunk: &CompletionChunk) {
+        self.iter().for_each(|x| x.on_chunk(chunk))
+    }
+    fn on_progress(&self, progress: &StreamProgress) {
+        self.iter().for_each(|x| x.on_progress(progress))
+    }
+    fn on_error(&self, error: &dyn std::error::Error) {
+        self.iter().for_each(|x| x.on_error(error))
+    }
+    fn on_flagged(&self, flag: &ContentFlag) {
+        self.iter().for_each(|x| x.on_flagged(flag))
+    }
+    fn on_done(&self, response: &ChatCompletionsResponse) {
+        self.iter().for_each(|x| x.on_done(response))
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// PROGRESS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// How far along a streaming response is.
+///
+/// Tokens are counted as chunks carrying output, which is one token per
+/// chunk for OpenAI and close to it for most compatible APIs.
+#[derive(Debug, Clone, Copy)]
+pub struct StreamProgress {
+    pub tokens: usize,
+    /// Since the request was sent.
+    pub elapsed: Duration,
+    /// Over the last [`StreamProgress::WINDOW`].
+    pub tokens_per_second: f64,
+    /// Time left if the reply runs to `max_tokens` at the current rate; an
+    /// upper bound, since most replies end sooner.
+    pub estimated_remaining: Option<Duration>,
+}
+
+impl StreamProgress {
+    pub const WINDOW: Duration = Duration::from_secs(2);
+}
+
+pub(crate) struct ProgressTracker {
+    started: Instant,
+    max_tokens: Option<usize>,
+    tokens: usize,
+    /// Arrival times of the tokens within the window.
+    recent: VecDeque<Instant>,
+}
+
+impl ProgressTracker {
+    pub(crate) fn new(max_tokens: Option<usize>) -> Self {
+        ProgressTracker { started: Instant::now(), max_tokens, tokens: 0, recent: VecDeque::default() }
+    }
+    pub(crate) fn update(&mut self, chunk: &CompletionChunk) -> Option<StreamProgress> {
+        if !chunk.has_output() {
+            return None
+        }
+        let now = Instant::now();
+        self.tokens += 1;
+        self.recent.push_back(now);
+        while self.recent.front().is_some_and(|x| now.duration_since(*x) > StreamProgress::WINDOW) {
+            self.recent.pop_front();
+        }
+        let elapsed = now.duration_since(self.started);
+        // Until the window has filled, measure from the first token.
+        let span = match self.recent.front() {
+            Some(first) if self.recent.len() > 1 => now.duration_since(*first),
+            _ => Duration::ZERO,
+        };
+        let tokens_per_second = match span.is_zero() {
+            true => 0.0,
+            false => (self.recent.len() - 1) as f64 / span.as_secs_f64(),
+        };
+        let estimated_remaining = self.max_tokens
+            .filter(|_| tokens_per_second > 0.0)
+            .map(|max| Duration::from_secs_f64(max.saturating_sub(self.tokens) as f64 / tokens_per_second));
+        Some(StreamProgress { tokens: self.tokens, elapsed, tokens_per_second, estimated_remaining })
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// CLOSURES
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// An observer that passes each delta to a closure; what
+/// [`crate::client::ChatCompletionsRequestBuilder::with_logger_closure`]
+/// attaches.
+pub struct DeltaFn<F>(RefCell<F>);
+
+impl<F: FnMut(&str)> DeltaFn<F> {
+    pub fn new(f: F) -> Self {
+        DeltaFn(RefCell::new(f))
+    }
+}
+
+impl<F: FnMut(&str)> Observer for DeltaFn<F> {
+    fn on_delta(&self, delta: &str) {
+        let mut f = self.0.borrow_mut();
+        f(delta)
+    }
+}
@@ -0,0 +1,97 @@
+use opentelemetry::trace::{Span, SpanKind, Status, Tracer};
+use opentelemetry::{global, KeyValue, StringValue};
+
+use crate::client::{ApiEndpoint, ChatCompletionsBody, ChatCompletionsResponse, Error};
+
+/// The instrumentation scope spans are reported under.
+pub const TRACER_NAME: &str = "chatgpt-subsystems";
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// GENAI SPANS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Starts a client span for a request to `endpoint`, named and attributed following the
+/// OpenTelemetry GenAI semantic conventions. The parent is the current
+/// OpenTelemetry context, so the span nests under the caller's trace.
+pub(crate) fn start_span(endpoint: &ApiEndpoint, body: &ChatCompletionsBody) -> global::BoxedSpan {
+    let mut attributes = vec![
+        KeyValue::new("gen_ai.operation.name", "chat"),
+        KeyValue::new("gen_ai.request.model", body.model.clone()),
+    ];
+    if let Some(host) = endpoint.host() {
+        attributes.push(KeyValue::new("gen_ai.provider.name", provider_name(&host)));
+        attributes.push(KeyValue::new("server.address", host));
+    }
+    if let Some(temperature) = body.temperature {
+        attributes.push(KeyValue::new("gen_ai.request.temperature", temperature as f64));
+    }
+    if let Some(top_p) = body.top_p {
+        attributes.push(KeyValue::new("gen_ai.request.top_p", top_p as f64));
+    }
+    if let Some(max_tokens) = body.max_tokens {
+        attributes.push(KeyValue::new("gen_ai.request.max_tokens", max_tokens as i64));
+    }
+    if let Some(seed) = body.seed {
+        attributes.push(KeyValue::new("gen_ai.request.seed", seed as i64));
+    }
+    let tracer = global::tracer(TRACER_NAME);
+    tracer
+        .span_builder(format!("chat {}", body.model))
+        .with_kind(SpanKind::Client)
+        .with_attributes(attributes)
+        .start(&tracer)
+}
+
+/// Records the response model, id, finish reasons and token usage, or the
+/// error, then ends the span.
+pub(crate) fn end_span(span: &mut global::BoxedSpan, result: &Result<ChatCompletionsResponse, Error>) {
+    match result {
+        Ok(response) => {
+            if let Some(chunk) = response.output.first() {
+                span.set_attribute(KeyValue::new("gen_ai.response.model", chunk.model.clone()));
+                span.set_attribute(KeyValue::new("gen_ai.response.id", chunk.id.clone()));
+            }
+            let mut indices = response.output
+                .iter()
+                .flat_map(|chunk| chunk.choices.iter().map(|x| x.index))
+                .collect::<Vec<_>>();
+            indices.sort_unstable();
+            indices.dedup();
+            let finish_reasons = indices
+                .into_iter()
+                .filter_map(|index| response.finish_reason(index))
+                .map(|x| StringValue::from(x.to_string()))
+                .collect::<Vec<_>>();
+            if !finish_reasons.is_empty() {
+                span.set_attribute(KeyValue::new(
+                    "gen_ai.response.finish_reasons",
+                    opentelemetry::Value::Array(finish_reasons.into()),
+                ));
+            }
+            if let Some(usage) = response.usage() {
+                span.set_attribute(KeyValue::new("gen_ai.usage.input_tokens", usage.prompt_tokens as i64));
+                span.set_attribute(KeyValue::new("gen_ai.usage.output_tokens", usage.completion_tokens as i64));
+            }
+        }
+        Err(error) => {
+            span.set_attribute(KeyValue::new("error.type", error_type(error.as_ref())));
+            span.set_status(Status::error(error.to_string()));
+        }
+    }
+    span.end();
+}
+
+/// Well-known providers by host, otherwise the host itself.
+fn provider_name(host: &str) -> String {
+    match host {
+        "api.openai.com" => String::from("openai"),
+        "text.octoai.run" => String::from("octoai"),
+        _ => host.to_string(),
+    }
+}
+
+fn error_type(error: &(dyn std::error::Error + 'static)) -> String {
+    match error.downcast_ref::<crate::client::ApiError>() {
+        Some(error) => format!("{error:?}"),
+        None => String::from("_OTHER"),
+    }
+}
@@ -0,0 +1,265 @@
+use std::str::FromStr;
+use liquid::model::{Value, ValueView};
+use serde::Serialize;
+
+use crate::client as api;
+use crate::xml_dsl::{Prompt, PromptCollection, PromptNotFound};
+
+#[cfg(feature = "derive")]
+pub use chatgpt_subsystems_derive::PromptParams;
+
+/// The render-time globals produced by [`PromptParams::to_globals`].
+pub type Globals = liquid::Object;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// TYPED PARAMETERS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// A struct whose fields supply a prompt's template variables. Usually
+/// derived:
+///
+/// ```ignore
+/// #[derive(PromptParams)]
+/// #[prompt(name = "summarize")]
+/// struct Summarize {
+///     text: String,
+///     #[prompt(rename = "max-words")]
+///     max_words: usize,
+/// }
+///
+/// let body = Summarize { text, max_words: 50 }.render(&collection)?;
+/// ```
+pub trait PromptParams {
+    /// The prompt these parameters are for, if fixed.
+    const PROMPT: Option<&'static str>;
+    /// The variable names the fields map to.
+    const VARIABLES: &'static [&'static str];
+    fn to_globals(&self) -> Result<Globals, api::Error>;
+    /// Looks up [`PromptParams::PROMPT`] in the collection and renders it.
+    fn render(&self, collection: &PromptCollection) -> Result<api::ChatCompletionsBody, api::Error>
+    where
+        Self: Sized,
+    {
+        let name = Self::PROMPT.ok_or(Box::new(PromptNotFound(String::from("<unspecified>"))))?;
+        let prompt = collection.get(name).ok_or(Box::new(PromptNotFound(name.to_string())))?;
+        prompt.render_params(self)
+    }
+}
+
+impl Prompt {
+    /// Renders with typed parameters, first checking that they cover every
+    /// variable the prompt reads.
+    pub fn render_params<P: PromptParams>(&self, params: &P) -> Result<api::ChatCompletionsBody, api::Error> {
+        let missing = self.missing_params::<P>();
+        if !missing.is_empty() {
+            return Err(Box::new(MissingParams(self.name.clone(), missing)))
+        }
+        self.render_body(&params.to_globals()?)
+    }
+    /// The variables this prompt reads that `P` does not provide.
+    pub fn missing_params<P: PromptParams>(&self) -> Vec<String> {
+        self.variables()
+            .into_iter()
+            .filter(|x| !P::VARIABLES.contains(&x.as_str()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MissingParams(pub Option<String>, pub Vec<String>);
+impl std::fmt::Display for MissingParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let prompt = self.0.as_deref().unwrap_or("<unnamed>");
+        write!(f, "Parameters for prompt {prompt:?} do not cover: {}.", self.1.join(", "))
+    }
+}
+impl std::error::Error for MissingParams {}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// DECLARED PARAMETERS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParamType {
+    #[default]
+    String,
+    Int,
+    Bool,
+    List,
+}
+
+impl ParamType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParamType::String => "string",
+            ParamType::Int => "int",
+            ParamType::Bool => "bool",
+            ParamType::List => "list",
+        }
+    }
+    /// Converts a supplied value to this type, or `None` if it cannot be.
+    ///
+    /// Any scalar becomes a string; integers may also be given as strings
+    /// or whole floats; booleans as `true`/`false`, `yes`/`no` or `1`/`0`;
+    /// and lists as a comma separated string.
+    pub fn coerce(&self, value: &dyn ValueView) -> Option<Value> {
+        if let Some(array) = value.as_array() {
+            return (*self == ParamType::List).then(|| array.to_value())
+        }
+        let scalar = value.as_scalar()?;
+        match self {
+            ParamType::String => Some(Value::scalar(scalar.to_kstr().into_owned())),
+            ParamType::Int => {
+                let whole = || scalar.to_float().filter(|x| x.fract() == 0.0).map(|x| x as i64);
+                let integer = scalar.to_kstr().trim().parse::<i64>().ok().or_else(whole)?;
+                Some(Value::scalar(integer))
+            }
+            ParamType::Bool => {
+                let flag = match scalar.to_bool() {
+                    Some(x) => x,
+                    None => match scalar.to_kstr().trim().to_lowercase().as_str() {
+                        "true" | "yes" | "1" => true,
+                        "false" | "no" | "0" => false,
+                        _ => return None,
+                    },
+                };
+                Some(Value::scalar(flag))
+            }
+            ParamType::List => {
+                if value.type_name() != "string" {
+                    return None
+                }
+                let items = scalar.to_kstr()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|x| !x.is_empty())
+                    .map(|x| Value::scalar(x.to_string()))
+                    .collect();
+                Some(Value::Array(items))
+            }
+        }
+    }
+}
+
+impl FromStr for ParamType {
+    type Err = String;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "string" | "str" => Ok(ParamType::String),
+            "int" | "integer" => Ok(ParamType::Int),
+            "bool" | "boolean" => Ok(ParamType::Bool),
+            "list" | "array" => Ok(ParamType::List),
+            _ => Err(value.to_string()),
+        }
+    }
+}
+
+/// A variable declared in a prompt's `<params>` block:
+///
+/// ```xml
+/// <params>
+///     <param name="text" type="string"/>
+///     <param name="max-words" type="int" default="100"/>
+///     <param name="formal" type="bool" default="false"/>
+///     <param name="topics" type="list" required="false"/>
+/// </params>
+/// ```
+///
+/// Declared prompts check and convert their globals before rendering; see
+/// [`Prompt::bind_params`].
+#[derive(Debug, Clone)]
+pub struct ParamDeclaration {
+    pub name: String,
+    pub param_type: ParamType,
+    /// Defaults to whether there is no `default`.
+    pub required: Option<bool>,
+    /// As written in the DSL; converted like a supplied value.
+    pub default: Option<String>,
+}
+
+impl ParamDeclaration {
+    pub fn new(name: impl AsRef<str>, param_type: ParamType) -> Self {
+        ParamDeclaration { name: name.as_ref().to_string(), param_type, required: None, default: None }
+    }
+    pub fn with_required(mut self, required: bool) -> Self {
+        self.required = Some(required);
+        self
+    }
+    pub fn with_default(mut self, default: impl AsRef<str>) -> Self {
+        self.default = Some(default.as_ref().to_string());
+        self
+    }
+    pub fn is_required(&self) -> bool {
+        self.required.unwrap_or(self.default.is_none())
+    }
+    /// The default converted to the declared type.
+    pub fn default_value(&self) -> Option<Value> {
+        let default = Value::scalar(self.default.clone()?);
+        self.param_type.coerce(&default)
+    }
+}
+
+impl Prompt {
+    /// Checks the globals against the prompt's `<params>` declarations and
+    /// converts each declared value to its type. Missing values take their
+    /// default; optional ones without a default are bound to `nil`.
+    /// Undeclared globals pass through unchanged.
+    pub fn bind_params(&self, globals: &liquid::Object) -> Result<liquid::Object, api::Error> {
+        let mut bound = globals.clone();
+        for param in self.params.iter() {
+            let value = match globals.get(param.name.as_str()).filter(|x| !x.is_nil()) {
+                Some(value) => param.param_type.coerce(value).ok_or_else(|| InvalidParam {
+                    prompt: self.name.clone(),
+                    param: param.name.clone(),
+                    expected: param.param_type,
+                    value: value.to_kstr().to_string(),
+                })?,
+                None if param.is_required() => {
+                    return Err(Box::new(MissingParam { prompt: self.name.clone(), param: param.name.clone() }))
+                }
+                None => param.default_value().unwrap_or(Value::Nil),
+            };
+            bound.insert(param.name.clone().into(), value);
+        }
+        Ok(bound)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MissingParam {
+    pub prompt: Option<String>,
+    pub param: String,
+}
+impl std::fmt::Display for MissingParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let prompt = self.prompt.as_deref().unwrap_or("<unnamed>");
+        write!(f, "Cannot render prompt {prompt:?}: required parameter '{}' was not supplied.", self.param)
+    }
+}
+impl std::error::Error for MissingParam {}
+
+#[derive(Debug, Clone)]
+pub struct InvalidParam {
+    pub prompt: Option<String>,
+    pub param: String,
+    pub expected: ParamType,
+    pub value: String,
+}
+impl std::fmt::Display for InvalidParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let prompt = self.prompt.as_deref().unwrap_or("<unnamed>");
+        write!(
+            f,
+            "Cannot render prompt {prompt:?}: parameter '{}' must be of type {}, got {:?}.",
+            self.param,
+            self.expected.as_str(),
+            self.value,
+        )
+    }
+}
+impl std::error::Error for InvalidParam {}
+
+/// Used by the derive macro.
+#[doc(hidden)]
+pub fn insert(globals: &mut Globals, name: &str, value: &impl Serialize) -> Result<(), api::Error> {
+    globals.insert(name.to_string().into(), liquid::model::to_value(value)?);
+    Ok(())
+}
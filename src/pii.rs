@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::{cell::RefCell, rc::Rc};
+use regex::Regex;
+
+use crate::client::{ChatCompletionsBody, ChatCompletionsResponse, Error};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// PATTERNS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+pub const EMAIL_PATTERN: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}";
+/// Eight or more digits in groups, optionally with a country code and
+/// separated by spaces, dots, dashes or parentheses.
+pub const PHONE_PATTERN: &str = r"(?:\+\d{1,3}[\s.-]?)?(?:\(\d{2,4}\)|\b\d{2,4})[\s.-]?\d{3,4}[\s.-]?\d{3,4}\b";
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// SCRUBBER
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Masks personal data in outgoing messages with tokens such as
+/// `[EMAIL_1]`, and puts the originals back in replies. Each distinct value
+/// keeps its token for the life of the scrubber, so the model can refer to
+/// it consistently; the map never leaves the process. Cloning shares the
+/// map.
+///
+/// Set it with
+/// [`crate::client::ChatCompletionsRequestBuilder::with_pii_scrubber`] to
+/// scrub every request and restore every response. Chunks passed to
+/// `on_chunk` and observers are still masked, and the restored response is
+/// folded into one chunk, since a token may span several. Caches, logs and
+/// audit records only ever see the masked body.
+///
+/// ```rust,ignore
+/// let scrubber = PiiScrubber::new().with_pattern("account", r"ACC-\d{8}")?;
+/// let builder = builder.with_pii_scrubber(scrubber);
+/// ```
+#[derive(Clone)]
+pub struct PiiScrubber {
+    patterns: Vec<(String, Regex)>,
+    builtin: Vec<(String, Regex)>,
+    tokens: Rc<RefCell<TokenMap>>,
+    token_pattern: Regex,
+}
+
+#[derive(Default)]
+struct TokenMap {
+    by_value: HashMap<String, String>,
+    by_token: HashMap<String, String>,
+    counts: HashMap<String, usize>,
+}
+
+impl Default for PiiScrubber {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PiiScrubber {
+    /// Masks email addresses and phone numbers.
+    pub fn new() -> Self {
+        let builtin = vec![
+            (String::from("EMAIL"), Regex::new(EMAIL_PATTERN).unwrap()),
+            (String::from("PHONE"), Regex::new(PHONE_PATTERN).unwrap()),
+        ];
+        PiiScrubber { builtin, ..Self::empty() }
+    }
+    /// Masks nothing until patterns are added.
+    pub fn empty() -> Self {
+        PiiScrubber {
+            patterns: Vec::default(),
+            builtin: Vec::default(),
+            tokens: Rc::default(),
+            token_pattern: Regex::new(r"\[[A-Z0-9_]+_\d+\]").unwrap(),
+        }
+    }
+    /// Masks matches of `pattern` as `[KIND_n]`. Patterns apply in the
+    /// order added, before the email and phone patterns, so a specific
+    /// pattern wins over a general one.
+    pub fn with_pattern(self, kind: impl AsRef<str>, pattern: impl AsRef<str>) -> Result<Self, Error> {
+        Ok(self.with_regex(kind, Regex::new(pattern.as_ref())?))
+    }
+    pub fn with_regex(mut self, kind: impl AsRef<str>, regex: Regex) -> Self {
+        let kind = kind
+            .as_ref()
+            .chars()
+            .map(|x| match x.is_ascii_alphanumeric() {
+                true => x.to_ascii_uppercase(),
+                false => '_',
+            })
+            .collect();
+        self.patterns.push((kind, regex));
+        self
+    }
+    pub fn scrub(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        let mut tokens = self.tokens.borrow_mut();
+        for (kind, regex) in self.patterns.iter().chain(self.builtin.iter()) {
+            text = regex
+                .replace_all(&text, |captures: &regex::Captures| tokens.token(kind, &captures[0]))
+                .into_owned();
+        }
+        text
+    }
+    /// Replaces the tokens in `text` with the values they stand for;
+    /// unknown tokens are left as they are.
+    pub fn restore(&self, text: &str) -> String {
+        let tokens = self.tokens.borrow();
+        self.token_pattern
+            .replace_all(text, |captures: &regex::Captures| {
+                let token = &captures[0];
+                tokens.by_token.get(token).cloned().unwrap_or_else(|| token.to_string())
+            })
+            .into_owned()
+    }
+    /// Scrubs the content, name and tool-call arguments of every message.
+    pub fn scrub_body(&self, body: &ChatCompletionsBody) -> ChatCompletionsBody {
+        let mut body = body.clone();
+        for message in body.messages.iter_mut() {
+            message.content = self.scrub(&message.content);
+            // Names allow only letters, digits, `_` and `-`, so the
+            // token goes in without its brackets.
+            if let Some(name) = message.name.as_mut() {
+                *name = self.scrub(name).replace(['[', ']'], "");
+            }
+            for call in message.tool_calls.iter_mut() {
+                call.function.arguments = self.scrub(&call.function.arguments);
+            }
+        }
+        body
+    }
+    /// Restores the content and tool-call arguments of every choice,
+    /// folding the chunks into one.
+    pub fn restore_response(&self, response: ChatCompletionsResponse) -> ChatCompletionsResponse {
+        let mut response = response.folded();
+        for choice in response.output.iter_mut().flat_map(|x| x.choices.iter_mut()) {
+            if let Some(content) = choice.delta.content.as_mut() {
+                *content = self.restore(content);
+            }
+            let calls = choice.delta.tool_calls.iter_mut().flatten();
+            for arguments in calls.filter_map(|x| x.function.as_mut()?.arguments.as_mut()) {
+                *arguments = self.restore(arguments);
+            }
+        }
+        response
+    }
+    /// Every token handed out so far, with the value it stands for.
+    pub fn tokens(&self) -> Vec<(String, String)> {
+        let mut tokens = self.tokens
+            .borrow()
+            .by_token
+            .iter()
+            .map(|(token, value)| (token.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        tokens.sort();
+        tokens
+    }
+}
+
+impl TokenMap {
+    fn token(&mut self, kind: &str, value: &str) -> String {
+        if let Some(token) = self.by_value.get(value) {
+            return token.clone()
+        }
+        let count = self.counts.entry(kind.to_string()).or_default();
+        *count += 1;
+        let token = format!("[{kind}_{count}]");
+        self.by_value.insert(value.to_string(), token.clone());
+        self.by_token.insert(token.clone(), value.to_string());
+        token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{Message, Role};
+    use crate::tools::{FunctionCall, ToolCall};
+
+    #[test]
+    fn scrub_body_masks_names_and_tool_call_arguments() {
+        let scrubber = PiiScrubber::new().with_pattern("customer", r"C\d{6}").unwrap();
+        let call = ToolCall {
+            id: String::from("call_1"),
+            r#type: String::from("function"),
+            function: FunctionCall {
+                name: String::from("send_email"),
+                arguments: String::from(r#"{"to":"ada@example.com","customer":"C123456"}"#),
+            },
+        };
+        let body = ChatCompletionsBody::new("gpt-4o-mini", [
+            Message::new(Role::User, "I am ada@example.com").with_name("C123456"),
+            Message::new(Role::Assistant, "").with_tool_calls(vec![call]),
+        ]);
+        let body = scrubber.scrub_body(&body);
+        assert_eq!(body.messages[0].content, "I am [EMAIL_1]");
+        assert_eq!(body.messages[0].name.as_deref(), Some("CUSTOMER_1"));
+        let arguments = &body.messages[1].tool_calls[0].function.arguments;
+        assert_eq!(arguments, r#"{"to":"[EMAIL_1]","customer":"[CUSTOMER_1]"}"#);
+        assert_eq!(scrubber.restore(arguments), r#"{"to":"ada@example.com","customer":"C123456"}"#);
+    }
+}
@@ -0,0 +1,241 @@
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::client::{ChatCompletionsRequestBuilder, Error, Usage};
+use crate::extract::{strip_code_fence, MissingBody};
+use crate::lint::estimate_tokens;
+use crate::xml_dsl::{Chain, Prompt, PromptCollection, PromptNotFound};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// STAGES
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageOutput {
+    /// The reply as a string.
+    Text,
+    /// The reply parsed as JSON, so later prompts can read its fields.
+    /// A surrounding Markdown code block is ignored.
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub struct Stage {
+    /// Defaults to the prompt's name.
+    pub name: String,
+    pub prompt: Prompt,
+    /// The variable later stages read the output from.
+    pub variable: String,
+    pub output: StageOutput,
+}
+
+impl Stage {
+    pub fn new(prompt: Prompt, variable: impl AsRef<str>) -> Self {
+        let name = prompt.name.clone().unwrap_or_else(|| variable.as_ref().to_string());
+        Stage { name, prompt, variable: variable.as_ref().to_string(), output: StageOutput::Text }
+    }
+    pub fn with_name(mut self, name: impl AsRef<str>) -> Self {
+        self.name = name.as_ref().to_string();
+        self
+    }
+    pub fn with_json_output(mut self) -> Self {
+        self.output = StageOutput::Json;
+        self
+    }
+    pub(crate) async fn run(&self, variables: &liquid::Object, setup: Option<&Setup>) -> Result<StageTrace, Error> {
+        let started = Instant::now();
+        let failed = |error: Error| StageFailed { stage: self.name.clone(), error: error.to_string() };
+        let mut builder = self.prompt.render_request_builder(variables).map_err(failed)?;
+        if let Some(setup) = setup {
+            builder = setup(builder);
+        }
+        let request = builder.build().ok_or_else(|| failed(Box::new(MissingBody)))?;
+        let response = request.execute().await.map_err(failed)?;
+        let content = response.content(0);
+        let output = match self.output {
+            StageOutput::Text => serde_json::Value::String(content.clone()),
+            StageOutput::Json => serde_json::from_str(strip_code_fence(&content))
+                .map_err(|e| failed(Box::new(e)))?,
+        };
+        Ok(StageTrace {
+            name: self.name.clone(),
+            variable: self.variable.clone(),
+            inputs: variables.clone(),
+            content,
+            output,
+            usage: response.usage(),
+            duration: started.elapsed(),
+        })
+    }
+}
+
+/// What one stage did.
+#[derive(Debug, Clone)]
+pub struct StageTrace {
+    pub name: String,
+    pub variable: String,
+    /// The variables the prompt was rendered with.
+    pub inputs: liquid::Object,
+    /// The reply as received.
+    pub content: String,
+    /// The value bound to `variable`.
+    pub output: serde_json::Value,
+    pub usage: Option<Usage>,
+    pub duration: Duration,
+}
+
+impl StageTrace {
+    /// From the reported usage, or estimated from the reply.
+    pub fn tokens(&self) -> usize {
+        self.usage.map(|x| x.total_tokens).unwrap_or_else(|| estimate_tokens(&self.content))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PipelineOutcome {
+    /// The last stage's output.
+    pub output: serde_json::Value,
+    /// The globals plus every stage's output.
+    pub variables: liquid::Object,
+    pub stages: Vec<StageTrace>,
+    /// Summed over the stages, using estimates where usage was not reported.
+    pub tokens: usize,
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// PIPELINE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+pub(crate) type Setup = Rc<dyn Fn(ChatCompletionsRequestBuilder) -> ChatCompletionsRequestBuilder>;
+
+/// Runs prompts one after another, binding each reply to a variable that
+/// the following prompts can use.
+///
+/// ```rust,ignore
+/// let outcome = Pipeline::new()
+///     .then(Stage::new(collection.get("extract-facts").unwrap(), "facts").with_json_output())
+///     .then(Stage::new(collection.get("write-summary").unwrap(), "summary"))
+///     .with_setup(move |x| x.with_api_key(&api_key))
+///     .with_max_tokens(20_000)
+///     .run(&liquid::object!({ "article": article }))
+///     .await?;
+/// ```
+///
+/// Here `write-summary` can refer to `{{ facts.title }}` as well as
+/// `{{ article }}`.
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+    setup: Option<Setup>,
+    max_tokens: Option<usize>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// The stages of the collection's `<chain>` of that name; see
+    /// [`Chain`].
+    pub fn from_chain(collection: &PromptCollection, chain_name: impl AsRef<str>) -> Result<Self, Error> {
+        let chain_name = chain_name.as_ref();
+        let chain = collection
+            .chain(chain_name)
+            .ok_or_else(|| Box::new(ChainNotFound(chain_name.to_string())))?;
+        Self::for_chain(collection, chain)
+    }
+    /// Fails with the [`crate::validate::Diagnostics`] if the chain does not
+    /// pass [`Chain::validate`].
+    pub fn for_chain(collection: &PromptCollection, chain: &Chain) -> Result<Self, Error> {
+        let diagnostics = chain.validate(collection);
+        if !diagnostics.is_ok() {
+            return Err(Box::new(diagnostics))
+        }
+        let mut pipeline = Pipeline::new();
+        for step in chain.steps.iter() {
+            let prompt = collection
+                .get(&step.prompt)
+                .ok_or_else(|| Box::new(PromptNotFound(step.prompt.clone())))?;
+            let mut stage = Stage::new(prompt, &step.var);
+            stage.output = step.output;
+            pipeline = pipeline.then(stage);
+        }
+        Ok(pipeline)
+    }
+    pub fn then(mut self, stage: Stage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+    /// Applied to every stage's request, e.g. to set the API key, endpoint
+    /// or transport.
+    pub fn with_setup(
+        mut self,
+        setup: impl Fn(ChatCompletionsRequestBuilder) -> ChatCompletionsRequestBuilder + 'static,
+    ) -> Self {
+        self.setup = Some(Rc::new(setup));
+        self
+    }
+    /// Total tokens to spend across the stages; the pipeline stops with
+    /// [`TokenBudgetExceeded`] once a stage takes it past the limit.
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+    pub async fn run(&self, globals: &liquid::Object) -> Result<PipelineOutcome, Error> {
+        let mut variables = globals.clone();
+        let mut traces = Vec::<StageTrace>::with_capacity(self.stages.len());
+        let mut tokens = 0;
+        for stage in self.stages.iter() {
+            let trace = stage.run(&variables, self.setup.as_ref()).await?;
+            tokens += trace.tokens();
+            variables.insert(stage.variable.clone().into(), liquid::model::to_value(&trace.output)?);
+            traces.push(trace);
+            if let Some(max_tokens) = self.max_tokens.filter(|max| tokens > *max) {
+                return Err(Box::new(TokenBudgetExceeded { stage: stage.name.clone(), tokens, max_tokens }))
+            }
+        }
+        let output = traces.last().map(|x| x.output.clone()).unwrap_or_default();
+        Ok(PipelineOutcome { output, variables, stages: traces, tokens })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainNotFound(pub String);
+impl std::fmt::Display for ChainNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot find chain: {:?}.", self.0)
+    }
+}
+impl std::error::Error for ChainNotFound {}
+
+#[derive(Debug, Clone)]
+pub struct StageFailed {
+    pub stage: String,
+    pub error: String,
+}
+impl std::fmt::Display for StageFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Pipeline stage {:?} failed: {}.", self.stage, self.error.trim_end().trim_end_matches('.'))
+    }
+}
+impl std::error::Error for StageFailed {}
+
+#[derive(Debug, Clone)]
+pub struct TokenBudgetExceeded {
+    /// The stage that went over.
+    pub stage: String,
+    pub tokens: usize,
+    pub max_tokens: usize,
+}
+impl std::fmt::Display for TokenBudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Pipeline stopped after stage {:?}: {} tokens used, over the limit of {}.",
+            self.stage,
+            self.tokens,
+            self.max_tokens,
+        )
+    }
+}
+impl std::error::Error for TokenBudgetExceeded {}
@@ -0,0 +1,64 @@
+use crate::client::Usage;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// PRICES
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// List prices in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    pub input: f64,
+    /// For prompt tokens served from the prompt cache; `None` if the model
+    /// has no cache discount.
+    pub cached_input: Option<f64>,
+    pub output: f64,
+}
+
+impl ModelPrice {
+    pub const fn new(input: f64, cached_input: Option<f64>, output: f64) -> Self {
+        ModelPrice { input, cached_input, output }
+    }
+    /// The cost of a request in USD.
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        let cached = usage.cached_tokens().min(usage.prompt_tokens);
+        let uncached = usage.prompt_tokens - cached;
+        let cached_price = self.cached_input.unwrap_or(self.input);
+        (uncached as f64 * self.input + cached as f64 * cached_price + usage.completion_tokens as f64 * self.output)
+            / 1_000_000.0
+    }
+}
+
+/// OpenAI's published prices, by model name prefix. These change; check
+/// them before relying on the totals.
+pub const OPENAI_PRICES: &[(&str, ModelPrice)] = &[
+    ("gpt-4.1-nano", ModelPrice::new(0.10, Some(0.025), 0.40)),
+    ("gpt-4.1-mini", ModelPrice::new(0.40, Some(0.10), 1.60)),
+    ("gpt-4.1", ModelPrice::new(2.00, Some(0.50), 8.00)),
+    ("gpt-4o-mini", ModelPrice::new(0.15, Some(0.075), 0.60)),
+    ("gpt-4o", ModelPrice::new(2.50, Some(1.25), 10.00)),
+    ("gpt-4-turbo", ModelPrice::new(10.00, None, 30.00)),
+    ("gpt-4-0125-preview", ModelPrice::new(10.00, None, 30.00)),
+    ("gpt-4-1106-preview", ModelPrice::new(10.00, None, 30.00)),
+    ("gpt-4", ModelPrice::new(30.00, None, 60.00)),
+    ("gpt-3.5-turbo", ModelPrice::new(0.50, None, 1.50)),
+    ("o1-mini", ModelPrice::new(1.10, Some(0.55), 4.40)),
+    ("o1", ModelPrice::new(15.00, Some(7.50), 60.00)),
+    ("o3-mini", ModelPrice::new(1.10, Some(0.55), 4.40)),
+    ("o3", ModelPrice::new(2.00, Some(0.50), 8.00)),
+    ("o4-mini", ModelPrice::new(1.10, Some(0.275), 4.40)),
+];
+
+/// Looks up a model in [`OPENAI_PRICES`], preferring the longest matching
+/// prefix so dated snapshots like `gpt-4o-mini-2024-07-18` resolve.
+pub fn price(model: impl AsRef<str>) -> Option<ModelPrice> {
+    let model = model.as_ref();
+    OPENAI_PRICES
+        .iter()
+        .filter(|(prefix, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, price)| *price)
+}
+
+/// The cost of a request in USD, if the model's price is known.
+pub fn cost(model: impl AsRef<str>, usage: &Usage) -> Option<f64> {
+    price(model).map(|x| x.cost(usage))
+}
@@ -0,0 +1,146 @@
+use std::rc::Rc;
+
+use crate::client::{ChatCompletionsRequestBuilder, Error};
+use crate::lint::estimate_tokens;
+use crate::vector_store::{SearchResult, VectorStore};
+use crate::xml_dsl::{PromptCollection, PromptNotFound};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// CONTEXT
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Retrieves the records most relevant to a query and hands them to a
+/// prompt as template variables.
+///
+/// Up to `k` records are fetched, then kept best first while they fit in
+/// the token budget; a record too long for what is left is skipped in
+/// favor of shorter ones after it. They are bound as a list (by default
+/// `context`) of `{ id, text, score, metadata }` objects, and the query
+/// text as `query`:
+///
+/// ```xml
+/// <prompt name="answer" model="gpt-4o-mini">
+///     <message role="system">Answer using only the sources below.</message>
+///     <for-each var="source" in="context">
+///         <message role="system">[{{ source.id }}] {{ source.text }}</message>
+///     </for-each>
+///     <message role="user">{{ query }}</message>
+/// </prompt>
+/// ```
+///
+/// The query's embedding must come from the same model as the stored
+/// vectors.
+///
+/// ```rust,ignore
+/// let request = ContextBuilder::new(store)
+///     .with_max_tokens(3000)
+///     .request(&collection, "answer", &question, &question_embedding, &liquid::Object::new())
+///     .await?
+///     .with_api_key(&api_key);
+/// ```
+#[derive(Clone)]
+pub struct ContextBuilder {
+    store: Rc<dyn VectorStore>,
+    k: usize,
+    max_tokens: usize,
+    min_score: Option<f32>,
+    variable: String,
+    text_variable: Option<String>,
+}
+
+impl ContextBuilder {
+    /// Fetches 8 records and keeps up to 2000 tokens of them.
+    pub fn new(store: impl VectorStore + 'static) -> Self {
+        ContextBuilder {
+            store: Rc::new(store),
+            k: 8,
+            max_tokens: 2000,
+            min_score: None,
+            variable: String::from("context"),
+            text_variable: None,
+        }
+    }
+    pub fn with_k(mut self, k: usize) -> Self {
+        self.k = k;
+        self
+    }
+    /// The budget for the records' text, estimated with
+    /// [`crate::lint::estimate_tokens`].
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+    /// Drops records less similar to the query than this.
+    pub fn with_min_score(mut self, min_score: f32) -> Self {
+        self.min_score = Some(min_score);
+        self
+    }
+    /// The name the list of records is bound to. Defaults to `context`.
+    pub fn with_variable(mut self, variable: impl AsRef<str>) -> Self {
+        self.variable = variable.as_ref().to_string();
+        self
+    }
+    /// Also binds the records' text, joined by blank lines, to this name,
+    /// for prompts that take the context as one string.
+    pub fn with_text_variable(mut self, variable: impl AsRef<str>) -> Self {
+        self.text_variable = Some(variable.as_ref().to_string());
+        self
+    }
+    /// The records that fit the budget, best first.
+    pub async fn retrieve(&self, embedding: &[f32]) -> Result<Vec<SearchResult>, Error> {
+        let results = self.store.search(embedding, self.k).await?;
+        let mut tokens = 0;
+        let mut context = Vec::default();
+        for result in results {
+            if self.min_score.is_some_and(|min| result.score < min) {
+                continue
+            }
+            let size = estimate_tokens(&result.record.text);
+            if tokens + size > self.max_tokens {
+                continue
+            }
+            tokens += size;
+            context.push(result);
+        }
+        Ok(context)
+    }
+    /// Adds the retrieved records and the query to `globals`.
+    pub async fn inject(&self, query: &str, embedding: &[f32], globals: &mut liquid::Object) -> Result<Vec<SearchResult>, Error> {
+        let context = self.retrieve(embedding).await?;
+        let list = context
+            .iter()
+            .map(|x| {
+                serde_json::json!({
+                    "id": x.record.id,
+                    "text": x.record.text,
+                    "score": x.score,
+                    "metadata": x.record.metadata,
+                })
+            })
+            .collect::<Vec<_>>();
+        globals.insert(self.variable.clone().into(), liquid::model::to_value(&list)?);
+        if let Some(variable) = self.text_variable.as_ref() {
+            let text = context.iter().map(|x| x.record.text.as_str()).collect::<Vec<_>>().join("\n\n");
+            globals.insert(variable.clone().into(), liquid::model::Value::scalar(text));
+        }
+        globals.insert("query".into(), liquid::model::Value::scalar(query.to_string()));
+        Ok(context)
+    }
+    /// Renders the named prompt with the retrieved context, ready for an
+    /// API key.
+    pub async fn request(
+        &self,
+        collection: &PromptCollection,
+        prompt_name: impl AsRef<str>,
+        query: &str,
+        embedding: &[f32],
+        globals: &liquid::Object,
+    ) -> Result<ChatCompletionsRequestBuilder, Error> {
+        let prompt_name = prompt_name.as_ref();
+        let prompt = collection
+            .get(prompt_name)
+            .ok_or_else(|| Box::new(PromptNotFound(prompt_name.to_string())))?;
+        let mut globals = globals.clone();
+        self.inject(query, embedding, &mut globals).await?;
+        prompt.render_request_builder(&globals)
+    }
+}
@@ -0,0 +1,178 @@
+use std::{cell::RefCell, rc::Rc};
+use serde::Serialize;
+
+use crate::client::{ChatCompletionsBody, CompletionChunk};
+
+const REDACTED: &str = "[REDACTED]";
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// RECORDS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// What happens to message content, tool-call arguments and streamed
+/// deltas before they are logged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentPolicy {
+    #[default]
+    Full,
+    /// Keep at most this many characters.
+    Truncate(usize),
+    /// Replace with the length only.
+    Mask,
+}
+
+/// One entry of the request log. Serializes to JSON as
+/// `{"event": "request", ...}` and so on.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LogRecord {
+    Request {
+        url: String,
+        /// The API key is always redacted.
+        headers: Vec<(String, String)>,
+        body: serde_json::Value,
+    },
+    Response {
+        status: u16,
+    },
+    Chunk {
+        chunk: serde_json::Value,
+    },
+}
+
+pub type RecordSink = Rc<RefCell<dyn FnMut(&LogRecord)>>;
+
+/// Logs outgoing bodies and incoming chunks with the API key redacted and
+/// message content handled according to a [`ContentPolicy`].
+///
+/// Attach with
+/// [`crate::client::ChatCompletionsRequestBuilder::with_request_log`].
+#[derive(Clone)]
+pub struct RequestLog {
+    policy: ContentPolicy,
+    sink: RecordSink,
+}
+
+impl RequestLog {
+    pub fn new(policy: ContentPolicy, sink: impl FnMut(&LogRecord) + 'static) -> Self {
+        RequestLog { policy, sink: Rc::new(RefCell::new(sink)) }
+    }
+    /// Writes each record to stderr as a line of JSON.
+    pub fn stderr(policy: ContentPolicy) -> Self {
+        Self::new(policy, |record| {
+            if let Ok(line) = serde_json::to_string(record) {
+                eprintln!("{line}");
+            }
+        })
+    }
+    pub fn policy(&self) -> ContentPolicy {
+        self.policy
+    }
+    pub(crate) fn log_request(&self, url: &str, api_key: &str, headers: &[(String, String)], body: &ChatCompletionsBody) {
+        let mut body = serde_json::to_value(body).unwrap_or_default();
+        if let Some(messages) = body.get_mut("messages").and_then(|x| x.as_array_mut()) {
+            for message in messages {
+                self.apply_to_message(message);
+            }
+        }
+        redact_secret(&mut body, api_key);
+        let headers = redact_headers(headers, api_key);
+        let url = redact(url, api_key);
+        self.emit(&LogRecord::Request { url, headers, body });
+    }
+    pub(crate) fn log_response(&self, status: u16) {
+        self.emit(&LogRecord::Response { status });
+    }
+    pub(crate) fn log_chunk(&self, api_key: &str, chunk: &CompletionChunk) {
+        let mut chunk = serde_json::to_value(chunk).unwrap_or_default();
+        if let Some(choices) = chunk.get_mut("choices").and_then(|x| x.as_array_mut()) {
+            for choice in choices {
+                if let Some(delta) = choice.get_mut("delta") {
+                    self.apply_to_message(delta);
+                }
+            }
+        }
+        redact_secret(&mut chunk, api_key);
+        self.emit(&LogRecord::Chunk { chunk });
+    }
+    fn emit(&self, record: &LogRecord) {
+        let mut sink = self.sink.borrow_mut();
+        sink(record);
+    }
+    /// Applies the policy to a message or delta: its text content, text
+    /// parts and tool-call arguments.
+    fn apply_to_message(&self, message: &mut serde_json::Value) {
+        match message.get_mut("content") {
+            Some(serde_json::Value::String(text)) => *text = self.apply(text),
+            Some(serde_json::Value::Array(parts)) => {
+                let texts = parts.iter_mut().filter_map(|x| x.get_mut("text"));
+                for text in texts {
+                    if let serde_json::Value::String(text) = text {
+                        *text = self.apply(text);
+                    }
+                }
+            }
+            _ => {}
+        }
+        let calls = message
+            .get_mut("tool_calls")
+            .and_then(|x| x.as_array_mut())
+            .into_iter()
+            .flatten();
+        for call in calls {
+            if let Some(serde_json::Value::String(arguments)) = call.pointer_mut("/function/arguments") {
+                *arguments = self.apply(arguments);
+            }
+        }
+    }
+    fn apply(&self, text: &str) -> String {
+        match self.policy {
+            ContentPolicy::Full => text.to_string(),
+            ContentPolicy::Truncate(max) => match text.char_indices().nth(max) {
+                Some((offset, _)) => format!("{}…", &text[..offset]),
+                None => text.to_string(),
+            },
+            ContentPolicy::Mask => format!("[{} chars]", text.chars().count()),
+        }
+    }
+}
+
+impl std::fmt::Debug for RequestLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RequestLog").field("policy", &self.policy).finish_non_exhaustive()
+    }
+}
+
+/// Secrets shorter than this are left alone in free text, since replacing
+/// them would garble it; real API keys are far longer.
+const MIN_SECRET_LEN: usize = 8;
+
+pub(crate) fn redact(text: &str, secret: &str) -> String {
+    if secret.len() < MIN_SECRET_LEN {
+        return text.to_string()
+    }
+    text.replace(secret, REDACTED)
+}
+
+/// The `Authorization` header is always redacted, whatever the key.
+pub(crate) fn redact_headers(headers: &[(String, String)], secret: &str) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(key, value)| match key.eq_ignore_ascii_case("Authorization") {
+            true => (key.clone(), format!("Bearer {REDACTED}")),
+            false => (key.clone(), redact(value, secret)),
+        })
+        .collect()
+}
+
+/// Replaces the secret wherever it appears in a string value, in case it
+/// was pasted into a prompt.
+fn redact_secret(value: &mut serde_json::Value, secret: &str) {
+    match value {
+        serde_json::Value::String(text) if secret.len() >= MIN_SECRET_LEN && text.contains(secret) => {
+            *text = redact(text, secret);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|x| redact_secret(x, secret)),
+        serde_json::Value::Object(map) => map.values_mut().for_each(|x| redact_secret(x, secret)),
+        _ => {}
+    }
+}
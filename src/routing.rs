@@ -0,0 +1,175 @@
+use std::rc::Rc;
+
+use crate::client::{ChatCompletionsRequestBuilder, ChatCompletionsResponse, Error, Usage};
+use crate::extract::MissingBody;
+use crate::validators::Validator;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// ESCALATION
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// A sign that the cheap model's reply should not be trusted.
+#[derive(Clone)]
+pub enum Escalation {
+    /// The mean log probability of the reply's tokens is below this, e.g.
+    /// `-0.5`. Requests `logprobs` from the cheap model; replies without
+    /// them never trigger it.
+    MinMeanLogprob(f64),
+    /// The reply fails the validator.
+    Validator(Validator),
+    /// The reply contains this text, ignoring case. Tell the model to use
+    /// it when it is unsure, such as `UNSURE`.
+    Marker(String),
+    /// Returns the reason to escalate, if any.
+    Custom(EscalationFn),
+}
+
+pub type EscalationFn = Rc<dyn Fn(&ChatCompletionsResponse) -> Option<String>>;
+
+impl Escalation {
+    /// Why `response` should be escalated, if it should.
+    pub fn check(&self, response: &ChatCompletionsResponse) -> Option<String> {
+        match self {
+            Escalation::MinMeanLogprob(threshold) => {
+                let mean = mean_logprob(response)?;
+                (mean < *threshold).then(|| format!("mean logprob {mean:.3} is below {threshold}"))
+            }
+            Escalation::Validator(validator) => validator.check(&response.content(0)).err(),
+            Escalation::Marker(marker) => {
+                let content = response.content(0).to_lowercase();
+                content
+                    .contains(&marker.to_lowercase())
+                    .then(|| format!("the reply contains {marker:?}"))
+            }
+            Escalation::Custom(f) => f(response),
+        }
+    }
+}
+
+impl std::fmt::Debug for Escalation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Escalation::MinMeanLogprob(threshold) => f.debug_tuple("MinMeanLogprob").field(threshold).finish(),
+            Escalation::Validator(validator) => f.debug_tuple("Validator").field(validator).finish(),
+            Escalation::Marker(marker) => f.debug_tuple("Marker").field(marker).finish(),
+            Escalation::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// The mean log probability of the first choice's tokens, if the response
+/// has them.
+pub fn mean_logprob(response: &ChatCompletionsResponse) -> Option<f64> {
+    let logprobs = response.logprobs(0);
+    if logprobs.is_empty() {
+        return None
+    }
+    Some(logprobs.iter().map(|x| x.logprob).sum::<f64>() / logprobs.len() as f64)
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// ROUTER
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Sends a request to a cheap model first and repeats it on an expensive
+/// one only when an [`Escalation`] fires, so most requests cost what the
+/// cheap model costs.
+///
+/// ```rust,ignore
+/// let routed = ConfidenceRouter::new(Model::Gpt4oMini, Model::Gpt4o)
+///     .with_min_mean_logprob(-0.4)
+///     .with_marker("UNSURE")
+///     .run(&builder)
+///     .await?;
+/// println!("{} answered: {}", routed.model, routed.response.content(0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfidenceRouter {
+    cheap: String,
+    expensive: String,
+    escalations: Vec<Escalation>,
+}
+
+/// A response and the route it took.
+#[derive(Debug, Clone)]
+pub struct RoutedResponse {
+    pub response: ChatCompletionsResponse,
+    /// The model that answered.
+    pub model: String,
+    pub escalated: bool,
+    /// Why the cheap model's reply was not used.
+    pub reasons: Vec<String>,
+    /// The cheap model's reply, when it was escalated.
+    pub cheap_response: Option<ChatCompletionsResponse>,
+    /// Summed over both requests.
+    pub usage: Usage,
+}
+
+impl ConfidenceRouter {
+    pub fn new(cheap: impl AsRef<str>, expensive: impl AsRef<str>) -> Self {
+        ConfidenceRouter {
+            cheap: cheap.as_ref().to_string(),
+            expensive: expensive.as_ref().to_string(),
+            escalations: Vec::default(),
+        }
+    }
+    pub fn with_escalation(mut self, escalation: Escalation) -> Self {
+        self.escalations.push(escalation);
+        self
+    }
+    pub fn with_min_mean_logprob(self, threshold: f64) -> Self {
+        self.with_escalation(Escalation::MinMeanLogprob(threshold))
+    }
+    pub fn with_validator(self, validator: Validator) -> Self {
+        self.with_escalation(Escalation::Validator(validator))
+    }
+    pub fn with_marker(self, marker: impl AsRef<str>) -> Self {
+        self.with_escalation(Escalation::Marker(marker.as_ref().to_string()))
+    }
+    pub fn with_check(self, f: impl Fn(&ChatCompletionsResponse) -> Option<String> + 'static) -> Self {
+        self.with_escalation(Escalation::Custom(Rc::new(f)))
+    }
+    /// Why `response` should be escalated; empty if it should not.
+    pub fn reasons(&self, response: &ChatCompletionsResponse) -> Vec<String> {
+        self.escalations.iter().filter_map(|x| x.check(response)).collect()
+    }
+    /// Sends the builder's body to the cheap model, then to the expensive
+    /// one if any escalation fires.
+    pub async fn run(&self, builder: &ChatCompletionsRequestBuilder) -> Result<RoutedResponse, Error> {
+        let body = builder.body.clone().ok_or(Box::new(MissingBody))?;
+        let mut cheap_body = body.clone().with_model(&self.cheap);
+        if self.escalations.iter().any(|x| matches!(x, Escalation::MinMeanLogprob(_))) {
+            cheap_body.logprobs = Some(true);
+        }
+        let request = builder.clone().with_body(cheap_body).build().ok_or(Box::new(MissingBody))?;
+        let cheap_response = request.execute().await?;
+        let mut usage = cheap_response.usage().unwrap_or_default();
+        let reasons = self.reasons(&cheap_response);
+        if reasons.is_empty() {
+            return Ok(RoutedResponse {
+                response: cheap_response,
+                model: self.cheap.clone(),
+                escalated: false,
+                reasons,
+                cheap_response: None,
+                usage,
+            })
+        }
+        #[cfg(feature = "tracing")]
+        tracing::info!(from = %self.cheap, to = %self.expensive, reasons = ?reasons, "escalating");
+        let expensive_body = body.with_model(&self.expensive);
+        let request = builder.clone().with_body(expensive_body).build().ok_or(Box::new(MissingBody))?;
+        let response = request.execute().await?;
+        if let Some(x) = response.usage() {
+            usage.prompt_tokens += x.prompt_tokens;
+            usage.completion_tokens += x.completion_tokens;
+            usage.total_tokens += x.total_tokens;
+        }
+        Ok(RoutedResponse {
+            response,
+            model: self.expensive.clone(),
+            escalated: true,
+            reasons,
+            cheap_response: Some(cheap_response),
+            usage,
+        })
+    }
+}
@@ -0,0 +1,262 @@
+use std::path::Path;
+
+use crate::client as api;
+use crate::validate::{Diagnostic, DiagnosticKind, Diagnostics, Severity};
+use crate::xml;
+use crate::xml_dsl::{
+    PromptCollection,
+    ASSERT_ATTRIBUTES,
+    BLOCK_ATTRIBUTES,
+    CHAIN_ATTRIBUTES,
+    FOR_EACH_ATTRIBUTES,
+    INPUT_ATTRIBUTES,
+    MESSAGE_ATTRIBUTES,
+    META_ATTRIBUTES,
+    NODE_ATTRIBUTES,
+    PARAMS_ATTRIBUTES,
+    PARAM_ATTRIBUTES,
+    PROMPT_ATTRIBUTES,
+    STEP_ATTRIBUTES,
+    USE_ATTRIBUTES,
+    WORKFLOW_ATTRIBUTES,
+};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// SCHEMA
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// What may appear between an element's tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Content {
+    /// Only the listed child elements (and whitespace).
+    Elements(&'static [&'static str]),
+    /// Free-form markup that becomes message content; never checked.
+    Markup,
+    /// Nothing at all.
+    Empty,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ElementSchema {
+    pub name: &'static str,
+    pub attributes: &'static [&'static str],
+    pub required: &'static [&'static str],
+    pub content: Content,
+}
+
+/// Elements allowed at the top level of a prompt file.
+pub const TOP_LEVEL: &[&str] = &["prompt", "block", "workflow", "chain"];
+
+const MESSAGE_NODES: &[&str] = &["message", "for-each", "use"];
+const PROMPT_CHILDREN: &[&str] = &["message", "for-each", "use", "meta", "params", "assert"];
+
+/// The XML DSL, one entry per element. `assets/prompt.xsd` describes the
+/// same structure for editors and should be kept in sync.
+pub const SCHEMA: &[ElementSchema] = &[
+    ElementSchema {
+        name: "prompt",
+        attributes: PROMPT_ATTRIBUTES,
+        required: &["name"],
+        content: Content::Elements(PROMPT_CHILDREN),
+    },
+    ElementSchema {
+        name: "block",
+        attributes: BLOCK_ATTRIBUTES,
+        required: &["name"],
+        content: Content::Elements(MESSAGE_NODES),
+    },
+    ElementSchema {
+        name: "message",
+        attributes: MESSAGE_ATTRIBUTES,
+        required: &[],
+        content: Content::Markup,
+    },
+    ElementSchema {
+        name: "for-each",
+        attributes: FOR_EACH_ATTRIBUTES,
+        required: &["in"],
+        content: Content::Elements(MESSAGE_NODES),
+    },
+    ElementSchema {
+        name: "use",
+        attributes: USE_ATTRIBUTES,
+        required: &["block"],
+        content: Content::Empty,
+    },
+    ElementSchema {
+        name: "meta",
+        attributes: META_ATTRIBUTES,
+        required: &["name"],
+        content: Content::Empty,
+    },
+    ElementSchema {
+        name: "params",
+        attributes: PARAMS_ATTRIBUTES,
+        required: &[],
+        content: Content::Elements(&["param"]),
+    },
+    ElementSchema {
+        name: "param",
+        attributes: PARAM_ATTRIBUTES,
+        required: &["name"],
+        content: Content::Empty,
+    },
+    ElementSchema {
+        name: "assert",
+        attributes: ASSERT_ATTRIBUTES,
+        required: &[],
+        content: Content::Empty,
+    },
+    ElementSchema {
+        name: "workflow",
+        attributes: WORKFLOW_ATTRIBUTES,
+        required: &["name"],
+        content: Content::Elements(&["node"]),
+    },
+    ElementSchema {
+        name: "node",
+        attributes: NODE_ATTRIBUTES,
+        required: &["id", "prompt"],
+        content: Content::Elements(&["input"]),
+    },
+    ElementSchema {
+        name: "input",
+        attributes: INPUT_ATTRIBUTES,
+        required: &["from"],
+        content: Content::Empty,
+    },
+    ElementSchema {
+        name: "chain",
+        attributes: CHAIN_ATTRIBUTES,
+        required: &["name"],
+        content: Content::Elements(&["step"]),
+    },
+    ElementSchema {
+        name: "step",
+        attributes: STEP_ATTRIBUTES,
+        required: &["prompt"],
+        content: Content::Empty,
+    },
+];
+
+pub fn element_schema(name: &str) -> Option<&'static ElementSchema> {
+    SCHEMA.iter().find(|x| x.name == name)
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// VALIDATION
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Checks a prompt file against [`SCHEMA`], reporting unknown elements and
+/// attributes, misplaced elements, missing required attributes and stray
+/// text outside of messages.
+///
+/// Unlike [`PromptCollection::validate`], this works on the source text, so
+/// it also catches typos such as `<mesage>` that the parser would silently
+/// skip over.
+pub fn validate_against_schema(contents: impl AsRef<str>) -> Diagnostics {
+    let nodes = match xml::parse_fragment(contents.as_ref()) {
+        Ok(nodes) => nodes,
+        Err(error) => {
+            let diagnostic = Diagnostic::error(DiagnosticKind::MalformedXml, error.message).at(error.position);
+            return Diagnostics(vec![diagnostic])
+        }
+    };
+    let mut diagnostics = Vec::default();
+    check_children(&nodes, "file", TOP_LEVEL, None, &mut diagnostics);
+    Diagnostics(diagnostics)
+}
+
+impl PromptCollection {
+    /// Like [`PromptCollection::parse`], but fails if the source does not
+    /// conform to the schema.
+    pub fn parse_strict(contents: impl AsRef<str>) -> Result<Self, api::Error> {
+        let diagnostics = validate_against_schema(contents.as_ref());
+        if !diagnostics.is_empty() {
+            return Err(Box::new(diagnostics))
+        }
+        Self::parse(contents)
+    }
+    pub fn open_strict(file_path: impl AsRef<Path>) -> Result<Self, api::Error> {
+        let file_path = file_path.as_ref();
+        let source = std::fs::read_to_string(file_path)?;
+        let diagnostics = validate_against_schema(&source);
+        if !diagnostics.is_empty() {
+            let diagnostics = diagnostics.0
+                .into_iter()
+                .map(|mut x| {
+                    x.source_file = Some(file_path.to_path_buf());
+                    x
+                })
+                .collect();
+            return Err(Box::new(Diagnostics(diagnostics)))
+        }
+        Ok(Self::parse(source)?.with_source_file(file_path))
+    }
+}
+
+fn check_children(
+    nodes: &[xml::Node],
+    parent: &str,
+    allowed: &[&str],
+    prompt: Option<&str>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for node in nodes {
+        match node {
+            xml::Node::Text(text) | xml::Node::CData(text) => {
+                if !text.trim().is_empty() {
+                    let mut diagnostic = Diagnostic::error(
+                        DiagnosticKind::StrayText,
+                        format!("unexpected text {:?} in {parent}", text.trim()),
+                    );
+                    diagnostic.prompt = prompt.map(str::to_string);
+                    diagnostics.push(diagnostic);
+                }
+            }
+            xml::Node::Element(element) => check_element(element, allowed, prompt, diagnostics),
+        }
+    }
+}
+
+fn check_element(
+    element: &xml::Element,
+    allowed: &[&str],
+    prompt: Option<&str>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let name = element.name.as_str();
+    let prompt = match name {
+        "prompt" | "workflow" | "chain" => element.attr("name"),
+        _ => prompt,
+    };
+    let mut push = |diagnostic: Diagnostic| {
+        let mut diagnostic = diagnostic.at(element.position);
+        diagnostic.prompt = prompt.map(str::to_string);
+        diagnostics.push(diagnostic);
+    };
+    let Some(schema) = element_schema(name) else {
+        push(Diagnostic::error(DiagnosticKind::UnknownElement, format!("unknown element <{name}>")));
+        return
+    };
+    if !allowed.contains(&name) {
+        push(Diagnostic::error(DiagnosticKind::UnexpectedElement, format!("<{name}> is not allowed here")));
+    }
+    for (key, _) in element.attributes.iter() {
+        if !schema.attributes.contains(&key.as_str()) {
+            let mut diagnostic = Diagnostic::unknown_attribute(name, key);
+            diagnostic.severity = Severity::Error;
+            push(diagnostic);
+        }
+    }
+    for key in schema.required {
+        if element.attr(key).is_none() {
+            push(Diagnostic::missing_attribute(name, key));
+        }
+    }
+    match schema.content {
+        Content::Elements(children) => {
+            check_children(&element.children, &format!("<{name}>"), children, prompt, diagnostics)
+        }
+        Content::Markup => {}
+        Content::Empty => check_children(&element.children, &format!("<{name}>"), &[], prompt, diagnostics),
+    }
+}
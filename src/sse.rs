@@ -0,0 +1,164 @@
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// SERVER-SENT EVENTS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// The payloads of the `data:` lines in a chunk of an event stream,
+/// borrowed from it.
+pub fn data_lines(chunk: &[u8]) -> impl Iterator<Item = &[u8]> {
+    chunk.split(|x| *x == b'\n').filter_map(data)
+}
+
+/// The payload of a `data:` line. The space after the colon and a `\r`
+/// before the newline are optional.
+pub fn data(line: &[u8]) -> Option<&[u8]> {
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let payload = line.strip_prefix(b"data:")?;
+    Some(payload.strip_prefix(b" ").unwrap_or(payload))
+}
+
+/// Splits an event stream into `data:` payloads as its chunks arrive. A
+/// line cut off at the end of a chunk, along with any multibyte character
+/// split with it, is kept until the rest arrives; complete lines are
+/// borrowed from the chunk without copying.
+#[derive(Debug, Clone, Default)]
+pub struct SseDecoder {
+    partial: Vec<u8>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Calls `on_data` with the payload of each line `chunk` completes.
+    pub fn feed(&mut self, chunk: &[u8], mut on_data: impl FnMut(&[u8])) {
+        let mut rest = chunk;
+        if !self.partial.is_empty() {
+            let Some(end) = rest.iter().position(|x| *x == b'\n') else {
+                self.partial.extend_from_slice(rest);
+                return
+            };
+            self.partial.extend_from_slice(&rest[..end]);
+            if let Some(payload) = data(&self.partial) {
+                on_data(payload);
+            }
+            self.partial.clear();
+            rest = &rest[end + 1..];
+        }
+        let Some(end) = rest.iter().rposition(|x| *x == b'\n') else {
+            self.partial.extend_from_slice(rest);
+            return
+        };
+        self.partial.extend_from_slice(&rest[end + 1..]);
+        data_lines(&rest[..end]).for_each(on_data);
+    }
+    /// Calls `on_data` with the last line, if the stream did not end with
+    /// a newline.
+    pub fn finish(&mut self, mut on_data: impl FnMut(&[u8])) {
+        let line = std::mem::take(&mut self.partial);
+        if let Some(payload) = data(&line) {
+            on_data(payload);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(chunk: &str) -> Vec<&str> {
+        data_lines(chunk.as_bytes()).map(|x| std::str::from_utf8(x).unwrap()).collect()
+    }
+
+    #[test]
+    fn data_lines_yield_payloads() {
+        let cases: [(&str, &[&str]); 8] = [
+            ("data: {\"a\":1}\n\n", &["{\"a\":1}"]),
+            ("data:{\"a\":1}\n\n", &["{\"a\":1}"]),
+            ("data:  {\"a\":1}\n", &[" {\"a\":1}"]),
+            ("data: [DONE]\n\n", &["[DONE]"]),
+            (": keep-alive\n\n", &[]),
+            (":\ndata: 1\n", &["1"]),
+            ("event: message\nid: 7\nretry: 10\ndata: 1\n\n", &["1"]),
+            ("data: 1\n\ndata: 2\r\n\r\ndata: [DONE]\n\n", &["1", "2", "[DONE]"]),
+        ];
+        for (chunk, expected) in cases {
+            assert_eq!(lines(chunk), expected, "{chunk:?}");
+        }
+    }
+
+    #[test]
+    fn data_needs_the_field_name_at_the_start() {
+        let cases: [(&str, Option<&str>); 6] = [
+            ("data: x", Some("x")),
+            ("data:x", Some("x")),
+            ("data:", Some("")),
+            ("data: x\r", Some("x")),
+            (" data: x", None),
+            ("Data: x", None),
+        ];
+        for (line, expected) in cases {
+            let payload = data(line.as_bytes()).map(|x| std::str::from_utf8(x).unwrap());
+            assert_eq!(payload, expected, "{line:?}");
+        }
+    }
+
+    /// The payloads of a stream arriving in `chunks`, as text.
+    fn decode(chunks: &[&[u8]]) -> Vec<String> {
+        let mut decoder = SseDecoder::new();
+        let mut payloads = Vec::default();
+        let mut on_data = |x: &[u8]| payloads.push(String::from_utf8(x.to_vec()).unwrap());
+        for chunk in chunks {
+            decoder.feed(chunk, &mut on_data);
+        }
+        decoder.finish(&mut on_data);
+        payloads
+    }
+
+    #[test]
+    fn decoder_joins_split_lines() {
+        let cases: [(&[&[u8]], &[&str]); 5] = [
+            (&[b"data: {\"a\"", b":1}\n\n"], &["{\"a\":1}"]),
+            (&[b"da", b"ta: 1\n", b"\ndata: 2\n\n"], &["1", "2"]),
+            (&[b"data: 1", b"", b"\n"], &["1"]),
+            (&[b"data: 1\n\nda", b"ta: 2\n\ndata: [DO", b"NE]\n\n"], &["1", "2", "[DONE]"]),
+            (&[b"data: a", b"b", b"c\n"], &["abc"]),
+        ];
+        for (chunks, expected) in cases {
+            assert_eq!(decode(chunks), expected, "{chunks:?}");
+        }
+    }
+
+    #[test]
+    fn decoder_joins_split_characters() {
+        // A three byte character, split after its first byte.
+        let euro = "data: 5\u{20ac}\n\n".as_bytes();
+        assert_eq!(decode(&[&euro[..8], &euro[8..]]), ["5\u{20ac}"]);
+        // A four byte character, split after its second byte.
+        let emoji = "data: \u{1f600}!\n\n".as_bytes();
+        assert_eq!(decode(&[&emoji[..8], &emoji[8..]]), ["\u{1f600}!"]);
+    }
+
+    #[test]
+    fn decoder_reads_crlf_line_endings() {
+        let stream = b"data: 1\r\n\r\ndata: 2\r\n\r\n";
+        assert_eq!(decode(&[stream]), ["1", "2"]);
+        // Split between the carriage return and the line feed.
+        assert_eq!(decode(&[&stream[..8], &stream[8..]]), ["1", "2"]);
+    }
+
+    #[test]
+    fn finish_flushes_a_trailing_line() {
+        assert_eq!(decode(&[b"data: 1\n\ndata: 2"]), ["1", "2"]);
+        assert_eq!(decode(&[b"data: 1\n\nda", b"ta: 2\r"]), ["1", "2"]);
+        assert_eq!(decode(&[b"data: 1\n\n"]), ["1"]);
+        assert_eq!(decode(&[b": comment"]), Vec::<String>::new());
+    }
+
+    #[test]
+    fn decoder_splits_anywhere() {
+        let stream = "data: h\u{e9}llo\r\n\r\n: ping\n\ndata:\u{1f600}\n\ndata: [DONE]\n\n".as_bytes();
+        for index in 0..=stream.len() {
+            let (head, tail) = stream.split_at(index);
+            assert_eq!(decode(&[head, tail]), ["h\u{e9}llo", "\u{1f600}", "[DONE]"], "split at {index}");
+        }
+    }
+}
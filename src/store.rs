@@ -0,0 +1,315 @@
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+use std::path::{Path, PathBuf};
+use futures::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::client::{Error, Message};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// STORE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    pub id: String,
+    pub title: Option<String>,
+    /// RFC 3339, in UTC.
+    pub created_at: String,
+    pub message_count: usize,
+}
+
+/// Persists conversations as sessions of messages. [`MemoryStore`] and
+/// [`FileStore`] come with the crate, as does an implementation for
+/// [`crate::history::SqliteStore`] with the `sqlite` feature; implement
+/// this to keep sessions in Postgres, Redis or anything else.
+///
+/// Session IDs are opaque strings chosen by the store.
+///
+/// ```rust,ignore
+/// let session = store.create(Some("Support")).await?;
+/// store.append(&session, &[Message::new(Role::User, "Hello")]).await?;
+/// let messages = store.load(&session).await?.unwrap_or_default();
+/// ```
+pub trait ConversationStore {
+    /// Starts an empty session, returning its ID.
+    fn create<'a>(&'a self, title: Option<&'a str>) -> LocalBoxFuture<'a, Result<String, Error>>;
+    /// Adds messages to the end of a session. Fails with
+    /// [`UnknownSession`] if there is no such session.
+    fn append<'a>(&'a self, session: &'a str, messages: &'a [Message]) -> LocalBoxFuture<'a, Result<(), Error>>;
+    /// A session's messages in order, or `None` if there is no such
+    /// session.
+    fn load<'a>(&'a self, session: &'a str) -> LocalBoxFuture<'a, Result<Option<Vec<Message>>, Error>>;
+    /// Every session, newest first.
+    fn list(&self) -> LocalBoxFuture<'_, Result<Vec<SessionInfo>, Error>>;
+    /// Removes a session; removing one that does not exist is not an error.
+    fn delete<'a>(&'a self, session: &'a str) -> LocalBoxFuture<'a, Result<(), Error>>;
+}
+
+impl<T: ConversationStore + ?Sized> ConversationStore for Rc<T> {
+    fn create<'a>(&'a self, title: Option<&'a str>) -> LocalBoxFuture<'a, Result<String, Error>> {
+        (**self).create(title)
+    }
+    fn append<'a>(&'a self, session: &'a str, messages: &'a [Message]) -> LocalBoxFuture<'a, Result<(), Error>> {
+        (**self).append(session, messages)
+    }
+    fn load<'a>(&'a self, session: &'a str) -> LocalBoxFuture<'a, Result<Option<Vec<Message>>, Error>> {
+        (**self).load(session)
+    }
+    fn list(&self) -> LocalBoxFuture<'_, Result<Vec<SessionInfo>, Error>> {
+        (**self).list()
+    }
+    fn delete<'a>(&'a self, session: &'a str) -> LocalBoxFuture<'a, Result<(), Error>> {
+        (**self).delete(session)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnknownSession(pub String);
+impl std::fmt::Display for UnknownSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot find session {}.", self.0)
+    }
+}
+impl std::error::Error for UnknownSession {}
+
+/// A session with its messages, as [`FileStore`] writes it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct StoredSession {
+    id: String,
+    title: Option<String>,
+    created_at: String,
+    messages: Vec<Message>,
+}
+
+impl StoredSession {
+    fn new(id: String, title: Option<&str>) -> Self {
+        StoredSession {
+            id,
+            title: title.map(ToString::to_string),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            messages: Vec::default(),
+        }
+    }
+    fn info(&self) -> SessionInfo {
+        SessionInfo {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            created_at: self.created_at.clone(),
+            message_count: self.messages.len(),
+        }
+    }
+}
+
+/// Creation time in microseconds followed by a counter, so IDs sort by age
+/// and two sessions created in the same instant still differ.
+fn session_id(counter: usize) -> String {
+    format!("{:016x}-{counter}", chrono::Utc::now().timestamp_micros())
+}
+
+fn newest_first(mut sessions: Vec<SessionInfo>) -> Vec<SessionInfo> {
+    sessions.sort_by(|a, b| (&b.created_at, &b.id).cmp(&(&a.created_at, &a.id)));
+    sessions
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// MEMORY STORE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Keeps sessions for the life of the process. Cloning shares them.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    sessions: Rc<RefCell<BTreeMap<String, StoredSession>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn len(&self) -> usize {
+        self.sessions.borrow().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.sessions.borrow().is_empty()
+    }
+}
+
+impl ConversationStore for MemoryStore {
+    fn create<'a>(&'a self, title: Option<&'a str>) -> LocalBoxFuture<'a, Result<String, Error>> {
+        let mut sessions = self.sessions.borrow_mut();
+        let id = session_id(sessions.len());
+        sessions.insert(id.clone(), StoredSession::new(id.clone(), title));
+        Box::pin(futures::future::ready(Ok(id)))
+    }
+    fn append<'a>(&'a self, session: &'a str, messages: &'a [Message]) -> LocalBoxFuture<'a, Result<(), Error>> {
+        let result = match self.sessions.borrow_mut().get_mut(session) {
+            Some(stored) => {
+                stored.messages.extend_from_slice(messages);
+                Ok(())
+            }
+            None => Err(Box::new(UnknownSession(session.to_string())) as Error),
+        };
+        Box::pin(futures::future::ready(result))
+    }
+    fn load<'a>(&'a self, session: &'a str) -> LocalBoxFuture<'a, Result<Option<Vec<Message>>, Error>> {
+        let messages = self.sessions.borrow().get(session).map(|x| x.messages.clone());
+        Box::pin(futures::future::ready(Ok(messages)))
+    }
+    fn list(&self) -> LocalBoxFuture<'_, Result<Vec<SessionInfo>, Error>> {
+        let sessions = self.sessions.borrow().values().map(StoredSession::info).collect();
+        Box::pin(futures::future::ready(Ok(newest_first(sessions))))
+    }
+    fn delete<'a>(&'a self, session: &'a str) -> LocalBoxFuture<'a, Result<(), Error>> {
+        self.sessions.borrow_mut().remove(session);
+        Box::pin(futures::future::ready(Ok(())))
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// FILE STORE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Keeps each session as a JSON file in a directory, so sessions survive
+/// restarts. Files are replaced whole on every append, through a temporary
+/// file, so a crash never leaves one half written; the store is meant for
+/// one process at a time.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    /// Creates `dir` if needed.
+    pub fn open(dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        Ok(FileStore { dir: dir.as_ref().to_path_buf() })
+    }
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    fn path(&self, session: &str) -> Option<PathBuf> {
+        let valid = !session.is_empty() && session.chars().all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_');
+        valid.then(|| self.dir.join(format!("{session}.json")))
+    }
+    fn read(&self, session: &str) -> Result<Option<StoredSession>, Error> {
+        let Some(path) = self.path(session) else {
+            return Ok(None)
+        };
+        match std::fs::read(path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(Box::new(error)),
+        }
+    }
+    fn write(&self, stored: &StoredSession) -> Result<(), Error> {
+        let path = self.path(&stored.id).ok_or_else(|| UnknownSession(stored.id.clone()))?;
+        let temporary = path.with_extension("json.tmp");
+        std::fs::write(&temporary, serde_json::to_vec_pretty(stored)?)?;
+        std::fs::rename(temporary, path)?;
+        Ok(())
+    }
+}
+
+impl ConversationStore for FileStore {
+    fn create<'a>(&'a self, title: Option<&'a str>) -> LocalBoxFuture<'a, Result<String, Error>> {
+        Box::pin(async move {
+            let mut counter = 0;
+            let id = loop {
+                let id = session_id(counter);
+                if !self.path(&id).is_some_and(|x| x.exists()) {
+                    break id
+                }
+                counter += 1;
+            };
+            self.write(&StoredSession::new(id.clone(), title))?;
+            Ok(id)
+        })
+    }
+    fn append<'a>(&'a self, session: &'a str, messages: &'a [Message]) -> LocalBoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let mut stored = self.read(session)?.ok_or_else(|| UnknownSession(session.to_string()))?;
+            stored.messages.extend_from_slice(messages);
+            self.write(&stored)
+        })
+    }
+    fn load<'a>(&'a self, session: &'a str) -> LocalBoxFuture<'a, Result<Option<Vec<Message>>, Error>> {
+        Box::pin(async move {
+            Ok(self.read(session)?.map(|x| x.messages))
+        })
+    }
+    fn list(&self) -> LocalBoxFuture<'_, Result<Vec<SessionInfo>, Error>> {
+        Box::pin(async move {
+            let mut sessions = Vec::default();
+            for entry in std::fs::read_dir(&self.dir)? {
+                let path = entry?.path();
+                if path.extension().is_some_and(|x| x == "json") {
+                    let stored = serde_json::from_slice::<StoredSession>(&std::fs::read(path)?)?;
+                    sessions.push(stored.info());
+                }
+            }
+            Ok(newest_first(sessions))
+        })
+    }
+    fn delete<'a>(&'a self, session: &'a str) -> LocalBoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let Some(path) = self.path(session) else {
+                return Ok(())
+            };
+            match std::fs::remove_file(path) {
+                Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(Box::new(error) as Error),
+                _ => Ok(()),
+            }
+        })
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// SQLITE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Sessions are conversations, with their row IDs as session IDs.
+#[cfg(feature = "sqlite")]
+impl ConversationStore for crate::history::SqliteStore {
+    fn create<'a>(&'a self, title: Option<&'a str>) -> LocalBoxFuture<'a, Result<String, Error>> {
+        Box::pin(futures::future::ready(self.create_conversation(title).map(|x| x.to_string())))
+    }
+    fn append<'a>(&'a self, session: &'a str, messages: &'a [Message]) -> LocalBoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let conversation = session.parse::<i64>().ok().filter(|x| self.conversation(*x).ok().flatten().is_some());
+            let conversation = conversation.ok_or_else(|| UnknownSession(session.to_string()))?;
+            for message in messages {
+                self.append_message(conversation, message)?;
+            }
+            Ok(())
+        })
+    }
+    fn load<'a>(&'a self, session: &'a str) -> LocalBoxFuture<'a, Result<Option<Vec<Message>>, Error>> {
+        Box::pin(async move {
+            let Ok(conversation) = session.parse::<i64>() else {
+                return Ok(None)
+            };
+            match self.conversation(conversation)? {
+                Some(_) => Ok(Some(self.messages(conversation)?)),
+                None => Ok(None),
+            }
+        })
+    }
+    fn list(&self) -> LocalBoxFuture<'_, Result<Vec<SessionInfo>, Error>> {
+        Box::pin(async move {
+            let sessions = self
+                .conversations()?
+                .into_iter()
+                .map(|x| SessionInfo {
+                    id: x.id.to_string(),
+                    title: x.title,
+                    created_at: x.created_at,
+                    message_count: x.message_count,
+                })
+                .collect();
+            Ok(sessions)
+        })
+    }
+    fn delete<'a>(&'a self, session: &'a str) -> LocalBoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            match session.parse::<i64>() {
+                Ok(conversation) => self.delete_conversation(conversation),
+                Err(_) => Ok(()),
+            }
+        })
+    }
+}
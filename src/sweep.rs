@@ -0,0 +1,281 @@
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::client::{ChatCompletionsBody, ChatCompletionsRequestBuilder, Error, FinishReason, Usage};
+use crate::extract::MissingBody;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// GRID
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// One combination of parameters. `None` leaves the body's own value.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SweepPoint {
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub seed: Option<isize>,
+}
+
+impl SweepPoint {
+    pub fn apply(&self, mut body: ChatCompletionsBody) -> ChatCompletionsBody {
+        if let Some(model) = self.model.as_ref() {
+            body.model = model.clone();
+        }
+        body.temperature = self.temperature.or(body.temperature);
+        body.top_p = self.top_p.or(body.top_p);
+        body.seed = self.seed.or(body.seed);
+        body
+    }
+    /// E.g. `model=gpt-4o temperature=0.7`; empty when nothing is set.
+    pub fn label(&self) -> String {
+        let mut parts = Vec::default();
+        if let Some(x) = self.model.as_ref() {
+            parts.push(format!("model={x}"));
+        }
+        if let Some(x) = self.temperature {
+            parts.push(format!("temperature={x}"));
+        }
+        if let Some(x) = self.top_p {
+            parts.push(format!("top_p={x}"));
+        }
+        if let Some(x) = self.seed {
+            parts.push(format!("seed={x}"));
+        }
+        parts.join(" ")
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// RUNNER
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Sends one request across every combination of the given models,
+/// temperatures, `top_p` values and seeds, recording each reply with its
+/// usage and latency for comparison:
+///
+/// ```rust,ignore
+/// let report = Sweep::new()
+///     .with_models(["gpt-4o-mini", "gpt-4o"])
+///     .with_temperatures([0.0, 0.7, 1.2])
+///     .with_repetitions(3)
+///     .run(&prompt.request_builder().unwrap().with_api_key(&api_key))
+///     .await?;
+/// for summary in report.summaries() {
+///     println!("{}: {} distinct, {:.0}ms", summary.point.label(), summary.distinct_outputs, summary.mean_latency_ms);
+/// }
+/// report.write_jsonl("sweep.jsonl")?;
+/// ```
+///
+/// An axis left empty keeps the body's value. Failed requests are recorded
+/// in the report rather than ending the sweep.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    models: Vec<String>,
+    temperatures: Vec<f32>,
+    top_ps: Vec<f32>,
+    seeds: Vec<isize>,
+    repetitions: usize,
+    concurrency: usize,
+}
+
+impl Default for Sweep {
+    fn default() -> Self {
+        Sweep {
+            models: Vec::default(),
+            temperatures: Vec::default(),
+            top_ps: Vec::default(),
+            seeds: Vec::default(),
+            repetitions: 1,
+            concurrency: 4,
+        }
+    }
+}
+
+impl Sweep {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_models(mut self, models: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.models.extend(models.into_iter().map(|x| x.as_ref().to_string()));
+        self
+    }
+    pub fn with_temperatures(mut self, temperatures: impl IntoIterator<Item = f32>) -> Self {
+        self.temperatures.extend(temperatures);
+        self
+    }
+    pub fn with_top_ps(mut self, top_ps: impl IntoIterator<Item = f32>) -> Self {
+        self.top_ps.extend(top_ps);
+        self
+    }
+    pub fn with_seeds(mut self, seeds: impl IntoIterator<Item = isize>) -> Self {
+        self.seeds.extend(seeds);
+        self
+    }
+    /// How many times each point is sent. Defaults to 1.
+    pub fn with_repetitions(mut self, repetitions: usize) -> Self {
+        self.repetitions = repetitions.max(1);
+        self
+    }
+    /// How many requests may be in flight at once. Defaults to 4.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    /// Every combination, varying the seed fastest and the model slowest.
+    pub fn points(&self) -> Vec<SweepPoint> {
+        fn axis<T: Clone>(values: &[T]) -> Vec<Option<T>> {
+            match values.is_empty() {
+                true => vec![None],
+                false => values.iter().cloned().map(Some).collect(),
+            }
+        }
+        let mut points = Vec::default();
+        for model in axis(&self.models) {
+            for temperature in axis(&self.temperatures) {
+                for top_p in axis(&self.top_ps) {
+                    for seed in axis(&self.seeds) {
+                        points.push(SweepPoint { model: model.clone(), temperature, top_p, seed });
+                    }
+                }
+            }
+        }
+        points
+    }
+    /// Runs come back grouped by point, in the order of
+    /// [`Sweep::points`].
+    pub async fn run(&self, builder: &ChatCompletionsRequestBuilder) -> Result<SweepReport, Error> {
+        let body = builder.body.clone().ok_or(Box::new(MissingBody))?;
+        let jobs = self.points()
+            .into_iter()
+            .flat_map(|point| (0..self.repetitions).map(move |repetition| (point.clone(), repetition)))
+            .collect::<Vec<_>>();
+        #[cfg(feature = "tracing")]
+        tracing::info!(requests = jobs.len(), "starting parameter sweep");
+        let mut runs = futures::stream::iter(jobs.into_iter().enumerate())
+            .map(|(index, (point, repetition))| {
+                let builder = builder.clone().with_body(point.apply(body.clone()));
+                async move { (index, run_point(builder, point, repetition).await) }
+            })
+            .buffer_unordered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await;
+        runs.sort_by_key(|(index, _)| *index);
+        Ok(SweepReport { runs: runs.into_iter().map(|(_, x)| x).collect() })
+    }
+}
+
+async fn run_point(builder: ChatCompletionsRequestBuilder, point: SweepPoint, repetition: usize) -> SweepRun {
+    let started = Instant::now();
+    let response = match builder.build() {
+        Some(request) => request.execute().await,
+        None => Err(Box::new(MissingBody) as Error),
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+    match response {
+        Ok(response) => SweepRun {
+            point,
+            repetition,
+            content: Some(response.content(0)),
+            finish_reason: response.finish_reason(0),
+            usage: response.usage(),
+            latency_ms,
+            error: None,
+        },
+        Err(error) => SweepRun {
+            point,
+            repetition,
+            content: None,
+            finish_reason: None,
+            usage: None,
+            latency_ms,
+            error: Some(error.to_string()),
+        },
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// REPORT
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SweepRun {
+    pub point: SweepPoint,
+    /// Counts from 0 within the point.
+    pub repetition: usize,
+    /// The first choice's reply, if the request succeeded.
+    pub content: Option<String>,
+    pub finish_reason: Option<FinishReason>,
+    pub usage: Option<Usage>,
+    /// From sending the request to the end of the reply.
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+/// The runs of one point, aggregated.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PointSummary {
+    pub point: SweepPoint,
+    pub runs: usize,
+    pub failures: usize,
+    /// Over successful runs.
+    pub mean_latency_ms: f64,
+    /// Summed over runs that reported it.
+    pub usage: Usage,
+    /// How many different replies the successful runs gave.
+    pub distinct_outputs: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SweepReport {
+    pub runs: Vec<SweepRun>,
+}
+
+impl SweepReport {
+    /// One summary per point, in the order the points were run.
+    pub fn summaries(&self) -> Vec<PointSummary> {
+        let mut points = Vec::<&SweepPoint>::default();
+        for run in self.runs.iter() {
+            if !points.contains(&&run.point) {
+                points.push(&run.point);
+            }
+        }
+        points
+            .into_iter()
+            .map(|point| {
+                let runs = self.runs.iter().filter(|x| &x.point == point).collect::<Vec<_>>();
+                let succeeded = runs.iter().filter(|x| x.error.is_none()).collect::<Vec<_>>();
+                let mut usage = Usage::default();
+                for x in runs.iter().filter_map(|x| x.usage.as_ref()) {
+                    usage.prompt_tokens += x.prompt_tokens;
+                    usage.completion_tokens += x.completion_tokens;
+                    usage.total_tokens += x.total_tokens;
+                }
+                let mut outputs = succeeded.iter().filter_map(|x| x.content.as_deref()).collect::<Vec<_>>();
+                outputs.sort_unstable();
+                outputs.dedup();
+                let mean_latency_ms = match succeeded.len() {
+                    0 => 0.0,
+                    n => succeeded.iter().map(|x| x.latency_ms as f64).sum::<f64>() / n as f64,
+                };
+                PointSummary {
+                    point: point.clone(),
+                    runs: runs.len(),
+                    failures: runs.len() - succeeded.len(),
+                    mean_latency_ms,
+                    usage,
+                    distinct_outputs: outputs.len(),
+                }
+            })
+            .collect()
+    }
+    /// Writes one run per line as JSON.
+    pub fn write_jsonl(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        for run in self.runs.iter() {
+            writeln!(file, "{}", serde_json::to_string(run)?)?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
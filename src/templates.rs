@@ -0,0 +1,98 @@
+use serde::Serialize;
+
+use crate::client as api;
+use crate::xml_dsl::{resolve_image_url, MessageNode, MissingModel, NotAList, Prompt, UnresolvedVariable};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// MINIJINJA
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+impl Prompt {
+    /// Like [`Prompt::render`], but runs message bodies and image URLs through
+    /// MiniJinja, so templates get Jinja filters, conditionals and loops, e.g.
+    /// `{% if user.premium %}...{% endif %}` or `{{ name | title }}`.
+    ///
+    /// The context can be anything that serializes to a map, and is checked
+    /// against the prompt's `<params>` first. Undefined variables are an
+    /// error rather than rendering as empty.
+    pub fn render_with(&self, context: impl Serialize) -> Result<Vec<api::Message>, api::Error> {
+        let globals = liquid::to_object(&context).map_err(|_| InvalidContext)?;
+        let serde_json::Value::Object(context) = serde_json::to_value(self.bind_params(&globals)?)? else {
+            return Err(Box::new(InvalidContext))
+        };
+        let mut environment = minijinja::Environment::new();
+        environment.set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
+        let mut messages = Vec::default();
+        let base_dir = self.source_file.as_ref().and_then(|x| x.parent());
+        render_nodes(&environment, &self.messages, &context, base_dir, &mut messages)?;
+        Ok(messages)
+    }
+    pub fn render_body_with(&self, context: impl Serialize) -> Result<api::ChatCompletionsBody, api::Error> {
+        let messages = self.render_with(context)?;
+        let body = self.effective_configuration()
+            .build(messages)
+            .ok_or(Box::new(MissingModel(self.name.clone())))?;
+        Ok(body)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidContext;
+impl std::fmt::Display for InvalidContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Template context must serialize to a map.")
+    }
+}
+impl std::error::Error for InvalidContext {}
+
+type Context = serde_json::Map<String, serde_json::Value>;
+
+fn render_nodes(
+    environment: &minijinja::Environment,
+    nodes: &[MessageNode],
+    context: &Context,
+    base_dir: Option<&std::path::Path>,
+    output: &mut Vec<api::Message>,
+) -> Result<(), api::Error> {
+    for node in nodes {
+        match node {
+            MessageNode::Message(message) => {
+                let content = environment.render_str(&message.content, context)?;
+                let images = message.images
+                    .iter()
+                    .map(|image| {
+                        let url = environment.render_str(&image.url, context)?;
+                        let url = resolve_image_url(&url, base_dir)?;
+                        Ok(api::ImageUrl { url, detail: image.detail })
+                    })
+                    .collect::<Result<Vec<_>, api::Error>>()?;
+                let name = message.name
+                    .as_ref()
+                    .map(|x| environment.render_str(x, context))
+                    .transpose()?;
+                output.push(api::Message { images, name, ..api::Message::new(message.role.clone(), content) });
+            }
+            MessageNode::ForEach(for_each) => {
+                let items = lookup(context, &for_each.source)
+                    .ok_or(Box::new(UnresolvedVariable(for_each.source.clone())))?;
+                let items = items
+                    .as_array()
+                    .ok_or(Box::new(NotAList(for_each.source.clone())))?;
+                for item in items {
+                    let mut scope = context.clone();
+                    scope.insert(for_each.var.clone(), item.clone());
+                    render_nodes(environment, &for_each.children, &scope, base_dir, output)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn lookup<'a>(context: &'a Context, path: &str) -> Option<&'a serde_json::Value> {
+    let mut segments = path.split('.');
+    let mut value = context.get(segments.next()?)?;
+    for segment in segments {
+        value = value.get(segment)?;
+    }
+    Some(value)
+}
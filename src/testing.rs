@@ -0,0 +1,538 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::{cell::RefCell, rc::Rc};
+use bytes::Bytes;
+use futures::future::LocalBoxFuture;
+use serde_json::json;
+use sha2::Digest;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::client::{ApiEndpoint, ChatCompletionsBody, Error};
+use crate::transport::{HttpRequest, HttpResponse, MockResponse, Transport};
+use crate::xml_dsl::{PromptCollection, PromptNotFound};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// FIXTURES
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Responses shaped like OpenAI's, for use with [`MockServer`] or
+/// [`crate::transport::MockTransport`].
+pub mod fixtures {
+    use super::*;
+
+    pub const MODEL: &str = "gpt-4o-mini-2024-07-18";
+
+    fn chunk(delta: serde_json::Value, finish_reason: Option<&str>) -> String {
+        choice_chunk(0, delta, finish_reason)
+    }
+
+    fn choice_chunk(index: usize, delta: serde_json::Value, finish_reason: Option<&str>) -> String {
+        json!({
+            "id": "chatcmpl-fixture",
+            "object": "chat.completion.chunk",
+            "created": 1_700_000_000,
+            "model": MODEL,
+            "system_fingerprint": "fp_fixture",
+            "choices": [{ "index": index, "delta": delta, "logprobs": null, "finish_reason": finish_reason }],
+        })
+        .to_string()
+    }
+
+    fn usage_chunk(prompt_tokens: usize, completion_tokens: usize) -> String {
+        json!({
+            "id": "chatcmpl-fixture",
+            "object": "chat.completion.chunk",
+            "created": 1_700_000_000,
+            "model": MODEL,
+            "system_fingerprint": "fp_fixture",
+            "choices": [],
+            "usage": {
+                "prompt_tokens": prompt_tokens,
+                "completion_tokens": completion_tokens,
+                "total_tokens": prompt_tokens + completion_tokens,
+            },
+        })
+        .to_string()
+    }
+
+    /// Splits text roughly the way tokenizers do: each word keeps its
+    /// leading space.
+    fn tokens(text: &str) -> Vec<String> {
+        let mut tokens = Vec::<String>::default();
+        for (index, word) in text.split(' ').enumerate() {
+            match index {
+                0 => tokens.push(word.to_string()),
+                _ => tokens.push(format!(" {word}")),
+            }
+        }
+        tokens.retain(|x| !x.is_empty());
+        tokens
+    }
+
+    /// A reply streaming `content` a word at a time, ending with
+    /// `finish_reason: "stop"` and a usage chunk.
+    pub fn text_stream(content: &str) -> MockResponse {
+        let tokens = tokens(content);
+        let mut events = vec![chunk(json!({ "role": "assistant", "content": "" }), None)];
+        events.extend(tokens.iter().map(|x| chunk(json!({ "content": x }), None)));
+        events.push(chunk(json!({}), Some("stop")));
+        events.push(usage_chunk(12, tokens.len()));
+        MockResponse::sse(events)
+    }
+
+    /// A reply with one choice per entry of `contents`, as requested with
+    /// `n`, their words interleaved round-robin the way OpenAI streams
+    /// them.
+    pub fn choices_stream(contents: &[&str]) -> MockResponse {
+        let tokens = contents.iter().map(|x| tokens(x)).collect::<Vec<_>>();
+        let mut events = (0..contents.len())
+            .map(|index| choice_chunk(index, json!({ "role": "assistant", "content": "" }), None))
+            .collect::<Vec<_>>();
+        let longest = tokens.iter().map(Vec::len).max().unwrap_or_default();
+        for position in 0..longest {
+            for (index, tokens) in tokens.iter().enumerate() {
+                if let Some(token) = tokens.get(position) {
+                    events.push(choice_chunk(index, json!({ "content": token }), None));
+                }
+            }
+        }
+        events.extend((0..contents.len()).map(|index| choice_chunk(index, json!({}), Some("stop"))));
+        events.push(usage_chunk(12, tokens.iter().map(Vec::len).sum()));
+        MockResponse::sse(events)
+    }
+
+    /// A reply cut off by `max_tokens`: `finish_reason` is `"length"`.
+    pub fn truncated_stream(content: &str) -> MockResponse {
+        let mut events = vec![chunk(json!({ "role": "assistant", "content": "" }), None)];
+        events.extend(tokens(content).iter().map(|x| chunk(json!({ "content": x }), None)));
+        events.push(chunk(json!({}), Some("length")));
+        MockResponse::sse(events)
+    }
+
+    /// A reply calling one tool, with the arguments split into fragments.
+    pub fn tool_call_stream(name: &str, arguments: &serde_json::Value) -> MockResponse {
+        let arguments = arguments.to_string();
+        let mut events = vec![chunk(json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{ "index": 0, "id": "call_fixture", "type": "function", "function": { "name": name, "arguments": "" } }],
+        }), None)];
+        let fragments = arguments
+            .chars()
+            .collect::<Vec<_>>()
+            .chunks(8)
+            .map(|x| x.iter().collect::<String>())
+            .collect::<Vec<_>>();
+        for fragment in fragments {
+            events.push(chunk(json!({ "tool_calls": [{ "index": 0, "function": { "arguments": fragment } }] }), None));
+        }
+        events.push(chunk(json!({}), Some("tool_calls")));
+        MockResponse::sse(events)
+    }
+
+    /// A text reply with an unparsable event in the middle, as seen from
+    /// misbehaving proxies.
+    pub fn malformed_stream(content: &str) -> MockResponse {
+        let mut events = vec![chunk(json!({ "role": "assistant", "content": "" }), None)];
+        events.push(String::from(r#"{"id": "chatcmpl-fixture", "choices": [{"delta": {"content": "#));
+        events.extend(tokens(content).iter().map(|x| chunk(json!({ "content": x }), None)));
+        events.push(chunk(json!({}), Some("stop")));
+        MockResponse::sse(events)
+    }
+
+    /// A text reply whose bytes arrive in pieces of `size`, splitting events
+    /// and multibyte characters across reads.
+    pub fn fragmented_stream(content: &str, size: usize) -> MockResponse {
+        let whole = text_stream(content).chunks.concat();
+        let chunks = whole.chunks(size.max(1)).map(Bytes::copy_from_slice).collect();
+        MockResponse::from_chunks(chunks).with_header("content-type", "text/event-stream")
+    }
+
+    /// `429` with OpenAI's rate-limit headers and error body.
+    pub fn rate_limited(retry_after_seconds: usize) -> MockResponse {
+        let body = json!({
+            "error": {
+                "message": "Rate limit reached for requests. Please try again later.",
+                "type": "requests",
+                "param": null,
+                "code": "rate_limit_exceeded",
+            }
+        });
+        MockResponse::new(429, body.to_string())
+            .with_header("content-type", "application/json")
+            .with_header("retry-after", retry_after_seconds.to_string())
+            .with_header("retry-after-ms", (retry_after_seconds * 1000).to_string())
+            .with_header("x-ratelimit-limit-requests", "500")
+            .with_header("x-ratelimit-limit-tokens", "200000")
+            .with_header("x-ratelimit-remaining-requests", "0")
+            .with_header("x-ratelimit-remaining-tokens", "0")
+            .with_header("x-ratelimit-reset-requests", format!("{retry_after_seconds}s"))
+            .with_header("x-ratelimit-reset-tokens", format!("{retry_after_seconds}s"))
+    }
+
+    /// An error response with OpenAI's error body.
+    pub fn error(status: u16, message: &str) -> MockResponse {
+        let body = json!({ "error": { "message": message, "type": "server_error", "param": null, "code": null } });
+        MockResponse::new(status, body.to_string()).with_header("content-type", "application/json")
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// MOCK SERVER
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// A request the [`MockServer`] received.
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl ReceivedRequest {
+    pub fn json(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    responses: VecDeque<MockResponse>,
+    requests: Vec<ReceivedRequest>,
+}
+
+/// An HTTP server on a local port that answers each request with the next
+/// queued [`MockResponse`], writing each of its chunks separately. Unlike
+/// [`crate::transport::MockTransport`] this goes through the real network
+/// stack, for integration tests of code that builds its own client.
+///
+/// Connections are kept alive, so a shared client sends many requests over
+/// one, as it would to a real host. With nothing queued it answers `500`.
+/// The server stops when dropped.
+pub struct MockServer {
+    address: std::net::SocketAddr,
+    state: Arc<Mutex<State>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Must be called from within a Tokio runtime.
+    pub async fn start() -> std::io::Result<Self> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(State::default()));
+        let task = tokio::spawn(serve(listener, state.clone()));
+        Ok(MockServer { address, state, task })
+    }
+    pub fn with_response(self, response: MockResponse) -> Self {
+        self.push(response);
+        self
+    }
+    pub fn push(&self, response: MockResponse) {
+        self.state.lock().unwrap().responses.push_back(response);
+    }
+    /// The chat completions URL.
+    pub fn url(&self) -> String {
+        format!("http://{}/v1/chat/completions", self.address)
+    }
+    pub fn endpoint(&self) -> ApiEndpoint {
+        ApiEndpoint::new(self.url(), "sk-test")
+    }
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(listener: tokio::net::TcpListener, state: Arc<Mutex<State>>) {
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(respond(stream, state.clone()));
+    }
+}
+
+/// Answers requests on one connection until the client closes it.
+async fn respond(mut stream: tokio::net::TcpStream, state: Arc<Mutex<State>>) {
+    // Each chunk is written on its own; without this the second waits on
+    // the client's delayed acknowledgement of the first.
+    let _ = stream.set_nodelay(true);
+    let mut buffer = Vec::default();
+    while let Some(request) = read_request(&mut stream, &mut buffer).await {
+        let head_only = request.method == "HEAD";
+        let response = {
+            let mut state = state.lock().unwrap();
+            state.requests.push(request);
+            state.responses.pop_front()
+        };
+        let response = response.unwrap_or_else(|| fixtures::error(500, "No mock response queued."));
+        if write_response(&mut stream, &response, head_only).await.is_err() {
+            return
+        }
+    }
+}
+
+async fn write_response(
+    stream: &mut tokio::net::TcpStream,
+    response: &MockResponse,
+    head_only: bool,
+) -> std::io::Result<()> {
+    let mut head = format!("HTTP/1.1 {} Mock\r\ntransfer-encoding: chunked\r\n", response.status);
+    for (key, value) in response.headers.iter() {
+        head.push_str(&format!("{key}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    if head_only {
+        return stream.flush().await
+    }
+    for chunk in response.chunks.iter().filter(|x| !x.is_empty()) {
+        let mut frame = format!("{:x}\r\n", chunk.len()).into_bytes();
+        frame.extend_from_slice(chunk);
+        frame.extend_from_slice(b"\r\n");
+        stream.write_all(&frame).await?;
+        stream.flush().await?;
+    }
+    stream.write_all(b"0\r\n\r\n").await?;
+    stream.flush().await
+}
+
+/// Reads the next request, keeping any bytes past it in `buffer` for the
+/// one after.
+async fn read_request(stream: &mut tokio::net::TcpStream, buffer: &mut Vec<u8>) -> Option<ReceivedRequest> {
+    let mut read = [0u8; 4096];
+    let head_end = loop {
+        if let Some(index) = buffer.windows(4).position(|x| x == b"\r\n\r\n") {
+            break index
+        }
+        let count = stream.read(&mut read).await.ok().filter(|x| *x > 0)?;
+        buffer.extend_from_slice(&read[..count]);
+    };
+    let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_lowercase(), value.trim().to_string()))
+        .collect::<Vec<_>>();
+    let length = headers
+        .iter()
+        .find(|(key, _)| key == "content-length")
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or_default();
+    let end = head_end + 4 + length;
+    while buffer.len() < end {
+        let count = stream.read(&mut read).await.ok().filter(|x| *x > 0)?;
+        buffer.extend_from_slice(&read[..count]);
+    }
+    let body = buffer.drain(..end).skip(head_end + 4).collect();
+    Some(ReceivedRequest { method, path, headers, body })
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// STUB CLIENT
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// SHA-256 of a request body in hex, as the client sends it; the same hash
+/// the audit log records.
+pub fn request_hash(body: &[u8]) -> String {
+    format!("{:x}", sha2::Sha256::digest(body))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StubKey {
+    /// The name of the prompt the request was rendered from.
+    Prompt(String),
+    /// See [`request_hash`].
+    RequestHash(String),
+}
+
+/// A [`Transport`] that answers from a fixed table instead of the network,
+/// for testing code built on prompts without mocking them.
+///
+/// A request is answered by the stub for its exact body hash if there is
+/// one, then by the stub for its prompt name, then by the fallback.
+/// Responses are not used up: the same request always gets the same reply.
+/// Cloning shares the table and the recorded requests.
+#[derive(Debug, Clone, Default)]
+pub struct StubClient {
+    stubs: Rc<RefCell<Vec<(StubKey, MockResponse)>>>,
+    fallback: Rc<RefCell<Option<MockResponse>>>,
+    requests: Rc<RefCell<Vec<HttpRequest>>>,
+}
+
+impl StubClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_prompt(self, name: impl AsRef<str>, response: MockResponse) -> Self {
+        self.insert(StubKey::Prompt(name.as_ref().to_string()), response);
+        self
+    }
+    pub fn with_request_hash(self, hash: impl AsRef<str>, response: MockResponse) -> Self {
+        self.insert(StubKey::RequestHash(hash.as_ref().to_lowercase()), response);
+        self
+    }
+    /// Stubs the request that would be sent for `body`.
+    pub fn with_request(self, body: &ChatCompletionsBody, response: MockResponse) -> Result<Self, Error> {
+        let hash = request_hash(&serde_json::to_vec(body)?);
+        Ok(self.with_request_hash(hash, response))
+    }
+    /// Answers requests no stub matches; without one they fail.
+    pub fn with_fallback(self, response: MockResponse) -> Self {
+        *self.fallback.borrow_mut() = Some(response);
+        self
+    }
+    /// Replaces any stub with the same key.
+    pub fn insert(&self, key: StubKey, response: MockResponse) {
+        let mut stubs = self.stubs.borrow_mut();
+        stubs.retain(|(x, _)| *x != key);
+        stubs.push((key, response));
+    }
+    /// Every request sent so far.
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.borrow().clone()
+    }
+    fn lookup(&self, key: &StubKey) -> Option<MockResponse> {
+        self.stubs
+            .borrow()
+            .iter()
+            .find(|(x, _)| x == key)
+            .map(|(_, response)| response.clone())
+    }
+}
+
+impl Transport for StubClient {
+    fn send(&self, request: HttpRequest) -> LocalBoxFuture<'static, Result<HttpResponse, Error>> {
+        let hash = request_hash(&request.body);
+        let response = self.lookup(&StubKey::RequestHash(hash.clone()))
+            .or_else(|| self.lookup(&StubKey::Prompt(request.prompt.clone()?)))
+            .or_else(|| self.fallback.borrow().clone());
+        let error = NoStubResponse { prompt: request.prompt.clone(), request_hash: hash };
+        self.requests.borrow_mut().push(request);
+        Box::pin(async move {
+            response.ok_or(Box::new(error) as Error)?.into_http_response()
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NoStubResponse {
+    pub prompt: Option<String>,
+    pub request_hash: String,
+}
+impl std::fmt::Display for NoStubResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.prompt.as_ref() {
+            Some(prompt) => write!(f, "No stub matches prompt {:?} or request hash {}.", prompt, self.request_hash),
+            None => write!(f, "No stub matches request hash {}.", self.request_hash),
+        }
+    }
+}
+impl std::error::Error for NoStubResponse {}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// SNAPSHOTS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Renders a prompt from the collection and returns its request body as
+/// pretty-printed JSON, the form snapshots are stored in.
+pub fn prompt_snapshot(
+    collection: &PromptCollection,
+    prompt_name: impl AsRef<str>,
+    globals: &liquid::Object,
+) -> Result<String, Error> {
+    let name = prompt_name.as_ref();
+    let prompt = collection.get(name).ok_or(Box::new(PromptNotFound(name.to_string())))?;
+    let mut snapshot = serde_json::to_string_pretty(&prompt.render_body(globals)?)?;
+    snapshot.push('\n');
+    Ok(snapshot)
+}
+
+/// Compares `actual` with the snapshot stored at `path`.
+///
+/// A missing snapshot is written and passes. With `UPDATE_SNAPSHOTS=1` set
+/// in the environment, a differing snapshot is overwritten instead of
+/// failing, so intended prompt changes show up as a diff in review.
+pub fn check_snapshot(path: impl AsRef<std::path::Path>, actual: &str) -> Result<(), Error> {
+    let path = path.as_ref();
+    let update = std::env::var("UPDATE_SNAPSHOTS").is_ok_and(|x| x == "1");
+    match std::fs::read_to_string(path) {
+        Ok(expected) if expected == actual => Ok(()),
+        Ok(expected) if !update => Err(Box::new(SnapshotMismatch {
+            path: path.to_path_buf(),
+            expected,
+            actual: actual.to_string(),
+        })),
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(Box::new(error)),
+        _ => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, actual)?;
+            Ok(())
+        }
+    }
+}
+
+/// Renders a prompt and checks its request body against a snapshot,
+/// panicking on drift. Snapshots live in `snapshots/<prompt>.json` under the
+/// calling crate, unless a path is given. See [`check_snapshot`].
+///
+/// ```ignore
+/// let collection = PromptCollection::open("prompts/support.xml")?;
+/// let mut globals = liquid::Object::new();
+/// globals.insert("customer".into(), liquid::model::Value::scalar("Ada"));
+/// assert_prompt_snapshot!(collection, "greeting", globals);
+/// ```
+#[macro_export]
+macro_rules! assert_prompt_snapshot {
+    ($collection:expr, $prompt_name:expr, $globals:expr) => {
+        $crate::assert_prompt_snapshot!(
+            $collection,
+            $prompt_name,
+            $globals,
+            ::std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("snapshots")
+                .join(format!("{}.json", $prompt_name))
+        )
+    };
+    ($collection:expr, $prompt_name:expr, $globals:expr, $path:expr) => {{
+        let result = $crate::testing::prompt_snapshot(&$collection, $prompt_name, &$globals)
+            .and_then(|actual| $crate::testing::check_snapshot($path, &actual));
+        if let Err(error) = result {
+            panic!("{}", error);
+        }
+    }};
+}
+
+#[derive(Debug, Clone)]
+pub struct SnapshotMismatch {
+    pub path: std::path::PathBuf,
+    pub expected: String,
+    pub actual: String,
+}
+impl std::fmt::Display for SnapshotMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Snapshot {} does not match; rerun with UPDATE_SNAPSHOTS=1 to accept.", self.path.display())?;
+        let expected = self.expected.lines().collect::<Vec<_>>();
+        let actual = self.actual.lines().collect::<Vec<_>>();
+        for index in 0..expected.len().max(actual.len()) {
+            match (expected.get(index), actual.get(index)) {
+                (Some(x), Some(y)) if x == y => {}
+                (x, y) => {
+                    if let Some(x) = x {
+                        writeln!(f, "{:>4} - {}", index + 1, x)?;
+                    }
+                    if let Some(y) = y {
+                        writeln!(f, "{:>4} + {}", index + 1, y)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for SnapshotMismatch {}
@@ -0,0 +1,222 @@
+use std::future::Future;
+use std::rc::Rc;
+use futures::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::client::Error;
+
+#[cfg(feature = "derive")]
+pub use chatgpt_subsystems_derive::ai_function;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// DEFINITIONS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// A tool as sent in the request's `tools` array.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ToolDefinition {
+    /// Always `function`.
+    pub r#type: String,
+    pub function: FunctionDefinition,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FunctionDefinition {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// A JSON schema for the arguments object.
+    pub parameters: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+impl ToolDefinition {
+    pub fn function(name: impl AsRef<str>, parameters: serde_json::Value) -> Self {
+        let function = FunctionDefinition {
+            name: name.as_ref().to_string(),
+            description: None,
+            parameters,
+            strict: None,
+        };
+        ToolDefinition { r#type: String::from("function"), function }
+    }
+    pub fn with_description(mut self, description: impl AsRef<str>) -> Self {
+        self.function.description = Some(description.as_ref().to_string());
+        self
+    }
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.function.strict = Some(strict);
+        self
+    }
+    pub fn name(&self) -> &str {
+        &self.function.name
+    }
+}
+
+/// A call the model asked for, as found in an assistant message. Reply to
+/// each with [`crate::client::Message::tool_result`].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ToolCall {
+    pub id: String,
+    /// Always `function`.
+    pub r#type: String,
+    pub function: FunctionCall,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FunctionCall {
+    pub name: String,
+    /// A JSON object, as a string; the model may produce invalid JSON.
+    pub arguments: String,
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// TOOLS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Something the model can call.
+pub trait Tool {
+    fn definition(&self) -> ToolDefinition;
+    /// Runs the tool with the raw JSON `arguments` string from the model.
+    fn call(&self, arguments: &str) -> LocalBoxFuture<'static, Result<serde_json::Value, Error>>;
+}
+
+type Handler = Rc<dyn Fn(&str) -> LocalBoxFuture<'static, Result<serde_json::Value, Error>>>;
+
+/// A [`Tool`] backed by a closure; this is what `#[ai_function]` generates.
+#[derive(Clone)]
+pub struct FunctionTool {
+    definition: ToolDefinition,
+    handler: Handler,
+}
+
+impl FunctionTool {
+    pub fn new(
+        definition: ToolDefinition,
+        handler: impl Fn(&str) -> LocalBoxFuture<'static, Result<serde_json::Value, Error>> + 'static,
+    ) -> Self {
+        FunctionTool { definition, handler: Rc::new(handler) }
+    }
+    /// Derives the parameter schema from `A` and deserializes the model's
+    /// arguments into it before calling `f`.
+    #[cfg(feature = "schemars")]
+    pub fn from_fn<A, F, Fut>(name: impl AsRef<str>, description: Option<&str>, f: F) -> Self
+    where
+        A: serde::de::DeserializeOwned + schemars::JsonSchema + 'static,
+        F: Fn(A) -> Fut + 'static,
+        Fut: std::future::Future<Output = Result<serde_json::Value, Error>> + 'static,
+    {
+        let mut definition = ToolDefinition::function(name, crate::client::strict_schema_for::<A>())
+            .with_strict(true);
+        definition.function.description = description.map(str::to_string);
+        let f = Rc::new(f);
+        FunctionTool::new(definition, move |arguments| {
+            let f = f.clone();
+            let arguments = serde_json::from_str::<A>(arguments);
+            Box::pin(async move { f(arguments?).await })
+        })
+    }
+}
+
+impl Tool for FunctionTool {
+    fn definition(&self) -> ToolDefinition {
+        self.definition.clone()
+    }
+    fn call(&self, arguments: &str) -> LocalBoxFuture<'static, Result<serde_json::Value, Error>> {
+        (self.handler)(arguments)
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// REGISTRY
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// The tools available to a conversation, in registration order.
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: Vec<Rc<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds a tool, replacing any existing tool with the same name.
+    pub fn with_tool(mut self, tool: impl Tool + 'static) -> Self {
+        self.register(tool);
+        self
+    }
+    pub fn register(&mut self, tool: impl Tool + 'static) {
+        let name = tool.definition().function.name;
+        self.tools.retain(|x| x.definition().function.name != name);
+        self.tools.push(Rc::new(tool));
+    }
+    /// Registers a closure that receives the arguments as parsed JSON.
+    /// `parameters` is the JSON schema of the arguments object.
+    pub fn with_function<F, Fut>(
+        self,
+        name: impl AsRef<str>,
+        description: impl AsRef<str>,
+        parameters: serde_json::Value,
+        f: F,
+    ) -> Self
+    where
+        F: Fn(serde_json::Value) -> Fut + 'static,
+        Fut: Future<Output = Result<serde_json::Value, Error>> + 'static,
+    {
+        let definition = ToolDefinition::function(name, parameters).with_description(description);
+        let tool = FunctionTool::new(definition, move |arguments| {
+            match serde_json::from_str::<serde_json::Value>(arguments) {
+                Ok(arguments) => Box::pin(f(arguments)),
+                Err(error) => Box::pin(futures::future::ready(Err(Box::new(error) as Error))),
+            }
+        });
+        self.with_tool(tool)
+    }
+    /// The `tools` array for the request body.
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools.iter().map(|x| x.definition()).collect()
+    }
+    pub fn get(&self, name: impl AsRef<str>) -> Option<Rc<dyn Tool>> {
+        let name = name.as_ref();
+        self.tools
+            .iter()
+            .find(|x| x.definition().function.name == name)
+            .cloned()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+    /// Runs the tool the model asked for.
+    pub async fn dispatch(&self, call: &ToolCall) -> Result<serde_json::Value, Error> {
+        let tool = self.get(&call.function.name)
+            .ok_or(Box::new(UnknownTool(call.function.name.clone())))?;
+        tool.call(&call.function.arguments).await
+    }
+    /// Runs several calls concurrently, at most `concurrency` at a time.
+    /// Results are in the same order as `calls`.
+    pub async fn dispatch_all(
+        &self,
+        calls: &[ToolCall],
+        concurrency: usize,
+    ) -> Vec<Result<serde_json::Value, Error>> {
+        use futures::StreamExt;
+        futures::stream::iter(calls.iter().map(|call| self.dispatch(call)))
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnknownTool(pub String);
+impl std::fmt::Display for UnknownTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No tool named {:?} is registered.", self.0)
+    }
+}
+impl std::error::Error for UnknownTool {}
+
+/// Used by `#[ai_function]` to turn a return value into the tool's output.
+#[doc(hidden)]
+pub fn to_output(value: impl Serialize) -> Result<serde_json::Value, Error> {
+    Ok(serde_json::to_value(value)?)
+}
@@ -0,0 +1,274 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+use bytes::Bytes;
+use futures::future::LocalBoxFuture;
+use futures::stream::LocalBoxStream;
+use reqwest::header::HeaderMap;
+
+use crate::client::Error;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// TRANSPORT
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// A POST request with a JSON body, as the client sends it.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub timeout: Option<std::time::Duration>,
+    /// The prompt the request was rendered from, for transports that route
+    /// on it. Never sent.
+    pub prompt: Option<String>,
+}
+
+pub type ByteStream = LocalBoxStream<'static, Result<Bytes, Error>>;
+
+/// Set with
+/// [`crate::client::ChatCompletionsRequestBuilder::with_compression`].
+/// By default responses are compressed and requests are not.
+#[cfg(feature = "compression")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compression {
+    /// Accepts gzip and brotli responses, decompressed as they stream in.
+    pub responses: bool,
+    /// Gzips request bodies of at least this many bytes. Not every server
+    /// accepts compressed requests; OpenAI's does not.
+    pub request_threshold: Option<usize>,
+}
+
+#[cfg(feature = "compression")]
+impl Default for Compression {
+    fn default() -> Self {
+        Compression { responses: true, request_threshold: None }
+    }
+}
+
+#[cfg(feature = "compression")]
+impl Compression {
+    /// Neither requests nor responses.
+    pub fn none() -> Self {
+        Compression { responses: false, request_threshold: None }
+    }
+    pub fn with_responses(mut self, responses: bool) -> Self {
+        self.responses = responses;
+        self
+    }
+    pub fn with_request_threshold(mut self, request_threshold: usize) -> Self {
+        self.request_threshold = Some(request_threshold);
+        self
+    }
+    /// Compresses the body if it is large enough, setting the headers to
+    /// match.
+    pub(crate) fn apply(&self, request: &mut HttpRequest) -> std::io::Result<()> {
+        use std::io::Write;
+        if !self.responses {
+            request.headers.push((String::from("Accept-Encoding"), String::from("identity")));
+        }
+        if self.request_threshold.is_some_and(|x| request.body.len() >= x) {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::default(), flate2::Compression::default());
+            encoder.write_all(&request.body)?;
+            request.body = encoder.finish()?;
+            request.headers.push((String::from("Content-Encoding"), String::from("gzip")));
+        }
+        Ok(())
+    }
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: HeaderMap,
+    /// The body as it arrives; for streaming requests, server-sent events.
+    pub body: ByteStream,
+}
+
+impl HttpResponse {
+    /// Reads the whole body.
+    pub async fn bytes(self) -> Result<Vec<u8>, Error> {
+        let mut body = Vec::default();
+        let mut stream = self.body;
+        while let Some(chunk) = futures::StreamExt::next(&mut stream).await {
+            body.extend_from_slice(&chunk?);
+        }
+        Ok(body)
+    }
+}
+
+/// Sends requests on behalf of the client. The default uses `reqwest`;
+/// swap it with
+/// [`crate::client::ChatCompletionsRequestBuilder::with_transport`], e.g.
+/// for a [`MockTransport`] in tests.
+pub trait Transport {
+    fn send(&self, request: HttpRequest) -> LocalBoxFuture<'static, Result<HttpResponse, Error>>;
+}
+
+/// Sends with `reqwest`. By default every request builds a client of its
+/// own; share one with [`ReqwestTransport::with_client`] or
+/// [`ReqwestTransport::high_throughput`] to reuse connections.
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: Option<reqwest::Client>,
+}
+
+impl ReqwestTransport {
+    /// Sends every request with `client`, sharing its connection pool.
+    pub fn with_client(client: reqwest::Client) -> Self {
+        ReqwestTransport { client: Some(client) }
+    }
+    /// A shared client tuned for many concurrent requests to one host:
+    /// a large pool of idle connections kept for 90 seconds, TCP and
+    /// HTTP/2 keep-alive pings, and no Nagle delay. HTTP/2 is negotiated
+    /// where the server supports it with the `http2` feature.
+    ///
+    /// ```rust,ignore
+    /// let transport = ReqwestTransport::high_throughput()?;
+    /// transport.warm_up(&endpoint.api_url, 8).await?;
+    /// let prepared = ChatCompletionsRequestBuilder::default()
+    ///     .with_api_endpoint(endpoint)
+    ///     .with_transport(transport)
+    ///     .prepare()
+    ///     .unwrap();
+    /// ```
+    pub fn high_throughput() -> Result<Self, Error> {
+        let client = reqwest::ClientBuilder::new()
+            .pool_max_idle_per_host(64)
+            .pool_idle_timeout(std::time::Duration::from_secs(90))
+            .tcp_nodelay(true)
+            .tcp_keepalive(std::time::Duration::from_secs(30))
+            .http2_adaptive_window(true)
+            .http2_keep_alive_interval(std::time::Duration::from_secs(30))
+            .http2_keep_alive_timeout(std::time::Duration::from_secs(10))
+            .http2_keep_alive_while_idle(true)
+            .build()?;
+        Ok(Self::with_client(client))
+    }
+    /// Opens up to `connections` connections to the host of `url` ahead of
+    /// the first requests, so they skip the handshakes. Any response will
+    /// do; only failing to connect is an error. Does nothing without a
+    /// shared client.
+    pub async fn warm_up(&self, url: impl AsRef<str>, connections: usize) -> Result<(), Error> {
+        let Some(client) = self.client.as_ref() else {
+            return Ok(())
+        };
+        let requests = (0..connections).map(|_| client.head(url.as_ref()).send());
+        for result in futures::future::join_all(requests).await {
+            result?;
+        }
+        Ok(())
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> LocalBoxFuture<'static, Result<HttpResponse, Error>> {
+        let client = self.client.clone();
+        Box::pin(async move {
+            let client = match client {
+                Some(client) => client,
+                None => reqwest::ClientBuilder::new().build()?,
+            };
+            let mut builder = client.post(request.url).body(request.body);
+            if let Some(timeout) = request.timeout {
+                builder = builder.timeout(timeout);
+            }
+            for (key, value) in request.headers {
+                builder = builder.header(key, value);
+            }
+            let response = builder.send().await?;
+            let status = response.status().as_u16();
+            let headers = response.headers().clone();
+            let body = futures::StreamExt::map(response.bytes_stream(), |x| x.map_err(|e| Box::new(e) as Error));
+            Ok(HttpResponse { status, headers, body: Box::pin(body) })
+        })
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// MOCK
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// A canned response for [`MockTransport`].
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// Delivered as separate body chunks, exactly as given.
+    pub chunks: Vec<Bytes>,
+}
+
+impl MockResponse {
+    pub fn new(status: u16, body: impl Into<Bytes>) -> Self {
+        MockResponse { status, headers: Vec::default(), chunks: vec![body.into()] }
+    }
+    /// A `200` event stream with one `data:` event per item, followed by
+    /// `data: [DONE]`.
+    pub fn sse<S: AsRef<str>>(events: impl IntoIterator<Item = S>) -> Self {
+        let chunks = events
+            .into_iter()
+            .map(|event| Bytes::from(format!("data: {}\n\n", event.as_ref())))
+            .chain(std::iter::once(Bytes::from("data: [DONE]\n\n")))
+            .collect();
+        MockResponse::from_chunks(chunks).with_header("content-type", "text/event-stream")
+    }
+    /// A `200` response whose body arrives in exactly these pieces, for
+    /// testing events split across reads.
+    pub fn from_chunks(chunks: Vec<Bytes>) -> Self {
+        MockResponse { status: 200, headers: Vec::default(), chunks }
+    }
+    pub fn with_header(mut self, key: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.headers.push((key.as_ref().to_string(), value.as_ref().to_string()));
+        self
+    }
+    pub fn into_http_response(self) -> Result<HttpResponse, Error> {
+        let mut headers = HeaderMap::new();
+        for (key, value) in self.headers {
+            headers.append(
+                reqwest::header::HeaderName::from_bytes(key.as_bytes())?,
+                reqwest::header::HeaderValue::from_str(&value)?,
+            );
+        }
+        let body = futures::stream::iter(self.chunks.into_iter().map(Ok));
+        Ok(HttpResponse { status: self.status, headers, body: Box::pin(body) })
+    }
+}
+
+/// Serves queued [`MockResponse`]s in order and records every request.
+/// Cloning shares the queue and the record.
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    responses: Rc<RefCell<VecDeque<MockResponse>>>,
+    requests: Rc<RefCell<Vec<HttpRequest>>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_response(self, response: MockResponse) -> Self {
+        self.push(response);
+        self
+    }
+    pub fn push(&self, response: MockResponse) {
+        self.responses.borrow_mut().push_back(response);
+    }
+    /// Every request sent so far.
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.borrow().clone()
+    }
+}
+
+impl Transport for MockTransport {
+    fn send(&self, request: HttpRequest) -> LocalBoxFuture<'static, Result<HttpResponse, Error>> {
+        self.requests.borrow_mut().push(request);
+        let response = self.responses.borrow_mut().pop_front();
+        Box::pin(async move {
+            response.ok_or(Box::new(NoMockResponse) as Error)?.into_http_response()
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NoMockResponse;
+impl std::fmt::Display for NoMockResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The mock transport has no responses left.")
+    }
+}
+impl std::error::Error for NoMockResponse {}
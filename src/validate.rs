@@ -0,0 +1,452 @@
+use std::path::PathBuf;
+
+use crate::client as api;
+use crate::xml::Position;
+use crate::xml_dsl::{Chain, MessageNode, Prompt, PromptCollection, Workflow};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// DIAGNOSTICS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    MissingModel,
+    MissingName,
+    DuplicateName,
+    MissingAttribute,
+    UnknownAttribute,
+    InvalidValue,
+    OutOfRange,
+    EmptyMessage,
+    NoMessages,
+    UnresolvedVariable,
+    UnknownBlock,
+    UnknownElement,
+    UnexpectedElement,
+    StrayText,
+    MalformedXml,
+    TokenBudget,
+    MissingSystemMessage,
+    ConflictingParameters,
+    UnusedVariable,
+    UndeclaredVariable,
+    /// A workflow node names a prompt or node that does not exist.
+    UnknownReference,
+    DependencyCycle,
+    /// Something in an imported prompt that has no equivalent here and was
+    /// left out.
+    Unsupported,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    /// The prompt the diagnostic refers to, when it has a name.
+    pub prompt: Option<String>,
+    /// The file the prompt was loaded from, if any.
+    pub source_file: Option<PathBuf>,
+    /// Where in the file the problem is, when known.
+    pub position: Option<Position>,
+    pub message: String,
+}
+
+/// The result of [`PromptCollection::validate`].
+///
+/// Diagnostics are ordered by file and prompt, so the `Display` output is
+/// stable enough to diff in CI logs.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl Diagnostic {
+    pub fn error(kind: DiagnosticKind, message: impl Into<String>) -> Self {
+        Diagnostic { severity: Severity::Error, kind, prompt: None, source_file: None, position: None, message: message.into() }
+    }
+    pub fn warning(kind: DiagnosticKind, message: impl Into<String>) -> Self {
+        Diagnostic { severity: Severity::Warning, kind, prompt: None, source_file: None, position: None, message: message.into() }
+    }
+    pub(crate) fn invalid_value(attribute: &str, value: &str) -> Self {
+        Self::error(DiagnosticKind::InvalidValue, format!("invalid value {value:?} for attribute '{attribute}'"))
+    }
+    pub(crate) fn unknown_attribute(element: &str, attribute: &str) -> Self {
+        Self::warning(DiagnosticKind::UnknownAttribute, format!("unknown attribute '{attribute}' on <{element}>"))
+    }
+    pub(crate) fn missing_attribute(element: &str, attribute: &str) -> Self {
+        Self::error(DiagnosticKind::MissingAttribute, format!("<{element}> is missing the '{attribute}' attribute"))
+    }
+    pub(crate) fn unknown_block(name: &str) -> Self {
+        Self::error(DiagnosticKind::UnknownBlock, format!("<use> refers to unknown block {name:?}"))
+    }
+    pub(crate) fn at(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+    fn for_prompt(mut self, prompt: &Prompt) -> Self {
+        self.prompt = prompt.name.clone();
+        self.source_file = prompt.source_file.clone();
+        self
+    }
+    /// Workflow diagnostics carry the workflow's name in place of a prompt
+    /// name.
+    fn for_workflow(mut self, workflow: &Workflow) -> Self {
+        self.prompt = workflow.name.clone();
+        self.source_file = workflow.source_file.clone();
+        self
+    }
+    /// Likewise for chains.
+    fn for_chain(mut self, chain: &Chain) -> Self {
+        self.prompt = chain.name.clone();
+        self.source_file = chain.source_file.clone();
+        self
+    }
+}
+
+impl Diagnostics {
+    pub fn is_ok(&self) -> bool {
+        self.errors().next().is_none()
+    }
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.0.iter().filter(|x| x.severity == Severity::Error)
+    }
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.0.iter().filter(|x| x.severity == Severity::Warning)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn sort(&mut self) {
+        self.0.sort_by(|a, b| {
+            (&a.source_file, &a.prompt, a.position).cmp(&(&b.source_file, &b.prompt, b.position))
+        });
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        let file = self.source_file
+            .as_ref()
+            .map(|x| x.display().to_string())
+            .unwrap_or_else(|| String::from("<inline>"));
+        let prompt = self.prompt.as_deref().unwrap_or("<unnamed>");
+        match self.position {
+            Some(position) => write!(f, "{severity}: {file}:{position}: {prompt}: {}", self.message),
+            None => write!(f, "{severity}: {file}: {prompt}: {}", self.message),
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for diagnostic in self.0.iter() {
+            writeln!(f, "{diagnostic}")?;
+        }
+        let errors = self.errors().count();
+        let warnings = self.warnings().count();
+        write!(f, "{errors} error(s), {warnings} warning(s)")
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// VALIDATION
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+impl PromptCollection {
+    /// Checks every prompt for structural problems.
+    ///
+    /// Variables are not checked here since their values are only known at
+    /// render time; use [`PromptCollection::validate_with`] for that.
+    pub fn validate(&self) -> Diagnostics {
+        let mut diagnostics = Vec::default();
+        for prompt in self.prompts() {
+            diagnostics.extend(prompt.validate().0);
+        }
+        for workflow in self.workflows() {
+            diagnostics.extend(workflow.validate(self).0);
+        }
+        for chain in self.chains() {
+            diagnostics.extend(chain.validate(self).0);
+        }
+        if let Err(duplicates) = self.check_duplicates() {
+            for (name, files) in duplicates.0 {
+                for file in files {
+                    let mut diagnostic = Diagnostic::error(
+                        DiagnosticKind::DuplicateName,
+                        format!("prompt name {name:?} is defined more than once"),
+                    );
+                    diagnostic.prompt = Some(name.clone());
+                    diagnostic.source_file = file;
+                    diagnostics.push(diagnostic);
+                }
+            }
+        }
+        let mut diagnostics = Diagnostics(diagnostics);
+        diagnostics.sort();
+        diagnostics
+    }
+    /// Like [`PromptCollection::validate`], but also reports variables that
+    /// the given render-time globals do not define.
+    pub fn validate_with(&self, globals: &liquid::Object) -> Diagnostics {
+        let mut diagnostics = self.validate();
+        for prompt in self.prompts() {
+            diagnostics.0.extend(prompt.unresolved_variables(globals));
+        }
+        diagnostics.sort();
+        diagnostics
+    }
+}
+
+impl Prompt {
+    pub fn validate(&self) -> Diagnostics {
+        let mut diagnostics = self.diagnostics.clone();
+        if self.name.is_none() {
+            diagnostics.push(Diagnostic::warning(
+                DiagnosticKind::MissingName,
+                "prompt has no name and cannot be looked up",
+            ));
+        }
+        let effective = self.effective_configuration();
+        if effective.model.is_none() {
+            diagnostics.push(Diagnostic::error(DiagnosticKind::MissingModel, "prompt does not specify a model"));
+        }
+        // Values as written, then any problems the overrides introduce.
+        let written = check_ranges(Parameters::from(&self.configuration), false);
+        let overridden = check_ranges(Parameters::from(&effective), false)
+            .into_iter()
+            .filter(|x| !written.iter().any(|y| y.message == x.message))
+            .map(|x| Diagnostic { message: format!("{} in the overrides", x.message), ..x })
+            .collect::<Vec<_>>();
+        diagnostics.extend(written);
+        diagnostics.extend(overridden);
+        if self.messages.is_empty() {
+            diagnostics.push(Diagnostic::error(DiagnosticKind::NoMessages, "prompt has no messages"));
+        }
+        check_messages(&self.messages, &mut diagnostics);
+        let diagnostics = diagnostics
+            .into_iter()
+            .map(|x| x.for_prompt(self))
+            .collect();
+        Diagnostics(diagnostics)
+    }
+    /// Declared parameters that are optional or have a default always
+    /// resolve.
+    fn unresolved_variables(&self, globals: &liquid::Object) -> Vec<Diagnostic> {
+        self.variables()
+            .into_iter()
+            .filter(|variable| !globals.contains_key(variable.as_str()))
+            .filter(|variable| !self.params.iter().any(|x| &x.name == variable && !x.is_required()))
+            .map(|variable| {
+                Diagnostic::error(DiagnosticKind::UnresolvedVariable, format!("unresolved variable {variable:?}"))
+                    .for_prompt(self)
+            })
+            .collect()
+    }
+}
+
+impl api::ChatCompletionsBody {
+    /// Checks the sampling parameters against the ranges the API accepts,
+    /// reporting every violation. Requests run this before sending unless
+    /// built with
+    /// [`api::ChatCompletionsRequestBuilder::without_parameter_checks`].
+    pub fn validate(&self) -> Diagnostics {
+        Diagnostics(check_ranges(Parameters::from(self), true))
+    }
+}
+
+impl Workflow {
+    /// Checks that every node names a prompt in `collection`, node ids are
+    /// unique, inputs come from existing nodes and the edges have no cycle.
+    pub fn validate(&self, collection: &PromptCollection) -> Diagnostics {
+        let mut diagnostics = self.diagnostics.clone();
+        let mut push = |diagnostic: Diagnostic, position: Option<Position>| {
+            diagnostics.push(Diagnostic { position, ..diagnostic });
+        };
+        if self.name.is_none() {
+            push(Diagnostic::warning(DiagnosticKind::MissingName, "workflow has no name and cannot be looked up"), None);
+        }
+        for (index, node) in self.nodes.iter().enumerate() {
+            if self.nodes[..index].iter().any(|x| x.id == node.id) {
+                let message = format!("node id {:?} is used more than once", node.id);
+                push(Diagnostic::error(DiagnosticKind::DuplicateName, message), node.position);
+            }
+            if collection.get(&node.prompt).is_none() {
+                let message = format!("node {:?} refers to unknown prompt {:?}", node.id, node.prompt);
+                push(Diagnostic::error(DiagnosticKind::UnknownReference, message), node.position);
+            }
+            for input in node.inputs.iter() {
+                if self.node(&input.from).is_none() {
+                    let message = format!("node {:?} takes input from unknown node {:?}", node.id, input.from);
+                    push(Diagnostic::error(DiagnosticKind::UnknownReference, message), node.position);
+                }
+            }
+        }
+        for node in self.cycle() {
+            let message = format!("node {:?} is part of a dependency cycle", node.id);
+            push(Diagnostic::error(DiagnosticKind::DependencyCycle, message), node.position);
+        }
+        let diagnostics = diagnostics
+            .into_iter()
+            .map(|x| x.for_workflow(self))
+            .collect();
+        Diagnostics(diagnostics)
+    }
+    /// The nodes that can never run because they depend, directly or not,
+    /// on themselves.
+    pub(crate) fn cycle(&self) -> Vec<&crate::xml_dsl::WorkflowNode> {
+        let mut done = Vec::<&str>::default();
+        loop {
+            let ready = self.nodes
+                .iter()
+                .filter(|x| !done.contains(&x.id.as_str()))
+                .filter(|x| {
+                    x.inputs.iter().all(|input| done.contains(&input.from.as_str()) || self.node(&input.from).is_none())
+                })
+                .map(|x| x.id.as_str())
+                .collect::<Vec<_>>();
+            if ready.is_empty() {
+                break
+            }
+            done.extend(ready);
+        }
+        self.nodes.iter().filter(|x| !done.contains(&x.id.as_str())).collect()
+    }
+}
+
+impl Chain {
+    /// Checks that every step names a prompt in `collection`.
+    pub fn validate(&self, collection: &PromptCollection) -> Diagnostics {
+        let mut diagnostics = self.diagnostics.clone();
+        if self.name.is_none() {
+            diagnostics.push(Diagnostic::warning(DiagnosticKind::MissingName, "chain has no name and cannot be looked up"));
+        }
+        for step in self.steps.iter().filter(|x| collection.get(&x.prompt).is_none()) {
+            let message = format!("step refers to unknown prompt {:?}", step.prompt);
+            diagnostics.push(Diagnostic { position: step.position, ..Diagnostic::error(DiagnosticKind::UnknownReference, message) });
+        }
+        let diagnostics = diagnostics
+            .into_iter()
+            .map(|x| x.for_chain(self))
+            .collect();
+        Diagnostics(diagnostics)
+    }
+}
+
+fn check_messages(nodes: &[MessageNode], diagnostics: &mut Vec<Diagnostic>) {
+    for node in nodes {
+        match node {
+            MessageNode::Message(message) => {
+                if message.content.trim().is_empty() {
+                    diagnostics.push(Diagnostic::warning(DiagnosticKind::EmptyMessage, "message is empty"));
+                }
+            }
+            MessageNode::ForEach(for_each) => {
+                if for_each.children.is_empty() {
+                    diagnostics.push(Diagnostic::warning(DiagnosticKind::EmptyMessage, "<for-each> has no messages"));
+                }
+                check_messages(&for_each.children, diagnostics);
+            }
+        }
+    }
+}
+
+/// The sampling parameters prompt configurations and request bodies share.
+struct Parameters<'a> {
+    temperature: Option<f32>,
+    top_p: Option<f32>,
+    frequency_penalty: Option<f32>,
+    presence_penalty: Option<f32>,
+    n: Option<usize>,
+    max_tokens: Option<usize>,
+    logprobs: Option<bool>,
+    top_logprobs: Option<usize>,
+    stop: Option<&'a [String]>,
+}
+
+impl<'a> From<&'a api::ConfigurationBuilder> for Parameters<'a> {
+    fn from(configuration: &'a api::ConfigurationBuilder) -> Self {
+        Parameters {
+            temperature: configuration.temperature,
+            top_p: configuration.top_p,
+            frequency_penalty: configuration.frequency_penalty,
+            presence_penalty: configuration.presence_penalty,
+            n: configuration.n,
+            max_tokens: configuration.max_tokens,
+            logprobs: configuration.logprobs,
+            top_logprobs: configuration.top_logprobs,
+            stop: configuration.stop.as_deref(),
+        }
+    }
+}
+
+impl<'a> From<&'a api::ChatCompletionsBody> for Parameters<'a> {
+    fn from(body: &'a api::ChatCompletionsBody) -> Self {
+        Parameters {
+            temperature: body.temperature,
+            top_p: body.top_p,
+            frequency_penalty: body.frequency_penalty,
+            presence_penalty: body.presence_penalty,
+            n: body.n,
+            max_tokens: body.max_tokens,
+            logprobs: body.logprobs,
+            top_logprobs: body.top_logprobs,
+            stop: body.stop.as_deref(),
+        }
+    }
+}
+
+/// Names parameters as DSL attributes (`top-p`), or as API fields (`top_p`)
+/// when `api_names` is set.
+fn check_ranges(parameters: Parameters, api_names: bool) -> Vec<Diagnostic> {
+    let name = |attribute: &str| match api_names {
+        true => attribute.replace('-', "_"),
+        false => attribute.to_string(),
+    };
+    let mut diagnostics = Vec::default();
+    let mut check = |attribute: &str, value: Option<f32>, min: f32, max: f32| {
+        if let Some(value) = value {
+            if !(min..=max).contains(&value) {
+                diagnostics.push(Diagnostic::error(
+                    DiagnosticKind::OutOfRange,
+                    format!("'{}' must be between {min} and {max}, got {value}", name(attribute)),
+                ));
+            }
+        }
+    };
+    check("temperature", parameters.temperature, 0.0, 2.0);
+    check("top-p", parameters.top_p, 0.0, 1.0);
+    check("frequency-penalty", parameters.frequency_penalty, -2.0, 2.0);
+    check("presence-penalty", parameters.presence_penalty, -2.0, 2.0);
+    check("n", parameters.n.map(|x| x as f32), 1.0, 128.0);
+    check("top-logprobs", parameters.top_logprobs.map(|x| x as f32), 0.0, 5.0);
+    if parameters.max_tokens == Some(0) {
+        diagnostics.push(Diagnostic::error(
+            DiagnosticKind::OutOfRange,
+            format!("'{}' must be at least 1", name("max-tokens")),
+        ));
+    }
+    if parameters.top_logprobs.is_some() && parameters.logprobs != Some(true) {
+        diagnostics.push(Diagnostic::error(
+            DiagnosticKind::InvalidValue,
+            format!("'{}' requires 'logprobs' to be true", name("top-logprobs")),
+        ));
+    }
+    if let Some(stop) = parameters.stop.filter(|x| x.len() > 4) {
+        diagnostics.push(Diagnostic::error(
+            DiagnosticKind::OutOfRange,
+            format!("'stop' takes at most 4 sequences, got {}", stop.len()),
+        ));
+    }
+    diagnostics
+}
@@ -0,0 +1,315 @@
+use std::rc::Rc;
+
+use crate::client::{ChatCompletionsRequestBuilder, Error, Message, Role, Usage};
+use crate::extract::MissingBody;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// VALIDATORS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// A check on the model's reply. Failures are described in plain language,
+/// since they are sent back to the model.
+#[derive(Clone)]
+pub enum Validator {
+    /// The whole reply must match.
+    Regex(regex::Regex),
+    /// In characters.
+    MaxLength(usize),
+    /// The reply must be JSON matching the schema. Supports `type`, `enum`,
+    /// `const`, `properties`, `required`, `additionalProperties`, `items`,
+    /// `anyOf`, and the length, size and range keywords.
+    JsonSchema(serde_json::Value),
+    Custom(CheckFn),
+}
+
+pub type CheckFn = Rc<dyn Fn(&str) -> Result<(), String>>;
+
+impl Validator {
+    pub fn regex(pattern: impl AsRef<str>) -> Result<Self, Error> {
+        let pattern = format!("^(?:{})$", pattern.as_ref());
+        Ok(Validator::Regex(regex::Regex::new(&pattern)?))
+    }
+    pub fn max_length(max_length: usize) -> Self {
+        Validator::MaxLength(max_length)
+    }
+    pub fn json_schema(schema: serde_json::Value) -> Self {
+        Validator::JsonSchema(schema)
+    }
+    pub fn custom(f: impl Fn(&str) -> Result<(), String> + 'static) -> Self {
+        Validator::Custom(Rc::new(f))
+    }
+    pub fn check(&self, output: &str) -> Result<(), String> {
+        match self {
+            Validator::Regex(regex) => {
+                if regex.is_match(output.trim()) {
+                    return Ok(())
+                }
+                let pattern = regex.as_str();
+                let pattern = &pattern[4..pattern.len() - 2];
+                Err(format!("the reply must match the pattern /{pattern}/"))
+            }
+            Validator::MaxLength(max_length) => {
+                let length = output.chars().count();
+                if length <= *max_length {
+                    return Ok(())
+                }
+                Err(format!("the reply is {length} characters long, but must be at most {max_length}"))
+            }
+            Validator::JsonSchema(schema) => {
+                let value = serde_json::from_str::<serde_json::Value>(output.trim())
+                    .map_err(|e| format!("the reply is not valid JSON ({e})"))?;
+                let mut violations = Vec::default();
+                check_schema(schema, &value, "$", &mut violations);
+                if violations.is_empty() {
+                    return Ok(())
+                }
+                Err(violations.join("; "))
+            }
+            Validator::Custom(f) => f(output),
+        }
+    }
+}
+
+impl std::fmt::Debug for Validator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Validator::Regex(regex) => f.debug_tuple("Regex").field(regex).finish(),
+            Validator::MaxLength(max_length) => f.debug_tuple("MaxLength").field(max_length).finish(),
+            Validator::JsonSchema(schema) => f.debug_tuple("JsonSchema").field(schema).finish(),
+            Validator::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// EXECUTION
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Debug, Clone)]
+pub struct Attempt {
+    pub content: String,
+    /// Empty if the reply passed.
+    pub violations: Vec<String>,
+    /// The message sent back to the model about the violations, if it was
+    /// asked again.
+    pub feedback: Option<String>,
+    pub usage: Option<Usage>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidatedOutput {
+    /// The reply that passed every validator.
+    pub content: String,
+    /// Every attempt in order, the last being the one that passed.
+    pub attempts: Vec<Attempt>,
+    /// The whole conversation, ending with the reply that passed.
+    pub messages: Vec<Message>,
+}
+
+impl ValidatedOutput {
+    /// Summed over every attempt that reported it.
+    pub fn usage(&self) -> Usage {
+        let mut usage = Usage::default();
+        for x in self.attempts.iter().filter_map(|x| x.usage.as_ref()) {
+            usage.prompt_tokens += x.prompt_tokens;
+            usage.completion_tokens += x.completion_tokens;
+            usage.total_tokens += x.total_tokens;
+        }
+        usage
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidationFailed {
+    pub attempts: Vec<Attempt>,
+    /// The whole conversation, ending with the last rejected reply.
+    pub messages: Vec<Message>,
+}
+impl std::fmt::Display for ValidationFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let violations = self.attempts
+            .last()
+            .map(|x| x.violations.join("; "))
+            .unwrap_or_default();
+        write!(f, "Reply failed validation after {} attempt(s): {violations}.", self.attempts.len())
+    }
+}
+impl std::error::Error for ValidationFailed {}
+
+impl ChatCompletionsRequestBuilder {
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validators.push(validator);
+        self
+    }
+    /// Executes the request and checks the reply against every validator.
+    /// When any fail, the violations are sent back to the model and it is
+    /// asked again, up to `max_retries` times.
+    pub async fn execute_validated(&self, max_retries: usize) -> Result<ValidatedOutput, Error> {
+        validate(self, &self.validators, max_retries + 1).await
+    }
+}
+
+/// Sends the builder's conversation until `validator` accepts the reply,
+/// at most `max_attempts` times. Each rejected reply is added to the
+/// conversation along with a message telling the model what was wrong, so
+/// it can correct itself:
+///
+/// ```rust,ignore
+/// let output = run_until_valid(&builder, &Validator::json_schema(schema), 3).await?;
+/// for attempt in output.attempts.iter() {
+///     println!("{:?}", attempt.violations);
+/// }
+/// ```
+///
+/// Fails with [`ValidationFailed`], which holds the transcript, once the
+/// attempts run out. The builder's own validators are not consulted.
+pub async fn run_until_valid(
+    builder: &ChatCompletionsRequestBuilder,
+    validator: &Validator,
+    max_attempts: usize,
+) -> Result<ValidatedOutput, Error> {
+    validate(builder, std::slice::from_ref(validator), max_attempts).await
+}
+
+async fn validate(
+    builder: &ChatCompletionsRequestBuilder,
+    validators: &[Validator],
+    max_attempts: usize,
+) -> Result<ValidatedOutput, Error> {
+    let max_attempts = max_attempts.max(1);
+    let mut body = builder.body.clone().ok_or(Box::new(MissingBody))?;
+    // The response is read as a server-sent event stream.
+    body.stream = Some(true);
+    let mut attempts = Vec::<Attempt>::default();
+    while attempts.len() < max_attempts {
+        let request = builder.clone()
+            .with_body(body.clone())
+            .build()
+            .ok_or(Box::new(MissingBody))?;
+        let response = request.execute().await?;
+        let content = response.content(0);
+        let violations = validators
+            .iter()
+            .filter_map(|validator| validator.check(&content).err())
+            .collect::<Vec<_>>();
+        body.messages.push(Message::new(Role::Assistant, &content));
+        if violations.is_empty() {
+            attempts.push(Attempt { content: content.clone(), violations, feedback: None, usage: response.usage() });
+            return Ok(ValidatedOutput { content, attempts, messages: body.messages })
+        }
+        #[cfg(feature = "tracing")]
+        tracing::info!(attempt = attempts.len() + 1, violations = violations.len(), "reply failed validation");
+        let feedback = (attempts.len() + 1 < max_attempts).then(|| feedback_message(&violations));
+        if let Some(feedback) = feedback.as_ref() {
+            body.messages.push(Message::new(Role::User, feedback));
+        }
+        attempts.push(Attempt { content, violations, feedback, usage: response.usage() });
+    }
+    Err(Box::new(ValidationFailed { attempts, messages: body.messages }))
+}
+
+/// The follow-up turn sent after a rejected reply.
+fn feedback_message(violations: &[String]) -> String {
+    let violations = violations
+        .iter()
+        .map(|x| format!("- {x}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("Your reply has the following problems:\n{violations}\n\nPlease reply again with these fixed.")
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// JSON SCHEMA
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+fn check_schema(schema: &serde_json::Value, value: &serde_json::Value, path: &str, output: &mut Vec<String>) {
+    use serde_json::Value;
+    let Some(schema) = schema.as_object() else { return };
+    if let Some(expected) = schema.get("type") {
+        let types = match expected {
+            Value::String(x) => vec![x.as_str()],
+            Value::Array(xs) => xs.iter().filter_map(|x| x.as_str()).collect(),
+            _ => Vec::default(),
+        };
+        if !types.is_empty() && !types.iter().any(|x| has_type(value, x)) {
+            output.push(format!("{path} must be of type {}", types.join(" or ")));
+            return
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(|x| x.as_array()) {
+        if !options.contains(value) {
+            let options = options.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", ");
+            output.push(format!("{path} must be one of {options}"));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            output.push(format!("{path} must equal {expected}"));
+        }
+    }
+    if let Some(variants) = schema.get("anyOf").and_then(|x| x.as_array()) {
+        let matches = variants.iter().any(|variant| {
+            let mut errors = Vec::default();
+            check_schema(variant, value, path, &mut errors);
+            errors.is_empty()
+        });
+        if !matches {
+            output.push(format!("{path} does not match any of the allowed shapes"));
+        }
+    }
+    let bound = |key: &str| schema.get(key).and_then(|x| x.as_f64());
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = bound("minimum").filter(|x| number < *x) {
+            output.push(format!("{path} must be at least {minimum}"));
+        }
+        if let Some(maximum) = bound("maximum").filter(|x| number > *x) {
+            output.push(format!("{path} must be at most {maximum}"));
+        }
+    }
+    let size = match value {
+        Value::String(x) => Some(("minLength", "maxLength", "characters", x.chars().count())),
+        Value::Array(xs) => Some(("minItems", "maxItems", "items", xs.len())),
+        _ => None,
+    };
+    if let Some((min_key, max_key, unit, size)) = size {
+        if let Some(min) = bound(min_key).filter(|x| (size as f64) < *x) {
+            output.push(format!("{path} must have at least {min} {unit}"));
+        }
+        if let Some(max) = bound(max_key).filter(|x| (size as f64) > *x) {
+            output.push(format!("{path} must have at most {max} {unit}"));
+        }
+    }
+    if let Value::Object(object) = value {
+        let properties = schema.get("properties").and_then(|x| x.as_object());
+        for key in schema.get("required").and_then(|x| x.as_array()).into_iter().flatten() {
+            if let Some(key) = key.as_str().filter(|key| !object.contains_key(*key)) {
+                output.push(format!("{path} is missing the field {key:?}"));
+            }
+        }
+        for (key, child) in object {
+            match properties.and_then(|x| x.get(key)) {
+                Some(property) => check_schema(property, child, &format!("{path}.{key}"), output),
+                None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                    output.push(format!("{path} has the unexpected field {key:?}"));
+                }
+                None => {}
+            }
+        }
+    }
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (index, item) in items.iter().enumerate() {
+            check_schema(item_schema, item, &format!("{path}[{index}]"), output);
+        }
+    }
+}
+
+fn has_type(value: &serde_json::Value, expected: &str) -> bool {
+    use serde_json::Value;
+    match expected {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "null" => matches!(value, Value::Null),
+        _ => true,
+    }
+}
@@ -0,0 +1,137 @@
+use std::cell::RefCell;
+use std::hash::{BuildHasher, Hasher};
+use std::rc::Rc;
+
+use crate::xml_dsl::{Prompt, PromptCollection};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// SELECTION
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Which variant served a request, passed to the recorder.
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub prompt: String,
+    pub variant: Option<String>,
+    /// The sticky key, when one was given.
+    pub key: Option<String>,
+}
+
+pub type Recorder = Rc<RefCell<dyn FnMut(&Assignment)>>;
+
+/// Picks one of a prompt's variants per request, in proportion to their
+/// weights:
+///
+/// ```xml
+/// <prompt name="welcome" variant="a" weight="3" model="gpt-3.5-turbo-0125">...</prompt>
+/// <prompt name="welcome" variant="b" weight="1" model="gpt-3.5-turbo-0125">...</prompt>
+/// ```
+#[derive(Clone)]
+pub struct VariantSelector {
+    name: String,
+    variants: Vec<Prompt>,
+    recorder: Option<Recorder>,
+}
+
+impl PromptCollection {
+    /// Every variant of the named prompt, at its highest version.
+    pub fn variants(&self, prompt_name: impl AsRef<str>) -> Vec<Prompt> {
+        let target = prompt_name.as_ref();
+        let matching = self.prompts()
+            .iter()
+            .filter(|prompt| prompt.name.as_deref() == Some(target))
+            .collect::<Vec<_>>();
+        let latest = matching.iter().map(|x| x.version.as_ref()).max().flatten();
+        matching
+            .into_iter()
+            .filter(|x| x.version.as_ref() == latest)
+            .cloned()
+            .collect()
+    }
+    pub fn variant_selector(&self, prompt_name: impl AsRef<str>) -> VariantSelector {
+        VariantSelector {
+            name: prompt_name.as_ref().to_string(),
+            variants: self.variants(prompt_name),
+            recorder: None,
+        }
+    }
+}
+
+impl VariantSelector {
+    /// Called with every assignment, e.g. to log it next to the request so
+    /// outcomes can be compared per variant later.
+    pub fn with_recorder(mut self, recorder: impl FnMut(&Assignment) + 'static) -> Self {
+        self.recorder = Some(Rc::new(RefCell::new(recorder)));
+        self
+    }
+    pub fn variants(&self) -> &[Prompt] {
+        &self.variants
+    }
+    /// Picks a variant at random, in proportion to the weights.
+    pub fn select(&self) -> Option<Prompt> {
+        let roll = std::collections::hash_map::RandomState::new().build_hasher().finish();
+        self.pick(roll, None)
+    }
+    /// Picks a variant deterministically from `key` (such as a user id), so
+    /// the same key always sees the same variant while the weights and the
+    /// set of variants stay unchanged.
+    pub fn select_sticky(&self, key: impl AsRef<str>) -> Option<Prompt> {
+        let key = key.as_ref();
+        self.pick(stable_hash(key.as_bytes()), Some(key))
+    }
+    /// Picks a specific variant by name.
+    pub fn select_variant(&self, variant: impl AsRef<str>) -> Option<Prompt> {
+        let variant = variant.as_ref();
+        let prompt = self.variants
+            .iter()
+            .find(|x| x.variant.as_deref() == Some(variant))
+            .cloned()?;
+        self.record(&prompt, None);
+        Some(prompt)
+    }
+    fn pick(&self, roll: u64, key: Option<&str>) -> Option<Prompt> {
+        let weights = self.variants
+            .iter()
+            .map(|x| x.weight.unwrap_or(1.0) as f64)
+            .collect::<Vec<_>>();
+        let total = weights.iter().sum::<f64>();
+        if total <= 0.0 {
+            return None
+        }
+        let mut target = (roll as f64 / u64::MAX as f64) * total;
+        let index = weights
+            .iter()
+            .position(|weight| {
+                target -= weight;
+                target < 0.0
+            })
+            .unwrap_or(weights.len() - 1);
+        let prompt = self.variants[index].clone();
+        self.record(&prompt, key);
+        Some(prompt)
+    }
+    fn record(&self, prompt: &Prompt, key: Option<&str>) {
+        if let Some(recorder) = self.recorder.as_ref() {
+            let assignment = Assignment {
+                prompt: self.name.clone(),
+                variant: prompt.variant.clone(),
+                key: key.map(str::to_string),
+            };
+            (recorder.borrow_mut())(&assignment);
+        }
+    }
+}
+
+/// FNV-1a followed by a SplitMix64 finalizer, so similar keys still spread
+/// evenly. Stable across runs and platforms, unlike `DefaultHasher`.
+fn stable_hash(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash ^= hash >> 30;
+    hash = hash.wrapping_mul(0xbf58476d1ce4e5b9);
+    hash ^= hash >> 27;
+    hash = hash.wrapping_mul(0x94d049bb133111eb);
+    hash ^ (hash >> 31)
+}
@@ -0,0 +1,241 @@
+use std::{cell::RefCell, path::{Path, PathBuf}, rc::Rc};
+use bytes::Bytes;
+use futures::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::client::Error;
+use crate::transport::{HttpRequest, HttpResponse, MockResponse, ReqwestTransport, Transport};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Response headers that are never written to a cassette.
+const DROPPED_HEADERS: &[&str] = &["set-cookie", "openai-organization", "openai-project", "authorization"];
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// CASSETTES
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Send real requests and save each exchange.
+    Record,
+    /// Serve saved exchanges; never touch the network.
+    Replay,
+    /// Replay if the cassette file exists, otherwise record.
+    Auto,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CassetteFile {
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    /// With the API key redacted.
+    pub url: String,
+    pub body: serde_json::Value,
+}
+
+impl RecordedRequest {
+    /// Unset parameters match whether they were recorded as `null` or
+    /// omitted, so cassettes recorded by earlier releases still replay.
+    pub fn matches(&self, other: &RecordedRequest) -> bool {
+        self.url == other.url && without_nulls(&self.body) == without_nulls(&other.body)
+    }
+}
+
+fn without_nulls(body: &serde_json::Value) -> serde_json::Value {
+    match body {
+        serde_json::Value::Object(map) => map
+            .iter()
+            .filter(|(_, x)| !x.is_null())
+            .map(|(key, x)| (key.clone(), x.clone()))
+            .collect(),
+        body => body.clone(),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// The whole body; for streams, the raw event text.
+    pub body: String,
+}
+
+impl Mode {
+    /// Reads `VCR_MODE` (`record`, `replay` or `auto`), defaulting to
+    /// `Replay` so CI never hits the API by accident.
+    pub fn from_env() -> Self {
+        match std::env::var("VCR_MODE").unwrap_or_default().to_lowercase().as_str() {
+            "record" => Mode::Record,
+            "auto" => Mode::Auto,
+            _ => Mode::Replay,
+        }
+    }
+}
+
+/// A [`Transport`] that records exchanges to, or replays them from, a JSON
+/// cassette file.
+///
+/// When recording, the API key is replaced with `[REDACTED]` wherever it
+/// appears and identifying response headers are dropped. The file is
+/// rewritten after every exchange. Recorded responses are read in full
+/// before being handed back, so streaming is not live while recording.
+///
+/// When replaying, each request is matched by URL and JSON body against
+/// the exchanges not yet served, in order, and its response is streamed
+/// back one event at a time.
+#[derive(Clone)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: Mode,
+    file: Rc<RefCell<CassetteFile>>,
+    /// Which interactions have been replayed.
+    used: Rc<RefCell<Vec<bool>>>,
+    inner: Rc<dyn Transport>,
+}
+
+impl Cassette {
+    pub fn new(path: impl AsRef<Path>, mode: Mode) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let mode = match mode {
+            Mode::Auto if path.exists() => Mode::Replay,
+            Mode::Auto => Mode::Record,
+            mode => mode,
+        };
+        let file = match mode {
+            Mode::Replay => serde_json::from_str(&std::fs::read_to_string(&path)?)?,
+            _ => CassetteFile::default(),
+        };
+        let used = vec![false; file.interactions.len()];
+        Ok(Cassette {
+            path,
+            mode,
+            file: Rc::new(RefCell::new(file)),
+            used: Rc::new(RefCell::new(used)),
+            inner: Rc::new(ReqwestTransport::default()),
+        })
+    }
+    /// Uses [`Mode::from_env`].
+    pub fn from_env(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::new(path, Mode::from_env())
+    }
+    /// The transport real requests go through when recording.
+    pub fn with_inner(mut self, inner: impl Transport + 'static) -> Self {
+        self.inner = Rc::new(inner);
+        self
+    }
+    /// `Record` or `Replay`; `Auto` is resolved when the cassette is opened.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.file.borrow().interactions.clone()
+    }
+    fn replay(&self, request: &RecordedRequest) -> Result<MockResponse, Error> {
+        let file = self.file.borrow();
+        let mut used = self.used.borrow_mut();
+        let index = file.interactions
+            .iter()
+            .enumerate()
+            .position(|(index, x)| !used[index] && x.request.matches(request))
+            .ok_or_else(|| Box::new(NoMatchingInteraction { path: self.path.clone(), url: request.url.clone() }))?;
+        used[index] = true;
+        let response = &file.interactions[index].response;
+        // One chunk per event, as a server would send them.
+        let chunks = response.body
+            .split_inclusive("\n\n")
+            .map(|x| Bytes::from(x.to_string()))
+            .collect();
+        let mut output = MockResponse::from_chunks(chunks);
+        output.status = response.status;
+        output.headers = response.headers.clone();
+        Ok(output)
+    }
+    fn save(&self) -> Result<(), Error> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let contents = serde_json::to_string_pretty(&*self.file.borrow())?;
+        std::fs::write(&self.path, contents)?;
+        Ok(())
+    }
+}
+
+impl Transport for Cassette {
+    fn send(&self, request: HttpRequest) -> LocalBoxFuture<'static, Result<HttpResponse, Error>> {
+        let this = self.clone();
+        Box::pin(async move {
+            let api_key = request.headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("authorization"))
+                .and_then(|(_, value)| value.strip_prefix("Bearer "))
+                .unwrap_or_default()
+                .to_string();
+            let mut body = serde_json::from_slice::<serde_json::Value>(&request.body)?;
+            redact(&mut body, &api_key);
+            let recorded = RecordedRequest { url: redact_str(&request.url, &api_key), body };
+            if this.mode == Mode::Replay {
+                return this.replay(&recorded)?.into_http_response()
+            }
+            let response = this.inner.send(request).await?;
+            let (status, header_map) = (response.status, response.headers.clone());
+            let headers = response.headers
+                .iter()
+                .filter(|(key, _)| !DROPPED_HEADERS.contains(&key.as_str()))
+                .filter_map(|(key, value)| Some((key.to_string(), value.to_str().ok()?.to_string())))
+                .collect::<Vec<_>>();
+            let mut bytes = Vec::<u8>::default();
+            let mut stream = response.body;
+            while let Some(chunk) = futures::StreamExt::next(&mut stream).await {
+                bytes.extend_from_slice(&chunk?);
+            }
+            let body = redact_str(&String::from_utf8_lossy(&bytes), &api_key);
+            let response = RecordedResponse { status, headers, body };
+            this.file.borrow_mut().interactions.push(Interaction { request: recorded, response });
+            this.save()?;
+            let body = futures::stream::iter([Ok(Bytes::from(bytes))]);
+            Ok(HttpResponse { status, headers: header_map, body: Box::pin(body) })
+        })
+    }
+}
+
+fn redact_str(text: &str, secret: &str) -> String {
+    match secret.is_empty() {
+        true => text.to_string(),
+        false => text.replace(secret, REDACTED),
+    }
+}
+
+fn redact(value: &mut serde_json::Value, secret: &str) {
+    match value {
+        serde_json::Value::String(text) => *text = redact_str(text, secret),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|x| redact(x, secret)),
+        serde_json::Value::Object(map) => map.values_mut().for_each(|x| redact(x, secret)),
+        _ => {}
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NoMatchingInteraction {
+    pub path: PathBuf,
+    pub url: String,
+}
+impl std::fmt::Display for NoMatchingInteraction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cannot replay request to {}: no unused interaction in {} matches it; re-record with VCR_MODE=record.",
+            self.url,
+            self.path.display(),
+        )
+    }
+}
+impl std::error::Error for NoMatchingInteraction {}
@@ -0,0 +1,65 @@
+use std::cmp::Ordering;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// VECTOR MATH
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Panics if the lengths differ.
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len(), "vectors must have the same length");
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// The Euclidean length.
+pub fn norm(vector: &[f32]) -> f32 {
+    vector.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Scales `vector` to unit length in place. A zero vector is left as is.
+pub fn normalize(vector: &mut [f32]) {
+    let norm = norm(vector);
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+pub fn normalized(vector: &[f32]) -> Vec<f32> {
+    let mut vector = vector.to_vec();
+    normalize(&mut vector);
+    vector
+}
+
+/// Between -1 and 1; 0 if either vector is zero. For vectors already
+/// normalized, [`dot`] gives the same result with less work.
+///
+/// Panics if the lengths differ.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let norms = norm(a) * norm(b);
+    match norms > 0.0 {
+        true => dot(a, b) / norms,
+        false => 0.0,
+    }
+}
+
+/// The `k` candidates most similar to `query` by cosine similarity, best
+/// first, each with its score.
+///
+/// ```rust,ignore
+/// let best = top_k(&query, documents.iter().map(|x| (x, x.embedding.as_slice())), 5);
+/// ```
+pub fn top_k<'a, T>(
+    query: &[f32],
+    candidates: impl IntoIterator<Item = (T, &'a [f32])>,
+    k: usize,
+) -> Vec<(T, f32)> {
+    let mut scored = candidates
+        .into_iter()
+        .map(|(item, vector)| (item, cosine_similarity(query, vector)))
+        .collect::<Vec<_>>();
+    let by_score = |a: &(T, f32), b: &(T, f32)| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal);
+    if k < scored.len() {
+        scored.select_nth_unstable_by(k, by_score);
+        scored.truncate(k);
+    }
+    scored.sort_by(by_score);
+    scored
+}
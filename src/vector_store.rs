@@ -0,0 +1,197 @@
+use std::{cell::RefCell, rc::Rc};
+use std::io::{BufRead, Write};
+use std::path::Path;
+use futures::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::client::Error;
+use crate::vector::top_k;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// RECORDS
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Record {
+    pub id: String,
+    pub vector: Vec<f32>,
+    /// The text the vector was computed from.
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Record {
+    pub fn new(id: impl AsRef<str>, vector: Vec<f32>) -> Self {
+        Record { id: id.as_ref().to_string(), vector, text: String::default(), metadata: Default::default() }
+    }
+    pub fn with_text(mut self, text: impl AsRef<str>) -> Self {
+        self.text = text.as_ref().to_string();
+        self
+    }
+    pub fn with_metadata(mut self, key: impl AsRef<str>, value: impl Into<serde_json::Value>) -> Self {
+        self.metadata.insert(key.as_ref().to_string(), value.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub record: Record,
+    /// Cosine similarity to the query.
+    pub score: f32,
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// STORE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Holds embedded records for similarity search. [`MemoryVectorStore`] is
+/// enough for prototypes and tests; implement this to use a vector
+/// database instead.
+pub trait VectorStore {
+    /// Replaces any records with the same ids.
+    fn insert<'a>(&'a self, records: Vec<Record>) -> LocalBoxFuture<'a, Result<(), Error>>;
+    /// The `k` records most similar to `query`, best first.
+    fn search<'a>(&'a self, query: &'a [f32], k: usize) -> LocalBoxFuture<'a, Result<Vec<SearchResult>, Error>>;
+    fn remove<'a>(&'a self, ids: &'a [String]) -> LocalBoxFuture<'a, Result<(), Error>>;
+}
+
+impl<T: VectorStore + ?Sized> VectorStore for Rc<T> {
+    fn insert<'a>(&'a self, records: Vec<Record>) -> LocalBoxFuture<'a, Result<(), Error>> {
+        (**self).insert(records)
+    }
+    fn search<'a>(&'a self, query: &'a [f32], k: usize) -> LocalBoxFuture<'a, Result<Vec<SearchResult>, Error>> {
+        (**self).search(query, k)
+    }
+    fn remove<'a>(&'a self, ids: &'a [String]) -> LocalBoxFuture<'a, Result<(), Error>> {
+        (**self).remove(ids)
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// MEMORY STORE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Searches by comparing the query with every record, which is fast enough
+/// for tens of thousands of records. Cloning shares the records.
+///
+/// Saved as JSON Lines, one record per line.
+///
+/// ```rust,ignore
+/// let store = MemoryVectorStore::new();
+/// store.add(Record::new("faq-1", embedding).with_text(answer).with_metadata("source", "faq.md"))?;
+/// let hits = store.query(&query_embedding, 3);
+/// store.save("faq.vectors.jsonl")?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemoryVectorStore {
+    records: Rc<RefCell<Vec<Record>>>,
+}
+
+impl MemoryVectorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let store = Self::new();
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        for line in file.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                store.add(serde_json::from_str(&line)?)?;
+            }
+        }
+        Ok(store)
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        // Write then rename, so a crash never leaves a partial file behind.
+        let temporary = path.with_extension(format!("tmp-{}", std::process::id()));
+        let mut file = std::io::BufWriter::new(std::fs::File::create(&temporary)?);
+        for record in self.records.borrow().iter() {
+            writeln!(file, "{}", serde_json::to_string(record)?)?;
+        }
+        file.flush()?;
+        drop(file);
+        std::fs::rename(&temporary, path)?;
+        Ok(())
+    }
+    /// Fails if the vector's length differs from the records already
+    /// stored.
+    pub fn add(&self, record: Record) -> Result<(), DimensionMismatch> {
+        let mut records = self.records.borrow_mut();
+        if let Some(expected) = records.first().map(|x| x.vector.len()).filter(|x| *x != record.vector.len()) {
+            return Err(DimensionMismatch { id: record.id, expected, actual: record.vector.len() })
+        }
+        match records.iter_mut().find(|x| x.id == record.id) {
+            Some(existing) => *existing = record,
+            None => records.push(record),
+        }
+        Ok(())
+    }
+    pub fn query(&self, query: &[f32], k: usize) -> Vec<SearchResult> {
+        let records = self.records.borrow();
+        let candidates = records.iter().map(|x| (x, x.vector.as_slice()));
+        top_k(query, candidates, k)
+            .into_iter()
+            .map(|(record, score)| SearchResult { record: record.clone(), score })
+            .collect()
+    }
+    pub fn get(&self, id: impl AsRef<str>) -> Option<Record> {
+        self.records.borrow().iter().find(|x| x.id == id.as_ref()).cloned()
+    }
+    pub fn delete(&self, id: impl AsRef<str>) -> Option<Record> {
+        let mut records = self.records.borrow_mut();
+        let index = records.iter().position(|x| x.id == id.as_ref())?;
+        Some(records.remove(index))
+    }
+    pub fn records(&self) -> Vec<Record> {
+        self.records.borrow().clone()
+    }
+    pub fn len(&self) -> usize {
+        self.records.borrow().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.records.borrow().is_empty()
+    }
+    pub fn clear(&self) {
+        self.records.borrow_mut().clear();
+    }
+}
+
+impl VectorStore for MemoryVectorStore {
+    fn insert<'a>(&'a self, records: Vec<Record>) -> LocalBoxFuture<'a, Result<(), Error>> {
+        let result = records
+            .into_iter()
+            .try_for_each(|x| self.add(x))
+            .map_err(|e| Box::new(e) as Error);
+        Box::pin(futures::future::ready(result))
+    }
+    fn search<'a>(&'a self, query: &'a [f32], k: usize) -> LocalBoxFuture<'a, Result<Vec<SearchResult>, Error>> {
+        Box::pin(futures::future::ready(Ok(self.query(query, k))))
+    }
+    fn remove<'a>(&'a self, ids: &'a [String]) -> LocalBoxFuture<'a, Result<(), Error>> {
+        for id in ids {
+            self.delete(id);
+        }
+        Box::pin(futures::future::ready(Ok(())))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DimensionMismatch {
+    pub id: String,
+    pub expected: usize,
+    pub actual: usize,
+}
+impl std::fmt::Display for DimensionMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cannot store record {:?}: its vector has {} dimensions but the store holds {}-dimensional vectors.",
+            self.id,
+            self.actual,
+            self.expected,
+        )
+    }
+}
+impl std::error::Error for DimensionMismatch {}
@@ -0,0 +1,70 @@
+use std::{path::Path, sync::{Arc, RwLock}};
+
+use crate::client as api;
+use crate::xml_dsl::{Prompt, PromptCollection};
+
+/// Keeps a [`PromptCollection`] in sync with the files it was loaded from.
+///
+/// Files are re-parsed whenever something under the watched path changes;
+/// the shared collection is only swapped when the reload succeeds, so a
+/// half-saved file never replaces a working set of prompts.
+pub struct PromptWatcher {
+    collection: Arc<RwLock<PromptCollection>>,
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl PromptCollection {
+    /// Loads `path` (a single file, or a directory searched recursively) and
+    /// reloads it on every change.
+    ///
+    /// The callback is invoked after each reload attempt with either the new
+    /// collection or the error that kept the previous one in place.
+    pub fn watch(
+        path: impl AsRef<Path>,
+        mut callback: impl FnMut(Result<&PromptCollection, api::Error>) + Send + 'static,
+    ) -> Result<PromptWatcher, api::Error> {
+        use notify::Watcher;
+        let path = path.as_ref().to_path_buf();
+        let collection = Arc::new(RwLock::new(load(&path)?));
+        let shared = collection.clone();
+        let watched = path.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else { return };
+            if !(event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove()) {
+                return
+            }
+            match load(&watched) {
+                Ok(reloaded) => {
+                    // The write guard is dropped before the callback, which
+                    // may read the collection through the watcher.
+                    *shared.write().unwrap() = reloaded.clone();
+                    callback(Ok(&reloaded));
+                }
+                Err(error) => callback(Err(error)),
+            }
+        })?;
+        watcher.watch(&path, notify::RecursiveMode::Recursive)?;
+        Ok(PromptWatcher { collection, _watcher: watcher })
+    }
+}
+
+impl PromptWatcher {
+    /// A snapshot of the most recently loaded collection.
+    pub fn collection(&self) -> PromptCollection {
+        self.collection.read().unwrap().clone()
+    }
+    pub fn get(&self, prompt_name: impl AsRef<str>) -> Option<Prompt> {
+        self.collection.read().unwrap().get(prompt_name)
+    }
+    /// The shared handle that is swapped on reload.
+    pub fn shared(&self) -> Arc<RwLock<PromptCollection>> {
+        self.collection.clone()
+    }
+}
+
+fn load(path: &Path) -> Result<PromptCollection, api::Error> {
+    if path.is_dir() {
+        return PromptCollection::open_dir_recursive(path)
+    }
+    PromptCollection::open(path)
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use futures::stream::{FuturesUnordered, StreamExt};
+
+use crate::client::{ChatCompletionsRequestBuilder, Error};
+use crate::pipeline::{Setup, Stage, StageTrace};
+use crate::xml_dsl::{PromptCollection, Workflow, WorkflowNode};
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// EXECUTION
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+#[derive(Debug, Clone)]
+pub struct WorkflowOutcome {
+    /// Each node's output, by node id.
+    pub outputs: BTreeMap<String, serde_json::Value>,
+    /// In the order the nodes finished; each trace is named after its node.
+    pub traces: Vec<StageTrace>,
+    /// Summed over the nodes, using estimates where usage was not reported.
+    pub tokens: usize,
+}
+
+/// Runs the `<workflow>`s of a collection.
+///
+/// A node starts as soon as every node it takes input from has finished,
+/// so independent nodes run concurrently, up to the concurrency limit.
+/// Each node's prompt is rendered with the globals plus its inputs; the
+/// first failure stops the run.
+///
+/// ```rust,ignore
+/// let collection = PromptCollection::open("brief.xml")?;
+/// let outcome = WorkflowExecutor::new(collection)
+///     .with_setup(move |x| x.with_api_key(&api_key))
+///     .run("brief", &liquid::object!({ "article": article }))
+///     .await?;
+/// println!("{}", outcome.outputs["summary"]);
+/// ```
+#[derive(Clone)]
+pub struct WorkflowExecutor {
+    collection: PromptCollection,
+    setup: Option<Setup>,
+    concurrency: usize,
+}
+
+impl WorkflowExecutor {
+    pub fn new(collection: PromptCollection) -> Self {
+        WorkflowExecutor { collection, setup: None, concurrency: 4 }
+    }
+    /// Applied to every node's request, e.g. to set the API key, endpoint
+    /// or transport.
+    pub fn with_setup(
+        mut self,
+        setup: impl Fn(ChatCompletionsRequestBuilder) -> ChatCompletionsRequestBuilder + 'static,
+    ) -> Self {
+        self.setup = Some(Rc::new(setup));
+        self
+    }
+    /// How many nodes may run at once. Defaults to 4.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+    pub async fn run(&self, workflow_name: impl AsRef<str>, globals: &liquid::Object) -> Result<WorkflowOutcome, Error> {
+        let workflow_name = workflow_name.as_ref();
+        let workflow = self.collection
+            .workflow(workflow_name)
+            .ok_or_else(|| Box::new(WorkflowNotFound(workflow_name.to_string())))?;
+        self.run_workflow(workflow, globals).await
+    }
+    /// Fails with the [`crate::validate::Diagnostics`] if the workflow does
+    /// not pass [`Workflow::validate`].
+    pub async fn run_workflow(&self, workflow: &Workflow, globals: &liquid::Object) -> Result<WorkflowOutcome, Error> {
+        let diagnostics = workflow.validate(&self.collection);
+        if !diagnostics.is_ok() {
+            return Err(Box::new(diagnostics))
+        }
+        let mut outputs = BTreeMap::<String, serde_json::Value>::default();
+        let mut traces = Vec::default();
+        let mut waiting = workflow.nodes.iter().collect::<Vec<_>>();
+        let mut running = FuturesUnordered::new();
+        loop {
+            while running.len() < self.concurrency {
+                let Some(index) = waiting.iter().position(|node| is_ready(node, &outputs)) else {
+                    break
+                };
+                let node = waiting.remove(index);
+                let stage = self.stage(node)?;
+                let variables = inputs(node, globals, &outputs)?;
+                running.push(async move { stage.run(&variables, self.setup.as_ref()).await });
+            }
+            let Some(trace) = running.next().await else {
+                break
+            };
+            let trace = trace?;
+            outputs.insert(trace.name.clone(), trace.output.clone());
+            traces.push(trace);
+        }
+        let tokens = traces.iter().map(StageTrace::tokens).sum();
+        Ok(WorkflowOutcome { outputs, traces, tokens })
+    }
+    fn stage(&self, node: &WorkflowNode) -> Result<Stage, Error> {
+        let prompt = self.collection
+            .get(&node.prompt)
+            .ok_or_else(|| Box::new(crate::xml_dsl::PromptNotFound(node.prompt.clone())))?;
+        let mut stage = Stage::new(prompt, &node.id).with_name(&node.id);
+        stage.output = node.output;
+        Ok(stage)
+    }
+}
+
+fn is_ready(node: &WorkflowNode, outputs: &BTreeMap<String, serde_json::Value>) -> bool {
+    node.inputs.iter().all(|x| outputs.contains_key(&x.from))
+}
+
+fn inputs(
+    node: &WorkflowNode,
+    globals: &liquid::Object,
+    outputs: &BTreeMap<String, serde_json::Value>,
+) -> Result<liquid::Object, Error> {
+    let mut variables = globals.clone();
+    for input in node.inputs.iter() {
+        variables.insert(input.var.clone().into(), liquid::model::to_value(&outputs[&input.from])?);
+    }
+    Ok(variables)
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkflowNotFound(pub String);
+impl std::fmt::Display for WorkflowNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot find workflow: {:?}.", self.0)
+    }
+}
+impl std::error::Error for WorkflowNotFound {}
@@ -0,0 +1,321 @@
+use std::path::{Path, PathBuf};
+use quick_xml::events::Event;
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// TREE
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// A minimal XML tree that keeps element names and whitespace exactly as
+/// written, along with the source text of every node.
+#[derive(Debug, Clone)]
+pub enum Node {
+    Element(Element),
+    /// Character data exactly as written, entities included.
+    Text(String),
+    /// The contents of a `<![CDATA[...]]>` section.
+    CData(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Element {
+    pub name: String,
+    /// Attribute values are unescaped.
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+    /// Where the start tag begins.
+    pub position: Position,
+    /// The element's full markup, from start tag to end tag.
+    pub raw: String,
+}
+
+/// One-based line and column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct XmlError {
+    pub source_file: Option<PathBuf>,
+    pub position: Position,
+    pub message: String,
+}
+
+impl Element {
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(Node::as_element)
+    }
+}
+
+impl XmlError {
+    pub fn in_file(mut self, file_path: impl AsRef<Path>) -> Self {
+        self.source_file = Some(file_path.as_ref().to_path_buf());
+        self
+    }
+}
+
+impl Node {
+    pub fn as_element(&self) -> Option<&Element> {
+        match self {
+            Node::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+}
+
+/// Every element in `nodes`, depth first.
+pub fn descendants(nodes: &[Node]) -> Vec<&Element> {
+    let mut output = Vec::default();
+    for element in nodes.iter().filter_map(Node::as_element) {
+        output.push(element);
+        output.extend(descendants(&element.children));
+    }
+    output
+}
+
+impl Position {
+    pub fn from_offset(source: &str, offset: usize) -> Self {
+        let before = &source[..offset.min(source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+        Position { line, column }
+    }
+}
+
+/// The offset of every line start, so that positions can be looked up
+/// without rescanning the source from the beginning each time.
+struct Lines<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> Lines<'a> {
+    fn new(source: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(x, _)| x + 1))
+            .collect();
+        Lines { source, starts }
+    }
+    fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.source.len());
+        let line = self.starts.partition_point(|x| *x <= offset);
+        let column = self.source[self.starts[line - 1]..offset].chars().count() + 1;
+        Position { line, column }
+    }
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+impl std::fmt::Display for XmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.source_file.as_ref() {
+            Some(file) => write!(f, "Malformed XML at {}:{}: {}.", file.display(), self.position, self.message),
+            None => write!(f, "Malformed XML at {}: {}.", self.position, self.message),
+        }
+    }
+}
+impl std::error::Error for XmlError {}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// PARSING
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+/// Parses a document that may have several top-level elements, as prompt
+/// files do.
+///
+/// As in HTML, a `<` that cannot start a tag, e.g. in `a < b` or `x <= 3`,
+/// is read as text, and so is a bare `&`. A `<` directly followed by a
+/// letter always starts a tag, so `a<b` must be written `a &lt; b` or put
+/// in a CDATA section.
+pub fn parse_fragment(source: &str) -> Result<Vec<Node>, XmlError> {
+    // The reader sees stray brackets masked; text and markup are still
+    // sliced from `source`, which has the same offsets.
+    let masked = mask_stray_brackets(source);
+    let mut reader = quick_xml::Reader::from_str(masked.as_deref().unwrap_or(source));
+    reader.trim_text(false);
+    reader.check_end_names(true);
+    let lines = Lines::new(source);
+    let error = |offset: usize, message: String| XmlError {
+        source_file: None,
+        position: lines.position(offset),
+        message,
+    };
+    let mut root = Vec::<Node>::default();
+    // Open elements along with the offset of their start tag.
+    let mut stack = Vec::<(Element, usize)>::default();
+    loop {
+        let start = reader.buffer_position();
+        let event = reader
+            .read_event()
+            .map_err(|e| error(start, e.to_string()))?;
+        let end = reader.buffer_position();
+        let node = match event {
+            Event::Start(tag) => {
+                let element = new_element(&tag, lines.position(start)).map_err(|e| error(start, e))?;
+                stack.push((element, start));
+                continue
+            }
+            Event::Empty(tag) => {
+                let mut element = new_element(&tag, lines.position(start)).map_err(|e| error(start, e))?;
+                element.raw = source[start..end].to_string();
+                Node::Element(element)
+            }
+            Event::End(_) => {
+                let Some((mut element, offset)) = stack.pop() else {
+                    return Err(error(start, String::from("unexpected closing tag")))
+                };
+                element.raw = source[offset..end].to_string();
+                Node::Element(element)
+            }
+            Event::Text(_) => Node::Text(source[start..end].to_string()),
+            Event::CData(data) => {
+                let data = String::from_utf8(data.into_inner().into_owned())
+                    .map_err(|e| error(start, e.to_string()))?;
+                Node::CData(unmask(data))
+            }
+            Event::Comment(_) | Event::Decl(_) | Event::PI(_) | Event::DocType(_) => continue,
+            Event::Eof => break,
+        };
+        match stack.last_mut() {
+            Some((parent, _)) => parent.children.push(node),
+            None => root.push(node),
+        }
+    }
+    if let Some((element, offset)) = stack.pop() {
+        return Err(error(offset, format!("<{}> is never closed", element.name)))
+    }
+    Ok(root)
+}
+
+fn new_element(tag: &quick_xml::events::BytesStart, position: Position) -> Result<Element, String> {
+    let name = String::from_utf8_lossy(tag.name().as_ref()).to_string();
+    let mut attributes = Vec::default();
+    for attribute in tag.attributes() {
+        let attribute = attribute.map_err(|e| e.to_string())?;
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).to_string();
+        let value = unmask(attribute.unescape_value().map_err(|e| e.to_string())?.to_string());
+        attributes.push((key, value));
+    }
+    Ok(Element {
+        name,
+        attributes,
+        children: Vec::default(),
+        position,
+        raw: String::default(),
+    })
+}
+
+/// Stands in for a `<` that cannot start markup; one byte, so offsets into
+/// the masked source match the original.
+const STRAY: char = '\u{1}';
+
+/// The source with every `<` that is not followed by a name, `/`, `!` or
+/// `?` replaced by [`STRAY`], or `None` if there are none. A source that
+/// already contains the stand-in is left strict.
+fn mask_stray_brackets(source: &str) -> Option<String> {
+    let is_markup = |next: Option<&u8>| match next {
+        Some(x) => x.is_ascii_alphabetic() || !x.is_ascii() || b"/!?_:".contains(x),
+        None => false,
+    };
+    let bytes = source.as_bytes();
+    let stray = (0..bytes.len())
+        .filter(|i| bytes[*i] == b'<' && !is_markup(bytes.get(i + 1)))
+        .collect::<Vec<_>>();
+    if stray.is_empty() || source.contains(STRAY) {
+        return None
+    }
+    let mut masked = bytes.to_vec();
+    for i in stray {
+        masked[i] = STRAY as u8;
+    }
+    String::from_utf8(masked).ok()
+}
+
+fn unmask(value: String) -> String {
+    match value.contains(STRAY) {
+        true => value.replace(STRAY, "<"),
+        false => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(nodes: &[Node]) -> String {
+        nodes
+            .iter()
+            .map(|node| match node {
+                Node::Text(x) | Node::CData(x) => x.clone(),
+                Node::Element(element) => text(&element.children),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn line_index_matches_from_offset() {
+        let source = "<a>\n  é<b/>\n\n\tλ</a>\n";
+        let lines = Lines::new(source);
+        for (offset, _) in source.char_indices().chain([(source.len(), ' ')]) {
+            assert_eq!(lines.position(offset), Position::from_offset(source, offset), "offset {offset}");
+        }
+    }
+
+    #[test]
+    fn elements_report_their_start_tag() {
+        let source = "<prompt name=\"a\">\n    <message>é</message>\n  <message/>\n</prompt>";
+        let nodes = parse_fragment(source).unwrap();
+        let prompt = nodes[0].as_element().unwrap();
+        assert_eq!(prompt.position, Position { line: 1, column: 1 });
+        let messages = prompt.elements().map(|x| x.position).collect::<Vec<_>>();
+        assert_eq!(messages, [Position { line: 2, column: 5 }, Position { line: 3, column: 3 }]);
+        assert_eq!(prompt.elements().next().unwrap().raw, "<message>é</message>");
+    }
+
+    #[test]
+    fn stray_brackets_and_ampersands_are_text() {
+        let cases = [
+            ("<m>R&D</m>", "R&D"),
+            ("<m>a < b</m>", "a < b"),
+            ("<m>x <= 3 && y<3</m>", "x <= 3 && y<3"),
+            ("<m>ends with <</m>", "ends with <"),
+            ("<m><![CDATA[a < b]]></m>", "a < b"),
+            ("<m>a &lt; b</m>", "a &lt; b"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(text(&parse_fragment(source).unwrap()), expected, "{source}");
+        }
+        let nodes = parse_fragment(r#"<assert equals="a < b"/>"#).unwrap();
+        assert_eq!(nodes[0].as_element().unwrap().attr("equals"), Some("a < b"));
+    }
+
+    #[test]
+    fn well_formedness_errors_report_positions() {
+        let cases = [
+            ("<prompt>\n  <message>hi\n</prompt>", 3, 1),
+            ("<prompt>\n  <message>hi</message>\n", 1, 1),
+            ("<a/>\n</b>", 2, 1),
+            ("<m>\n  a<b and c\n</m>", 2, 4),
+            ("<m name=\"x>hi</m>", 1, 1),
+        ];
+        for (source, line, column) in cases {
+            let error = parse_fragment(source).unwrap_err();
+            assert_eq!(error.position, Position { line, column }, "{source}: {error}");
+        }
+    }
+
+    #[test]
+    fn errors_name_the_unclosed_element() {
+        let error = parse_fragment("<prompt>\n  <message>hi</message>\n").unwrap_err();
+        assert_eq!(error.message, "<prompt> is never closed");
+        assert_eq!(error.in_file("a.xml").to_string(), "Malformed XML at a.xml:1:1: <prompt> is never closed.");
+    }
+}
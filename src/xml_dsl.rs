@@ -1,45 +1,517 @@
-use std::{path::Path, str::FromStr};
+use std::{collections::{BTreeMap, BTreeSet}, path::{Path, PathBuf}, str::FromStr, sync::OnceLock};
 
+use crate::assertions::Assertion;
 use crate::client::{self as api, ChatCompletionsRequestBuilder};
+use crate::params::{ParamDeclaration, ParamType};
+use crate::pipeline::StageOutput;
+use crate::validate::Diagnostic;
+use crate::xml;
 
 #[derive(Debug, Clone)]
 pub struct PromptCollection {
     prompts: Vec<Prompt>,
+    workflows: Vec<Workflow>,
+    chains: Vec<Chain>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Prompt {
     pub name: Option<String>,
+    /// Several prompts may share a name as long as their versions differ.
+    /// Partial versions such as `version="3"` are read as `3.0.0`.
+    pub version: Option<semver::Version>,
+    /// Prompts sharing a name (and version) but with different variants form
+    /// an A/B group; see [`crate::variants::VariantSelector`].
+    pub variant: Option<String>,
+    /// The variant's relative share of traffic. Defaults to 1.
+    pub weight: Option<f32>,
+    /// A provider name understood by [`api::ApiEndpoint::for_provider`].
+    pub endpoint: Option<String>,
+    /// Takes precedence over `endpoint`.
+    pub base_url: Option<String>,
+    /// Models tried in order on the same endpoint when `model` fails,
+    /// declared as `fallback="gpt-4o-mini, gpt-3.5-turbo"`; see
+    /// [`api::Fallback`].
+    pub fallbacks: Vec<String>,
+    /// The file this prompt was loaded from, if any.
+    pub source_file: Option<PathBuf>,
     pub configuration: api::ConfigurationBuilder,
-    pub messages: Vec<api::Message>
+    /// Programmatic changes layered over `configuration` when building the
+    /// request body; see [`Prompt::with_overrides`].
+    pub overrides: api::ConfigurationBuilder,
+    pub metadata: PromptMetadata,
+    /// The variables declared in the `<params>` block, checked and converted
+    /// on render; see [`Prompt::bind_params`].
+    pub params: Vec<ParamDeclaration>,
+    /// Messages and `<for-each>` blocks in document order. Before
+    /// `<for-each>` this was a `Vec<api::Message>`; [`Prompt::messages()`]
+    /// still returns the plain messages for code written against that.
+    pub messages: Vec<MessageNode>,
+    /// Expectations on the reply, declared with `<assert>`; see
+    /// [`PromptCollection::run_tests`].
+    pub assertions: Vec<Assertion>,
+    /// Problems noticed while parsing the markup, such as unknown attributes
+    /// or values that failed to parse. See [`PromptCollection::validate`].
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Declared with `<meta name="..." content="..."/>` elements.
+///
+/// ```xml
+/// <meta name="description" content="Routes support tickets."/>
+/// <meta name="tags" content="support, triage"/>
+/// <meta name="owner" content="support-team"/>
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PromptMetadata {
+    pub description: Option<String>,
+    /// Comma separated in the DSL.
+    pub tags: Vec<String>,
+    pub owner: Option<String>,
+    /// Any other `<meta>` entries.
+    pub extra: BTreeMap<String, String>,
+}
+
+/// Controls how the markup between `<message>` tags becomes the content
+/// sent to the model.
+///
+/// The default trims the body and strips its common indentation, leaving
+/// everything else (including character references such as `&amp;`) as
+/// written.
+#[derive(Debug, Clone)]
+pub struct Normalization {
+    pub trim: bool,
+    pub unindent: bool,
+    /// Replace runs of blank lines with a single blank line.
+    pub collapse_blank_lines: bool,
+    pub trim_trailing_spaces: bool,
+    /// Decode `&amp;`, `&lt;`, `&gt;`, `&quot;`, `&apos;`, `&nbsp;` and numeric
+    /// character references.
+    pub decode_entities: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum MessageNode {
+    Message(api::Message),
+    ForEach(ForEach),
+}
+
+/// Repeats its children once per element of a render-time list.
+///
+/// ```xml
+/// <for-each var="doc" in="documents">
+///     <message role="user">{{ doc.title }}: {{ doc.body }}</message>
+/// </for-each>
+/// ```
+#[derive(Debug, Clone)]
+pub struct ForEach {
+    /// The name each element is bound to while rendering the children.
+    pub var: String,
+    /// Dotted path to the list within the render-time globals.
+    pub source: String,
+    pub children: Vec<MessageNode>,
+}
+
+/// Prompts wired together by data edges, declared with `<workflow>`; run
+/// with [`crate::workflow::WorkflowExecutor`].
+///
+/// ```xml
+/// <workflow name="brief">
+///     <node id="facts" prompt="extract-facts" output="json"/>
+///     <node id="risks" prompt="list-risks">
+///         <input var="facts" from="facts"/>
+///     </node>
+///     <node id="summary" prompt="write-summary">
+///         <input var="facts" from="facts"/>
+///         <input var="risks" from="risks"/>
+///     </node>
+/// </workflow>
+/// ```
+#[derive(Debug, Clone)]
+pub struct Workflow {
+    pub name: Option<String>,
+    pub nodes: Vec<WorkflowNode>,
+    /// The file this workflow was loaded from, if any.
+    pub source_file: Option<PathBuf>,
+    /// Problems noticed while parsing the markup.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkflowNode {
+    pub id: String,
+    /// The name of the prompt to run.
+    pub prompt: String,
+    pub output: StageOutput,
+    pub inputs: Vec<WorkflowInput>,
+    pub position: Option<xml::Position>,
+}
+
+/// A data edge: the output of node `from` is bound to the variable `var`.
+#[derive(Debug, Clone)]
+pub struct WorkflowInput {
+    /// Defaults to the id of the `from` node.
+    pub var: String,
+    pub from: String,
+}
+
+/// Prompts run one after another, declared with `<chain>`: a lighter
+/// alternative to `<workflow>` when each step only feeds the next. Run with
+/// [`crate::pipeline::Pipeline::from_chain`].
+///
+/// ```xml
+/// <chain name="brief">
+///     <step prompt="extract-facts" var="facts" output="json"/>
+///     <step prompt="write-summary" var="summary"/>
+/// </chain>
+/// ```
+///
+/// Each step's output is bound to its `var`, so `write-summary` can read
+/// `{{ facts.title }}`.
+#[derive(Debug, Clone)]
+pub struct Chain {
+    pub name: Option<String>,
+    pub steps: Vec<ChainStep>,
+    /// The file this chain was loaded from, if any.
+    pub source_file: Option<PathBuf>,
+    /// Problems noticed while parsing the markup.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainStep {
+    /// The name of the prompt to run.
+    pub prompt: String,
+    /// The variable the output is bound to. Defaults to the prompt's name.
+    pub var: String,
+    pub output: StageOutput,
+    pub position: Option<xml::Position>,
+}
+
+impl Workflow {
+    pub fn node(&self, id: impl AsRef<str>) -> Option<&WorkflowNode> {
+        self.nodes.iter().find(|x| x.id == id.as_ref())
+    }
 }
 
 impl PromptCollection {
     pub fn open(file_path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
-        let source = std::fs::read_to_string(file_path.as_ref())?;
-        Self::parse(source)
-    } 
+        Self::open_with(file_path, &Normalization::default())
+    }
+    pub fn from_prompts(prompts: impl IntoIterator<Item = Prompt>) -> Self {
+        let prompts = prompts.into_iter().collect();
+        PromptCollection { prompts, workflows: Vec::default(), chains: Vec::default() }
+    }
+    /// Records `file_path` as the origin of every prompt in the collection.
+    pub fn with_source_file(mut self, file_path: impl AsRef<Path>) -> Self {
+        for prompt in self.prompts.iter_mut() {
+            prompt.source_file = Some(file_path.as_ref().to_path_buf());
+        }
+        for workflow in self.workflows.iter_mut() {
+            workflow.source_file = Some(file_path.as_ref().to_path_buf());
+        }
+        for chain in self.chains.iter_mut() {
+            chain.source_file = Some(file_path.as_ref().to_path_buf());
+        }
+        self
+    }
+    /// Loads and merges every prompt file directly inside `dir`, in the XML
+    /// DSL or the YAML and JSON formats; see [`PROMPT_EXTENSIONS`]. Other
+    /// files, such as a README, are skipped.
+    pub fn open_dir(dir: impl AsRef<Path>) -> Result<Self, api::Error> {
+        Self::open_prompt_files(escape_glob(dir.as_ref()).join("*"))
+    }
+    /// Loads and merges every prompt file under `dir`, including
+    /// subdirectories.
+    pub fn open_dir_recursive(dir: impl AsRef<Path>) -> Result<Self, api::Error> {
+        Self::open_prompt_files(escape_glob(dir.as_ref()).join("**").join("*"))
+    }
+    /// Opens a file in the format its extension names: YAML for `.yaml` and
+    /// `.yml`, JSON for `.json`, and the XML DSL otherwise.
+    pub fn open_any(file_path: impl AsRef<Path>) -> Result<Self, api::Error> {
+        let file_path = file_path.as_ref();
+        match file_path.extension().and_then(|x| x.to_str()) {
+            Some("yaml" | "yml") => Self::open_yaml(file_path),
+            Some("json") => Self::open_json(file_path),
+            _ => Self::open(file_path),
+        }
+    }
+    fn open_prompt_files(pattern: PathBuf) -> Result<Self, api::Error> {
+        let files = glob_files(&pattern)?
+            .into_iter()
+            .filter(|path| is_prompt_file(path))
+            .collect();
+        Self::open_files(files)
+    }
+    /// Loads and merges every file matching the given glob pattern,
+    /// e.g. `prompts/**/*.prompt.liquid`.
+    ///
+    /// Fails with [`DuplicatePrompts`] if two prompts share a name.
+    pub fn open_glob(pattern: impl AsRef<Path>) -> Result<Self, api::Error> {
+        Self::open_files(glob_files(pattern.as_ref())?)
+    }
+    fn open_files(files: Vec<PathBuf>) -> Result<Self, api::Error> {
+        let (mut prompts, mut workflows, mut chains) = (Vec::default(), Vec::default(), Vec::default());
+        for file in files {
+            let collection = Self::open_any(file)?;
+            prompts.extend(collection.prompts);
+            workflows.extend(collection.workflows);
+            chains.extend(collection.chains);
+        }
+        let collection = PromptCollection { prompts, workflows, chains };
+        collection.check_duplicates()?;
+        Ok(collection)
+    }
+    /// Appends the prompts, workflows and chains of `other`, failing if any
+    /// prompt name would be defined twice.
+    pub fn merge(&mut self, other: PromptCollection) -> Result<(), DuplicatePrompts> {
+        let mut merged = self.clone();
+        merged.prompts.extend(other.prompts);
+        merged.workflows.extend(other.workflows);
+        merged.chains.extend(other.chains);
+        merged.check_duplicates()?;
+        *self = merged;
+        Ok(())
+    }
+    pub(crate) fn check_duplicates(&self) -> Result<(), DuplicatePrompts> {
+        type Key<'a> = (&'a str, Option<&'a semver::Version>, Option<&'a str>);
+        let mut sources: BTreeMap<Key, Vec<Option<PathBuf>>> = BTreeMap::default();
+        for prompt in self.prompts.iter() {
+            if let Some(name) = prompt.name.as_ref() {
+                let key = (name.as_str(), prompt.version.as_ref(), prompt.variant.as_deref());
+                sources.entry(key).or_default().push(prompt.source_file.clone());
+            }
+        }
+        let conflicts = sources
+            .into_iter()
+            .filter(|(_, files)| files.len() > 1)
+            .map(|((name, version, variant), files)| {
+                let mut label = name.to_string();
+                if let Some(version) = version {
+                    label.push_str(&format!("@{version}"));
+                }
+                if let Some(variant) = variant {
+                    label.push_str(&format!(" (variant {variant:?})"));
+                }
+                (label, files)
+            })
+            .collect::<Vec<_>>();
+        if conflicts.is_empty() {
+            return Ok(())
+        }
+        Err(DuplicatePrompts(conflicts))
+    }
+    /// `${NAME}` in `<prompt>` attributes is replaced with the environment
+    /// variable's value, or with `default` for `${NAME:-default}`; an unset
+    /// variable without a default fails with [`UnsetEnvVar`].
     pub fn parse(contents: impl AsRef<str>) -> Result<Self, Box<dyn std::error::Error>> {
-        // let contents = std::fs::read_to_string(file_path.as_ref());
-        let source = contents.as_ref();
-        let html = scraper::Html::parse_fragment(source);
-        let selector = scraper::Selector::parse("prompt").unwrap();
-        let prompts = html
-            .select(&selector)
-            .filter_map(process_prompt_element)
+        Self::parse_with(contents, &Normalization::default())
+    }
+    /// Like [`PromptCollection::parse`], but with control over how message
+    /// bodies are cleaned up.
+    pub fn parse_with(contents: impl AsRef<str>, normalization: &Normalization) -> Result<Self, api::Error> {
+        let nodes = xml::parse_fragment(contents.as_ref())?;
+        let elements = xml::descendants(&nodes);
+        let mut context = ParseContext { normalization: normalization.clone(), blocks: Blocks::default() };
+        for element in elements.iter().filter(|x| x.name == "block") {
+            process_block_element(element, &mut context);
+        }
+        let mut prompts = Vec::default();
+        for element in elements.iter().filter(|x| x.name == "prompt") {
+            let element = interpolate_attributes(element)?;
+            prompts.extend(process_prompt_element(&element, &context));
+        }
+        let workflows = elements
+            .iter()
+            .filter(|x| x.name == "workflow")
+            .map(|element| process_workflow_element(element))
+            .collect::<Vec<_>>();
+        let chains = elements
+            .iter()
+            .filter(|x| x.name == "chain")
+            .map(|element| process_chain_element(element))
             .collect::<Vec<_>>();
-        Ok(PromptCollection { prompts })
+        Ok(PromptCollection { prompts, workflows, chains })
+    }
+    pub fn open_with(file_path: impl AsRef<Path>, normalization: &Normalization) -> Result<Self, api::Error> {
+        let file_path = file_path.as_ref();
+        let source = std::fs::read_to_string(file_path)?;
+        let collection = Self::parse_with(source, normalization).map_err(|error| {
+            let error = match error.downcast::<UnsetEnvVar>() {
+                Ok(error) => return Box::new(error.in_file(file_path)) as api::Error,
+                Err(error) => error,
+            };
+            match error.downcast::<xml::XmlError>() {
+                Ok(error) => Box::new(error.in_file(file_path)),
+                Err(error) => error,
+            }
+        })?;
+        Ok(collection.with_source_file(file_path))
+    }
+    pub fn prompts(&self) -> &[Prompt] {
+        &self.prompts
+    }
+    /// Prompts whose metadata includes the given tag.
+    pub fn filter_by_tag(&self, tag: impl AsRef<str>) -> PromptCollection {
+        let tag = tag.as_ref();
+        let prompts = self.prompts
+            .iter()
+            .filter(|prompt| prompt.metadata.tags.iter().any(|x| x == tag))
+            .cloned()
+            .collect();
+        PromptCollection { prompts, workflows: self.workflows.clone(), chains: self.chains.clone() }
+    }
+    pub fn workflows(&self) -> &[Workflow] {
+        &self.workflows
+    }
+    pub fn workflow(&self, workflow_name: impl AsRef<str>) -> Option<&Workflow> {
+        self.workflows.iter().find(|x| x.name.as_deref() == Some(workflow_name.as_ref()))
     }
+    pub fn chains(&self) -> &[Chain] {
+        &self.chains
+    }
+    pub fn chain(&self, chain_name: impl AsRef<str>) -> Option<&Chain> {
+        self.chains.iter().find(|x| x.name.as_deref() == Some(chain_name.as_ref()))
+    }
+    /// Returns the prompt with the given name, preferring the highest version
+    /// when there are several.
     pub fn get(&self, prompt_name: impl AsRef<str>) -> Option<Prompt> {
         let target = prompt_name.as_ref();
-        for prompt in self.prompts.iter() {
-            if let Some(name) = prompt.name.as_ref() {
-                if name == &target {
-                    return Some(prompt.clone());
-                }
+        self.prompts
+            .iter()
+            .filter(|prompt| prompt.name.as_deref() == Some(target))
+            .max_by(|a, b| a.version.cmp(&b.version))
+            .cloned()
+    }
+    /// Returns the highest version of the named prompt that satisfies the
+    /// given semver requirement, e.g. `"3"`, `"^2.1"` or `"=1.0.0"`.
+    pub fn get_version(
+        &self,
+        prompt_name: impl AsRef<str>,
+        requirement: impl AsRef<str>,
+    ) -> Result<Option<Prompt>, api::Error> {
+        let target = prompt_name.as_ref();
+        let requirement = semver::VersionReq::parse(requirement.as_ref())?;
+        let prompt = self.prompts
+            .iter()
+            .filter(|prompt| prompt.name.as_deref() == Some(target))
+            .filter(|prompt| prompt.version.as_ref().is_some_and(|x| requirement.matches(x)))
+            .max_by(|a, b| a.version.cmp(&b.version))
+            .cloned();
+        Ok(prompt)
+    }
+}
+
+impl PromptCollection {
+    /// Serializes every prompt, workflow and chain back into the XML DSL.
+    pub fn to_xml(&self) -> String {
+        self.prompts
+            .iter()
+            .map(Prompt::to_xml)
+            .chain(self.workflows.iter().map(Workflow::to_xml))
+            .chain(self.chains.iter().map(Chain::to_xml))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Workflow {
+    /// Serializes this workflow back into the XML DSL.
+    pub fn to_xml(&self) -> String {
+        let mut attributes = Vec::<(&str, String)>::default();
+        push_attr(&mut attributes, "name", self.name.as_ref());
+        let mut output = format!("<workflow{}>\n", format_attributes(&attributes));
+        for node in self.nodes.iter() {
+            let mut attributes = vec![("id", node.id.clone()), ("prompt", node.prompt.clone())];
+            if node.output == StageOutput::Json {
+                attributes.push(("output", String::from("json")));
+            }
+            if node.inputs.is_empty() {
+                output.push_str(&format!("{INDENT}<node{}/>\n", format_attributes(&attributes)));
+                continue
             }
+            output.push_str(&format!("{INDENT}<node{}>\n", format_attributes(&attributes)));
+            for input in node.inputs.iter() {
+                let attributes = [("var", input.var.clone()), ("from", input.from.clone())];
+                output.push_str(&format!("{INDENT}{INDENT}<input{}/>\n", format_attributes(&attributes)));
+            }
+            output.push_str(&format!("{INDENT}</node>\n"));
         }
-        None
+        output.push_str("</workflow>\n");
+        output
+    }
+}
+
+impl Chain {
+    /// Serializes this chain back into the XML DSL.
+    pub fn to_xml(&self) -> String {
+        let mut attributes = Vec::<(&str, String)>::default();
+        push_attr(&mut attributes, "name", self.name.as_ref());
+        let mut output = format!("<chain{}>\n", format_attributes(&attributes));
+        for step in self.steps.iter() {
+            let mut attributes = vec![("prompt", step.prompt.clone())];
+            if step.var != step.prompt {
+                attributes.push(("var", step.var.clone()));
+            }
+            if step.output == StageOutput::Json {
+                attributes.push(("output", String::from("json")));
+            }
+            output.push_str(&format!("{INDENT}<step{}/>\n", format_attributes(&attributes)));
+        }
+        output.push_str("</chain>\n");
+        output
+    }
+}
+
+impl Prompt {
+    /// Serializes this prompt back into the XML DSL.
+    ///
+    /// Message bodies are written verbatim, since they are stored as the
+    /// markup that appeared between the `<message>` tags.
+    pub fn to_xml(&self) -> String {
+        let mut attributes = Vec::<(&str, String)>::default();
+        let configuration = &self.configuration;
+        if let Some(name) = self.name.as_ref() {
+            attributes.push(("name", name.clone()));
+        }
+        push_attr(&mut attributes, "version", self.version.as_ref());
+        push_attr(&mut attributes, "variant", self.variant.as_ref());
+        push_attr(&mut attributes, "weight", self.weight);
+        push_attr(&mut attributes, "endpoint", self.endpoint.as_ref());
+        push_attr(&mut attributes, "base-url", self.base_url.as_ref());
+        if let Some(model) = configuration.model.as_ref() {
+            attributes.push(("model", model.clone()));
+        }
+        push_attr(&mut attributes, "fallback", (!self.fallbacks.is_empty()).then(|| self.fallbacks.join(", ")));
+        push_attr(&mut attributes, "stream", configuration.stream);
+        push_attr(&mut attributes, "temperature", configuration.temperature);
+        push_attr(&mut attributes, "n", configuration.n);
+        push_attr(&mut attributes, "max-tokens", configuration.max_tokens);
+        push_attr(&mut attributes, "top-p", configuration.top_p);
+        push_attr(&mut attributes, "frequency-penalty", configuration.frequency_penalty);
+        push_attr(&mut attributes, "presence-penalty", configuration.presence_penalty);
+        push_attr(&mut attributes, "logprobs", configuration.logprobs);
+        push_attr(&mut attributes, "top-logprobs", configuration.top_logprobs);
+        push_attr(&mut attributes, "stop", configuration.stop.as_deref().filter(|x| !x.is_empty()).map(format_stop));
+        push_attr(&mut attributes, "seed", configuration.seed);
+        if let Some(response_format) = configuration.response_format.as_ref() {
+            // JSON schemas are set programmatically and have no DSL form.
+            let value = match response_format.response_type() {
+                api::ResponseType::Text => Some("text"),
+                api::ResponseType::JsonObject => Some("json-object"),
+                api::ResponseType::JsonSchema => None,
+            };
+            push_attr(&mut attributes, "response-format", value);
+        }
+        let mut output = format!("<prompt{}>\n", format_attributes(&attributes));
+        write_metadata(&self.metadata, &mut output);
+        write_params(&self.params, &mut output);
+        write_message_nodes(&self.messages, 1, &mut output);
+        for assertion in self.assertions.iter() {
+            output.push_str(&format!("{INDENT}<assert{}/>\n", format_attributes(&assertion.attributes())));
+        }
+        output.push_str("</prompt>\n");
+        output
     }
 }
 
@@ -58,14 +530,95 @@ impl Prompt {
             .ok_or(Box::new(PromptNotFound(prompt_name.to_string())))?;
         Ok(prompt)
     }
+    /// The body with message contents as written, without rendering: any
+    /// `{{ ... }}` is sent verbatim and `<for-each>` bodies, having no data
+    /// to iterate over, are left out. Use [`Prompt::render_body`] to fill
+    /// them in.
     pub fn build_body(&self) -> Option<api::ChatCompletionsBody> {
-        self.configuration.clone().build(self.messages.clone())
+        self.effective_configuration().build(self.messages())
+    }
+    /// The top-level `<message>` elements as written, leaving out
+    /// `<for-each>` bodies.
+    pub fn messages(&self) -> Vec<api::Message> {
+        self.messages
+            .iter()
+            .filter_map(|node| match node {
+                MessageNode::Message(message) => Some(message.clone()),
+                MessageNode::ForEach(_) => None,
+            })
+            .collect()
     }
     pub fn request_builder(&self) -> Option<ChatCompletionsRequestBuilder> {
         let body = self.build_body()?;
-        let builder = ChatCompletionsRequestBuilder::default().with_body(body);
+        let builder = self.empty_request_builder().with_body(body);
         Some(builder)
     }
+    /// The endpoint declared by the `endpoint` or `base-url` attributes, with
+    /// an empty API key; supply one with
+    /// [`ChatCompletionsRequestBuilder::with_api_key`].
+    pub fn api_endpoint(&self) -> Option<api::ApiEndpoint> {
+        if let Some(base_url) = self.base_url.as_ref() {
+            return Some(api::ApiEndpoint::from_base_url(base_url, ""))
+        }
+        api::ApiEndpoint::for_provider(self.endpoint.as_ref()?, "")
+    }
+    fn empty_request_builder(&self) -> ChatCompletionsRequestBuilder {
+        ChatCompletionsRequestBuilder {
+            api_endpoint: self.api_endpoint(),
+            prompt_name: self.name.clone(),
+            fallbacks: self.fallbacks.iter().map(api::Fallback::new).collect(),
+            ..Default::default()
+        }
+    }
+    /// Expands `<for-each>` nodes and renders each message body as a liquid
+    /// template against the given globals, after checking them against any
+    /// `<params>` declarations.
+    pub fn render(&self, globals: &liquid::Object) -> Result<Vec<api::Message>, api::Error> {
+        let parser = liquid::ParserBuilder::with_stdlib().build()?;
+        let globals = self.bind_params(globals)?;
+        let mut messages = Vec::default();
+        let base_dir = self.source_file.as_ref().and_then(|x| x.parent());
+        render_nodes(&parser, &self.messages, &globals, base_dir, &mut messages)?;
+        Ok(messages)
+    }
+    /// Layers programmatic changes over the parsed configuration, e.g.
+    /// `prompt.with_overrides(|cfg| cfg.with_temperature(0.9))`.
+    ///
+    /// Overrides accumulate across calls and leave `configuration` (and so
+    /// [`Prompt::to_xml`]) untouched.
+    pub fn with_overrides(
+        mut self,
+        f: impl FnOnce(api::ConfigurationBuilder) -> api::ConfigurationBuilder,
+    ) -> Self {
+        self.overrides = f(self.overrides);
+        self
+    }
+    pub fn override_model(self, model: impl AsRef<str>) -> Self {
+        self.with_overrides(|cfg| cfg.with_model(model))
+    }
+    /// The parsed configuration with any overrides applied.
+    pub fn effective_configuration(&self) -> api::ConfigurationBuilder {
+        self.configuration.clone().merge(self.overrides.clone())
+    }
+    /// Root names of the variables this prompt reads from the render-time
+    /// globals; `<for-each>` loop variables are not included.
+    pub fn variables(&self) -> BTreeSet<String> {
+        let mut variables = BTreeSet::default();
+        collect_variables(&self.messages, &mut Vec::default(), &mut variables);
+        variables
+    }
+    pub fn render_body(&self, globals: &liquid::Object) -> Result<api::ChatCompletionsBody, api::Error> {
+        let messages = self.render(globals)?;
+        let body = self.effective_configuration()
+            .build(messages)
+            .ok_or(Box::new(MissingModel(self.name.clone())))?;
+        Ok(body)
+    }
+    pub fn render_request_builder(&self, globals: &liquid::Object) -> Result<ChatCompletionsRequestBuilder, api::Error> {
+        let body = self.render_body(globals)?;
+        let builder = self.empty_request_builder().with_body(body);
+        Ok(builder)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -77,71 +630,1182 @@ impl std::fmt::Display for PromptNotFound {
 }
 impl std::error::Error for PromptNotFound {}
 
+/// Each conflicting prompt name, along with every file that defines it.
+#[derive(Debug, Clone)]
+pub struct DuplicatePrompts(pub Vec<(String, Vec<Option<PathBuf>>)>);
+impl std::fmt::Display for DuplicatePrompts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Duplicate prompt names:")?;
+        for (index, (name, files)) in self.0.iter().enumerate() {
+            let files = files
+                .iter()
+                .map(|file| match file {
+                    Some(file) => file.display().to_string(),
+                    None => String::from("<inline>"),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let separator = if index == 0 { "" } else { ";" };
+            write!(f, "{separator} {:?} ({})", name, files)?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for DuplicatePrompts {}
+
+#[derive(Debug, Clone)]
+pub struct MissingModel(pub Option<String>);
+impl std::fmt::Display for MissingModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0.as_ref() {
+            Some(name) => write!(f, "Prompt {:?} does not specify a model.", name),
+            None => write!(f, "Prompt does not specify a model."),
+        }
+    }
+}
+impl std::error::Error for MissingModel {}
+
+#[derive(Debug, Clone)]
+pub struct UnsetEnvVar {
+    pub variable: String,
+    pub attribute: String,
+    pub prompt: Option<String>,
+    pub source_file: Option<PathBuf>,
+    pub position: xml::Position,
+}
+impl UnsetEnvVar {
+    pub fn in_file(mut self, file_path: impl AsRef<Path>) -> Self {
+        self.source_file = Some(file_path.as_ref().to_path_buf());
+        self
+    }
+}
+impl std::fmt::Display for UnsetEnvVar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let location = match self.source_file.as_ref() {
+            Some(path) => format!("{}:{}", path.display(), self.position),
+            None => self.position.to_string(),
+        };
+        let prompt = self.prompt.as_ref().map(|x| format!(" of prompt {x:?}")).unwrap_or_default();
+        write!(
+            f,
+            "Cannot load prompts: environment variable {} is not set, but attribute '{}'{prompt} at {location} uses it.",
+            self.variable,
+            self.attribute,
+        )
+    }
+}
+impl std::error::Error for UnsetEnvVar {}
+
+#[derive(Debug, Clone)]
+pub struct UnresolvedVariable(pub String);
+impl std::fmt::Display for UnresolvedVariable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cannot resolve variable: {:?}.", self.0)
+    }
+}
+impl std::error::Error for UnresolvedVariable {}
+
+#[derive(Debug, Clone)]
+pub struct UnsupportedImage(pub PathBuf);
+impl std::fmt::Display for UnsupportedImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unsupported image type: {:?}.", self.0)
+    }
+}
+impl std::error::Error for UnsupportedImage {}
+
+#[derive(Debug, Clone)]
+pub struct NotAList(pub String);
+impl std::fmt::Display for NotAList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Variable {:?} is not a list.", self.0)
+    }
+}
+impl std::error::Error for NotAList {}
+
 
 
 //―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
 // TODO
 //―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
-fn process_prompt_element(element: scraper::ElementRef) -> Option<Prompt> {
+pub(crate) const PROMPT_ATTRIBUTES: &[&str] = &[
+    "name",
+    "version",
+    "variant",
+    "weight",
+    "endpoint",
+    "base-url",
+    "model",
+    "fallback",
+    "stream",
+    "temperature",
+    "n",
+    "max-tokens",
+    "top-p",
+    "frequency-penalty",
+    "presence-penalty",
+    "logprobs",
+    "top-logprobs",
+    "stop",
+    "seed",
+    "response-format",
+];
+pub(crate) const MESSAGE_ATTRIBUTES: &[&str] = &["role", "name"];
+pub(crate) const IMAGE_ATTRIBUTES: &[&str] = &["src", "url", "detail"];
+pub(crate) const FOR_EACH_ATTRIBUTES: &[&str] = &["var", "in"];
+pub(crate) const META_ATTRIBUTES: &[&str] = &["name", "content"];
+pub(crate) const PARAMS_ATTRIBUTES: &[&str] = &[];
+pub(crate) const PARAM_ATTRIBUTES: &[&str] = &["name", "type", "required", "default"];
+pub(crate) const ASSERT_ATTRIBUTES: &[&str] = &["contains", "not-contains", "matches", "equals", "json-path"];
+pub(crate) const BLOCK_ATTRIBUTES: &[&str] = &["name"];
+pub(crate) const USE_ATTRIBUTES: &[&str] = &["block"];
+pub(crate) const WORKFLOW_ATTRIBUTES: &[&str] = &["name"];
+pub(crate) const NODE_ATTRIBUTES: &[&str] = &["id", "prompt", "output"];
+pub(crate) const INPUT_ATTRIBUTES: &[&str] = &["var", "from"];
+pub(crate) const CHAIN_ATTRIBUTES: &[&str] = &["name"];
+pub(crate) const STEP_ATTRIBUTES: &[&str] = &["prompt", "var", "output"];
+
+/// Extensions of the files [`PromptCollection::open_dir`] loads.
+pub const PROMPT_EXTENSIONS: &[&str] = &["xml", "liquid", "yaml", "yml", "json"];
+
+/// Whether `path` has one of the [`PROMPT_EXTENSIONS`]. The contents are
+/// not looked at.
+pub fn is_prompt_file(path: &Path) -> bool {
+    path.extension().is_some_and(|x| PROMPT_EXTENSIONS.iter().any(|e| x == *e))
+}
+
+/// `dir` as a glob pattern that matches only itself, so a directory named
+/// e.g. `prompts[v2]` is not read as a character class.
+pub fn escape_glob(dir: &Path) -> PathBuf {
+    PathBuf::from(glob::Pattern::escape(&dir.to_string_lossy()))
+}
+
+/// The files matching a glob pattern, sorted.
+fn glob_files(pattern: &Path) -> Result<Vec<PathBuf>, api::Error> {
+    let mut files = glob::glob(&pattern.to_string_lossy())?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+/// Named message snippets declared with `<block name="...">`, keyed by name.
+type Blocks = BTreeMap<String, Block>;
+
+#[derive(Debug, Clone, Default)]
+struct Block {
+    nodes: Vec<MessageNode>,
+    /// Reported on every prompt that uses the block.
+    diagnostics: Vec<Diagnostic>,
+}
+
+struct ParseContext {
+    normalization: Normalization,
+    blocks: Blocks,
+}
+
+/// Blocks may `<use>` any block declared before them.
+fn process_block_element(element: &xml::Element, context: &mut ParseContext) {
+    let mut diagnostics = Vec::default();
+    check_attributes(element, BLOCK_ATTRIBUTES, &mut diagnostics);
+    let Some(name) = element.attr("name") else {
+        return
+    };
+    let nodes = process_message_nodes(element, context, &mut diagnostics);
+    context.blocks.insert(name.to_string(), Block { nodes, diagnostics });
+}
+
+fn process_prompt_element(element: &xml::Element, context: &ParseContext) -> Option<Prompt> {
+    let mut diagnostics = Vec::default();
+    check_attributes(element, PROMPT_ATTRIBUTES, &mut diagnostics);
     let name = element.attr("name")
         .map(str::to_string);
+    let version = element.attr("version")
+        .and_then(|x| {
+            let version = parse_version(x);
+            if version.is_none() {
+                diagnostics.push(Diagnostic::invalid_value("version", x).at(element.position));
+            }
+            version
+        });
+    let variant = element.attr("variant")
+        .map(str::to_string);
+    let weight = parse_attr::<f32>(element, "weight", &mut diagnostics)
+        .filter(|x| {
+            if *x < 0.0 {
+                diagnostics.push(Diagnostic::invalid_value("weight", &x.to_string()).at(element.position));
+            }
+            *x >= 0.0
+        });
+    let endpoint = element.attr("endpoint")
+        .map(str::to_string);
+    if let Some(endpoint) = endpoint.as_ref() {
+        if api::ApiEndpoint::for_provider(endpoint, "").is_none() {
+            diagnostics.push(Diagnostic::invalid_value("endpoint", endpoint).at(element.position));
+        }
+    }
+    let base_url = element.attr("base-url")
+        .map(str::to_string);
     let model = element.attr("model")
         .map(str::to_string);
-    let stream = element.attr("stream")
-        .and_then(|x| bool::from_str(&x).ok());
-    let temperature = element.attr("temperature")
-        .and_then(|x| f32::from_str(&x).ok());
-    let n: Option<usize> = element.attr("n")
-        .and_then(|x| usize::from_str(&x).ok());
-    let max_tokens = element.attr("max-tokens")
-        .and_then(|x| usize::from_str(&x).ok());
-    let top_p = element.attr("top-p")
-        .and_then(|x| f32::from_str(&x).ok());
-    let frequency_penalty = element.attr("frequency-penalty")
-        .and_then(|x| f32::from_str(&x).ok());
-    let presence_penalty = element.attr("presence-penalty")
-        .and_then(|x| f32::from_str(&x).ok());
-    let logprobs = element.attr("logprobs")
-        .and_then(|x| bool::from_str(&x).ok());
-    let top_logprobs = element.attr("top-logprobs")
-        .and_then(|x| usize::from_str(&x).ok());
+    let fallbacks = element.attr("fallback")
+        .map(|x| {
+            x.split(',')
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let stream = parse_attr::<bool>(element, "stream", &mut diagnostics);
+    let temperature = parse_attr::<f32>(element, "temperature", &mut diagnostics);
+    let n = parse_attr::<usize>(element, "n", &mut diagnostics);
+    let max_tokens = parse_attr::<usize>(element, "max-tokens", &mut diagnostics);
+    let top_p = parse_attr::<f32>(element, "top-p", &mut diagnostics);
+    let frequency_penalty = parse_attr::<f32>(element, "frequency-penalty", &mut diagnostics);
+    let presence_penalty = parse_attr::<f32>(element, "presence-penalty", &mut diagnostics);
+    let logprobs = parse_attr::<bool>(element, "logprobs", &mut diagnostics);
+    let top_logprobs = parse_attr::<usize>(element, "top-logprobs", &mut diagnostics);
     let response_format = element
         .attr("response-format")
         .and_then(|x| {
-            match x.to_lowercase().as_str() {
-                "json-object" => Some(api::ResponseFormat::json_object()),
-                "json_object" => Some(api::ResponseFormat::json_object()),
-                "text" => Some(api::ResponseFormat::text()),
-                _ => None
+            let format = parse_response_format(x);
+            if format.is_none() {
+                diagnostics.push(Diagnostic::invalid_value("response-format", x).at(element.position));
+            }
+            format
+        });
+    let stop = element
+        .attr("stop")
+        .and_then(|x| {
+            let stop = parse_stop(x);
+            if stop.is_none() {
+                diagnostics.push(Diagnostic::invalid_value("stop", x).at(element.position));
             }
+            stop
         });
-    // let stop = element.attr("stop").map(str::to_string);
+    let seed = parse_attr::<isize>(element, "seed", &mut diagnostics);
     // - * -
-    let mut configuration = api::ConfigurationBuilder::default();
-    configuration.model = model;
-    configuration.stream = stream;
-    configuration.temperature = temperature;
-    configuration.n = n;
-    configuration.max_tokens = max_tokens;
-    configuration.top_p = top_p;
-    configuration.frequency_penalty = frequency_penalty;
-    configuration.presence_penalty = presence_penalty;
-    configuration.logprobs = logprobs;
-    configuration.top_logprobs = top_logprobs;
-    configuration.response_format = response_format;
+    let configuration = api::ConfigurationBuilder {
+        model,
+        stream,
+        temperature,
+        n,
+        max_tokens,
+        top_p,
+        frequency_penalty,
+        presence_penalty,
+        logprobs,
+        top_logprobs,
+        response_format,
+        stop,
+        seed,
+    };
     // - * -
-    let message_selector = scraper::Selector::parse("message").unwrap();
-    let messages = element
-        .select(&message_selector)
-        .map(|message_element| {
-            let role = message_element.attr("role").unwrap_or("user");
-            let role = api::Role::from(role).unwrap();
-            let content = message_element.inner_html().trim().to_string();
-            let content = unindent::unindent(&content);
-            api::Message{role, content}
-        })
-        .collect::<Vec<_>>();
+    let metadata = process_meta_elements(element, &mut diagnostics);
+    let params = process_params_elements(element, &mut diagnostics);
+    let messages = process_message_nodes(element, context, &mut diagnostics);
+    let mut assertions = Vec::default();
+    for child in element.elements().filter(|x| x.name == "assert") {
+        check_attributes(child, ASSERT_ATTRIBUTES, &mut diagnostics);
+        match Assertion::from_element(child) {
+            Ok(assertion) => assertions.push(assertion),
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        }
+    }
     // - * -
-    let prompt = Prompt { name, configuration, messages };
+    let prompt = Prompt {
+        name,
+        version,
+        variant,
+        weight,
+        endpoint,
+        base_url,
+        fallbacks,
+        source_file: None,
+        configuration,
+        overrides: Default::default(),
+        metadata,
+        params,
+        messages,
+        assertions,
+        diagnostics,
+    };
     Some(prompt)
 }
+fn process_workflow_element(element: &xml::Element) -> Workflow {
+    let mut diagnostics = Vec::default();
+    check_attributes(element, WORKFLOW_ATTRIBUTES, &mut diagnostics);
+    let name = element.attr("name").map(str::to_string);
+    let mut nodes = Vec::default();
+    for child in element.elements().filter(|x| x.name == "node") {
+        check_attributes(child, NODE_ATTRIBUTES, &mut diagnostics);
+        let (Some(id), Some(prompt)) = (child.attr("id"), child.attr("prompt")) else {
+            let missing = if child.attr("id").is_none() { "id" } else { "prompt" };
+            diagnostics.push(Diagnostic::missing_attribute("node", missing).at(child.position));
+            continue
+        };
+        let output = parse_stage_output(child, &mut diagnostics);
+        let mut inputs = Vec::default();
+        for input in child.elements().filter(|x| x.name == "input") {
+            check_attributes(input, INPUT_ATTRIBUTES, &mut diagnostics);
+            let Some(from) = input.attr("from") else {
+                diagnostics.push(Diagnostic::missing_attribute("input", "from").at(input.position));
+                continue
+            };
+            let var = input.attr("var").unwrap_or(from).to_string();
+            inputs.push(WorkflowInput { var, from: from.to_string() });
+        }
+        let node = WorkflowNode {
+            id: id.to_string(),
+            prompt: prompt.to_string(),
+            output,
+            inputs,
+            position: Some(child.position),
+        };
+        nodes.push(node);
+    }
+    Workflow { name, nodes, source_file: None, diagnostics }
+}
+fn process_chain_element(element: &xml::Element) -> Chain {
+    let mut diagnostics = Vec::default();
+    check_attributes(element, CHAIN_ATTRIBUTES, &mut diagnostics);
+    let name = element.attr("name").map(str::to_string);
+    let mut steps = Vec::default();
+    for child in element.elements().filter(|x| x.name == "step") {
+        check_attributes(child, STEP_ATTRIBUTES, &mut diagnostics);
+        let Some(prompt) = child.attr("prompt") else {
+            diagnostics.push(Diagnostic::missing_attribute("step", "prompt").at(child.position));
+            continue
+        };
+        let step = ChainStep {
+            prompt: prompt.to_string(),
+            var: child.attr("var").unwrap_or(prompt).to_string(),
+            output: parse_stage_output(child, &mut diagnostics),
+            position: Some(child.position),
+        };
+        steps.push(step);
+    }
+    Chain { name, steps, source_file: None, diagnostics }
+}
+fn parse_stage_output(element: &xml::Element, diagnostics: &mut Vec<Diagnostic>) -> StageOutput {
+    match element.attr("output").unwrap_or("text") {
+        "text" => StageOutput::Text,
+        "json" => StageOutput::Json,
+        other => {
+            diagnostics.push(Diagnostic::invalid_value("output", other).at(element.position));
+            StageOutput::Text
+        }
+    }
+}
+fn process_message_nodes(
+    element: &xml::Element,
+    context: &ParseContext,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<MessageNode> {
+    let mut nodes = Vec::default();
+    for child in element.elements() {
+        match child.name.as_str() {
+            "message" => {
+                check_attributes(child, MESSAGE_ATTRIBUTES, diagnostics);
+                let role = child.attr("role").unwrap_or("user");
+                let role = role.parse::<api::Role>().unwrap_or_else(|_| {
+                    diagnostics.push(Diagnostic::invalid_value("role", role).at(child.position));
+                    api::Role::User
+                });
+                let mut content = String::default();
+                let mut images = Vec::default();
+                for node in child.children.iter() {
+                    match node {
+                        xml::Node::Text(text) => content.push_str(text),
+                        xml::Node::CData(text) => content.push_str(text),
+                        xml::Node::Element(image) if image.name == "image" => {
+                            check_attributes(image, IMAGE_ATTRIBUTES, diagnostics);
+                            let Some(url) = image.attr("src").or(image.attr("url")) else {
+                                diagnostics.push(Diagnostic::missing_attribute("image", "src").at(image.position));
+                                continue
+                            };
+                            let detail = image.attr("detail").and_then(|x| {
+                                let detail = parse_image_detail(x);
+                                if detail.is_none() {
+                                    diagnostics.push(Diagnostic::invalid_value("detail", x).at(image.position));
+                                }
+                                detail
+                            });
+                            images.push(api::ImageUrl { url: url.to_string(), detail });
+                        }
+                        xml::Node::Element(element) => content.push_str(&element.raw),
+                    }
+                }
+                let content = context.normalization.apply(&content);
+                let name = child.attr("name").map(str::to_string);
+                if let Some(name) = name.as_ref().filter(|x| !is_valid_message_name(x)) {
+                    diagnostics.push(Diagnostic::invalid_value("name", name).at(child.position));
+                }
+                nodes.push(MessageNode::Message(api::Message { images, name, ..api::Message::new(role, content) }));
+            }
+            "for-each" => {
+                check_attributes(child, FOR_EACH_ATTRIBUTES, diagnostics);
+                let var = child.attr("var").unwrap_or("item").to_string();
+                let source = child.attr("in").unwrap_or_else(|| {
+                    diagnostics.push(Diagnostic::missing_attribute("for-each", "in").at(child.position));
+                    ""
+                });
+                let source = source.to_string();
+                let children = process_message_nodes(child, context, diagnostics);
+                nodes.push(MessageNode::ForEach(ForEach { var, source, children }));
+            }
+            "use" => {
+                check_attributes(child, USE_ATTRIBUTES, diagnostics);
+                match child.attr("block") {
+                    Some(name) => match context.blocks.get(name) {
+                        Some(block) => {
+                            nodes.extend(block.nodes.iter().cloned());
+                            diagnostics.extend(block.diagnostics.iter().cloned());
+                        }
+                        None => diagnostics.push(Diagnostic::unknown_block(name).at(child.position)),
+                    },
+                    None => diagnostics.push(Diagnostic::missing_attribute("use", "block").at(child.position)),
+                }
+            }
+            "meta" | "params" | "assert" => {}
+            _ => nodes.extend(process_message_nodes(child, context, diagnostics)),
+        }
+    }
+    nodes
+}
+/// Parses a semver version, padding partial versions like `3` or `3.1`.
+pub(crate) fn parse_version(value: &str) -> Option<semver::Version> {
+    let value = value.trim().trim_start_matches('v');
+    let padding = match value.split('.').count() {
+        1 => ".0.0",
+        2 => ".0",
+        _ => "",
+    };
+    semver::Version::parse(&format!("{value}{padding}")).ok()
+}
+/// Templated names are checked once rendered, by the API.
+fn is_valid_message_name(value: &str) -> bool {
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+    value.contains("{{") || (!value.is_empty() && value.len() <= 64 && value.chars().all(valid))
+}
+pub(crate) fn parse_image_detail(value: &str) -> Option<api::ImageDetail> {
+    match value.to_lowercase().as_str() {
+        "auto" => Some(api::ImageDetail::Auto),
+        "low" => Some(api::ImageDetail::Low),
+        "high" => Some(api::ImageDetail::High),
+        _ => None
+    }
+}
+pub(crate) fn parse_response_format(value: &str) -> Option<api::ResponseFormat> {
+    match value.to_lowercase().as_str() {
+        "json-object" => Some(api::ResponseFormat::json_object()),
+        "json_object" => Some(api::ResponseFormat::json_object()),
+        "text" => Some(api::ResponseFormat::text()),
+        _ => None
+    }
+}
+/// A comma-separated list, or a JSON array for sequences that contain a
+/// comma or surrounding whitespace, e.g. `stop='["\n\n", "END"]'`.
+pub(crate) fn parse_stop(value: &str) -> Option<Vec<String>> {
+    if value.trim_start().starts_with('[') {
+        return serde_json::from_str(value).ok()
+    }
+    let stop = value
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    (!stop.is_empty()).then_some(stop)
+}
+fn process_meta_elements(element: &xml::Element, diagnostics: &mut Vec<Diagnostic>) -> PromptMetadata {
+    let mut metadata = PromptMetadata::default();
+    for meta in element.elements().filter(|child| child.name == "meta") {
+        check_attributes(meta, META_ATTRIBUTES, diagnostics);
+        let Some(name) = meta.attr("name") else {
+            diagnostics.push(Diagnostic::missing_attribute("meta", "name").at(meta.position));
+            continue
+        };
+        let content = meta.attr("content").unwrap_or_default().trim().to_string();
+        match name {
+            "description" => metadata.description = Some(content),
+            "owner" => metadata.owner = Some(content),
+            "tags" => metadata.tags.extend(parse_tags(&content)),
+            _ => {
+                metadata.extra.insert(name.to_string(), content);
+            }
+        }
+    }
+    metadata
+}
+fn process_params_elements(element: &xml::Element, diagnostics: &mut Vec<Diagnostic>) -> Vec<ParamDeclaration> {
+    let mut params = Vec::default();
+    for block in element.elements().filter(|child| child.name == "params") {
+        check_attributes(block, PARAMS_ATTRIBUTES, diagnostics);
+        for param in block.elements().filter(|child| child.name == "param") {
+            check_attributes(param, PARAM_ATTRIBUTES, diagnostics);
+            let Some(name) = param.attr("name") else {
+                diagnostics.push(Diagnostic::missing_attribute("param", "name").at(param.position));
+                continue
+            };
+            let param_type = parse_attr::<ParamType>(param, "type", diagnostics).unwrap_or_default();
+            let declaration = ParamDeclaration {
+                name: name.to_string(),
+                param_type,
+                required: parse_attr::<bool>(param, "required", diagnostics),
+                default: param.attr("default").map(str::to_string),
+            };
+            if let Some(default) = declaration.default.as_ref().filter(|_| declaration.default_value().is_none()) {
+                diagnostics.push(Diagnostic::invalid_value("default", default).at(param.position));
+            }
+            params.push(declaration);
+        }
+    }
+    params
+}
+fn parse_tags(tags: &str) -> Vec<String> {
+    tags
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+fn parse_attr<T: FromStr>(element: &xml::Element, key: &str, diagnostics: &mut Vec<Diagnostic>) -> Option<T> {
+    let value = element.attr(key)?;
+    match T::from_str(value) {
+        Ok(value) => Some(value),
+        Err(_) => {
+            diagnostics.push(Diagnostic::invalid_value(key, value).at(element.position));
+            None
+        }
+    }
+}
+/// Substitutes environment variables into a `<prompt>` element's
+/// attributes, so one file can serve several deployments:
+///
+/// ```xml
+/// <prompt name="triage" model="${TRIAGE_MODEL:-gpt-4o-mini}" base-url="${LLM_BASE_URL}">
+/// ```
+///
+/// `$${` stands for a literal `${`.
+fn interpolate_attributes(element: &xml::Element) -> Result<xml::Element, UnsetEnvVar> {
+    let mut element = element.clone();
+    let prompt = element.attr("name").map(str::to_string);
+    for (key, value) in element.attributes.iter_mut() {
+        *value = interpolate_env(value).map_err(|variable| UnsetEnvVar {
+            variable,
+            attribute: key.clone(),
+            prompt: prompt.clone(),
+            source_file: None,
+            position: element.position,
+        })?;
+    }
+    Ok(element)
+}
+/// Replaces each `${NAME}` or `${NAME:-default}` with the variable's value.
+/// Fails with the name of the first variable that is unset or empty and has
+/// no default. Defaults may refer to variables themselves, as in
+/// `${MODEL:-${DEFAULT_MODEL}}`.
+///
+/// ```rust
+/// use chatgpt_subsystems::xml_dsl::interpolate_env;
+///
+/// assert_eq!(interpolate_env("gpt-${UNSET_MODEL:-4o}").unwrap(), "gpt-4o");
+/// assert_eq!(interpolate_env("$${HOME}").unwrap(), "${HOME}");
+/// assert_eq!(interpolate_env("abc${foo").unwrap(), "abc${foo");
+/// assert_eq!(interpolate_env("${UNSET_MODEL}").unwrap_err(), "UNSET_MODEL");
+/// ```
+pub fn interpolate_env(value: &str) -> Result<String, String> {
+    let mut output = String::default();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("${");
+            rest = &rest[start + 2..];
+            continue
+        }
+        // An unterminated `${` is left as written, with the rest of the value.
+        let Some(end) = closing_brace(rest, start + 2) else {
+            break
+        };
+        output.push_str(&rest[..start]);
+        let expression = &rest[start + 2..end];
+        let (name, default) = match expression.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expression, None),
+        };
+        match (std::env::var(name.trim()).ok().filter(|x| !x.is_empty()), default) {
+            (Some(value), _) => output.push_str(&value),
+            (None, Some(default)) => output.push_str(&interpolate_env(default)?),
+            (None, None) => return Err(name.trim().to_string()),
+        }
+        rest = &rest[end + 1..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+/// The offset of the `}` closing an expression that starts at `from`,
+/// skipping over the expressions nested in it.
+fn closing_brace(value: &str, from: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut offset = from;
+    while let Some(index) = value[offset..].find(['{', '}']).map(|x| offset + x) {
+        offset = index + 1;
+        match &value[index..=index] {
+            "{" if value[..index].ends_with('$') => depth += 1,
+            "}" if depth == 0 => return Some(index),
+            "}" => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+fn check_attributes(element: &xml::Element, known: &[&str], diagnostics: &mut Vec<Diagnostic>) {
+    for (key, _) in element.attributes.iter() {
+        if !known.contains(&key.as_str()) {
+            diagnostics.push(Diagnostic::unknown_attribute(&element.name, key).at(element.position));
+        }
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// NORMALIZATION
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+impl Default for Normalization {
+    fn default() -> Self {
+        Normalization {
+            trim: true,
+            unindent: true,
+            collapse_blank_lines: false,
+            trim_trailing_spaces: false,
+            decode_entities: false,
+        }
+    }
+}
+
+impl Normalization {
+    /// Skips every cleanup step, sending message bodies exactly as they
+    /// appear in the source file.
+    pub fn raw() -> Self {
+        Normalization {
+            trim: false,
+            unindent: false,
+            collapse_blank_lines: false,
+            trim_trailing_spaces: false,
+            decode_entities: false,
+        }
+    }
+    /// Every cleanup step enabled.
+    pub fn strict() -> Self {
+        Normalization {
+            trim: true,
+            unindent: true,
+            collapse_blank_lines: true,
+            trim_trailing_spaces: true,
+            decode_entities: true,
+        }
+    }
+    pub fn with_trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+    pub fn with_unindent(mut self, unindent: bool) -> Self {
+        self.unindent = unindent;
+        self
+    }
+    pub fn with_collapse_blank_lines(mut self, collapse_blank_lines: bool) -> Self {
+        self.collapse_blank_lines = collapse_blank_lines;
+        self
+    }
+    pub fn with_trim_trailing_spaces(mut self, trim_trailing_spaces: bool) -> Self {
+        self.trim_trailing_spaces = trim_trailing_spaces;
+        self
+    }
+    pub fn with_decode_entities(mut self, decode_entities: bool) -> Self {
+        self.decode_entities = decode_entities;
+        self
+    }
+    pub fn apply(&self, content: &str) -> String {
+        let mut content = content.to_string();
+        if self.decode_entities {
+            content = decode_entities(&content);
+        }
+        if self.trim {
+            content = content.trim().to_string();
+        }
+        if self.unindent {
+            content = unindent::unindent(&content);
+        }
+        if self.trim_trailing_spaces {
+            content = content
+                .lines()
+                .map(str::trim_end)
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        if self.collapse_blank_lines {
+            let mut lines = Vec::<&str>::default();
+            for line in content.lines() {
+                let is_blank = line.trim().is_empty();
+                if is_blank && lines.last().is_some_and(|x| x.trim().is_empty()) {
+                    continue
+                }
+                lines.push(line);
+            }
+            content = lines.join("\n");
+        }
+        content
+    }
+}
+
+fn decode_entities(content: &str) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+        match decoded {
+            Some((character, end)) => {
+                output.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => {
+            let code = entity.strip_prefix('#')?;
+            let code = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse::<u32>().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// SERIALIZATION
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+const INDENT: &str = "    ";
+
+fn push_attr(attributes: &mut Vec<(&str, String)>, key: &'static str, value: Option<impl ToString>) {
+    if let Some(value) = value {
+        attributes.push((key, value.to_string()));
+    }
+}
+fn format_attributes(attributes: &[(&str, String)]) -> String {
+    attributes
+        .iter()
+        .map(|(key, value)| format!(" {key}=\"{}\"", escape_attribute(value)))
+        .collect()
+}
+/// The inverse of [`parse_stop`].
+fn format_stop(stop: &[String]) -> String {
+    let plain = stop.iter().all(|x| !x.is_empty() && x.trim() == x && !x.contains(',') && !x.starts_with('['));
+    match plain {
+        true => stop.join(", "),
+        false => serde_json::to_string(stop).unwrap(),
+    }
+}
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+fn write_metadata(metadata: &PromptMetadata, output: &mut String) {
+    let mut entries = Vec::<(&str, String)>::default();
+    if let Some(description) = metadata.description.as_ref() {
+        entries.push(("description", description.clone()));
+    }
+    if !metadata.tags.is_empty() {
+        entries.push(("tags", metadata.tags.join(", ")));
+    }
+    if let Some(owner) = metadata.owner.as_ref() {
+        entries.push(("owner", owner.clone()));
+    }
+    for (name, content) in metadata.extra.iter() {
+        entries.push((name, content.clone()));
+    }
+    for (name, content) in entries {
+        let attributes = [("name", name.to_string()), ("content", content)];
+        output.push_str(&format!("{INDENT}<meta{}/>\n", format_attributes(&attributes)));
+    }
+}
+fn write_params(params: &[ParamDeclaration], output: &mut String) {
+    if params.is_empty() {
+        return
+    }
+    output.push_str(&format!("{INDENT}<params>\n"));
+    for param in params {
+        let mut attributes = vec![("name", param.name.clone()), ("type", param.param_type.as_str().to_string())];
+        push_attr(&mut attributes, "required", param.required);
+        push_attr(&mut attributes, "default", param.default.as_ref());
+        output.push_str(&format!("{INDENT}{INDENT}<param{}/>\n", format_attributes(&attributes)));
+    }
+    output.push_str(&format!("{INDENT}</params>\n"));
+}
+/// Content that parses as markup, as anything read from a prompt file does,
+/// is written as is. Other text, such as `a < b` set in code, is wrapped
+/// in CDATA so that it reads back unchanged.
+fn message_markup(content: &str) -> String {
+    if xml::parse_fragment(content).is_ok() {
+        return content.to_string()
+    }
+    format!("<![CDATA[{}]]>", content.replace("]]>", "]]]]><![CDATA[>"))
+}
+fn write_message_nodes(nodes: &[MessageNode], depth: usize, output: &mut String) {
+    let indent = INDENT.repeat(depth);
+    for node in nodes {
+        match node {
+            MessageNode::Message(message) => {
+                let mut attributes = vec![("role", message.role.as_str().to_string())];
+                push_attr(&mut attributes, "name", message.name.as_ref());
+                output.push_str(&format!("{indent}<message{}>\n", format_attributes(&attributes)));
+                for line in message_markup(&message.content).lines() {
+                    if line.trim().is_empty() {
+                        output.push('\n');
+                    } else {
+                        output.push_str(&format!("{indent}{INDENT}{line}\n"));
+                    }
+                }
+                for image in message.images.iter() {
+                    let is_url = ["http://", "https://", "data:"].iter().any(|x| image.url.starts_with(x));
+                    let key = if is_url { "url" } else { "src" };
+                    let mut attributes = vec![(key, image.url.clone())];
+                    if let Some(detail) = image.detail {
+                        let detail = match detail {
+                            api::ImageDetail::Auto => "auto",
+                            api::ImageDetail::Low => "low",
+                            api::ImageDetail::High => "high",
+                        };
+                        attributes.push(("detail", detail.to_string()));
+                    }
+                    output.push_str(&format!("{indent}{INDENT}<image{}/>\n", format_attributes(&attributes)));
+                }
+                output.push_str(&format!("{indent}</message>\n"));
+            }
+            MessageNode::ForEach(for_each) => {
+                let attributes = [("var", for_each.var.clone()), ("in", for_each.source.clone())];
+                output.push_str(&format!("{indent}<for-each{}>\n", format_attributes(&attributes)));
+                write_message_nodes(&for_each.children, depth + 1, output);
+                output.push_str(&format!("{indent}</for-each>\n"));
+            }
+        }
+    }
+}
+
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+// RENDERING
+//―――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――――
+fn render_nodes(
+    parser: &liquid::Parser,
+    nodes: &[MessageNode],
+    globals: &liquid::Object,
+    base_dir: Option<&Path>,
+    output: &mut Vec<api::Message>,
+) -> Result<(), api::Error> {
+    for node in nodes {
+        match node {
+            MessageNode::Message(message) => {
+                let content = parser.parse(&message.content)?.render(globals)?;
+                let images = message.images
+                    .iter()
+                    .map(|image| {
+                        let url = parser.parse(&image.url)?.render(globals)?;
+                        let url = resolve_image_url(&url, base_dir)?;
+                        Ok(api::ImageUrl { url, detail: image.detail })
+                    })
+                    .collect::<Result<Vec<_>, api::Error>>()?;
+                let name = message.name
+                    .as_ref()
+                    .map(|x| parser.parse(x)?.render(globals))
+                    .transpose()?;
+                output.push(api::Message { images, name, ..api::Message::new(message.role.clone(), content) });
+            }
+            MessageNode::ForEach(for_each) => {
+                let items = lookup(globals, &for_each.source)
+                    .ok_or(Box::new(UnresolvedVariable(for_each.source.clone())))?;
+                let items = items
+                    .as_array()
+                    .ok_or(Box::new(NotAList(for_each.source.clone())))?;
+                for item in items.values() {
+                    let mut scope = globals.clone();
+                    scope.insert(for_each.var.clone().into(), item.to_value());
+                    render_nodes(parser, &for_each.children, &scope, base_dir, output)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+/// Web and `data:` URLs pass through; anything else is read as a file
+/// (relative to the prompt file) and inlined as a base64 `data:` URL.
+pub(crate) fn resolve_image_url(url: &str, base_dir: Option<&Path>) -> Result<String, api::Error> {
+    use base64::Engine;
+    if ["http://", "https://", "data:"].iter().any(|x| url.starts_with(x)) {
+        return Ok(url.to_string())
+    }
+    let path = match base_dir {
+        Some(base_dir) => base_dir.join(url),
+        None => PathBuf::from(url),
+    };
+    let extension = path
+        .extension()
+        .and_then(|x| x.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let mime_type = match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        _ => return Err(Box::new(UnsupportedImage(path))),
+    };
+    let bytes = std::fs::read(&path)?;
+    let data = base64::engine::general_purpose::STANDARD.encode(bytes);
+    Ok(format!("data:{mime_type};base64,{data}"))
+}
+fn collect_variables(nodes: &[MessageNode], scope: &mut Vec<String>, output: &mut BTreeSet<String>) {
+    for node in nodes {
+        match node {
+            MessageNode::Message(message) => {
+                for variable in template_variables(&message.content) {
+                    if !scope.contains(&variable) {
+                        output.insert(variable);
+                    }
+                }
+            }
+            MessageNode::ForEach(for_each) => {
+                if let Some(root) = for_each.source.split('.').next().filter(|x| !x.is_empty()) {
+                    if !scope.iter().any(|x| x == root) {
+                        output.insert(root.to_string());
+                    }
+                }
+                scope.push(for_each.var.clone());
+                collect_variables(&for_each.children, scope, output);
+                scope.pop();
+            }
+        }
+    }
+}
+/// The leading identifier of every `{{ ... }}` expression in `template`,
+/// the list each `{% for %}` loops over, and the names tested by `if`,
+/// `elsif`, `unless` and `case`. Names bound by the template's own `for`
+/// and `assign` tags, and `{% raw %}` text, are skipped.
+pub(crate) fn template_variables(template: &str) -> Vec<String> {
+    const LITERALS: &[&str] = &["true", "false", "nil", "null", "empty", "blank", "forloop"];
+    const OPERATORS: &[&str] = &["and", "or", "contains"];
+    // Compiled once; this runs for every message of every prompt checked.
+    static RAW: OnceLock<regex::Regex> = OnceLock::new();
+    static BINDINGS: OnceLock<regex::Regex> = OnceLock::new();
+    static CONDITIONS: OnceLock<regex::Regex> = OnceLock::new();
+    static STRINGS: OnceLock<regex::Regex> = OnceLock::new();
+    let raw = RAW.get_or_init(|| {
+        regex::Regex::new(r"(?s)\{%-?\s*raw\s*-?%\}.*?\{%-?\s*endraw\s*-?%\}").unwrap()
+    });
+    let bindings = BINDINGS.get_or_init(|| {
+        regex::Regex::new(r"\{%-?\s*(?:for\s+(\w+)\s+in\s+(\w+)|assign\s+(\w+))").unwrap()
+    });
+    let conditions = CONDITIONS.get_or_init(|| {
+        regex::Regex::new(r"\{%-?\s*(?:if|elsif|unless|case)\s+(.*?)-?%\}").unwrap()
+    });
+    let strings = STRINGS.get_or_init(|| regex::Regex::new(r#""[^"]*"|'[^']*'"#).unwrap());
+    let template = raw.replace_all(template, "");
+    let mut bound = Vec::default();
+    let mut variables = Vec::default();
+    for captures in bindings.captures_iter(&template) {
+        bound.extend(captures.get(1).or(captures.get(3)).map(|x| x.as_str()));
+        variables.extend(captures.get(2).map(|x| x.as_str().to_string()));
+    }
+    for captures in conditions.captures_iter(&template) {
+        let condition = strings.replace_all(&captures[1], "");
+        let roots = condition
+            .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-' || c == '.'))
+            .filter_map(|x| x.split('.').next())
+            .filter(|x| x.starts_with(|c: char| c.is_alphabetic() || c == '_'))
+            .filter(|x| !OPERATORS.contains(x))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        variables.extend(roots);
+    }
+    variables.retain(|x| !bound.contains(&x.as_str()) && !LITERALS.contains(&x.as_str()));
+    let mut rest = template.as_ref();
+    while let Some(start) = rest.find("{{") {
+        rest = &rest[start + 2..];
+        let end = rest.find("}}").unwrap_or(rest.len());
+        let expression = rest[..end].trim_start_matches('-').trim();
+        let root = expression
+            .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            .next()
+            .unwrap_or_default();
+        let is_identifier = root.starts_with(|c: char| c.is_alphabetic() || c == '_');
+        if is_identifier && !LITERALS.contains(&root) && !bound.contains(&root) {
+            variables.push(root.to_string());
+        }
+        rest = &rest[end..];
+    }
+    variables
+}
+fn lookup<'a>(globals: &'a liquid::Object, path: &str) -> Option<&'a dyn liquid::ValueView> {
+    let mut segments = path.split('.');
+    let mut value: &dyn liquid::ValueView = globals.get(segments.next()?)?;
+    for segment in segments {
+        value = value.as_object()?.get(segment)?;
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("chatgpt-subsystems-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn names(collection: &PromptCollection) -> Vec<String> {
+        let mut names = collection.prompts().iter().filter_map(|x| x.name.clone()).collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    fn message(content: &str) -> api::Message {
+        api::Message::new(api::Role::User, content)
+    }
+
+    fn contents(prompt: &Prompt) -> Vec<String> {
+        prompt.messages().into_iter().map(|x| x.content).collect()
+    }
+
+    #[test]
+    fn to_xml_round_trips_programmatic_content() {
+        let messages = [
+            "R&D",
+            "a < b",
+            "if a<b && c>d",
+            "ends with ]]> inside",
+            "line one\n\n    indented & <tagged\nlast line",
+            "<b>markup</b> is kept",
+        ];
+        let prompt = Prompt::from(crate::formats::PlaygroundPreset {
+            name: Some(String::from("p")),
+            model: Some(String::from("m")),
+            messages: messages.iter().map(|x| message(x)).collect(),
+            ..Default::default()
+        });
+        let xml = prompt.to_xml();
+        let parsed = PromptCollection::parse(&xml).unwrap().get("p").unwrap();
+        assert_eq!(contents(&parsed), messages);
+        assert_eq!(parsed.to_xml(), xml);
+    }
+
+    #[test]
+    fn to_xml_round_trips_file_content() {
+        let source = r#"<prompt name="p" model="m">
+    <message role="system">
+        Use <b>tags</b> and R&amp;D as written.
+    </message>
+</prompt>
+"#;
+        let collection = PromptCollection::parse(source).unwrap();
+        assert_eq!(collection.get("p").unwrap().to_xml(), source);
+    }
+
+    #[test]
+    fn open_dir_loads_every_format_and_skips_other_files() {
+        let dir = scratch_dir("formats");
+        std::fs::write(dir.join("a.xml"), r#"<prompt name="a" model="m"><message>hi</message></prompt>"#).unwrap();
+        std::fs::write(dir.join("b.yaml"), "- name: b\n  model: m\n  messages:\n    - content: hi\n").unwrap();
+        std::fs::write(dir.join("c.json"), r#"[{"name": "c", "model": "m", "messages": [{"content": "hi"}]}]"#).unwrap();
+        std::fs::write(dir.join("README.md"), "# Prompts").unwrap();
+        let collection = PromptCollection::open_dir(&dir).unwrap();
+        assert_eq!(names(&collection), ["a", "b", "c"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn open_dir_escapes_glob_characters_in_the_path() {
+        let parent = scratch_dir("escape");
+        for dir in ["prompts[v2]", "a*b"] {
+            std::fs::create_dir_all(parent.join(dir)).unwrap();
+            std::fs::write(parent.join(dir).join("a.xml"), r#"<prompt name="a" model="m"><message>hi</message></prompt>"#).unwrap();
+            assert_eq!(names(&PromptCollection::open_dir(parent.join(dir)).unwrap()), ["a"]);
+            assert_eq!(names(&PromptCollection::open_dir_recursive(parent.join(dir)).unwrap()), ["a"]);
+        }
+        std::fs::remove_dir_all(parent).unwrap();
+    }
+
+    #[test]
+    fn interpolate_env_expands_variables() {
+        std::env::set_var("CHATGPT_SUBSYSTEMS_TEST_MODEL", "gpt-4o");
+        std::env::set_var("CHATGPT_SUBSYSTEMS_TEST_SIZE", "mini");
+        std::env::set_var("CHATGPT_SUBSYSTEMS_TEST_EMPTY", "");
+        let cases = [
+            ("plain", "plain"),
+            ("${CHATGPT_SUBSYSTEMS_TEST_MODEL}", "gpt-4o"),
+            ("${ CHATGPT_SUBSYSTEMS_TEST_MODEL }", "gpt-4o"),
+            ("${CHATGPT_SUBSYSTEMS_TEST_MODEL:-gpt-3.5}", "gpt-4o"),
+            ("${CHATGPT_SUBSYSTEMS_TEST_UNSET:-gpt-3.5}", "gpt-3.5"),
+            ("${CHATGPT_SUBSYSTEMS_TEST_EMPTY:-fallback}", "fallback"),
+            ("${CHATGPT_SUBSYSTEMS_TEST_UNSET:-}", ""),
+            ("${CHATGPT_SUBSYSTEMS_TEST_MODEL}-${CHATGPT_SUBSYSTEMS_TEST_SIZE}", "gpt-4o-mini"),
+            ("${CHATGPT_SUBSYSTEMS_TEST_MODEL}${CHATGPT_SUBSYSTEMS_TEST_SIZE}", "gpt-4omini"),
+            ("${CHATGPT_SUBSYSTEMS_TEST_UNSET:-${CHATGPT_SUBSYSTEMS_TEST_MODEL}}", "gpt-4o"),
+            ("${CHATGPT_SUBSYSTEMS_TEST_UNSET:-${CHATGPT_SUBSYSTEMS_TEST_UNSET:-x}}!", "x!"),
+            ("$${CHATGPT_SUBSYSTEMS_TEST_MODEL}", "${CHATGPT_SUBSYSTEMS_TEST_MODEL}"),
+            ("$${CHATGPT_SUBSYSTEMS_TEST_MODEL}${CHATGPT_SUBSYSTEMS_TEST_SIZE}", "${CHATGPT_SUBSYSTEMS_TEST_MODEL}mini"),
+            ("cost: $5 ${CHATGPT_SUBSYSTEMS_TEST_SIZE}", "cost: $5 mini"),
+            ("abc${CHATGPT_SUBSYSTEMS_TEST_MODEL", "abc${CHATGPT_SUBSYSTEMS_TEST_MODEL"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(interpolate_env(value).as_deref(), Ok(expected), "{value}");
+        }
+    }
+
+    #[test]
+    fn interpolate_env_names_the_unset_variable() {
+        std::env::set_var("CHATGPT_SUBSYSTEMS_TEST_BLANK", "");
+        let cases = [
+            ("${CHATGPT_SUBSYSTEMS_TEST_MISSING}", "CHATGPT_SUBSYSTEMS_TEST_MISSING"),
+            ("${CHATGPT_SUBSYSTEMS_TEST_BLANK}", "CHATGPT_SUBSYSTEMS_TEST_BLANK"),
+            ("a-${CHATGPT_SUBSYSTEMS_TEST_MISSING}-${CHATGPT_SUBSYSTEMS_TEST_OTHER}", "CHATGPT_SUBSYSTEMS_TEST_MISSING"),
+            ("${CHATGPT_SUBSYSTEMS_TEST_MISSING:-${CHATGPT_SUBSYSTEMS_TEST_OTHER}}", "CHATGPT_SUBSYSTEMS_TEST_OTHER"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(interpolate_env(value), Err(String::from(expected)), "{value}");
+        }
+        let error = PromptCollection::parse(r#"<prompt name="x" model="${CHATGPT_SUBSYSTEMS_TEST_MISSING}"></prompt>"#)
+            .unwrap_err();
+        let error = error.downcast_ref::<UnsetEnvVar>().unwrap();
+        assert_eq!(error.variable, "CHATGPT_SUBSYSTEMS_TEST_MISSING");
+        assert_eq!(error.attribute, "model");
+    }
+}
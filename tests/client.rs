@@ -0,0 +1,255 @@
+//! Drives `ChatCompletionsRequest` end to end through `MockTransport`.
+use std::cell::RefCell;
+
+use serde_json::json;
+
+use chatgpt_subsystems::client::{
+    ApiEndpoint,
+    ApiError,
+    ChatCompletionsBody,
+    ChatCompletionsRequestBuilder,
+    ChatCompletionsResponse,
+    CompletionChunk,
+    Fallback,
+    Message,
+    Role,
+};
+use chatgpt_subsystems::observer::{Observer, StreamProgress};
+use chatgpt_subsystems::transport::{MockResponse, MockTransport, NoMockResponse};
+
+const URL: &str = "https://api.example.com/v1/chat/completions";
+
+fn chunk(index: usize, delta: serde_json::Value, finish_reason: Option<&str>) -> String {
+    json!({
+        "id": "chatcmpl-test",
+        "object": "chat.completion.chunk",
+        "created": 1_700_000_000,
+        "model": "gpt-4o-mini",
+        "choices": [{ "index": index, "delta": delta, "finish_reason": finish_reason }],
+    })
+    .to_string()
+}
+
+/// A reply streaming `tokens` one per event.
+fn reply(tokens: &[&str]) -> MockResponse {
+    let mut events = vec![chunk(0, json!({ "role": "assistant", "content": "" }), None)];
+    events.extend(tokens.iter().map(|x| chunk(0, json!({ "content": x }), None)));
+    events.push(chunk(0, json!({}), Some("stop")));
+    MockResponse::sse(events)
+}
+
+fn request(transport: &MockTransport) -> ChatCompletionsRequestBuilder {
+    ChatCompletionsRequestBuilder::default()
+        .with_api_endpoint(ApiEndpoint::new(URL, "sk-test"))
+        .with_body(ChatCompletionsBody::new("gpt-4o-mini", [Message::new(Role::User, "Say hello.")]))
+        .with_transport(transport.clone())
+}
+
+#[derive(Default)]
+struct Recorder {
+    events: RefCell<Vec<String>>,
+}
+
+impl Recorder {
+    fn events(&self) -> Vec<String> {
+        self.events.borrow().clone()
+    }
+    fn record(&self, event: String) {
+        self.events.borrow_mut().push(event);
+    }
+}
+
+impl Observer for Recorder {
+    fn on_delta(&self, delta: &str) {
+        self.record(format!("delta {delta}"));
+    }
+    fn on_choice_delta(&self, index: usize, delta: &str) {
+        self.record(format!("choice {index} {delta}"));
+    }
+    fn on_chunk(&self, _: &CompletionChunk) {
+        self.record(String::from("chunk"));
+    }
+    fn on_progress(&self, progress: &StreamProgress) {
+        self.record(format!("progress {}", progress.tokens));
+    }
+    fn on_error(&self, error: &dyn std::error::Error) {
+        self.record(format!("error {error}"));
+    }
+    fn on_done(&self, response: &ChatCompletionsResponse) {
+        self.record(format!("done {}", response.content(0)));
+    }
+}
+
+#[tokio::test]
+async fn streams_the_reply() {
+    let transport = MockTransport::new().with_response(reply(&["Hello", " there"]));
+    let response = request(&transport).build().unwrap().execute().await.unwrap();
+    assert_eq!(response.content(0), "Hello there");
+    assert_eq!(response.finish_reason(0).map(|x| x.to_string()).as_deref(), Some("stop"));
+}
+
+#[tokio::test]
+async fn sends_the_body_and_credentials() {
+    let transport = MockTransport::new().with_response(reply(&["Hi"]));
+    request(&transport).build().unwrap().execute().await.unwrap();
+    let requests = transport.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].url, URL);
+    let header = |name: &str| {
+        requests[0].headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.clone())
+    };
+    assert_eq!(header("authorization").as_deref(), Some("Bearer sk-test"));
+    assert_eq!(header("content-type").as_deref(), Some("application/json"));
+    let body = serde_json::from_slice::<serde_json::Value>(&requests[0].body).unwrap();
+    assert_eq!(body["model"], "gpt-4o-mini");
+    assert_eq!(body["messages"], json!([{ "role": "user", "content": "Say hello." }]));
+}
+
+#[tokio::test]
+async fn calls_back_with_each_chunk() {
+    let transport = MockTransport::new().with_response(reply(&["a", "b", "c"]));
+    let request = request(&transport).build().unwrap();
+    let mut deltas = Vec::default();
+    request.execute_with(|chunk| deltas.push(chunk.delta().to_string())).await.unwrap();
+    assert_eq!(deltas, ["", "a", "b", "c", ""]);
+}
+
+#[tokio::test]
+async fn maps_error_statuses() {
+    let cases = [
+        (400, "bad request error", false),
+        (401, "authentication error", false),
+        (403, "permission denied error", false),
+        (404, "not found error", false),
+        (409, "conflict error", false),
+        (422, "unprocessable entity error", false),
+        (429, "rate limit error", true),
+        (500, "internal server error", true),
+        (503, "internal server error", true),
+    ];
+    for (status, expected, retryable) in cases {
+        let body = json!({ "error": { "message": "nope" } }).to_string();
+        let transport = MockTransport::new().with_response(MockResponse::new(status, body));
+        let error = request(&transport).build().unwrap().execute().await.unwrap_err();
+        let error = error.downcast_ref::<ApiError>().unwrap_or_else(|| panic!("{status}: {error}"));
+        assert_eq!(error.to_string(), expected, "{status}");
+        assert_eq!(error.is_retryable(), retryable, "{status}");
+    }
+}
+
+#[tokio::test]
+async fn fails_when_no_response_is_queued() {
+    let transport = MockTransport::new();
+    let error = request(&transport).build().unwrap().execute().await.unwrap_err();
+    assert!(error.downcast_ref::<NoMockResponse>().is_some(), "{error}");
+}
+
+#[tokio::test]
+async fn observers_follow_the_stream() {
+    let transport = MockTransport::new().with_response(reply(&["Hello", " there"]));
+    let recorder = std::rc::Rc::new(Recorder::default());
+    request(&transport).with_observer(recorder.clone()).build().unwrap().execute().await.unwrap();
+    assert_eq!(recorder.events(), [
+        "chunk",
+        "chunk",
+        "delta Hello",
+        "choice 0 Hello",
+        "progress 1",
+        "chunk",
+        "delta  there",
+        "choice 0  there",
+        "progress 2",
+        "chunk",
+        "done Hello there",
+    ]);
+}
+
+#[tokio::test]
+async fn observers_see_errors() {
+    let transport = MockTransport::new().with_response(MockResponse::new(500, "{}"));
+    let recorder = std::rc::Rc::new(Recorder::default());
+    let result = request(&transport).with_observer(recorder.clone()).build().unwrap().execute().await;
+    assert!(result.is_err());
+    assert_eq!(recorder.events(), ["error internal server error"]);
+}
+
+#[tokio::test]
+async fn every_observer_is_called() {
+    let transport = MockTransport::new().with_response(reply(&["Hi"]));
+    let first = std::rc::Rc::new(Recorder::default());
+    let second = std::rc::Rc::new(Recorder::default());
+    request(&transport)
+        .with_observer(first.clone())
+        .with_observer(second.clone())
+        .build()
+        .unwrap()
+        .execute()
+        .await
+        .unwrap();
+    assert_eq!(first.events().last().map(String::as_str), Some("done Hi"));
+    assert_eq!(first.events(), second.events());
+}
+
+#[cfg(feature = "cache")]
+#[tokio::test]
+async fn moderation_screens_cached_replies() {
+    use chatgpt_subsystems::cache::MemoryCache;
+    use chatgpt_subsystems::moderation::{Classification, ContentBlocked, ModerationFilter};
+    let transport = MockTransport::new().with_response(reply(&["Hello"]));
+    let cache = MemoryCache::new();
+    request(&transport).with_cache(cache.clone()).build().unwrap().execute().await.unwrap();
+    assert_eq!(cache.len(), 1);
+    let filter = ModerationFilter::new(|_: &str| Ok(Classification::flagged(["harassment"])));
+    let error = request(&transport)
+        .with_cache(cache.clone())
+        .with_moderation(filter)
+        .build()
+        .unwrap()
+        .execute()
+        .await
+        .unwrap_err();
+    assert!(error.downcast_ref::<ContentBlocked>().is_some(), "{error}");
+}
+
+#[tokio::test]
+async fn rate_limits_carry_retry_after() {
+    let headers = [
+        ("retry-after", "3"),
+        ("retry-after-ms", "2500"),
+        ("x-ratelimit-limit-requests", "500"),
+        ("x-ratelimit-limit-tokens", "30000"),
+        ("x-ratelimit-remaining-requests", "0"),
+        ("x-ratelimit-remaining-tokens", "0"),
+        ("x-ratelimit-reset-requests", "3s"),
+        ("x-ratelimit-reset-tokens", "3s"),
+    ];
+    let response = headers
+        .into_iter()
+        .fold(MockResponse::new(429, "{}"), |response, (key, value)| response.with_header(key, value));
+    let transport = MockTransport::new().with_response(response);
+    let error = request(&transport).build().unwrap().execute().await.unwrap_err();
+    let error = error.downcast_ref::<ApiError>().unwrap();
+    assert_eq!(error.retry_after(), Some(std::time::Duration::from_millis(2500)));
+}
+
+#[tokio::test]
+async fn falls_back_to_another_endpoint() {
+    let transport = MockTransport::new()
+        .with_response(MockResponse::new(503, "{}"))
+        .with_response(reply(&["Hi"]));
+    let backup = ApiEndpoint::new("https://backup.example.com/v1/chat/completions", "sk-backup");
+    let response = request(&transport)
+        .with_fallback(Fallback::new("gpt-4o").with_api_endpoint(backup))
+        .build()
+        .unwrap()
+        .execute()
+        .await
+        .unwrap();
+    assert_eq!(response.content(0), "Hi");
+    let requests = transport.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].url, "https://backup.example.com/v1/chat/completions");
+    assert!(requests[1].headers.contains(&(String::from("Authorization"), String::from("Bearer sk-backup"))));
+    let body = serde_json::from_slice::<serde_json::Value>(&requests[1].body).unwrap();
+    assert_eq!(body["model"], "gpt-4o");
+}